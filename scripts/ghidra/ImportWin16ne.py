@@ -0,0 +1,52 @@
+# Imports the analysis written by `win16ne --export ghidra FILE.EXE > FILE.json`.
+# Run it on an empty program (e.g. a raw binary of size 0) with the
+# x86:LE:16:Real Mode language; segment N is placed at N0000h.
+# @category Win16ne
+# @runtime Jython
+
+import json
+
+import jarray
+from ghidra.program.model.listing import CodeUnit
+from ghidra.program.model.symbol import RefType, SourceType
+
+path = askFile("win16ne JSON bundle", "Import").getAbsolutePath()
+with open(path) as f:
+    bundle = json.load(f)
+
+memory = currentProgram.getMemory()
+space = currentProgram.getAddressFactory().getDefaultAddressSpace()
+
+
+def address(linear):
+    return space.getAddress(linear)
+
+
+for block in bundle["memory_blocks"]:
+    start = address(block["start"])
+    created = memory.createInitializedBlock(
+        block["name"], start, block["size"], 0, monitor, False
+    )
+    created.setExecute(block["code"])
+    created.setWrite(not block["code"])
+    data = bytearray.fromhex(block["bytes"])
+    if data:
+        signed = [byte - 256 if byte >= 128 else byte for byte in data]
+        memory.setBytes(start, jarray.array(signed, "b"))
+
+symbols = currentProgram.getSymbolTable()
+for symbol in bundle["symbols"]:
+    symbols.createLabel(address(symbol["address"]), symbol["name"], SourceType.IMPORTED)
+
+if bundle["entry_point"] is not None:
+    symbols.addExternalEntryPoint(address(bundle["entry_point"]))
+
+references = currentProgram.getReferenceManager()
+listing = currentProgram.getListing()
+for reference in bundle["references"]:
+    site = address(reference["from"])
+    listing.setComment(site, CodeUnit.EOL_COMMENT, reference["target"])
+    if reference["to"] is not None:
+        references.addMemoryReference(
+            site, address(reference["to"]), RefType.DATA, SourceType.IMPORTED, -1
+        )
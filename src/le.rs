@@ -0,0 +1,697 @@
+use std::io::{self, Write};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::util::bytes::{pstr_at, u16_at, u32_at};
+use crate::util::endian::{Lu16, Lu32};
+
+/// The Linear Executable header, shared by the LE format of Windows virtual
+/// device drivers and the LX format of OS/2 2.x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LeHeader {
+    /// `LE` or `LX`
+    pub magic: [u8; 2],
+    pub byte_order: u8,
+    pub word_order: u8,
+    pub format_level: Lu32,
+    pub cpu_type: Lu16,
+    pub os_type: Lu16,
+    pub module_version: Lu32,
+    pub module_flags: Lu32,
+    pub module_pages: Lu32,
+    pub eip_object: Lu32,
+    pub eip: Lu32,
+    pub esp_object: Lu32,
+    pub esp: Lu32,
+    pub page_size: Lu32,
+    /// The size of the last page in LE, the page offset shift in LX.
+    pub last_page_size_or_page_shift: Lu32,
+    pub fixup_section_size: Lu32,
+    pub fixup_section_checksum: Lu32,
+    pub loader_section_size: Lu32,
+    pub loader_section_checksum: Lu32,
+    pub object_table_offset: Lu32,
+    pub object_count: Lu32,
+    pub object_page_table_offset: Lu32,
+    pub object_iterated_pages_offset: Lu32,
+    pub resource_table_offset: Lu32,
+    pub resource_count: Lu32,
+    pub resident_name_table_offset: Lu32,
+    pub entry_table_offset: Lu32,
+    pub module_directives_offset: Lu32,
+    pub module_directives_count: Lu32,
+    pub fixup_page_table_offset: Lu32,
+    pub fixup_record_table_offset: Lu32,
+    pub import_module_table_offset: Lu32,
+    pub import_module_count: Lu32,
+    pub import_procedure_table_offset: Lu32,
+    pub per_page_checksum_offset: Lu32,
+    /// Relative to the start of the file rather than the header.
+    pub data_pages_offset: Lu32,
+    pub preload_page_count: Lu32,
+    /// Relative to the start of the file rather than the header.
+    pub nonresident_name_table_offset: Lu32,
+    pub nonresident_name_table_length: Lu32,
+    pub nonresident_name_table_checksum: Lu32,
+    pub auto_data_segment_object: Lu32,
+    pub debug_info_offset: Lu32,
+    pub debug_info_length: Lu32,
+    pub instance_preload_pages: Lu32,
+    pub instance_demand_pages: Lu32,
+    pub heap_size: Lu32,
+    pub stack_size: Lu32,
+    pub reserved: [u8; 8],
+    /// The Windows version resource of a virtual device driver.
+    pub win_resource_offset: Lu32,
+    pub win_resource_length: Lu32,
+    /// The device ID of a virtual device driver.
+    pub device_id: Lu16,
+    /// The DDK version of a virtual device driver.
+    pub ddk_version: Lu16,
+}
+
+/// The mask of the module type in the module flags.
+pub const MODULE_TYPE_MASK: u32 = 0x0003_8000;
+
+impl LeHeader {
+    /// Reads the header at `lfanew`. The fields past the end of the file,
+    /// which only virtual device drivers use, read as zero.
+    pub fn read(data: &[u8], lfanew: usize) -> io::Result<Self> {
+        let header = data.get(lfanew..).unwrap_or_default();
+        if header.len() < 0xB0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated LE header",
+            ));
+        }
+        let mut buf = header[..header.len().min(std::mem::size_of::<Self>())].to_vec();
+        buf.resize(std::mem::size_of::<Self>(), 0);
+        let header = bytemuck::pod_read_unaligned::<Self>(&buf);
+        if header.magic != *b"LE" && header.magic != *b"LX" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid magic"));
+        }
+        Ok(header)
+    }
+
+    pub fn is_lx(&self) -> bool {
+        self.magic == *b"LX"
+    }
+
+    /// Returns the kind of module from the module flags.
+    pub fn module_type(&self) -> &'static str {
+        match self.module_flags.value() & MODULE_TYPE_MASK {
+            0x0000_0000 => "program",
+            0x0000_8000 => "library",
+            0x0001_8000 => "protected memory library",
+            0x0002_0000 => "physical device driver",
+            0x0002_8000 => "virtual device driver",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Returns the name of a target CPU.
+pub fn cpu_name(cpu_type: u16) -> Option<&'static str> {
+    Some(match cpu_type {
+        1 => "80286",
+        2 => "80386",
+        3 => "80486",
+        _ => return None,
+    })
+}
+
+/// Returns the name of a target operating system.
+pub fn os_name(os_type: u16) -> Option<&'static str> {
+    Some(match os_type {
+        1 => "OS/2",
+        2 => "Windows",
+        3 => "DOS 4.x",
+        4 => "Windows 386",
+        _ => return None,
+    })
+}
+
+/// An entry of the object table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct ObjectEntry {
+    pub virtual_size: Lu32,
+    pub relocation_base: Lu32,
+    pub flags: Lu32,
+    /// The 1-based index of the first page in the object page map.
+    pub page_table_index: Lu32,
+    pub page_count: Lu32,
+    pub reserved: Lu32,
+}
+
+/// The names of the object flags, by bit.
+const OBJECT_FLAG_NAMES: [(u32, &str); 12] = [
+    (0x0001, "READ"),
+    (0x0002, "WRITE"),
+    (0x0004, "EXECUTE"),
+    (0x0008, "RESOURCE"),
+    (0x0010, "DISCARDABLE"),
+    (0x0020, "SHARED"),
+    (0x0040, "PRELOAD"),
+    (0x0080, "INVALID"),
+    (0x0100, "ZEROED"),
+    (0x1000, "ALIAS16"),
+    (0x2000, "BIG"),
+    (0x4000, "CONFORMING"),
+];
+
+/// Returns the names of the object flags, joined by `|`.
+pub fn object_flag_names(flags: u32) -> String {
+    let mut names = OBJECT_FLAG_NAMES
+        .iter()
+        .filter(|&&(mask, _)| (flags & mask) != 0)
+        .map(|&(_, name)| name.to_owned())
+        .collect::<Vec<_>>();
+    let rest = OBJECT_FLAG_NAMES
+        .iter()
+        .fold(flags, |flags, &(mask, _)| flags & !mask);
+    if rest != 0 {
+        names.push(format!("0x{:X}", rest));
+    }
+    if names.is_empty() {
+        "0".to_owned()
+    } else {
+        names.join(" | ")
+    }
+}
+
+/// An entry of the object page map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageEntry {
+    /// The offset of the page in the file.
+    pub offset: u64,
+    /// The size of the page in the file.
+    pub size: u32,
+    /// 0 for a legal page, 1 for an iterated page, 2 for an invalid page,
+    /// 3 for a zero-filled page.
+    pub flags: u16,
+}
+
+/// The target of a fixup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixupTarget {
+    Internal {
+        object: u16,
+        offset: u32,
+    },
+    ImportOrdinal {
+        module: u16,
+        ordinal: u32,
+    },
+    /// The name is at an offset in the import procedure name table.
+    ImportName {
+        module: u16,
+        name_offset: u32,
+    },
+    Entry {
+        ordinal: u16,
+    },
+}
+
+/// A fixup record of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixup {
+    /// The kind of address the fixup fills in, in the low nibble.
+    pub source_type: u8,
+    /// The offsets in the page the fixup applies to. They may be negative
+    /// for a fixup spanning the start of the page.
+    pub sources: Vec<i16>,
+    pub target: FixupTarget,
+    pub additive: Option<u32>,
+}
+
+/// An entry point of a bundle in the entry table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeEntry {
+    Unused,
+    Entry16 { object: u16, flags: u8, offset: u16 },
+    CallGate { object: u16, flags: u8, offset: u16 },
+    Entry32 { object: u16, flags: u8, offset: u32 },
+    Forwarder { flags: u8, module: u16, target: u32 },
+}
+
+/// A Linear Executable, with the tables that describe its objects.
+#[derive(Debug, Clone)]
+pub struct LeExecutable {
+    pub header: LeHeader,
+    pub module_name: Vec<u8>,
+    pub objects: Vec<ObjectEntry>,
+    pub pages: Vec<PageEntry>,
+    /// The fixups of each page.
+    pub fixups: Vec<Vec<Fixup>>,
+    pub entries: Vec<LeEntry>,
+    pub imported_modules: Vec<Vec<u8>>,
+}
+
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Truncated {}", what))
+}
+
+/// Reads a little-endian value of `size` bytes and advances `pos` past it.
+fn take(data: &[u8], pos: &mut usize, size: usize) -> Option<u32> {
+    let bytes = data.get(*pos..*pos + size)?;
+    *pos += size;
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | byte as u32),
+    )
+}
+
+/// Reads the fixup records between `pos` and `end`.
+fn read_fixups(data: &[u8], mut pos: usize, end: usize) -> Option<Vec<Fixup>> {
+    let mut fixups = Vec::new();
+    while pos < end {
+        let source_type = take(data, &mut pos, 1)? as u8;
+        let flags = take(data, &mut pos, 1)? as u8;
+        let mut sources = Vec::new();
+        let source_count = if (source_type & 0x20) != 0 {
+            take(data, &mut pos, 1)?
+        } else {
+            sources.push(take(data, &mut pos, 2)? as i16);
+            0
+        };
+        let index_size = if (flags & 0x40) != 0 { 2 } else { 1 };
+        let index = take(data, &mut pos, index_size)? as u16;
+        let value_size = if (flags & 0x10) != 0 { 4 } else { 2 };
+        let target = match flags & 0x03 {
+            0 => FixupTarget::Internal {
+                object: index,
+                // A selector fixup has no offset.
+                offset: if (source_type & 0x0F) == 0x02 {
+                    0
+                } else {
+                    take(data, &mut pos, value_size)?
+                },
+            },
+            1 => {
+                let ordinal_size = if (flags & 0x80) != 0 { 1 } else { value_size };
+                FixupTarget::ImportOrdinal {
+                    module: index,
+                    ordinal: take(data, &mut pos, ordinal_size)?,
+                }
+            }
+            2 => FixupTarget::ImportName {
+                module: index,
+                name_offset: take(data, &mut pos, value_size)?,
+            },
+            _ => FixupTarget::Entry { ordinal: index },
+        };
+        let additive = if (flags & 0x04) != 0 {
+            let additive_size = if (flags & 0x20) != 0 { 4 } else { 2 };
+            Some(take(data, &mut pos, additive_size)?)
+        } else {
+            None
+        };
+        for _ in 0..source_count {
+            sources.push(take(data, &mut pos, 2)? as i16);
+        }
+        fixups.push(Fixup {
+            source_type,
+            sources,
+            target,
+            additive,
+        });
+    }
+    Some(fixups)
+}
+
+/// Reads the bundles of the entry table.
+fn read_entries(data: &[u8], mut pos: usize) -> Option<Vec<LeEntry>> {
+    let mut entries = Vec::new();
+    loop {
+        let count = *data.get(pos)?;
+        if count == 0 {
+            return Some(entries);
+        }
+        let kind = *data.get(pos + 1)? & 0x7F;
+        pos += 2;
+        if kind == 0 {
+            entries.extend((0..count).map(|_| LeEntry::Unused));
+            continue;
+        }
+        let object = u16_at(data, pos)?;
+        pos += 2;
+        for _ in 0..count {
+            let flags = *data.get(pos)?;
+            let (entry, size) = match kind {
+                1 => (
+                    LeEntry::Entry16 {
+                        object,
+                        flags,
+                        offset: u16_at(data, pos + 1)?,
+                    },
+                    3,
+                ),
+                2 => (
+                    LeEntry::CallGate {
+                        object,
+                        flags,
+                        offset: u16_at(data, pos + 1)?,
+                    },
+                    5,
+                ),
+                3 => (
+                    LeEntry::Entry32 {
+                        object,
+                        flags,
+                        offset: u32_at(data, pos + 1)?,
+                    },
+                    5,
+                ),
+                4 => (
+                    LeEntry::Forwarder {
+                        flags,
+                        module: u16_at(data, pos + 1)?,
+                        target: u32_at(data, pos + 3)?,
+                    },
+                    7,
+                ),
+                _ => return None,
+            };
+            entries.push(entry);
+            pos += size;
+        }
+    }
+}
+
+impl LeExecutable {
+    pub fn read(data: &[u8], lfanew: usize) -> io::Result<Self> {
+        let header = LeHeader::read(data, lfanew)?;
+        let at = |offset: Lu32| lfanew + offset.value() as usize;
+
+        let module_name = pstr_at(data, at(header.resident_name_table_offset))
+            .unwrap_or_default()
+            .to_vec();
+
+        let objects = (0..header.object_count.value() as usize)
+            .map(|i| {
+                let pos = at(header.object_table_offset) + i * std::mem::size_of::<ObjectEntry>();
+                data.get(pos..pos + std::mem::size_of::<ObjectEntry>())
+                    .map(bytemuck::pod_read_unaligned::<ObjectEntry>)
+                    .ok_or_else(|| truncated("object table"))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let page_count = header.module_pages.value() as usize;
+        let page_size = header.page_size.value();
+        let data_pages = header.data_pages_offset.value() as u64;
+        let page_map = at(header.object_page_table_offset);
+        let pages = (0..page_count)
+            .map(|i| {
+                let page = if header.is_lx() {
+                    let pos = page_map + i * 8;
+                    let shift = header.last_page_size_or_page_shift.value();
+                    PageEntry {
+                        offset: data_pages
+                            + ((u32_at(data, pos).ok_or_else(|| truncated("page map"))? as u64)
+                                << shift.min(31)),
+                        size: u16_at(data, pos + 4).unwrap_or(0) as u32,
+                        flags: u16_at(data, pos + 6).unwrap_or(0),
+                    }
+                } else {
+                    // A 24-bit big-endian page number and the flags.
+                    let pos = page_map + i * 4;
+                    let entry = data
+                        .get(pos..pos + 4)
+                        .ok_or_else(|| truncated("page map"))?;
+                    let number = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]);
+                    let size = if i + 1 == page_count {
+                        header.last_page_size_or_page_shift.value()
+                    } else {
+                        page_size
+                    };
+                    PageEntry {
+                        offset: data_pages + number.saturating_sub(1) as u64 * page_size as u64,
+                        size,
+                        flags: entry[3] as u16,
+                    }
+                };
+                Ok(page)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let fixup_pages = at(header.fixup_page_table_offset);
+        let fixup_records = at(header.fixup_record_table_offset);
+        let fixups = (0..page_count)
+            .map(|i| {
+                let start = u32_at(data, fixup_pages + i * 4);
+                let end = u32_at(data, fixup_pages + i * 4 + 4);
+                let (start, end) = start
+                    .zip(end)
+                    .ok_or_else(|| truncated("fixup page table"))?;
+                read_fixups(
+                    data,
+                    fixup_records + start as usize,
+                    fixup_records + end as usize,
+                )
+                .ok_or_else(|| truncated("fixup records"))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let entries = if header.entry_table_offset.value() == 0 {
+            Vec::new()
+        } else {
+            read_entries(data, at(header.entry_table_offset))
+                .ok_or_else(|| truncated("entry table"))?
+        };
+
+        let mut pos = at(header.import_module_table_offset);
+        let mut imported_modules = Vec::new();
+        for _ in 0..header.import_module_count.value() {
+            let name = pstr_at(data, pos).ok_or_else(|| truncated("import module table"))?;
+            pos += 1 + name.len();
+            imported_modules.push(name.to_vec());
+        }
+
+        Ok(Self {
+            header,
+            module_name,
+            objects,
+            pages,
+            fixups,
+            entries,
+            imported_modules,
+        })
+    }
+
+    /// Prints the header, the objects with their pages and fixups, the entry
+    /// points and the imported modules.
+    pub fn describe<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let header = &self.header;
+        if header.is_lx() {
+            writeln!(w, "File Type: Linear Executable (LX)")?;
+        } else {
+            writeln!(w, "File Type: Linear Executable (LE)")?;
+        }
+        writeln!(w, "Header:")?;
+        writeln!(
+            w,
+            "    Module name: {}",
+            String::from_utf8_lossy(&self.module_name)
+        )?;
+        writeln!(w, "    Module type: {}", header.module_type())?;
+        let cpu_type = header.cpu_type.value();
+        writeln!(
+            w,
+            "    CPU: {} ({})",
+            cpu_name(cpu_type).unwrap_or("unknown"),
+            cpu_type
+        )?;
+        let os_type = header.os_type.value();
+        writeln!(
+            w,
+            "    Target OS: {} ({})",
+            os_name(os_type).unwrap_or("unknown"),
+            os_type
+        )?;
+        writeln!(w, "    Module flags: 0x{:08X}", header.module_flags.value())?;
+        writeln!(w, "    Objects: {}", header.object_count.value())?;
+        writeln!(
+            w,
+            "    Pages: {} of {} bytes",
+            header.module_pages.value(),
+            header.page_size.value()
+        )?;
+        writeln!(
+            w,
+            "    Entry point (object:EIP): {}:{:08X}",
+            header.eip_object.value(),
+            header.eip.value()
+        )?;
+        writeln!(
+            w,
+            "    Initial stack (object:ESP): {}:{:08X}",
+            header.esp_object.value(),
+            header.esp.value()
+        )?;
+        if header.device_id.value() != 0 || header.ddk_version.value() != 0 {
+            writeln!(w, "    Device ID: 0x{:04X}", header.device_id.value())?;
+            writeln!(
+                w,
+                "    DDK version: {}.{:02}",
+                header.ddk_version.value() >> 8,
+                header.ddk_version.value() & 0xFF
+            )?;
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            writeln!(w, "Object #{}:", i + 1)?;
+            writeln!(
+                w,
+                "    Relocation base: 0x{:08X}",
+                object.relocation_base.value()
+            )?;
+            writeln!(w, "    Virtual size: 0x{:08X}", object.virtual_size.value())?;
+            writeln!(
+                w,
+                "    Flags: 0x{:04X} ({})",
+                object.flags.value(),
+                object_flag_names(object.flags.value())
+            )?;
+            let first = object.page_table_index.value().saturating_sub(1) as usize;
+            let count = object.page_count.value() as usize;
+            let pages = self.pages.get(first..first + count).unwrap_or_default();
+            if let Some(page) = pages.first() {
+                writeln!(
+                    w,
+                    "    Pages: {} from #{} (file offset 0x{:X})",
+                    count,
+                    first + 1,
+                    page.offset
+                )?;
+            } else {
+                writeln!(w, "    Pages: {}", count)?;
+            }
+            let fixups = self
+                .fixups
+                .get(first..first + count)
+                .unwrap_or_default()
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>();
+            writeln!(w, "    Fixups: {}", fixups)?;
+        }
+
+        if self.entries.iter().any(|entry| *entry != LeEntry::Unused) {
+            writeln!(w, "Entry points:")?;
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            let ordinal = i + 1;
+            match *entry {
+                LeEntry::Unused => {}
+                LeEntry::Entry16 { object, offset, .. } => {
+                    writeln!(w, "    {:>7}  {}:{:04X}", ordinal, object, offset)?
+                }
+                LeEntry::CallGate { object, offset, .. } => writeln!(
+                    w,
+                    "    {:>7}  {}:{:04X} (call gate)",
+                    ordinal, object, offset
+                )?,
+                LeEntry::Entry32 { object, offset, .. } => {
+                    writeln!(w, "    {:>7}  {}:{:08X}", ordinal, object, offset)?
+                }
+                LeEntry::Forwarder { module, target, .. } => {
+                    let module = self
+                        .imported_modules
+                        .get((module as usize).wrapping_sub(1))
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .unwrap_or_else(|| format!("<module {}>", module));
+                    writeln!(w, "    {:>7}  -> {}.{}", ordinal, module, target)?
+                }
+            }
+        }
+
+        if !self.imported_modules.is_empty() {
+            writeln!(w, "Imported modules:")?;
+        }
+        for name in &self.imported_modules {
+            writeln!(w, "    {}", String::from_utf8_lossy(name))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_le_header_size() {
+        assert_eq!(std::mem::size_of::<LeHeader>(), 0xC4);
+    }
+
+    #[test]
+    fn test_le_header() {
+        let mut data = vec![0; 0x40 + 0xB0];
+        data[0x40..0x42].copy_from_slice(b"LE");
+        data[0x48] = 2;
+        data[0x4A] = 4;
+        data[0x50..0x54].copy_from_slice(&0x0002_8000_u32.to_le_bytes());
+        let header = LeHeader::read(&data, 0x40).unwrap();
+        assert!(!header.is_lx());
+        assert_eq!(header.module_type(), "virtual device driver");
+        assert_eq!(header.os_type.value(), 4);
+        assert_eq!(header.device_id.value(), 0);
+        assert!(LeHeader::read(&data, 0x41).is_err());
+    }
+
+    #[test]
+    fn test_read_fixups() {
+        // A 32-bit offset to 1:00001234, and a 16:16 pointer to KERNEL.5
+        // at two places.
+        let data = b"\x07\x10\x08\x00\x01\x34\x12\x00\x00\x23\x81\x02\x01\x05\x10\x00\x20\x00";
+        let fixups = read_fixups(data, 0, data.len()).unwrap();
+        assert_eq!(
+            fixups,
+            vec![
+                Fixup {
+                    source_type: 0x07,
+                    sources: vec![8],
+                    target: FixupTarget::Internal {
+                        object: 1,
+                        offset: 0x1234,
+                    },
+                    additive: None,
+                },
+                Fixup {
+                    source_type: 0x23,
+                    sources: vec![0x10, 0x20],
+                    target: FixupTarget::ImportOrdinal {
+                        module: 1,
+                        ordinal: 5,
+                    },
+                    additive: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let data = b"\x01\x00\x02\x03\x01\x00\x01\x10\x00\x00\x00\x03\x20\x00\x00\x00\x00";
+        assert_eq!(
+            read_entries(data, 0).unwrap(),
+            vec![
+                LeEntry::Unused,
+                LeEntry::Entry32 {
+                    object: 1,
+                    flags: 1,
+                    offset: 0x10,
+                },
+                LeEntry::Entry32 {
+                    object: 1,
+                    flags: 3,
+                    offset: 0x20,
+                },
+            ]
+        );
+    }
+}
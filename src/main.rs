@@ -1,28 +1,860 @@
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufReader, Cursor, Read};
-use std::path::PathBuf;
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use log::debug;
+use rayon::prelude::*;
 
+pub mod le;
 pub mod mz;
 pub mod ne;
+pub mod ordinals;
+pub mod pe;
 pub mod util;
 pub mod x86;
 
-use ne::NeExecutable;
+use le::LeExecutable;
+use mz::DosHeader;
+use ne::assembly::AsmSyntax;
+use ne::build::BuildSpec;
+use ne::catalog::Catalog;
+use ne::dependency_graph::DependencyGraph;
+use ne::exports::{ExportRename, ExportSpec};
+use ne::hashes::HashOptions;
+use ne::header::{HeaderEdit, NeFlags};
+use ne::map_file::MapFile;
+use ne::omf::{self, OmfModule};
+use ne::patch::{Patch, Retarget, SegmentResize};
+use ne::search::BytePattern;
+use ne::signatures::Signature;
+use ne::summary::Summary;
+use ne::symbol_file::SymbolFile;
+use ne::translation::{self, TranslationFormat};
+use ne::{DescribeFilter, DisassemblyOptions, NeExecutable};
+use pe::PeExecutable;
+use util::codepage::Codepage;
+use x86::BackendKind;
 
 #[derive(Debug, Clone, Parser)]
 pub struct Opts {
     #[clap(short, long)]
     disassemble: bool,
 
+    /// With -d, only disassemble code reachable from the entry points
+    #[clap(long)]
+    recursive: bool,
+
+    /// With -d, split the code into functions and list them one by one
+    #[clap(long)]
+    functions: bool,
+
+    /// Disassemble only the given segment (1-based); implies -d
+    #[clap(long, value_name = "N")]
+    disassemble_segment: Option<u16>,
+
+    /// Disassemble only the given range of a segment, e.g. 0003:0241..02C1 (hexadecimal); implies -d
+    #[clap(long, value_name = "SEG:OFF..OFF", value_parser = parse_address_range)]
+    disasm_range: Option<(u16, Range<usize>)>,
+
+    /// Disassemble the real-mode program in the DOS stub instead of describing the file
+    #[clap(long)]
+    disassemble_stub: bool,
+
+    /// With -d, the instruction decoder to use
+    #[clap(long, value_enum, value_name = "DECODER", default_value = "builtin")]
+    decoder: Decoder,
+
     #[clap(long)]
     data: bool,
 
+    /// When describing the file, only print the header and the module name and description
+    #[clap(long)]
+    header_only: bool,
+
+    /// When describing the file, print the number of relocations of each segment instead of listing them
+    #[clap(long)]
+    no_relocs: bool,
+
+    /// When describing the file, print the number of entry points instead of listing them
+    #[clap(long)]
+    no_entries: bool,
+
+    /// Print the export table instead of describing the file
+    #[clap(long)]
+    exports: bool,
+
+    /// Print the functions imported from other modules instead of describing the file
+    #[clap(long)]
+    imports: bool,
+
+    /// Print where a byte pattern such as "55 8B EC ?? 9A" occurs in the segments and resources instead of describing the file
+    #[clap(long, value_name = "HEX")]
+    search: Option<BytePattern>,
+
+    /// Print the entropy, compression ratio and most common bytes of each segment and resource instead of describing the file
+    #[clap(long)]
+    stats: bool,
+
+    /// Print the strings in the data segments, resources and nonresident names instead of describing the file
+    #[clap(long)]
+    strings: bool,
+
+    /// With --strings, the minimum number of characters in a string
+    #[clap(long, value_name = "N", default_value_t = 4)]
+    min_length: usize,
+
+    /// Print the Visual Basic runtime and custom controls of a VB program instead of describing the file
+    #[clap(long)]
+    vb_info: bool,
+
+    /// Print the callers of each function and imported function instead of describing the file
+    #[clap(long)]
+    xrefs: bool,
+
+    /// Print a module definition (.def) file for the module instead of describing the file
+    #[clap(long)]
+    dump_def: bool,
+
+    /// Print a Wine .spec file for the module instead of describing the file
+    #[clap(long)]
+    dump_spec: bool,
+
+    /// Print the named addresses as SEG:OFF NAME lines for emulator debuggers (e.g. DOSBox-X) instead of describing the file
+    #[clap(long)]
+    dump_symbols: bool,
+
+    /// Print a FLAIR pattern file (.pat) of the exported functions, or of the public functions of OMF objects and libraries (.OBJ/.LIB), for use with --signatures, instead of describing the file
+    #[clap(long)]
+    make_signatures: bool,
+
+    /// Print a Microsoft-style linker map (.MAP) with the segments and public symbols instead of describing the file
+    #[clap(long)]
+    map_out: bool,
+
+    /// Print assembler source for the module in the given syntax instead of describing the file
+    #[clap(long, value_enum, value_name = "SYNTAX")]
+    asm_out: Option<AsmFormat>,
+
+    /// Print a script or data that recreates the analysis in another tool instead of describing the file
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    export: Option<ExportFormat>,
+
+    /// Print the SHA-256 hashes of the file, each segment and each resource in the given format instead of describing the file
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    hashes: Option<HashFormat>,
+
+    /// With --hashes, print MD5 hashes as well
+    #[clap(long)]
+    md5: bool,
+
+    /// With --hashes, print the ssdeep fuzzy hash of the code segments with the fixups zeroed, to find other builds of the same program
+    #[clap(long)]
+    fuzzy: bool,
+
+    /// Print a YARA rule for finding related samples, to be refined by hand
+    #[clap(long)]
+    yara: bool,
+
+    /// Print which structure owns each range of the file, with the gaps between them and any overlaps
+    #[clap(long)]
+    layout: bool,
+
+    /// Print the DOS and NE headers field by field with their offsets, raw bytes and decoded values
+    #[clap(long)]
+    hex_headers: bool,
+
+    /// Compare each file with the given later version of it and print the changes to the headers, segments, exports, imports and resources
+    #[clap(long, value_name = "OTHER.EXE")]
+    diff: Option<PathBuf>,
+
+    /// Print the control flow graphs of the functions in the given format
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    cfg: Option<GraphFormat>,
+
+    /// With --cfg, only print the graph of the named function (e.g. an export or sub_0001_0010)
+    #[clap(long, value_name = "NAME")]
+    cfg_function: Option<String>,
+
+    /// Print the module dependencies of all given files as a Graphviz DOT graph
+    #[clap(long)]
+    dependency_graph: bool,
+
+    /// Assemble an executable from a JSON description (segments, exports, modules, resources) and write it to FILE
+    #[clap(long, value_name = "SPEC.JSON")]
+    build: Option<PathBuf>,
+
+    /// Change a header field and rewrite the file in place: expected-win-ver=3.10, heap-size=N, stack-size=N, library=yes|no or flags=N
+    #[clap(long, value_name = "FIELD=VALUE")]
+    set: Vec<HeaderEdit>,
+
+    /// Export an entry point and rewrite the file in place, e.g. MyFunc@5=1:0010 (ordinal optional, address in hex)
+    #[clap(long, value_name = "NAME[@ORD]=SEG:OFF")]
+    add_export: Vec<ExportSpec>,
+
+    /// Rename an export and rewrite the file in place
+    #[clap(long, value_name = "OLD=NEW")]
+    rename_export: Vec<ExportRename>,
+
+    /// Overwrite bytes of a segment and rewrite the file in place, e.g. 1:001A=9090 (address in hex)
+    #[clap(long, value_name = "SEG:OFF=HEXBYTES")]
+    patch: Vec<Patch>,
+
+    /// Redirect the relocation patching SEG:OFF to MODULE.ORDINAL, MODULE.NAME or SEG:OFF and rewrite the file in place
+    #[clap(long, value_name = "SEG:OFF=TARGET")]
+    retarget: Vec<Retarget>,
+
+    /// Grow or shrink a segment at its end and rewrite the file, moving the segments after it, e.g. 1=0x200
+    #[clap(long, value_name = "SEG=LENGTH")]
+    resize_segment: Vec<SegmentResize>,
+
+    /// With --patch, also overwrite bytes that relocations patch
+    #[clap(long)]
+    force: bool,
+
+    /// With --add-export, put the names in the resident name table instead of the nonresident one
+    #[clap(long)]
+    resident: bool,
+
+    /// Write a copy without appended debug information (CodeView, Turbo Debugger) and nonresident names to the given file
+    #[clap(long, value_name = "OUTPUT")]
+    strip: Option<PathBuf>,
+
+    /// Write a copy with segments and resources repacked at the alignment of --alignment-shift to the given file
+    #[clap(long, value_name = "OUTPUT")]
+    realign: Option<PathBuf>,
+
+    /// With --strip, --realign or edits, store repetitive data segments as iterated data
+    #[clap(long)]
+    compress_segments: bool,
+
+    /// Write the DOS program preceding the NE header to the given file as a standalone DOS executable
+    #[clap(long, value_name = "OUTPUT")]
+    extract_stub: Option<PathBuf>,
+
+    /// Add each NE file to a SQLite catalog with its exports, imports, segments and resources (requires the `sqlite` feature)
+    #[clap(long, value_name = "CATALOG.SQLITE")]
+    db: Option<PathBuf>,
+
+    /// The number of threads processing the files; 0 uses one per CPU
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    jobs: usize,
+
+    /// Scan each file, of any kind (self-extracting archives, memory dumps, disk images), for embedded NE executables and extract them to the given directory
+    #[clap(long, value_name = "DIR")]
+    carve: Option<PathBuf>,
+
+    /// Write the data appended after the NE image, such as an installer payload or debug information, to the given file
+    #[clap(long, value_name = "OUTPUT")]
+    extract_overlay: Option<PathBuf>,
+
+    /// Write an IPS or BPS patch, chosen by the extension, that turns the file given with --original into this one
+    #[clap(long, value_name = "PATCH", requires = "original")]
+    make_patch: Option<PathBuf>,
+
+    /// With --make-patch, the unmodified file
+    #[clap(long, value_name = "ORIGINAL.EXE")]
+    original: Option<PathBuf>,
+
+    /// Write the texts of the string tables, menus and dialogs to a PO or CSV file, chosen by the extension, for translation
+    #[clap(long, value_name = "TEXTS.PO")]
+    export_strings: Option<PathBuf>,
+
+    /// Replace the texts of the string tables, menus and dialogs with the translations in a PO or CSV file written by --export-strings, and rewrite the file in place, moving resources that grow
+    #[clap(long, value_name = "TEXTS.PO")]
+    import_strings: Option<PathBuf>,
+
+    /// Replace the DOS stub with the given DOS program and rewrite the file in place
+    #[clap(long, value_name = "STUB.EXE")]
+    replace_stub: Option<PathBuf>,
+
+    /// With --realign, align to 2^N bytes
+    #[clap(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..=15))]
+    alignment_shift: u16,
+
+    /// Check offsets and references for consistency instead of describing the file
+    #[clap(long)]
+    verify: bool,
+
+    /// Directory to look for referenced DLLs in, to name functions imported by ordinal
+    #[clap(long, value_name = "DIR")]
+    search_path: Vec<PathBuf>,
+
+    /// Symbol file (.SYM) written by MAPSYM, to name segments and addresses
+    #[clap(long, value_name = "FILE.SYM")]
+    sym: Option<PathBuf>,
+
+    /// Linker map (.MAP) written by LINK or WLINK, to name segments and addresses
+    #[clap(long, value_name = "FILE.MAP")]
+    map: Option<PathBuf>,
+
+    /// FLAIR pattern file (.pat) of library functions, to name the functions that match
+    #[clap(long, value_name = "FILE.PAT")]
+    signatures: Vec<PathBuf>,
+
+    /// OMF object or library (.OBJ/.LIB) linked into the file, to name segments and public symbols
+    #[clap(long, value_name = "FILE.OBJ")]
+    omf: Vec<PathBuf>,
+
+    /// Windows codepage used to decode names and strings (e.g. 437, 932, 1252)
+    #[clap(long, default_value_t = Codepage::default())]
+    codepage: Codepage,
+
+    /// Write the output to FILE instead of standard output; with several files, `{}` in FILE is replaced by the name of each file without its extension, for a report per file
+    #[clap(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
     #[clap(name = "FILE", value_parser)]
     files: Vec<PathBuf>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AsmFormat {
+    /// Microsoft Macro Assembler
+    Masm,
+    /// Netwide Assembler, for the obj output format
+    Nasm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// JSON for scripts/ghidra/ImportWin16ne.py
+    Ghidra,
+    /// IDA IDC script
+    Idc,
+    /// radare2/rizin commands
+    R2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HashFormat {
+    /// Lines in the format of `sha256sum --tag`
+    Text,
+    /// JSON
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Decoder {
+    /// The decoder built into win16ne
+    Builtin,
+    /// iced-x86 (requires the `iced` feature)
+    Iced,
+}
+
+/// Lists the files in a directory and its subdirectories.
+fn walk_directory(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// Reads a file and parses it if it's an NE executable, by its content
+/// rather than its extension.
+fn read_ne_file(path: &Path) -> io::Result<Option<NeExecutable>> {
+    let data = std::fs::read(path)?;
+    let Ok(dos_header) = DosHeader::read(&mut Cursor::new(data.as_slice())) else {
+        return Ok(None);
+    };
+    if dos_header.check_magic().is_err() || dos_header.extended_signature(&data) != Some(*b"NE") {
+        return Ok(None);
+    }
+    NeExecutable::read(&mut Cursor::new(data.as_slice())).map(Some)
+}
+
+/// Opens the file the output goes to, or standard output.
+fn open_output(path: Option<&Path>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+/// Returns the path of the report on `file` for an `--output` path with `{}`.
+fn report_path(output: &Path, file: &Path) -> PathBuf {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    PathBuf::from(output.to_string_lossy().replace("{}", &stem))
+}
+
+/// The number of files processed in parallel before their reports are written.
+const BATCH_SIZE: usize = 256;
+
+/// Prints a table of the NE executables among the files in the directories
+/// and adds them to the catalog, if any.
+///
+/// The files are parsed in parallel, a batch at a time, and the rows printed
+/// in the order of the files.
+fn scan_directories<W: Write>(
+    out: &mut W,
+    files: &[PathBuf],
+    mut catalog: Option<&mut Catalog>,
+    codepage: Codepage,
+) -> io::Result<()> {
+    let mut paths = Vec::new();
+    for path in files {
+        if path.is_dir() {
+            paths.extend(walk_directory(path)?);
+        } else {
+            paths.push(path.clone());
+        }
+    }
+    Summary::write_header(out)?;
+    for batch in paths.chunks(BATCH_SIZE) {
+        let exes = batch
+            .par_iter()
+            .map(|path| read_ne_file(path))
+            .collect::<Vec<_>>();
+        for (path, exe) in batch.iter().zip(exes) {
+            match exe {
+                Ok(Some(exe)) => {
+                    exe.summary().write_row(out, path, codepage)?;
+                    if let Some(catalog) = catalog.as_deref_mut() {
+                        catalog.add(path, &exe, codepage)?;
+                    }
+                }
+                Ok(None) => debug!("{}: not an NE executable", path.display()),
+                Err(e) => eprintln!("Warning: {}: {}", path.display(), e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses `SEG:OFF..OFF` in hexadecimal.
+fn parse_address_range(s: &str) -> Result<(u16, Range<usize>), String> {
+    let parse = |s: &str| u16::from_str_radix(s, 16).map_err(|e| format!("{}: {}", s, e));
+    let (segment, offsets) = s.split_once(':').ok_or("expected SEG:OFF..OFF")?;
+    let (start, end) = offsets.split_once("..").ok_or("expected SEG:OFF..OFF")?;
+    let (start, end) = (parse(start)? as usize, parse(end)? as usize);
+    if start > end {
+        return Err(format!("the range {:04X}..{:04X} is reversed", start, end));
+    }
+    Ok((parse(segment)?, start..end))
+}
+
+/// What the modes combining the files need from each of them.
+#[derive(Default)]
+struct FileOutcome {
+    /// The parsed executable, for the catalog or the dependency graph.
+    exe: Option<NeExecutable>,
+    violation_found: bool,
+}
+
+/// The inputs read once and shared by the files processed in parallel.
+struct Context {
+    backend: BackendKind,
+    symbol_file: Option<SymbolFile>,
+    map_file: Option<MapFile>,
+    signatures: Vec<Signature>,
+    omf_modules: Vec<OmfModule>,
+}
+
+/// An error printed as is, with exit status 1, once the reports on the
+/// files before it are written.
+#[derive(Debug)]
+struct Fatal(String);
+
+impl fmt::Display for Fatal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Fatal {}
+
+fn fatal(message: String) -> io::Error {
+    io::Error::other(Fatal(message))
+}
+
+/// Processes one of the files given on the command line, writing the report to `out`.
+fn process_file<W: Write>(
+    opts: &Opts,
+    context: &Context,
+    file: &Path,
+    out: &mut W,
+) -> io::Result<FileOutcome> {
+    let data = {
+        let mut f = BufReader::new(File::open(file)?);
+        let mut data = Vec::new();
+        f.read_to_end(&mut data)?;
+        data
+    };
+    if let Some(dir) = &opts.carve {
+        std::fs::create_dir_all(dir)?;
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        for image in ne::carve::carve(&data) {
+            let extension = if image.exe.ne_header.ne_flags().contains(NeFlags::LIBRARY) {
+                "dll"
+            } else {
+                "exe"
+            };
+            let output = dir.join(format!("{}_{:08X}.{}", stem, image.offset, extension));
+            File::create(&output)?.write_all(image.data(&data))?;
+            writeln!(
+                out,
+                "{}: 0x{:X} bytes at 0x{:08X} ({}) -> {}",
+                file.display(),
+                image.size,
+                image.offset,
+                opts.codepage.decode(&image.exe.summary().module_name),
+                output.display()
+            )?;
+        }
+        return Ok(FileOutcome::default());
+    }
+
+    if opts.make_signatures && omf::is_omf(&data) {
+        for module in omf::parse(&data)? {
+            for signature in module.signatures() {
+                writeln!(out, "{}", signature.to_pat_line())?;
+            }
+        }
+        writeln!(out, "---")?;
+        return Ok(FileOutcome::default());
+    }
+
+    let dos_header = DosHeader::read(&mut Cursor::new(data.as_slice()))?;
+    dos_header.check_magic()?;
+    let lfanew = dos_header.lfanew.value() as usize;
+    match dos_header.extended_signature(&data).as_ref() {
+        Some(b"NE") => {}
+        Some(b"PE") => {
+            PeExecutable::read(&data, lfanew)?.describe(out)?;
+            return Ok(FileOutcome::default());
+        }
+        Some(_) => {
+            LeExecutable::read(&data, lfanew)?.describe(out)?;
+            return Ok(FileOutcome::default());
+        }
+        None => {
+            dos_header.describe(out, &data)?;
+            return Ok(FileOutcome::default());
+        }
+    }
+
+    let mut cursor = Cursor::new(data.as_slice());
+
+    let parsed = if opts.verify {
+        NeExecutable::read_lenient(&mut cursor)
+    } else {
+        NeExecutable::read(&mut cursor)
+    };
+    let mut parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) if opts.verify => {
+            // A table that can't be read is a problem of its own.
+            writeln!(out, "{}:", file.display())?;
+            writeln!(out, "    unreadable: {}", e)?;
+            writeln!(out, "    1 problem(s) found")?;
+            return Ok(FileOutcome {
+                exe: None,
+                violation_found: true,
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    if !opts.search_path.is_empty() {
+        for name in parsed.load_imported_modules(&opts.search_path) {
+            eprintln!(
+                "Warning: module {} not found in the search path",
+                opts.codepage.decode(&name)
+            );
+        }
+    }
+    if let Some(sym) = &context.symbol_file {
+        parsed.load_symbol_file(sym);
+    }
+    if let Some(map) = &context.map_file {
+        parsed.load_map_file(map);
+    }
+    if !context.signatures.is_empty() {
+        let named = parsed.apply_signatures(&context.signatures);
+        debug!("{}: {} library functions named", file.display(), named);
+    }
+    if !context.omf_modules.is_empty() {
+        let named = parsed.apply_omf_modules(&context.omf_modules);
+        debug!(
+            "{}: {} symbols named from OMF modules",
+            file.display(),
+            named
+        );
+    }
+    if opts.compress_segments {
+        let compressed = parsed.compress_segments();
+        debug!("{}: {} segment(s) compressed", file.display(), compressed);
+    }
+    if !opts.set.is_empty()
+        || !opts.add_export.is_empty()
+        || !opts.rename_export.is_empty()
+        || !opts.patch.is_empty()
+        || !opts.retarget.is_empty()
+        || !opts.resize_segment.is_empty()
+    {
+        for &edit in &opts.set {
+            parsed.ne_header.apply(edit);
+        }
+        for spec in &opts.add_export {
+            let ordinal = parsed.add_export(spec, opts.resident)?;
+            debug!("{}: exported @{}", file.display(), ordinal);
+        }
+        for rename in &opts.rename_export {
+            parsed.rename_export(rename)?;
+        }
+        for &resize in &opts.resize_segment {
+            parsed.resize_segment(resize)?;
+        }
+        for patch in &opts.patch {
+            parsed.patch(patch, opts.force)?;
+        }
+        for retarget in &opts.retarget {
+            parsed.retarget(retarget)?;
+        }
+        let mut output = Vec::new();
+        if let Err(e) = parsed.write_preserving(&mut output) {
+            // Grown name tables rarely fit, since the other tables follow them directly.
+            eprintln!("Warning: {}; laying out the file anew", e);
+            output.clear();
+            parsed.write_with_overlay(&mut Cursor::new(&mut output))?;
+        }
+        if !opts.patch.is_empty() {
+            // The segments may have moved, so the offsets are those of the output.
+            let written = NeExecutable::read(&mut Cursor::new(&output[..]))?;
+            for patch in &opts.patch {
+                let file_offset = written
+                    .file_offset(patch.segment, patch.offset)
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "{}: patched {} byte(s) at {:04X}:{:04X} (file offset 0x{:X})",
+                    file.display(),
+                    patch.bytes.len(),
+                    patch.segment,
+                    patch.offset,
+                    file_offset
+                )?;
+            }
+        }
+        std::fs::write(file, output)?;
+    } else if let Some(stub) = &opts.replace_stub {
+        let replaced = parsed.replace_stub(&std::fs::read(stub)?)?;
+        std::fs::write(file, replaced)?;
+    } else if let Some(texts) = &opts.import_strings {
+        let Some(format) = TranslationFormat::from_path(texts) else {
+            return Err(fatal(
+                "the translations must be named *.po or *.csv".to_owned(),
+            ));
+        };
+        let translations =
+            translation::read_translations(&std::fs::read_to_string(texts)?, format)?;
+        let count = parsed.apply_translations(&translations, opts.codepage)?;
+        let mut output = Vec::new();
+        parsed.write_growing_resources(&mut output)?;
+        std::fs::write(file, output)?;
+        writeln!(out, "{}: replaced {} text(s)", file.display(), count)?;
+    } else if let Some(output) = &opts.strip {
+        let exported = parsed.strip();
+        if exported > 0 {
+            eprintln!(
+                "Warning: {} function(s) exported by nonresident name are now only exported by ordinal",
+                exported
+            );
+        }
+        parsed.write(&mut File::create(output)?)?;
+    } else if let Some(output) = &opts.realign {
+        parsed.realign(opts.alignment_shift);
+        parsed.write(&mut File::create(output)?)?;
+    } else if let Some(output) = &opts.extract_stub {
+        File::create(output)?.write_all(&parsed.extract_stub())?;
+    } else if let Some(output) = &opts.extract_overlay {
+        if parsed.overlay().is_none() {
+            return Err(fatal(format!("{} has no overlay", file.display())));
+        }
+        File::create(output)?.write_all(parsed.overlay_data())?;
+    } else if let Some(output) = &opts.export_strings {
+        let Some(format) = TranslationFormat::from_path(output) else {
+            return Err(fatal(
+                "the texts must be written to *.po or *.csv".to_owned(),
+            ));
+        };
+        let mut writer = io::BufWriter::new(File::create(output)?);
+        translation::write_translations(
+            &mut writer,
+            &parsed.translatable_texts(opts.codepage),
+            format,
+        )?;
+        writer.flush()?;
+    } else if let Some(output) = &opts.make_patch {
+        let original = std::fs::read(opts.original.as_ref().unwrap())?;
+        let extension = output
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+        let patch = match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("ips") => util::ips::create(&original, &data)?,
+            Some("bps") => util::bps::create(&original, &data),
+            _ => {
+                return Err(fatal("the patch must be named *.ips or *.bps".to_owned()));
+            }
+        };
+        File::create(output)?.write_all(&patch)?;
+    } else if opts.db.is_some() {
+        return Ok(FileOutcome {
+            exe: Some(parsed),
+            violation_found: false,
+        });
+    } else if opts.verify {
+        let violations = parsed.verify(data.len() as u64);
+        writeln!(out, "{}:", file.display())?;
+        for violation in &violations {
+            writeln!(out, "    {}", violation)?;
+        }
+        if violations.is_empty() {
+            writeln!(out, "    OK")?;
+        } else {
+            writeln!(out, "    {} problem(s) found", violations.len())?;
+            return Ok(FileOutcome {
+                exe: None,
+                violation_found: true,
+            });
+        }
+    } else if opts.dependency_graph {
+        return Ok(FileOutcome {
+            exe: Some(parsed),
+            violation_found: false,
+        });
+    } else if opts.disassemble_stub {
+        parsed.describe_stub_disassembly(out, context.backend)?;
+    } else if opts.dump_def {
+        parsed.describe_def(out, opts.codepage)?;
+    } else if opts.dump_spec {
+        parsed.describe_wine_spec(out, opts.codepage)?;
+    } else if opts.dump_symbols {
+        parsed.describe_symbol_list(out, opts.codepage)?;
+    } else if opts.make_signatures {
+        parsed.describe_signatures(out)?;
+    } else if opts.map_out {
+        parsed.describe_map(out, opts.codepage)?;
+    } else if let Some(format) = opts.asm_out {
+        let syntax = match format {
+            AsmFormat::Masm => AsmSyntax::Masm,
+            AsmFormat::Nasm => AsmSyntax::Nasm,
+        };
+        parsed.describe_asm(out, syntax, opts.codepage)?;
+    } else if let Some(format) = opts.export {
+        match format {
+            ExportFormat::Ghidra => parsed.describe_ghidra_json(out, opts.codepage)?,
+            ExportFormat::Idc => parsed.describe_idc(out, file, opts.codepage)?,
+            ExportFormat::R2 => parsed.describe_r2_script(out, opts.codepage)?,
+        }
+    } else if let Some(format) = opts.hashes {
+        let options = HashOptions {
+            md5: opts.md5,
+            fuzzy: opts.fuzzy,
+        };
+        match format {
+            HashFormat::Text => parsed.describe_hashes(out, options)?,
+            HashFormat::Json => parsed.describe_hashes_json(out, options)?,
+        }
+    } else if opts.yara {
+        write!(out, "{}", parsed.yara_rule(opts.codepage))?;
+    } else if opts.layout {
+        parsed.describe_layout(out, opts.codepage)?;
+    } else if opts.hex_headers {
+        parsed.describe_hex_headers(out)?;
+    } else if let Some(other) = &opts.diff {
+        let Some(other) = read_ne_file(other)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an NE executable", other.display()),
+            ));
+        };
+        parsed.describe_diff(out, &other, opts.codepage)?;
+    } else if let Some(GraphFormat::Dot) = opts.cfg {
+        match parsed.describe_cfg_dot(out, opts.cfg_function.as_deref(), opts.codepage) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(fatal(e.to_string())),
+            result => result?,
+        }
+    } else if opts.exports {
+        parsed.describe_exports(out, opts.codepage)?;
+    } else if opts.imports {
+        parsed.describe_imports(out, opts.codepage)?;
+    } else if let Some(pattern) = &opts.search {
+        parsed.describe_search(out, pattern)?;
+    } else if opts.stats {
+        parsed.describe_statistics(out)?;
+    } else if opts.strings {
+        parsed.describe_strings(out, opts.min_length, opts.codepage)?;
+    } else if opts.vb_info {
+        parsed.describe_vb_info(out, opts.codepage)?;
+    } else if opts.xrefs {
+        parsed.describe_xrefs(out, opts.codepage)?;
+    } else {
+        let disassembly = DisassemblyOptions {
+            recursive: opts.recursive,
+            functions: opts.functions,
+            backend: context.backend,
+            segment: opts.disassemble_segment,
+            range: opts.disasm_range.clone(),
+        };
+        let filter = DescribeFilter {
+            header_only: opts.header_only,
+            no_relocations: opts.no_relocs,
+            no_entries: opts.no_entries,
+        };
+        parsed.describe(
+            out,
+            opts.data,
+            Some(&disassembly).filter(|_| {
+                opts.disassemble
+                    || opts.disassemble_segment.is_some()
+                    || opts.disasm_range.is_some()
+            }),
+            filter,
+            opts.codepage,
+        )?;
+    }
+    Ok(FileOutcome::default())
+}
+
+/// Processes the files in parallel, a batch at a time, and hands each
+/// report and outcome to `finish` in the order of the files.
+fn process_files<F>(
+    opts: &Opts,
+    context: &Context,
+    batch_size: usize,
+    mut finish: F,
+) -> io::Result<()>
+where
+    F: FnMut(&Path, Vec<u8>, io::Result<FileOutcome>) -> io::Result<()>,
+{
+    for batch in opts.files.chunks(batch_size) {
+        let results = batch
+            .par_iter()
+            .map(|file| {
+                let mut report = Vec::new();
+                let outcome = process_file(opts, context, file, &mut report);
+                (report, outcome)
+            })
+            .collect::<Vec<_>>();
+        for (file, (report, outcome)) in batch.iter().zip(results) {
+            finish(file, report, outcome)?;
+        }
+    }
+    Ok(())
+}
+
 fn main() -> io::Result<()> {
     env_logger::init();
 
@@ -32,19 +864,170 @@ fn main() -> io::Result<()> {
         eprintln!("Error: no files specified");
         std::process::exit(1);
     }
-
-    for file in &opts.files {
-        let data = {
-            let mut f = BufReader::new(File::open(file)?);
-            let mut data = Vec::new();
-            f.read_to_end(&mut data)?;
-            data
+    if let Some(spec_path) = &opts.build {
+        let [output] = &opts.files[..] else {
+            eprintln!("Error: --build writes exactly one file");
+            std::process::exit(1);
         };
+        let spec = serde_json::from_slice::<BuildSpec>(&std::fs::read(spec_path)?)?;
+        let base = spec_path.parent().unwrap_or(Path::new(""));
+        let exe = NeExecutable::build(&spec, base)?;
+        exe.write(&mut File::create(output)?)?;
+        return Ok(());
+    }
+    if opts.db.is_some() && !cfg!(feature = "sqlite") {
+        eprintln!("Error: win16ne was built without the sqlite feature");
+        std::process::exit(1);
+    }
+    let mut catalog = match &opts.db {
+        Some(path) => Some(Catalog::open(path)?),
+        None => None,
+    };
+    let per_file = opts
+        .output
+        .as_ref()
+        .is_some_and(|output| output.to_string_lossy().contains("{}"));
+    let mut out = open_output(opts.output.as_deref().filter(|_| !per_file))?;
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(opts.jobs)
+        .build_global()
+        .map_err(io::Error::other)?;
+    if opts.files.iter().any(|path| path.is_dir()) {
+        scan_directories(&mut out, &opts.files, catalog.as_mut(), opts.codepage)?;
+        return out.flush();
+    }
+    let backend = match opts.decoder {
+        Decoder::Builtin => BackendKind::Builtin,
+        Decoder::Iced => BackendKind::Iced,
+    };
+    if backend.backend().is_none() {
+        eprintln!("Error: win16ne was built without the iced feature");
+        std::process::exit(1);
+    }
+
+    let symbol_file = match &opts.sym {
+        Some(path) => Some(SymbolFile::parse(&std::fs::read(path)?)?),
+        None => None,
+    };
+
+    let map_file = match &opts.map {
+        Some(path) => Some(MapFile::parse(&std::fs::read(path)?)),
+        None => None,
+    };
+
+    let mut signatures = Vec::new();
+    for path in &opts.signatures {
+        signatures.extend(Signature::parse_pat(&std::fs::read_to_string(path)?)?);
+    }
 
-        let mut cursor = Cursor::new(data.as_slice());
+    let mut omf_modules = Vec::new();
+    for path in &opts.omf {
+        omf_modules.extend(omf::parse(&std::fs::read(path)?)?);
+    }
 
-        let parsed = NeExecutable::read(&mut cursor)?;
-        parsed.describe(opts.data, opts.disassemble);
+    let context = Context {
+        backend,
+        symbol_file,
+        map_file,
+        signatures,
+        omf_modules,
+    };
+    // Files edited in place are done one at a time, so that none after a
+    // failing one are touched.
+    let edits_in_place = !opts.set.is_empty()
+        || !opts.add_export.is_empty()
+        || !opts.rename_export.is_empty()
+        || !opts.patch.is_empty()
+        || !opts.retarget.is_empty()
+        || !opts.resize_segment.is_empty()
+        || opts.replace_stub.is_some()
+        || opts.import_strings.is_some();
+    let batch_size = if edits_in_place { 1 } else { BATCH_SIZE };
+    let mut violation_found = false;
+    let mut dependency_graph = DependencyGraph::new();
+    process_files(&opts, &context, batch_size, |file, report, outcome| {
+        // Reports already written aren't lost if a later file fails.
+        if let (true, Some(output)) = (per_file, &opts.output) {
+            let mut file_out = open_output(Some(&report_path(output, file)))?;
+            file_out.write_all(&report)?;
+            file_out.flush()?;
+        } else {
+            out.write_all(&report)?;
+            out.flush()?;
+        }
+        let outcome = match outcome {
+            Err(e) if e.get_ref().is_some_and(|e| e.is::<Fatal>()) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            outcome => outcome?,
+        };
+        violation_found |= outcome.violation_found;
+        if let Some(exe) = outcome.exe {
+            if let Some(catalog) = &mut catalog {
+                catalog.add(file, &exe, opts.codepage)?;
+            } else {
+                let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+                dependency_graph.add(&exe, stem.as_bytes());
+            }
+        }
+        Ok(())
+    })?;
+    if opts.dependency_graph {
+        dependency_graph.describe_dot(&mut out, opts.codepage)?;
+    }
+    out.flush()?;
+    if violation_found {
+        std::process::exit(1);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_files_in_order() {
+        let dir = std::env::temp_dir().join(format!("win16ne-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // DOS executables whose reports differ in the stack pointer.
+        let mut files = Vec::new();
+        for i in 0..20u16 {
+            let mut data = vec![0; 0x40];
+            data[..2].copy_from_slice(b"MZ");
+            data[0x08] = 4;
+            data[0x10..0x12].copy_from_slice(&(0x100 + i).to_le_bytes());
+            let path = dir.join(format!("{}.exe", i));
+            std::fs::write(&path, data).unwrap();
+            files.push(path);
+        }
+        files.push(dir.join("missing.exe"));
+        let opts = Opts::parse_from(
+            std::iter::once("win16ne".as_ref()).chain(files.iter().map(|path| path.as_os_str())),
+        );
+        let context = Context {
+            backend: BackendKind::Builtin,
+            symbol_file: None,
+            map_file: None,
+            signatures: Vec::new(),
+            omf_modules: Vec::new(),
+        };
+
+        let mut expected = Vec::new();
+        for file in &files[..20] {
+            process_file(&opts, &context, file, &mut expected).unwrap();
+        }
+        let mut reports = Vec::new();
+        let mut finished = Vec::new();
+        let result = process_files(&opts, &context, 8, |file, report, outcome| {
+            finished.push(file.to_path_buf());
+            reports.extend(report);
+            outcome.map(|_| ())
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(finished, files);
+        assert_eq!(reports, expected);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
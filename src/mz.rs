@@ -1,9 +1,12 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 use bytemuck::{Pod, Zeroable};
 
 use crate::util::endian::{Lu16, Lu32};
 
+/// The signatures of the headers that `lfanew` may point at.
+const EXTENDED_SIGNATURES: [&[u8]; 4] = [b"NE", b"LE", b"LX", b"PE"];
+
 /// The DOS header.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
 #[repr(C)]
@@ -63,6 +66,132 @@ impl DosHeader {
         Ok(())
     }
 
+    /// Returns the size of the program in the file, including the header,
+    /// from the page count and the bytes on the last page.
+    pub fn image_size(&self) -> u64 {
+        let pages = self.cp.value() as u64;
+        let last_page = self.cblp.value() as u64;
+        if pages == 0 {
+            0
+        } else if last_page == 0 {
+            pages * 0x200
+        } else {
+            (pages - 1) * 0x200 + last_page
+        }
+    }
+
+    /// Sets the page count and the bytes on the last page for a program of `size` bytes.
+    pub fn set_image_size(&mut self, size: u64) {
+        self.cp = (size.div_ceil(0x200) as u16).into();
+        self.cblp = ((size % 0x200) as u16).into();
+    }
+
+    /// Returns the signature of the extended header that `lfanew` points at
+    /// in `data` (`NE`, `LE`, `LX` or `PE`), or `None` if `lfanew` is zero or
+    /// garbage left over in a plain DOS program.
+    pub fn extended_signature(&self, data: &[u8]) -> Option<[u8; 2]> {
+        let lfanew = self.lfanew.value() as usize;
+        if lfanew < 0x40 {
+            return None;
+        }
+        let signature = data.get(lfanew..lfanew + 2)?;
+        EXTENDED_SIGNATURES
+            .iter()
+            .find(|&&sig| sig == signature)
+            .map(|&sig| [sig[0], sig[1]])
+    }
+
+    /// Returns the segment and offset of the relocations in `data`, as far as
+    /// the table is within the file.
+    pub fn relocations(&self, data: &[u8]) -> Vec<(u16, u16)> {
+        let start = self.lfarlc.value() as usize;
+        let table = data.get(start..).unwrap_or_default();
+        table
+            .chunks_exact(4)
+            .take(self.crlc.value() as usize)
+            .map(|entry| {
+                let offset = u16::from_le_bytes([entry[0], entry[1]]);
+                let segment = u16::from_le_bytes([entry[2], entry[3]]);
+                (segment, offset)
+            })
+            .collect()
+    }
+
+    /// Prints the load module, the entry point, the relocations and the
+    /// overlay of a plain DOS program.
+    pub fn describe<W: Write>(&self, w: &mut W, data: &[u8]) -> io::Result<()> {
+        let header_size = self.cparhdr.value() as u64 * 0x10;
+        let image_size = match self.image_size() {
+            0 => data.len() as u64,
+            size => size,
+        };
+        writeln!(w, "File Type: DOS Executable")?;
+        writeln!(w, "Header:")?;
+        writeln!(w, "    Header size: {} paragraph(s)", self.cparhdr.value())?;
+        writeln!(
+            w,
+            "    Load module: {} bytes at 0x{:X}",
+            image_size.saturating_sub(header_size),
+            header_size
+        )?;
+        if image_size > data.len() as u64 {
+            writeln!(
+                w,
+                "    The load module ends at 0x{:X}, past the end of the file",
+                image_size
+            )?;
+        }
+        writeln!(
+            w,
+            "    Entry point (CS:IP): {:04X}:{:04X}",
+            self.cs.value(),
+            self.ip.value()
+        )?;
+        writeln!(
+            w,
+            "    Initial stack (SS:SP): {:04X}:{:04X}",
+            self.ss.value(),
+            self.sp.value()
+        )?;
+        writeln!(
+            w,
+            "    Extra memory: {} to {} paragraph(s)",
+            self.minalloc.value(),
+            self.maxalloc.value()
+        )?;
+        if self.lfanew.value() != 0 {
+            writeln!(
+                w,
+                "    Extended header offset: 0x{:X} (no extended header there)",
+                self.lfanew.value()
+            )?;
+        }
+        let relocations = self.relocations(data);
+        writeln!(
+            w,
+            "Relocations: {} at 0x{:X}",
+            self.crlc.value(),
+            self.lfarlc.value()
+        )?;
+        if relocations.len() < self.crlc.value() as usize {
+            writeln!(
+                w,
+                "    The table is cut short after {} relocation(s)",
+                relocations.len()
+            )?;
+        }
+        for (segment, offset) in relocations {
+            writeln!(w, "    {:04X}:{:04X}", segment, offset)?;
+        }
+        let overlay = (data.len() as u64).saturating_sub(image_size);
+        if overlay > 0 {
+            writeln!(w, "Overlay: {} bytes at 0x{:X}", overlay, image_size)?;
+        } else {
+            writeln!(w, "Overlay: none")?;
+        }
+        Ok(())
+    }
+
     pub fn check_sum(buf: &[u8]) -> io::Result<()> {
         let mut sum = 0_u16;
         let mut pos = 0;
@@ -172,6 +301,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_image_size() {
+        let mut h = DosHeader::zeroed();
+        for size in [0x80, 0x200, 0x201, 0x1234] {
+            h.set_image_size(size);
+            assert_eq!(h.image_size(), size);
+        }
+    }
+
+    #[test]
+    fn test_plain_dos() {
+        let mut data = vec![0; 0x48];
+        let mut h = DosHeader::zeroed();
+        h.magic = 0x5A4D.into();
+        h.crlc = 2.into();
+        h.lfarlc = 0x1C.into();
+        h.lfanew = 0x1234.into();
+        data[..0x40].copy_from_slice(bytemuck::bytes_of(&h));
+        data[0x1C..0x24].copy_from_slice(b"\x03\x00\x00\x00\x10\x00\x01\x00");
+        assert_eq!(h.extended_signature(&data), None);
+        assert_eq!(h.relocations(&data), vec![(0, 3), (1, 0x10)]);
+
+        h.lfanew = 0x40.into();
+        data[0x40..0x42].copy_from_slice(b"NE");
+        assert_eq!(h.extended_signature(&data), Some(*b"NE"));
+    }
+
     #[test]
     fn test_dos_header_size() {
         assert_eq!(std::mem::size_of::<DosHeader>(), 0x40);
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use super::imports::ImportedSymbol;
+use super::relocation_table::{self, RelocationTarget};
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+use crate::x86::{self, intel, Flow};
+
+/// The assembler dialect of [`NeExecutable::describe_asm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsmSyntax {
+    Masm,
+    Nasm,
+}
+
+impl AsmSyntax {
+    fn byte(self, byte: u8) -> String {
+        match self {
+            AsmSyntax::Masm => format!("0{:02X}h", byte),
+            AsmSyntax::Nasm => format!("0x{:02X}", byte),
+        }
+    }
+
+    fn offset(self, symbol: &str) -> String {
+        match self {
+            AsmSyntax::Masm => format!("offset {}", symbol),
+            AsmSyntax::Nasm => symbol.to_owned(),
+        }
+    }
+
+    fn dialect(self) -> intel::Dialect {
+        match self {
+            AsmSyntax::Masm => intel::Dialect::Masm,
+            AsmSyntax::Nasm => intel::Dialect::Nasm,
+        }
+    }
+}
+
+/// How a fixup site is written out.
+enum Fixup {
+    /// A symbolic reference replacing `size` bytes.
+    Directive { size: usize, text: String },
+    /// Left as bytes, with an explanation.
+    Unsupported { comment: String },
+}
+
+impl NeExecutable {
+    /// Prints assembler source that reproduces the segments.
+    ///
+    /// Instructions are written in the assembler's syntax where it encodes
+    /// them back to the same bytes, and as `db` directives commented with
+    /// their disassembly otherwise, e.g. where a shorter form exists.
+    /// Near jumps and calls get labels at their targets.
+    /// Fixup sites become references to labels, segments and `extrn`
+    /// declarations for the imports, so that the linker recreates them.
+    pub(crate) fn describe_asm<W: Write>(
+        &self,
+        w: &mut W,
+        syntax: AsmSyntax,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let segment_name = |segment: usize| format!("SEG{}", segment + 1);
+
+        // Names of the locations referred to, by 0-based segment index and offset.
+        let mut labels = BTreeMap::<(usize, usize), String>::new();
+        let mut publics = BTreeSet::new();
+        for export in self.exports() {
+            if let (Some(name), Some(segment)) =
+                (&export.name, (export.segment as usize).checked_sub(1))
+            {
+                let name = sanitize(&codepage.decode(&name.name));
+                labels.insert((segment, export.offset as usize), name.clone());
+                publics.insert(name);
+            }
+        }
+        for function in self.functions() {
+            labels
+                .entry((function.segment, function.start))
+                .or_insert_with(|| function.display_name(codepage));
+        }
+        let mut label_at = |segment: usize, offset: usize| {
+            labels
+                .entry((segment, offset))
+                .or_insert_with(|| format!("loc_{:04X}_{:04X}", segment + 1, offset))
+                .clone()
+        };
+        let entry_point = self.ne_header.entry_point.value();
+        let start = ((entry_point >> 16) as usize)
+            .checked_sub(1)
+            .filter(|&segment| segment < self.segment_entries.len())
+            .map(|segment| (segment, (entry_point & 0xFFFF) as usize));
+        let start_label = start.map(|(segment, offset)| label_at(segment, offset));
+        // The decoded code, by 0-based segment index and offset.
+        let mut code = BTreeMap::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if !segment.is_code() {
+                continue;
+            }
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let insts = x86::decode_linear(&data, false)
+                .into_iter()
+                .map(|inst| (inst.pos, inst))
+                .collect::<BTreeMap<_, _>>();
+            for inst in insts.values() {
+                if let Flow::Jump(target) | Flow::Branch(target) | Flow::Call(target) = inst.flow()
+                {
+                    // A target inside another instruction can't be labelled.
+                    if insts.contains_key(&(target as usize)) {
+                        label_at(i, target as usize);
+                    }
+                }
+            }
+            code.insert(i, insts);
+        }
+
+        let mut externs = BTreeMap::<String, String>::new();
+        let mut fixups = BTreeMap::<(usize, usize), Fixup>::new();
+        // The symbols of the far pointers, for far calls and jumps.
+        let mut far_symbols = BTreeMap::<(usize, usize), String>::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let relocations = match &segment.relocations {
+                Some(relocations) => relocations,
+                None => continue,
+            };
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            for entry in &relocations.entries {
+                let is_segment = entry.source_type == relocation_table::SOURCE_SEGMENT;
+                let symbol = match entry.target {
+                    RelocationTarget::InternalFixed { segment, offset } => {
+                        (segment as usize).checked_sub(1).map(|segment| {
+                            if is_segment {
+                                segment_name(segment)
+                            } else {
+                                label_at(segment, offset as usize)
+                            }
+                        })
+                    }
+                    RelocationTarget::InternalMoveable { ordinal } => {
+                        self.entry_address(ordinal).map(|(segment, offset)| {
+                            if is_segment {
+                                segment_name(segment)
+                            } else {
+                                label_at(segment, offset)
+                            }
+                        })
+                    }
+                    RelocationTarget::OsFixup { .. } => None,
+                    target => {
+                        let name = self.describe_relocation_target(target, codepage);
+                        let symbol = match self.imported_symbol(target) {
+                            Some((module, ImportedSymbol::Ordinal(ordinal)))
+                                if self.imported_ordinal_name(module, ordinal).is_none() =>
+                            {
+                                sanitize(&name)
+                            }
+                            _ => sanitize(
+                                name.split_once('.').map_or(&name[..], |(_, symbol)| symbol),
+                            ),
+                        };
+                        externs.insert(symbol.clone(), name);
+                        Some(if is_segment {
+                            format!("seg {}", symbol)
+                        } else {
+                            symbol
+                        })
+                    }
+                };
+                for site in entry.sites(&data).unwrap_or_default() {
+                    let site = site as usize;
+                    let addend = if entry.is_additive() {
+                        data.get(site..site + 2)
+                            .map_or(0, |word| u16::from_le_bytes([word[0], word[1]]))
+                    } else {
+                        0
+                    };
+                    let fixup = match (&symbol, entry.source_type) {
+                        (None, _) => Fixup::Unsupported {
+                            comment: self.describe_relocation_target(entry.target, codepage),
+                        },
+                        (Some(symbol), _) if is_segment => Fixup::Directive {
+                            size: 2,
+                            text: format!("dw {}", symbol),
+                        },
+                        (Some(symbol), relocation_table::SOURCE_OFFSET) => Fixup::Directive {
+                            size: 2,
+                            text: format!("dw {}", syntax.offset(&with_addend(symbol, addend))),
+                        },
+                        (Some(symbol), relocation_table::SOURCE_OFFSET32) => Fixup::Directive {
+                            size: 4,
+                            text: format!("dd {}", syntax.offset(&with_addend(symbol, addend))),
+                        },
+                        (Some(symbol), relocation_table::SOURCE_FAR_ADDR) => {
+                            let symbol = with_addend(symbol, addend);
+                            far_symbols.insert((i, site), symbol.clone());
+                            let text = match syntax {
+                                AsmSyntax::Masm => format!("dd {}", symbol),
+                                AsmSyntax::Nasm => format!("dw {}, seg {}", symbol, symbol),
+                            };
+                            Fixup::Directive { size: 4, text }
+                        }
+                        (Some(symbol), source_type) => Fixup::Unsupported {
+                            comment: format!(
+                                "{} fixup to {}",
+                                relocation_table::source_type_name(source_type)
+                                    .unwrap_or("unknown"),
+                                symbol
+                            ),
+                        },
+                    };
+                    fixups.insert((i, site), fixup);
+                }
+            }
+        }
+
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        writeln!(
+            w,
+            "; Assembler source for {}, reconstructed by win16ne.",
+            module_name
+        )?;
+        writeln!(
+            w,
+            "; Instructions an assembler could encode differently are kept as bytes."
+        )?;
+        writeln!(w)?;
+        if syntax == AsmSyntax::Masm {
+            writeln!(w, "        .286")?;
+        }
+        for (symbol, name) in &externs {
+            match syntax {
+                AsmSyntax::Masm => writeln!(w, "        extrn {}:far ; {}", symbol, name)?,
+                AsmSyntax::Nasm => writeln!(w, "        extern {} ; {}", symbol, name)?,
+            }
+        }
+        for symbol in &publics {
+            match syntax {
+                AsmSyntax::Masm => writeln!(w, "        public {}", symbol)?,
+                AsmSyntax::Nasm => writeln!(w, "        global {}", symbol)?,
+            }
+        }
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let name = segment_name(i);
+            let class = if segment.is_code() { "CODE" } else { "DATA" };
+            writeln!(w)?;
+            match syntax {
+                AsmSyntax::Masm => {
+                    writeln!(w, "{}    segment para public '{}'", name, class)?;
+                    if segment.is_code() {
+                        writeln!(w, "        assume cs:{}", name)?;
+                    }
+                }
+                AsmSyntax::Nasm => writeln!(
+                    w,
+                    "        segment {} public class={} align=16 use16",
+                    name, class
+                )?,
+            }
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let alloc = (segment.min_alloc() as usize).max(data.len());
+            let (insts, symbols) = match code.remove(&i) {
+                Some(insts) => (insts, self.listing_symbols(i, segment, &data, codepage)),
+                None => Default::default(),
+            };
+
+            let mut pos = 0;
+            while pos < alloc {
+                if let Some(label) = labels.get(&(i, pos)) {
+                    match syntax {
+                        AsmSyntax::Masm if !segment.is_code() => {
+                            writeln!(w, "{} label byte", label)?
+                        }
+                        _ => writeln!(w, "{}:", label)?,
+                    }
+                }
+                if syntax == AsmSyntax::Nasm && start == Some((i, pos)) {
+                    writeln!(w, "..start:")?;
+                }
+                let next_label = labels
+                    .range((i, pos + 1)..(i + 1, 0))
+                    .next()
+                    .map_or(alloc, |(&(_, offset), _)| offset.min(alloc));
+                if pos >= data.len() {
+                    match syntax {
+                        AsmSyntax::Masm => writeln!(w, "        db {} dup (?)", next_label - pos)?,
+                        AsmSyntax::Nasm => writeln!(w, "        resb {}", next_label - pos)?,
+                    }
+                    pos = next_label;
+                    continue;
+                }
+
+                let inst = insts.range(..=pos).next_back().map(|(_, inst)| inst);
+                let text = inst
+                    .filter(|inst| inst.pos == pos)
+                    .map(|inst| x86::format_inst(inst, &symbols));
+                if let Some(Fixup::Directive {
+                    size,
+                    text: directive,
+                }) = fixups.get(&(i, pos))
+                {
+                    if pos + size <= data.len() {
+                        match text {
+                            Some(text) => writeln!(w, "        {:<39} ; {}", directive, text)?,
+                            None => writeln!(w, "        {}", directive)?,
+                        }
+                        pos += size;
+                        continue;
+                    }
+                }
+                let unsupported = match fixups.get(&(i, pos)) {
+                    Some(Fixup::Unsupported { comment }) => Some(comment),
+                    _ => None,
+                };
+
+                let next_fixup = fixups
+                    .range((i, pos + 1)..(i + 1, 0))
+                    .next()
+                    .map_or(usize::MAX, |(&(_, offset), _)| offset);
+                let label = |target: u32| labels.get(&(i, target as usize)).cloned();
+                let far_symbol = far_symbols.get(&(i, pos + 1));
+                let asm = inst
+                    .filter(|inst| inst.pos == pos && unsupported.is_none())
+                    .filter(|inst| inst.end() <= next_label.min(data.len()))
+                    .and_then(|inst| {
+                        let asm = match (inst.opcode, far_symbol) {
+                            // The fixup covers the far pointer operand.
+                            (0x9A | 0xEA, Some(symbol)) if inst.end() == pos + 5 => {
+                                let opname = if inst.opcode == 0x9A { "call" } else { "jmp" };
+                                match syntax {
+                                    AsmSyntax::Masm => format!("{} far ptr {}", opname, symbol),
+                                    AsmSyntax::Nasm => format!("{} far {}", opname, symbol),
+                                }
+                            }
+                            _ if inst.end() <= next_fixup => {
+                                intel::format(inst, syntax.dialect(), &label)?
+                            }
+                            _ => return None,
+                        };
+                        Some((inst.end(), asm))
+                    });
+                if let Some((end, asm)) = asm {
+                    writeln!(w, "        {}", asm)?;
+                    pos = end;
+                    continue;
+                }
+                let end = match inst {
+                    Some(inst) if inst.end() > pos => inst.end(),
+                    _ => pos + 16,
+                };
+                let end = end.min(next_label).min(next_fixup).min(data.len());
+                let bytes = data[pos..end]
+                    .iter()
+                    .map(|&byte| syntax.byte(byte))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let comment = text
+                    .into_iter()
+                    .chain(unsupported.cloned())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if comment.is_empty() {
+                    writeln!(w, "        db {}", bytes)?;
+                } else {
+                    writeln!(w, "        db {:<36} ; {}", bytes, comment)?;
+                }
+                pos = end;
+            }
+            if syntax == AsmSyntax::Masm {
+                writeln!(w, "{}    ends", name)?;
+            }
+        }
+
+        if syntax == AsmSyntax::Masm {
+            writeln!(w)?;
+            match &start_label {
+                Some(label) => writeln!(w, "        end {}", label)?,
+                None => writeln!(w, "        end")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn with_addend(symbol: &str, addend: u16) -> String {
+    if addend == 0 {
+        symbol.to_owned()
+    } else {
+        format!("{}+{}", symbol, addend)
+    }
+}
+
+/// Replaces the characters assemblers don't accept in identifiers.
+fn sanitize(name: &str) -> String {
+    let mut name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_?@$".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::RelocationEntry;
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    fn asm(exe: &NeExecutable, syntax: AsmSyntax) -> String {
+        let mut output = Vec::new();
+        exe.describe_asm(&mut output, syntax, Codepage::default())
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_describe_asm() {
+        let mut exe = sample_exe();
+        // xor ax, ax; jz +5; call far KERNEL.3; add ax, 0x100; retf
+        set_code(
+            &mut exe,
+            b"\x33\xC0\x74\x05\x9A\xFF\xFF\x00\x00\x05\x00\x01\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 5,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        exe.retarget(&"1:0005=KERNEL.3".parse().unwrap()).unwrap();
+        assert_eq!(
+            asm(&exe, AsmSyntax::Masm),
+            [
+                "; Assembler source for TEST, reconstructed by win16ne.",
+                "; Instructions an assembler could encode differently are kept as bytes.",
+                "",
+                "        .286",
+                "        extrn GETVERSION:far ; KERNEL.GETVERSION",
+                "",
+                "SEG1    segment para public 'CODE'",
+                "        assume cs:SEG1",
+                "sub_0001_0000:",
+                "        xor ax, ax",
+                "        jz short loc_0001_0009",
+                "        call far ptr GETVERSION",
+                "loc_0001_0009:",
+                "        add ax, 0100h",
+                "        retf",
+                "SEG1    ends",
+                "",
+                "        end sub_0001_0000",
+                "",
+            ]
+            .join("\n")
+        );
+        // NASM encodes `xor ax, ax` as `31 C0`.
+        assert!(asm(&exe, AsmSyntax::Nasm).ends_with(
+            &[
+                "..start:",
+                "        db 0x33, 0xC0                           ; xor %ax, %ax",
+                "        jz short loc_0001_0009",
+                "        call far GETVERSION",
+                "loc_0001_0009:",
+                "        add ax, 0x100",
+                "        retf",
+                "",
+            ]
+            .join("\n")
+        ));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("GETVERSION"), "GETVERSION");
+        assert_eq!(sanitize("WEP.1"), "WEP_1");
+        assert_eq!(sanitize("3"), "_3");
+    }
+}
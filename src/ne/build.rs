@@ -0,0 +1,452 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bytemuck::Zeroable;
+use serde::Deserialize;
+
+use super::entry_table::EntryTable;
+use super::exports::ExportSpec;
+use super::header::{NeFlags, NeHeader};
+use super::imported_name_table::ImportedNameTable;
+use super::module_reference_table::ModuleReferenceTable;
+use super::nonresident_name_table::{NonresidentNameEntry, NonresidentNameTable};
+use super::patch::NewTarget;
+use super::relocation_table::{self, RelocationEntry, RelocationTable};
+use super::resident_name_table::{ResidentNameEntry, ResidentNameTable};
+use super::resource_table::{
+    name_len, NeResource, NeResourceHeader, NeResourceTable, NeResourceTableHeader, NeResourceType,
+    NeResourceTypeHeader,
+};
+use super::segment_table::{NeSegment, NeSegmentHeader, SegmentFlags};
+use super::NeExecutable;
+use crate::mz::DosHeader;
+
+/// The description of an executable for [`NeExecutable::build`], read from JSON.
+///
+/// Addresses are `SEG:OFF` strings in hex, as on the command line; paths
+/// are relative to the description.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildSpec {
+    pub module_name: String,
+    /// The module description, the first nonresident name.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub library: bool,
+    /// The Windows version, `3.10` by default.
+    #[serde(default)]
+    pub expected_win_ver: Option<String>,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// The initial `SS:SP`.
+    #[serde(default)]
+    pub stack: Option<String>,
+    #[serde(default)]
+    pub auto_data_segment: u16,
+    #[serde(default)]
+    pub heap_size: u16,
+    #[serde(default)]
+    pub stack_size: u16,
+    #[serde(default)]
+    pub segments: Vec<SegmentSpec>,
+    /// Modules to reference in this order, before those the relocations add.
+    #[serde(default)]
+    pub modules: Vec<String>,
+    #[serde(default)]
+    pub exports: Vec<ExportEntrySpec>,
+    #[serde(default)]
+    pub resources: Vec<ResourceSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SegmentSpec {
+    /// The raw contents.
+    pub file: PathBuf,
+    /// Whether this is a data segment, as opposed to a code segment.
+    #[serde(default)]
+    pub data: bool,
+    #[serde(default)]
+    pub moveable: bool,
+    /// The size in memory, at least the size of the contents.
+    #[serde(default)]
+    pub min_alloc: Option<u32>,
+    #[serde(default)]
+    pub relocations: Vec<RelocationSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelocationSpec {
+    pub offset: u16,
+    /// `lobyte`, `segment`, `far_addr` or `offset`.
+    #[serde(rename = "type")]
+    pub source_type: String,
+    /// `MODULE.ORDINAL`, `MODULE.NAME` or `SEG:OFF`.
+    pub target: String,
+    /// Whether to add to the value at the location instead of following a chain.
+    /// Otherwise the location is set to `0xFFFF`, ending the chain there.
+    #[serde(default)]
+    pub additive: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportEntrySpec {
+    pub name: String,
+    #[serde(default)]
+    pub ordinal: Option<u16>,
+    pub address: String,
+    #[serde(default)]
+    pub resident: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceSpec {
+    #[serde(rename = "type")]
+    pub type_id: ResourceId,
+    pub id: ResourceId,
+    pub file: PathBuf,
+}
+
+/// A resource type or name: a number or a string.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceId {
+    Number(u16),
+    Name(String),
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn parse_address(address: &str) -> io::Result<(u16, u16)> {
+    let parse = |s: &str| u16::from_str_radix(s, 16).ok();
+    address
+        .split_once(':')
+        .and_then(|(segment, offset)| Some((parse(segment)?, parse(offset)?)))
+        .ok_or_else(|| invalid(format!("expected SEG:OFF, got {:?}", address)))
+}
+
+/// The DOS program of the stub: print the message and exit.
+const STUB_CODE: &[u8] = b"\x0E\x1F\xBA\x0E\x00\xB4\x09\xCD\x21\xB8\x01\x4C\xCD\x21";
+const STUB_MESSAGE: &[u8] = b"This program requires Microsoft Windows.\r\n$";
+
+/// Returns a DOS stub that prints that the program requires Windows.
+pub(crate) fn dos_stub() -> Vec<u8> {
+    let mut stub = vec![0; 0x40];
+    stub.extend_from_slice(STUB_CODE);
+    stub.extend_from_slice(STUB_MESSAGE);
+    stub.resize(stub.len().next_multiple_of(0x10), 0);
+    let len = stub.len() as u16;
+    let header = [
+        (0x00, 0x5A4D),
+        (0x02, len % 0x200),
+        (0x04, len.div_ceil(0x200)),
+        (0x08, 4),
+        (0x0C, 0xFFFF),
+        (0x10, 0xB8),
+        (0x18, 0x40),
+    ];
+    for (offset, value) in header {
+        stub[offset..offset + 2].copy_from_slice(&u16::to_le_bytes(value));
+    }
+    stub[0x3C..0x40].copy_from_slice(&(len as u32).to_le_bytes());
+    stub
+}
+
+impl NeExecutable {
+    /// Assembles an executable from a description, reading the files it
+    /// refers to relative to `base`. Write it with [`NeExecutable::write`].
+    pub fn build(spec: &BuildSpec, base: &Path) -> io::Result<Self> {
+        let dos_stub = dos_stub();
+        let mut ne_header = NeHeader::zeroed();
+        ne_header.magic = *b"NE";
+        ne_header.flags = if spec.library {
+            NeFlags::LIBRARY | NeFlags::SINGLEDATA
+        } else {
+            NeFlags::MULTIPLEDATA
+        }
+        .bits()
+        .into();
+        ne_header.target_os = 2;
+        ne_header.file_alignment_shift_count = 4.into();
+        ne_header.auto_data_segment_index = spec.auto_data_segment.into();
+        ne_header.init_heap_size = spec.heap_size.into();
+        ne_header.init_stack_size = spec.stack_size.into();
+        let version = spec.expected_win_ver.as_deref().unwrap_or("3.10");
+        let edit = format!("expected-win-ver={}", version)
+            .parse()
+            .map_err(invalid)?;
+        ne_header.apply(edit);
+        for (address, field) in [
+            (&spec.entry_point, &mut ne_header.entry_point),
+            (&spec.stack, &mut ne_header.init_stack),
+        ] {
+            if let Some(address) = address {
+                let (segment, offset) = parse_address(address)?;
+                *field = ((segment as u32) << 16 | offset as u32).into();
+            }
+        }
+
+        let mut segment_entries = Vec::new();
+        for segment in &spec.segments {
+            let data = std::fs::read(base.join(&segment.file))?;
+            if data.len() > 0x10000 {
+                return Err(invalid(format!(
+                    "{} is larger than a segment",
+                    segment.file.display()
+                )));
+            }
+            let min_alloc = segment.min_alloc.unwrap_or(0).max(data.len() as u32);
+            let mut flags = SegmentFlags::empty();
+            flags.set(SegmentFlags::DATA, segment.data);
+            flags.set(SegmentFlags::MOVEABLE, segment.moveable);
+            segment_entries.push(NeSegment {
+                header: NeSegmentHeader {
+                    data_offset_shifted: 0,
+                    data_length: data.len() as u16,
+                    flags,
+                    min_alloc: min_alloc as u16,
+                },
+                shift_count: 4,
+                data: Some(data),
+                relocations: None,
+            });
+        }
+
+        let mut exe = Self {
+            dos_header: Box::new(bytemuck::pod_read_unaligned::<DosHeader>(&dos_stub[..0x40])),
+            dos_stub,
+            ne_header: Box::new(ne_header),
+            segment_entries,
+            resource_table: NeResourceTable {
+                header: NeResourceTableHeader {
+                    alignment_shift_count: 4,
+                },
+                resource_types: Vec::new(),
+                names: BTreeMap::new(),
+            },
+            resident_name_table: ResidentNameTable {
+                entries: vec![ResidentNameEntry {
+                    name: spec.module_name.as_bytes().to_vec(),
+                    index: 0,
+                }],
+            },
+            module_reference_table: ModuleReferenceTable {
+                entries: Vec::new(),
+            },
+            imported_name_table: ImportedNameTable::default(),
+            entry_table: EntryTable {
+                entries: Vec::new(),
+                offsets: Vec::new(),
+            },
+            nonresident_name_table: NonresidentNameTable {
+                entries: vec![NonresidentNameEntry {
+                    name: spec
+                        .description
+                        .as_ref()
+                        .unwrap_or(&spec.module_name)
+                        .as_bytes()
+                        .to_vec(),
+                    index: 0,
+                }],
+            },
+            imported_module_exports: BTreeMap::new(),
+            debug_symbols: BTreeMap::new(),
+            segment_names: BTreeMap::new(),
+            file_data: Vec::new(),
+        };
+
+        for module in &spec.modules {
+            exe.module_reference(module.as_bytes())?;
+        }
+        // Exports come first, so that relocations can refer to moveable entry points.
+        for export in &spec.exports {
+            let (segment, offset) = parse_address(&export.address)?;
+            let segment =
+                u8::try_from(segment).map_err(|_| invalid(format!("no segment {}", segment)))?;
+            let export_spec = ExportSpec {
+                name: export.name.as_bytes().to_vec(),
+                ordinal: export.ordinal,
+                segment,
+                offset,
+            };
+            exe.add_export(&export_spec, export.resident)?;
+        }
+        for (index, segment) in spec.segments.iter().enumerate() {
+            let mut entries = Vec::new();
+            for relocation in &segment.relocations {
+                let source_type = match &relocation.source_type[..] {
+                    "lobyte" => relocation_table::SOURCE_LOBYTE,
+                    "segment" => relocation_table::SOURCE_SEGMENT,
+                    "far_addr" => relocation_table::SOURCE_FAR_ADDR,
+                    "offset" => relocation_table::SOURCE_OFFSET,
+                    other => {
+                        return Err(invalid(format!(
+                            "unknown relocation type {:?}; expected lobyte, segment, far_addr or offset",
+                            other
+                        )))
+                    }
+                };
+                let target = relocation.target.parse::<NewTarget>().map_err(invalid)?;
+                // The loader reads a chain link even for a single byte.
+                let size = if relocation.additive {
+                    relocation_table::source_size(source_type)
+                } else {
+                    relocation_table::source_size(source_type).max(2)
+                };
+                let site = relocation.offset as usize;
+                let data = exe.segment_entries[index].data.as_mut().unwrap();
+                let Some(bytes) = data.get_mut(site..site + size) else {
+                    return Err(invalid(format!(
+                        "relocation at 0x{:04X} is past the end of {}",
+                        site,
+                        segment.file.display()
+                    )));
+                };
+                if !relocation.additive {
+                    bytes[..2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+                }
+                entries.push(RelocationEntry {
+                    source_type,
+                    flags: if relocation.additive { 0x04 } else { 0 },
+                    offset: relocation.offset,
+                    target: exe.relocation_target(&target)?,
+                });
+            }
+            if !entries.is_empty() {
+                exe.segment_entries[index].relocations = Some(RelocationTable { entries });
+            }
+        }
+
+        exe.add_resources(&spec.resources, base)?;
+        Ok(exe)
+    }
+
+    /// Adds resources grouped by type, with the names after the type table.
+    fn add_resources(&mut self, resources: &[ResourceSpec], base: &Path) -> io::Result<()> {
+        let mut types = Vec::<(&ResourceId, Vec<&ResourceSpec>)>::new();
+        for resource in resources {
+            match types
+                .iter_mut()
+                .find(|(type_id, _)| **type_id == resource.type_id)
+            {
+                Some((_, group)) => group.push(resource),
+                None => types.push((&resource.type_id, vec![resource])),
+            }
+        }
+        let mut name_offset = 2
+            + types
+                .iter()
+                .map(|(_, group)| 8 + 12 * group.len())
+                .sum::<usize>()
+            + 2;
+        let table = &mut self.resource_table;
+        let mut id = |id: &ResourceId| -> io::Result<u16> {
+            match id {
+                ResourceId::Number(number) => Ok(0x8000 | number),
+                ResourceId::Name(name) => {
+                    let offset = u16::try_from(name_offset)
+                        .map_err(|_| invalid("resource table too large".to_owned()))?;
+                    name_offset += 1 + name_len(name.as_bytes())? as usize;
+                    table.names.insert(offset, name.as_bytes().to_vec());
+                    Ok(offset)
+                }
+            }
+        };
+        let mut resource_types = Vec::new();
+        for (type_id, group) in types {
+            let type_id = id(type_id)?;
+            let mut entries = Vec::new();
+            for resource in group {
+                entries.push(NeResource {
+                    header: NeResourceHeader {
+                        data_offset_shifted: 0,
+                        data_length: 0,
+                        // Moveable and pure.
+                        flags: 0x0030,
+                        resource_id: id(&resource.id)?,
+                        res: [0; 2],
+                    },
+                    data: std::fs::read(base.join(&resource.file))?,
+                });
+            }
+            resource_types.push(NeResourceType {
+                header: NeResourceTypeHeader {
+                    type_id,
+                    num_resources: entries.len() as u16,
+                    res: [0; 2],
+                },
+                resources: entries,
+            });
+        }
+        table.resource_types = resource_types;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let spec = serde_json::from_str::<BuildSpec>(
+            r#"{"module_name": "EMPTY", "library": true, "modules": ["KERNEL"]}"#,
+        )
+        .unwrap();
+        let exe = NeExecutable::build(&spec, Path::new("")).unwrap();
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write(&mut written).unwrap();
+        let written = written.into_inner();
+
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert_eq!(rewritten.module_name_entry(), Some(&b"EMPTY"[..]));
+        assert_eq!(rewritten.module_name(1), Some(&b"KERNEL"[..]));
+        assert!(rewritten.verify(written.len() as u64).is_empty());
+        assert_eq!(rewritten.ne_header.expected_win_ver, [10, 3]);
+    }
+
+    #[test]
+    fn test_build_relocations_and_resources() {
+        let dir = std::env::temp_dir().join(format!("win16ne-build-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // call far KERNEL.1; call far 0001:0000; retf
+        std::fs::write(dir.join("code.bin"), b"\x9A\0\0\0\0\x9A\0\0\0\0\xCB").unwrap();
+        std::fs::write(dir.join("data.bin"), b"Hello").unwrap();
+        let spec = serde_json::from_str::<BuildSpec>(
+            r#"{
+                "module_name": "APP",
+                "entry_point": "1:000A",
+                "segments": [
+                    {"file": "code.bin", "relocations": [
+                        {"offset": 1, "type": "far_addr", "target": "KERNEL.1"},
+                        {"offset": 6, "type": "far_addr", "target": "1:000A"}
+                    ]},
+                    {"file": "data.bin", "data": true}
+                ],
+                "resources": [{"type": 6, "id": "HELLO", "file": "data.bin"}]
+            }"#,
+        )
+        .unwrap();
+        let exe = NeExecutable::build(&spec, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let exe = exe.unwrap();
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write(&mut written).unwrap();
+        let written = written.into_inner();
+
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert_eq!(rewritten.verify(written.len() as u64), []);
+        let code = rewritten.segment_entries[0].data.as_ref().unwrap();
+        assert_eq!(&code[1..3], b"\xFF\xFF");
+        assert_eq!(&code[6..8], b"\xFF\xFF");
+        let resource = &rewritten.resource_table.resource_types[0].resources[0];
+        assert!(resource.data.starts_with(b"Hello"));
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::Cursor;
+
+use log::debug;
+
+use super::NeExecutable;
+use crate::mz::DosHeader;
+
+/// An executable found inside another file.
+#[derive(Debug, Clone)]
+pub struct CarvedImage {
+    /// The offset of the DOS header in the scanned file.
+    pub offset: usize,
+    /// The length of the image, up to [`NeExecutable::image_end`].
+    pub size: usize,
+    pub exe: NeExecutable,
+}
+
+impl CarvedImage {
+    /// Returns the bytes of the image in the scanned file.
+    pub fn data<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.size]
+    }
+}
+
+/// Scans a file of any kind for DOS headers followed by NE headers and reads
+/// the executables they begin.
+///
+/// The scan resumes after the end of each image found, so the executables
+/// stored inside another one aren't reported twice.
+pub fn carve(data: &[u8]) -> Vec<CarvedImage> {
+    let mut images = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = data[pos..].windows(2).position(|window| window == b"MZ") {
+        let offset = pos + found;
+        pos = offset + 1;
+        let blob = &data[offset..];
+        let Ok(dos_header) = DosHeader::read(&mut Cursor::new(blob)) else {
+            continue;
+        };
+        if dos_header.extended_signature(blob) != Some(*b"NE") {
+            continue;
+        }
+        match NeExecutable::read(&mut Cursor::new(blob)) {
+            Ok(exe) => {
+                let size = (exe.image_end() as usize).min(blob.len());
+                debug!("NE image of 0x{:X} bytes at 0x{:08X}", size, offset);
+                pos = offset + size.max(1);
+                images.push(CarvedImage { offset, size, exe });
+            }
+            Err(e) => debug!("no NE image at 0x{:08X}: {}", offset, e),
+        }
+    }
+    images
+}
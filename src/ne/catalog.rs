@@ -0,0 +1,261 @@
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, Connection};
+
+#[cfg(feature = "sqlite")]
+use super::imports::ImportedSymbol;
+#[cfg(feature = "sqlite")]
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    size INTEGER NOT NULL,
+    module_name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    type TEXT NOT NULL,
+    platform TEXT NOT NULL,
+    imphash TEXT
+);
+CREATE TABLE IF NOT EXISTS exports (
+    file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+    ordinal INTEGER NOT NULL,
+    name TEXT,
+    segment INTEGER NOT NULL,
+    offset INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS imports (
+    file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+    module TEXT NOT NULL,
+    ordinal INTEGER,
+    name TEXT,
+    uses INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS segments (
+    file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    offset INTEGER NOT NULL,
+    length INTEGER NOT NULL,
+    flags INTEGER NOT NULL,
+    min_alloc INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS resources (
+    file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+    type TEXT NOT NULL,
+    id TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    length INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS files_imphash ON files (imphash);
+CREATE INDEX IF NOT EXISTS imports_module ON imports (module);
+";
+
+#[cfg(feature = "sqlite")]
+fn to_io_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+/// A SQLite database with a row for each executable and its exports,
+/// imports, segments and resources in child tables.
+pub struct Catalog {
+    #[cfg(feature = "sqlite")]
+    connection: Connection,
+}
+
+#[cfg(not(feature = "sqlite"))]
+impl Catalog {
+    pub fn open(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "win16ne was built without the sqlite feature",
+        ))
+    }
+
+    pub fn add(
+        &mut self,
+        _path: &Path,
+        _exe: &NeExecutable,
+        _codepage: Codepage,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Catalog {
+    /// Opens or creates a catalog.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let connection = Connection::open(path).map_err(to_io_error)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|()| connection.execute_batch(SCHEMA))
+            .map_err(to_io_error)?;
+        Ok(Self { connection })
+    }
+
+    /// Adds an executable, replacing the rows of an earlier one at the same path.
+    pub fn add(&mut self, path: &Path, exe: &NeExecutable, codepage: Codepage) -> io::Result<()> {
+        self.insert(path, exe, codepage).map_err(to_io_error)
+    }
+
+    fn insert(
+        &mut self,
+        path: &Path,
+        exe: &NeExecutable,
+        codepage: Codepage,
+    ) -> rusqlite::Result<()> {
+        let summary = exe.summary();
+        let tx = self.connection.transaction()?;
+        let path = path.to_string_lossy();
+        tx.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+        tx.execute(
+            "INSERT INTO files (path, size, module_name, description, type, platform, imphash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                path,
+                exe.file_data.len() as u64,
+                codepage.decode(&summary.module_name),
+                codepage.decode(&summary.description),
+                summary.classification.to_string(),
+                summary.platform,
+                exe.imphash(),
+            ],
+        )?;
+        let file_id = tx.last_insert_rowid();
+
+        for export in exe.exports() {
+            tx.execute(
+                "INSERT INTO exports (file_id, ordinal, name, segment, offset)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    file_id,
+                    export.ordinal,
+                    export.name.map(|name| codepage.decode(&name.name)),
+                    export.segment,
+                    export.offset,
+                ],
+            )?;
+        }
+        for module in exe.imports() {
+            let module_name = codepage.decode(&module.name);
+            for import in &module.symbols {
+                let (ordinal, name) = match &import.symbol {
+                    ImportedSymbol::Ordinal(ordinal) => (Some(*ordinal), None),
+                    ImportedSymbol::Name(name) => (None, Some(codepage.decode(name))),
+                };
+                tx.execute(
+                    "INSERT INTO imports (file_id, module, ordinal, name, uses)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        file_id,
+                        module_name,
+                        ordinal,
+                        name,
+                        import.references as u64
+                    ],
+                )?;
+            }
+        }
+        for (i, segment) in exe.segment_entries.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO segments (file_id, number, offset, length, flags, min_alloc)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    file_id,
+                    i as u64 + 1,
+                    segment.data_offset(),
+                    segment.data_length(),
+                    segment.header.flags.bits(),
+                    segment.header.min_alloc,
+                ],
+            )?;
+        }
+        for resource_type in &exe.resource_table.resource_types {
+            let resource_name = |id, is_type| match exe.resource_table.names.get(&id) {
+                Some(name) if (id & 0x8000) == 0 => codepage.decode(name),
+                _ => describe_resource_id(id, is_type),
+            };
+            let type_name = resource_name(resource_type.header.type_id, true);
+            for resource in &resource_type.resources {
+                let (offset, length) = exe.resource_table.data_range(resource);
+                tx.execute(
+                    "INSERT INTO resources (file_id, type, id, offset, length)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        file_id,
+                        type_name,
+                        resource_name(resource.header.resource_id, false),
+                        offset,
+                        length,
+                    ],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::super::resident_name_table::ResidentNameEntry;
+    use super::super::tests::{rewrite, sample_exe, set_resources};
+    use super::*;
+
+    #[test]
+    fn test_catalog() {
+        let mut catalog = Catalog::open(Path::new(":memory:")).unwrap();
+        let codepage = Codepage::default();
+        let mut other = sample_exe();
+        other.resident_name_table.entries.push(ResidentNameEntry {
+            name: b"Func".to_vec(),
+            index: 1,
+        });
+        set_resources(&mut other, &[(0x8006, 0x8001, b"Hello")]);
+        let other = rewrite(&other);
+        catalog.add(Path::new("b.dll"), &other, codepage).unwrap();
+        catalog
+            .add(Path::new("a.exe"), &sample_exe(), codepage)
+            .unwrap();
+        // Adding a path again replaces its rows.
+        catalog.add(Path::new("b.dll"), &other, codepage).unwrap();
+
+        let query = |sql: &str| -> Vec<String> {
+            let mut statement = catalog.connection.prepare(sql).unwrap();
+            let rows = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .unwrap();
+            rows.map(Result::unwrap).collect()
+        };
+        assert_eq!(
+            query("SELECT path || ' ' || module_name || ' ' || size FROM files ORDER BY id"),
+            vec![
+                format!("a.exe TEST {}", sample_exe().file_data.len()),
+                format!("b.dll TEST {}", other.file_data.len()),
+            ]
+        );
+        assert_eq!(
+            query(
+                "SELECT path || ' ' || ordinal || ' ' || coalesce(name, '-')
+                 FROM exports JOIN files ON files.id = file_id ORDER BY path, ordinal"
+            ),
+            vec!["a.exe 1 -", "b.dll 1 Func"]
+        );
+        assert_eq!(
+            query(
+                "SELECT path || ' ' || number || ' ' || length
+                 FROM segments JOIN files ON files.id = file_id ORDER BY path, number"
+            ),
+            vec!["a.exe 1 5", "b.dll 1 5"]
+        );
+        assert_eq!(
+            query("SELECT type || ' ' || id || ' ' || length FROM resources"),
+            vec!["STRING 1 16"]
+        );
+    }
+}
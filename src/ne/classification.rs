@@ -0,0 +1,109 @@
+use std::fmt;
+
+use super::fonts::{RT_FONT, RT_FONTDIR};
+use super::header::NeFlags;
+use super::NeExecutable;
+
+/// Exports that mark a library as a device driver: installable drivers
+/// export `DriverProc`, GDI drivers `Enable` and `Disable`, keyboard and
+/// mouse drivers `Inquire`, and printer drivers `ExtDeviceMode`.
+const DRIVER_EXPORTS: [&[u8]; 5] = [
+    b"DRIVERPROC",
+    b"ENABLE",
+    b"INQUIRE",
+    b"EXTDEVICEMODE",
+    b"DEVICEMODE",
+];
+
+/// What kind of module an executable is, as told by
+/// [`NeExecutable::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleKind {
+    WindowsProgram,
+    WindowsLibrary,
+    DeviceDriver,
+    FontLibrary,
+    Os2Program,
+    Os2Library,
+    /// A program or library for another target OS, by its target OS byte.
+    Other {
+        library: bool,
+        target_os: u8,
+    },
+}
+
+/// The kind of module and whether it runs in protected mode only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Classification {
+    pub kind: ModuleKind,
+    pub protected_mode_only: bool,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ModuleKind::WindowsProgram => write!(f, "Windows EXE")?,
+            ModuleKind::WindowsLibrary => write!(f, "Windows DLL")?,
+            ModuleKind::DeviceDriver => write!(f, "device driver (.DRV)")?,
+            ModuleKind::FontLibrary => write!(f, "font library (.FON)")?,
+            ModuleKind::Os2Program => write!(f, "OS/2 program")?,
+            ModuleKind::Os2Library => write!(f, "OS/2 DLL")?,
+            ModuleKind::Other { library, target_os } => write!(
+                f,
+                "{} for target OS {}",
+                if library { "library" } else { "program" },
+                target_os
+            )?,
+        }
+        if self.protected_mode_only {
+            write!(f, ", protected mode only")?;
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    /// Classifies the module by the LIBRARY flag, the target OS and, for
+    /// Windows libraries, the resources and exports.
+    pub fn classify(&self) -> Classification {
+        let flags = self.ne_header.ne_flags();
+        let library = flags.contains(NeFlags::LIBRARY);
+        let kind = match (self.ne_header.target_os, library) {
+            (1, false) => ModuleKind::Os2Program,
+            (1, true) => ModuleKind::Os2Library,
+            // Windows 1.x and 2.x modules often leave the target OS unset.
+            (0 | 2 | 4, false) => ModuleKind::WindowsProgram,
+            (0 | 2 | 4, true) => self.classify_library(),
+            (target_os, library) => ModuleKind::Other { library, target_os },
+        };
+        Classification {
+            kind,
+            protected_mode_only: flags.contains(NeFlags::PROTECTED_MODE_ONLY),
+        }
+    }
+
+    fn classify_library(&self) -> ModuleKind {
+        let names = self
+            .exports()
+            .into_iter()
+            .filter_map(|export| export.name)
+            .map(|name| name.name.to_ascii_uppercase())
+            .collect::<Vec<_>>();
+        let has_fonts = self
+            .resource_table
+            .resource_types
+            .iter()
+            .any(|resource_type| matches!(resource_type.header.type_id, RT_FONTDIR | RT_FONT));
+        // Font libraries export nothing but perhaps WEP.
+        if has_fonts && names.iter().all(|name| name == b"WEP") {
+            ModuleKind::FontLibrary
+        } else if names
+            .iter()
+            .any(|name| DRIVER_EXPORTS.contains(&name.as_slice()))
+        {
+            ModuleKind::DeviceDriver
+        } else {
+            ModuleKind::WindowsLibrary
+        }
+    }
+}
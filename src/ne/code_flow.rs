@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::entry_table::SegmentEntry;
+use super::relocation_table::{RelocationTarget, SOURCE_FAR_ADDR};
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+use crate::x86::{self, Flow, Inst};
+
+/// A function found by [`NeExecutable::functions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// The 0-based segment index.
+    pub segment: usize,
+    pub start: usize,
+    pub end: usize,
+    /// The exported name, if any.
+    pub name: Option<Vec<u8>>,
+}
+
+impl Function {
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns the exported name or a name made up from the address, e.g. `sub_0001_0010`.
+    pub fn display_name(&self, codepage: Codepage) -> String {
+        match &self.name {
+            Some(name) => codepage.decode(name),
+            None => format!("sub_{:04X}_{:04X}", self.segment + 1, self.start),
+        }
+    }
+}
+
+struct Traversal {
+    instructions: BTreeMap<usize, Vec<Inst>>,
+    /// The targets of near and far calls.
+    call_targets: BTreeSet<(usize, usize)>,
+}
+
+impl NeExecutable {
+    /// Lists the code segments' entry points: the program entry point (CS:IP),
+    /// the entry table and the loader table of a self-loading application,
+    /// as pairs of 0-based segment index and offset.
+    pub fn code_entry_points(&self) -> BTreeSet<(usize, usize)> {
+        let mut entry_points = BTreeSet::new();
+        let entry_point = self.ne_header.entry_point.value();
+        if let Some(segment) = ((entry_point >> 16) as usize).checked_sub(1) {
+            entry_points.insert((segment, (entry_point & 0xFFFF) as usize));
+        }
+        for entry in &self.entry_table.entries {
+            let (segment, offset) = match entry {
+                SegmentEntry::Unused => continue,
+                SegmentEntry::Fixed(entry) => (entry.segment, entry.offset),
+                SegmentEntry::Moveable(entry) => (entry.segment, entry.offset),
+            };
+            if let Some(segment) = (segment as usize).checked_sub(1) {
+                entry_points.insert((segment, offset as usize));
+            }
+        }
+        // The loader of a self-loading application is called through its table.
+        for procedure in self.loader_procedures().unwrap_or_default() {
+            entry_points.extend(procedure.target);
+        }
+        entry_points.retain(|&(segment, _)| {
+            self.segment_entries
+                .get(segment)
+                .is_some_and(|segment| segment.is_code())
+        });
+        entry_points
+    }
+
+    /// Returns the 0-based segment index and offset of an entry point.
+    pub fn entry_address(&self, ordinal: u16) -> Option<(usize, usize)> {
+        let (segment, offset) = match self
+            .entry_table
+            .entries
+            .get((ordinal as usize).checked_sub(1)?)?
+        {
+            SegmentEntry::Unused => return None,
+            SegmentEntry::Fixed(entry) => (entry.segment, entry.offset),
+            SegmentEntry::Moveable(entry) => (entry.segment, entry.offset),
+        };
+        Some(((segment as usize).checked_sub(1)?, offset as usize))
+    }
+
+    /// Disassembles only the code reachable from the entry points, following
+    /// jumps and calls, including far ones resolved through relocations.
+    ///
+    /// Returns the instructions of each code segment (keyed by 0-based index),
+    /// sorted by offset.
+    pub fn reachable_instructions(&self) -> BTreeMap<usize, Vec<Inst>> {
+        self.traverse().instructions
+    }
+
+    fn traverse(&self) -> Traversal {
+        let mut code = BTreeMap::new();
+        let mut far_targets = BTreeMap::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if !segment.is_code() {
+                continue;
+            }
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            far_targets.insert(i, self.internal_far_targets(i, &data));
+            code.insert(i, data);
+        }
+
+        let mut visited = BTreeMap::<usize, BTreeMap<usize, Inst>>::new();
+        let mut call_targets = BTreeSet::new();
+        let mut pending = self.code_entry_points().into_iter().collect::<Vec<_>>();
+        while let Some((segment, pos)) = pending.pop() {
+            let data = match code.get(&segment) {
+                Some(data) => data,
+                None => continue,
+            };
+            let insts = visited.entry(segment).or_default();
+            let mut pos = pos;
+            while pos < data.len() && !insts.contains_key(&pos) {
+                let inst = x86::decode(data, pos, false);
+                insts.insert(pos, inst);
+                let far_target = || {
+                    let far_pos = pos + inst.far_pointer_pos()?;
+                    far_targets[&segment].get(&far_pos).copied()
+                };
+                match inst.flow() {
+                    Flow::Next => {}
+                    Flow::Branch(target) => pending.push((segment, target as usize)),
+                    Flow::Call(target) => {
+                        pending.push((segment, target as usize));
+                        call_targets.insert((segment, target as usize));
+                    }
+                    Flow::Jump(target) => {
+                        pending.push((segment, target as usize));
+                        break;
+                    }
+                    Flow::FarCall => {
+                        pending.extend(far_target());
+                        call_targets.extend(far_target());
+                    }
+                    Flow::FarJump => {
+                        pending.extend(far_target());
+                        break;
+                    }
+                    Flow::Stop => break,
+                }
+                pos = inst.end();
+            }
+        }
+        // Far calls may go to segments that aren't code, or don't exist.
+        call_targets.retain(|(segment, _)| code.contains_key(segment));
+        Traversal {
+            instructions: visited
+                .into_iter()
+                .map(|(segment, insts)| (segment, insts.into_values().collect()))
+                .collect(),
+            call_targets,
+        }
+    }
+
+    /// Splits the code segments into functions.
+    ///
+    /// A function starts at an entry point, at the target of a call, or at a
+    /// standard prologue (`push bp; mov bp, sp`, optionally preceded by the
+    /// Windows far function prologue), and extends to the next function.
+    pub fn functions(&self) -> Vec<Function> {
+        let exports = self.exports();
+        let mut starts = self.code_entry_points();
+        starts.extend(self.traverse().call_targets);
+        let code = self
+            .segment_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.is_code())
+            .filter_map(|(i, segment)| Some((i, segment.expanded_data().ok()??)))
+            .collect::<BTreeMap<_, _>>();
+        for (&i, data) in &code {
+            // Don't split a far prologue at its `push bp`.
+            let mut prologue_end = 0;
+            for inst in x86::decode_linear(data, false) {
+                if inst.pos < prologue_end {
+                    continue;
+                }
+                if let Some(len) = prologue_length(&data[inst.pos..]) {
+                    starts.insert((i, inst.pos));
+                    prologue_end = inst.pos + len;
+                }
+            }
+        }
+
+        let starts = starts.into_iter().collect::<Vec<_>>();
+        starts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &(segment, start))| {
+                let length = code.get(&segment)?.len();
+                if start >= length {
+                    return None;
+                }
+                let end = match starts.get(index + 1) {
+                    Some(&(next_segment, next_start)) if next_segment == segment => next_start,
+                    _ => length,
+                };
+                let name = exports
+                    .iter()
+                    .find(|export| {
+                        export.segment as usize == segment + 1 && export.offset as usize == start
+                    })
+                    .and_then(|export| Some(export.name.as_ref()?.name.clone()));
+                Some(Function {
+                    segment,
+                    start,
+                    end,
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    /// Maps the far pointer fixups of a segment that refer to this module's
+    /// own code to the 0-based segment index and offset.
+    pub(crate) fn internal_far_targets(
+        &self,
+        segment: usize,
+        data: &[u8],
+    ) -> BTreeMap<usize, (usize, usize)> {
+        let mut targets = BTreeMap::new();
+        let relocations = match &self.segment_entries[segment].relocations {
+            Some(relocations) => relocations,
+            None => return targets,
+        };
+        for entry in &relocations.entries {
+            if entry.source_type != SOURCE_FAR_ADDR || entry.is_additive() {
+                continue;
+            }
+            let target = match entry.target {
+                RelocationTarget::InternalFixed { segment, offset } => (segment as usize)
+                    .checked_sub(1)
+                    .map(|s| (s, offset as usize)),
+                RelocationTarget::InternalMoveable { ordinal } => self.entry_address(ordinal),
+                _ => None,
+            };
+            if let Some(target) = target {
+                for site in entry.sites(data).unwrap_or_default() {
+                    targets.insert(site as usize, target);
+                }
+            }
+        }
+        targets
+    }
+}
+
+/// Returns the length of the function prologue at the start of the code, if any.
+fn prologue_length(code: &[u8]) -> Option<usize> {
+    const PROLOGUES: [&[u8]; 3] = [
+        // push ds; pop ax; nop; inc bp; push bp; mov bp, sp
+        b"\x1E\x58\x90\x45\x55\x8B\xEC",
+        // mov ax, ds; nop; inc bp; push bp; mov bp, sp
+        b"\x8C\xD8\x90\x45\x55\x8B\xEC",
+        // push bp; mov bp, sp
+        b"\x55\x8B\xEC",
+    ];
+    PROLOGUES
+        .iter()
+        .find(|prologue| code.starts_with(prologue))
+        .map(|prologue| prologue.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::RelocationEntry;
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    #[test]
+    fn test_prologue_length() {
+        assert_eq!(
+            prologue_length(b"\x1E\x58\x90\x45\x55\x8B\xEC\x1E"),
+            Some(7)
+        );
+        assert_eq!(prologue_length(b"\x55\x8B\xEC\x83\xEC\x04"), Some(3));
+        assert_eq!(prologue_length(b"\x55\x8B\xE5"), None);
+    }
+
+    #[test]
+    fn test_functions_far_call_outside() {
+        let mut exe = sample_exe();
+        // call far 0005:0010; retf
+        set_code(
+            &mut exe,
+            b"\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 5,
+                    offset: 0x10,
+                },
+            }],
+        );
+        let functions = exe.functions();
+        assert_eq!(
+            functions,
+            [Function {
+                segment: 0,
+                start: 0,
+                end: 6,
+                name: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::ops::Range;
+
+use super::code_flow::Function;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+use crate::x86::{self, Flow, Inst};
+
+/// A straight-line sequence of instructions.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub instructions: Vec<Inst>,
+    /// The start offsets of the blocks execution may continue with.
+    pub successors: Vec<usize>,
+}
+
+impl BasicBlock {
+    pub fn start(&self) -> usize {
+        self.instructions[0].pos
+    }
+}
+
+/// The basic blocks of a function, keyed by start offset.
+///
+/// Jumps leaving the function (e.g. tail calls) don't make edges.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+}
+
+impl NeExecutable {
+    pub fn control_flow_graph(&self, function: &Function) -> ControlFlowGraph {
+        let data = self.segment_entries[function.segment]
+            .expanded_data()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let range = function.start..function.end.min(data.len());
+
+        ControlFlowGraph {
+            blocks: basic_blocks(&data, range),
+        }
+    }
+
+    /// Prints the control flow graphs of the functions in Graphviz DOT.
+    ///
+    /// With `name`, only the function of that name is printed; it's a
+    /// [`io::ErrorKind::NotFound`] error if there's none.
+    pub(crate) fn describe_cfg_dot<W: Write>(
+        &self,
+        w: &mut W,
+        name: Option<&str>,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let functions = self
+            .functions()
+            .into_iter()
+            .filter(|function| {
+                name.is_none_or(|name| function.display_name(codepage).eq_ignore_ascii_case(name))
+            })
+            .collect::<Vec<_>>();
+        if functions.is_empty() {
+            if let Some(name) = name {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no function named {}", name),
+                ));
+            }
+            return Ok(());
+        }
+
+        writeln!(w, "digraph cfg {{")?;
+        writeln!(w, "    node [shape=box, fontname=monospace];")?;
+        for function in &functions {
+            let segment = &self.segment_entries[function.segment];
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let symbols = self.listing_symbols(function.segment, segment, &data, codepage);
+            let function_name = function.display_name(codepage);
+            let node = |offset: usize| format!("\"{:04X}:{:04X}\"", function.segment + 1, offset);
+            writeln!(w, "    subgraph \"cluster_{}\" {{", escape(&function_name))?;
+            writeln!(w, "        label=\"{}\";", escape(&function_name))?;
+            let cfg = self.control_flow_graph(function);
+            for (&start, block) in &cfg.blocks {
+                let mut label = format!("{:04X}:{:04X}\\l", function.segment + 1, start);
+                for inst in &block.instructions {
+                    label.push_str(&escape(&x86::format_inst(inst, &symbols)));
+                    label.push_str("\\l");
+                }
+                writeln!(w, "        {} [label=\"{}\"];", node(start), label)?;
+            }
+            for (&start, block) in &cfg.blocks {
+                for &successor in &block.successors {
+                    writeln!(w, "        {} -> {};", node(start), node(successor))?;
+                }
+            }
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+}
+
+/// Splits the code reachable from `range.start` within `range` into basic blocks.
+fn basic_blocks(data: &[u8], range: Range<usize>) -> BTreeMap<usize, BasicBlock> {
+    let mut insts = BTreeMap::<usize, Inst>::new();
+    let mut leaders = BTreeSet::from([range.start]);
+    let mut pending = vec![range.start];
+    while let Some(mut pos) = pending.pop() {
+        while range.contains(&pos) && !insts.contains_key(&pos) {
+            let inst = x86::decode(data, pos, false);
+            insts.insert(pos, inst);
+            let targets = match inst.flow() {
+                Flow::Next | Flow::Call(_) | Flow::FarCall => {
+                    pos = inst.end();
+                    continue;
+                }
+                Flow::Branch(target) => vec![target as usize, inst.end()],
+                Flow::Jump(target) => vec![target as usize],
+                Flow::FarJump | Flow::Stop => vec![],
+            };
+            for target in targets.into_iter().filter(|t| range.contains(t)) {
+                leaders.insert(target);
+                pending.push(target);
+            }
+            break;
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    let mut current: Option<BasicBlock> = None;
+    for (&pos, &inst) in &insts {
+        let contiguous = current
+            .as_ref()
+            .is_some_and(|block| block.instructions.last().unwrap().end() == pos);
+        if leaders.contains(&pos) || !contiguous {
+            if let Some(block) = current.take() {
+                blocks.insert(block.start(), block);
+            }
+            current = Some(BasicBlock {
+                instructions: Vec::new(),
+                successors: Vec::new(),
+            });
+        }
+        current.as_mut().unwrap().instructions.push(inst);
+    }
+    if let Some(block) = current {
+        blocks.insert(block.start(), block);
+    }
+
+    for block in blocks.values_mut() {
+        let last = block.instructions.last().unwrap();
+        let successors = match last.flow() {
+            Flow::Next | Flow::Call(_) | Flow::FarCall => vec![last.end()],
+            Flow::Branch(target) => vec![target as usize, last.end()],
+            Flow::Jump(target) => vec![target as usize],
+            Flow::FarJump | Flow::Stop => vec![],
+        };
+        block.successors = successors
+            .into_iter()
+            .filter(|target| insts.contains_key(target))
+            .collect();
+    }
+    blocks
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_blocks() {
+        // 0: test %ax, %ax; 2: je 7; 4: inc %ax; 5: jmp 8; 7: dec %ax; 8: ret
+        let code = b"\x85\xC0\x74\x03\x40\xEB\x01\x48\xC3";
+        let blocks = basic_blocks(code, 0..code.len());
+        let edges = blocks
+            .iter()
+            .map(|(&start, block)| (start, block.successors.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![(0, vec![7, 4]), (4, vec![8]), (7, vec![8]), (8, vec![])]
+        );
+    }
+}
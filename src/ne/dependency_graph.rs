@@ -0,0 +1,90 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// Which modules refer to which, collected over a set of executables.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// The references of each given module, keyed by uppercase module name.
+    modules: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the module and its references to the graph.
+    ///
+    /// `fallback_name` is used when the module has no name, e.g. the file stem.
+    pub fn add(&mut self, exe: &NeExecutable, fallback_name: &[u8]) {
+        let name = exe
+            .module_name_entry()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(fallback_name)
+            .to_ascii_uppercase();
+        let references = self.modules.entry(name).or_default();
+        for entry in &exe.module_reference_table.entries {
+            references.insert(entry.name.to_ascii_uppercase());
+        }
+    }
+
+    /// Lists the modules referred to but not given, in name order.
+    pub fn missing_modules(&self) -> Vec<&[u8]> {
+        self.modules
+            .values()
+            .flatten()
+            .filter(|name| !self.modules.contains_key(*name))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| &name[..])
+            .collect()
+    }
+
+    /// Prints the graph in Graphviz DOT. Missing modules are drawn in red.
+    pub(crate) fn describe_dot<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        let quote = |name: &[u8]| {
+            let name = codepage.decode(name);
+            format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        writeln!(w, "digraph dependencies {{")?;
+        writeln!(w, "    node [shape=box];")?;
+        for name in self.modules.keys() {
+            writeln!(w, "    {};", quote(name))?;
+        }
+        for name in self.missing_modules() {
+            writeln!(
+                w,
+                "    {} [color=red, fontcolor=red, style=dashed];",
+                quote(name)
+            )?;
+        }
+        for (name, references) in &self.modules {
+            for reference in references {
+                writeln!(w, "    {} -> {};", quote(name), quote(reference))?;
+            }
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_modules() {
+        let mut graph = DependencyGraph::new();
+        graph.modules.insert(
+            b"APP".to_vec(),
+            [b"MYLIB".to_vec(), b"KERNEL".to_vec()].into(),
+        );
+        graph
+            .modules
+            .insert(b"MYLIB".to_vec(), [b"KERNEL".to_vec()].into());
+        assert_eq!(graph.missing_modules(), vec![&b"KERNEL"[..]]);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::util::bytes::{cstr_at, u16_at, u32_at};
+
+/// The integer ID of the dialog resource type.
+pub const RT_DIALOG: u16 = 0x8005;
+
+/// The dialog template has a font size and face name.
+pub const DS_SETFONT: u32 = 0x0040;
+
+/// A string, or a resource ordinal written as 0xFF and a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameOrOrdinal {
+    Name(Vec<u8>),
+    Ordinal(u16),
+}
+
+impl NameOrOrdinal {
+    fn read(data: &[u8], pos: &mut usize) -> Option<Self> {
+        if *data.get(*pos)? == 0xFF {
+            let ordinal = u16_at(data, *pos + 1)?;
+            *pos += 3;
+            return Some(Self::Ordinal(ordinal));
+        }
+        let name = cstr_at(data, *pos)?.to_vec();
+        *pos += name.len() + 1;
+        Some(Self::Name(name))
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        match self {
+            Self::Name(name) => {
+                data.extend_from_slice(name);
+                data.push(0);
+            }
+            Self::Ordinal(ordinal) => {
+                data.push(0xFF);
+                data.extend_from_slice(&ordinal.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// The class of a dialog control: a predefined one, numbered from 0x80
+/// (button, edit, static, list box, scroll bar, combo box), or a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlClass {
+    Predefined(u8),
+    Name(Vec<u8>),
+}
+
+/// A control of a dialog template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogControl {
+    pub x: u16,
+    pub y: u16,
+    pub cx: u16,
+    pub cy: u16,
+    pub id: u16,
+    pub style: u32,
+    pub class: ControlClass,
+    /// The text, or the ordinal of an icon for static controls.
+    pub text: NameOrOrdinal,
+    /// The creation data passed to the control.
+    pub extra: Vec<u8>,
+}
+
+/// A `DIALOG` resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogTemplate {
+    pub style: u32,
+    pub x: u16,
+    pub y: u16,
+    pub cx: u16,
+    pub cy: u16,
+    /// The menu, or an empty name for none.
+    pub menu: NameOrOrdinal,
+    /// The window class, empty for the default dialog class.
+    pub class: Vec<u8>,
+    pub caption: Vec<u8>,
+    /// The point size and face name, with [`DS_SETFONT`].
+    pub font: Option<(u16, Vec<u8>)>,
+    pub controls: Vec<DialogControl>,
+}
+
+/// Reads a `DIALOG` resource in the 16-bit format: the window style, the
+/// number of controls, the position and size, the menu, class, caption and
+/// optionally the font, then the controls.
+pub fn read_dialog(data: &[u8]) -> Option<DialogTemplate> {
+    let style = u32_at(data, 0)?;
+    let count = *data.get(4)?;
+    let word = |pos: usize| u16_at(data, pos);
+    let (x, y, cx, cy) = (word(5)?, word(7)?, word(9)?, word(11)?);
+    let mut pos = 13;
+    // The menu is an empty string, 0xFF and an ordinal or a name.
+    let menu = NameOrOrdinal::read(data, &mut pos)?;
+    let class = cstr_at(data, pos)?.to_vec();
+    pos += class.len() + 1;
+    let caption = cstr_at(data, pos)?.to_vec();
+    pos += caption.len() + 1;
+    let font = if style & DS_SETFONT != 0 {
+        let size = word(pos)?;
+        let face = cstr_at(data, pos + 2)?.to_vec();
+        pos += 2 + face.len() + 1;
+        Some((size, face))
+    } else {
+        None
+    };
+    let mut controls = Vec::new();
+    for _ in 0..count {
+        let (x, y, cx, cy, id) = (
+            word(pos)?,
+            word(pos + 2)?,
+            word(pos + 4)?,
+            word(pos + 6)?,
+            word(pos + 8)?,
+        );
+        let style = u32_at(data, pos + 10)?;
+        pos += 14;
+        let class = if *data.get(pos)? & 0x80 != 0 {
+            pos += 1;
+            ControlClass::Predefined(data[pos - 1])
+        } else {
+            let name = cstr_at(data, pos)?.to_vec();
+            pos += name.len() + 1;
+            ControlClass::Name(name)
+        };
+        let text = NameOrOrdinal::read(data, &mut pos)?;
+        let extra_len = *data.get(pos)? as usize;
+        let extra = data.get(pos + 1..pos + 1 + extra_len)?.to_vec();
+        pos += 1 + extra_len;
+        controls.push(DialogControl {
+            x,
+            y,
+            cx,
+            cy,
+            id,
+            style,
+            class,
+            text,
+            extra,
+        });
+    }
+    Some(DialogTemplate {
+        style,
+        x,
+        y,
+        cx,
+        cy,
+        menu,
+        class,
+        caption,
+        font,
+        controls,
+    })
+}
+
+/// Writes a `DIALOG` resource. There are at most 255 controls, and at most
+/// 255 bytes of creation data for each.
+pub fn write_dialog(dialog: &DialogTemplate) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&dialog.style.to_le_bytes());
+    data.push(dialog.controls.len() as u8);
+    for value in [dialog.x, dialog.y, dialog.cx, dialog.cy] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    dialog.menu.write(&mut data);
+    for string in [&dialog.class, &dialog.caption] {
+        data.extend_from_slice(string);
+        data.push(0);
+    }
+    if let Some((size, face)) = &dialog.font {
+        data.extend_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(face);
+        data.push(0);
+    }
+    for control in &dialog.controls {
+        for value in [control.x, control.y, control.cx, control.cy, control.id] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&control.style.to_le_bytes());
+        match &control.class {
+            ControlClass::Predefined(class) => data.push(*class),
+            ControlClass::Name(name) => {
+                data.extend_from_slice(name);
+                data.push(0);
+            }
+        }
+        control.text.write(&mut data);
+        data.push(control.extra.len() as u8);
+        data.extend_from_slice(&control.extra);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialog() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x80C8_0040u32.to_le_bytes());
+        data.push(2);
+        data.extend_from_slice(b"\x0A\x00\x0A\x00\xC8\x00\x64\x00");
+        data.extend_from_slice(b"\x00\x00About\x00\x08\x00Helv\x00");
+        data.extend_from_slice(b"\x05\x00\x05\x00\x20\x00\x20\x00\xFF\xFF");
+        data.extend_from_slice(&0x5000_0003u32.to_le_bytes());
+        data.extend_from_slice(b"\x82\xFF\x01\x00\x00");
+        data.extend_from_slice(b"\x50\x00\x50\x00\x28\x00\x0E\x00\x01\x00");
+        data.extend_from_slice(&0x5001_0001u32.to_le_bytes());
+        data.extend_from_slice(b"\x80OK\x00\x00");
+        let dialog = read_dialog(&data).unwrap();
+        assert_eq!(dialog.caption, b"About");
+        assert_eq!(dialog.font, Some((8, b"Helv".to_vec())));
+        assert_eq!(dialog.controls[0].text, NameOrOrdinal::Ordinal(1));
+        assert_eq!(dialog.controls[1].class, ControlClass::Predefined(0x80));
+        assert_eq!(dialog.controls[1].text, NameOrOrdinal::Name(b"OK".to_vec()));
+        assert_eq!(write_dialog(&dialog), data);
+    }
+}
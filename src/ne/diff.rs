@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use super::hex_headers::decode_field;
+use super::resource_table::describe_resource_id;
+use super::segment_table::NeSegment;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// A difference between two executables, described in their terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Only in the second executable.
+    Added(String),
+    /// Only in the first executable.
+    Removed(String),
+    /// In both, but different.
+    Changed(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(what) => write!(f, "+ {}", what),
+            Change::Removed(what) => write!(f, "- {}", what),
+            Change::Changed(what) => write!(f, "~ {}", what),
+        }
+    }
+}
+
+/// The changes to one part of the executable.
+#[derive(Debug, Clone)]
+pub struct DiffSection {
+    pub title: &'static str,
+    pub changes: Vec<Change>,
+}
+
+/// Compares two maps, reporting the keys in only one of them and the
+/// values that differ.
+fn diff_maps<K: Ord, V: PartialEq>(
+    old: &BTreeMap<K, V>,
+    new: &BTreeMap<K, V>,
+    describe: impl Fn(&K, &V) -> String,
+) -> Vec<Change> {
+    let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+    keys.into_iter()
+        .filter_map(|key| match (old.get(key), new.get(key)) {
+            (Some(old), None) => Some(Change::Removed(describe(key, old))),
+            (None, Some(new)) => Some(Change::Added(describe(key, new))),
+            (Some(old), Some(new)) if old != new => Some(Change::Changed(format!(
+                "{} -> {}",
+                describe(key, old),
+                describe(key, new)
+            ))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Describes how the bytes of `new` differ from `old`, if they do.
+fn diff_bytes(old: &[u8], new: &[u8]) -> Option<String> {
+    let differing =
+        old.iter().zip(new).filter(|(a, b)| a != b).count() + old.len().abs_diff(new.len());
+    let first = old
+        .iter()
+        .zip(new)
+        .position(|(a, b)| a != b)
+        .unwrap_or(old.len().min(new.len()));
+    (differing != 0).then(|| format!("{} bytes differ, first at 0x{:04X}", differing, first))
+}
+
+impl NeExecutable {
+    fn resource_data(&self, codepage: Codepage) -> BTreeMap<(String, String), &[u8]> {
+        let resource_table = &self.resource_table;
+        let resource_name = |id, is_type| match resource_table.names.get(&id) {
+            Some(name) if (id & 0x8000) == 0 => codepage.decode(name),
+            _ => describe_resource_id(id, is_type),
+        };
+        resource_table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| {
+                let type_name = resource_name(resource_type.header.type_id, true);
+                resource_type.resources.iter().map(move |resource| {
+                    (
+                        (
+                            type_name.clone(),
+                            resource_name(resource.header.resource_id, false),
+                        ),
+                        &resource.data[..],
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Compares this executable with a later version of it: the NE header
+    /// fields, the segments, the exports, the imports and the resources.
+    pub fn diff(&self, other: &NeExecutable, codepage: Codepage) -> Vec<DiffSection> {
+        let mut header = Vec::new();
+        if self.dos_stub.get(0x40..) != other.dos_stub.get(0x40..) {
+            header.push(Change::Changed("DOS stub".to_owned()));
+        }
+        let header_fields = |exe: &NeExecutable| {
+            exe.spans()
+                .into_iter()
+                .filter(|field| field.path.starts_with("ne_header."))
+                .map(|field| {
+                    let range = field.span.range();
+                    let bytes = exe
+                        .file_data
+                        .get(range.start as usize..range.end as usize)
+                        .unwrap_or_default()
+                        .to_vec();
+                    (field.path, bytes)
+                })
+                .collect::<Vec<_>>()
+        };
+        for ((path, old), (_, new)) in header_fields(self).into_iter().zip(header_fields(other)) {
+            if old != new {
+                header.push(Change::Changed(format!(
+                    "{}: {} -> {}",
+                    path.trim_start_matches("ne_header."),
+                    decode_field(&path, &old),
+                    decode_field(&path, &new)
+                )));
+            }
+        }
+
+        let mut segments = Vec::new();
+        let segment_count = self.segment_entries.len().max(other.segment_entries.len());
+        for i in 0..segment_count {
+            let number = i + 1;
+            let (old, new) = match (self.segment_entries.get(i), other.segment_entries.get(i)) {
+                (Some(segment), None) => {
+                    segments.push(Change::Removed(format!(
+                        "segment {} (0x{:04X} bytes)",
+                        number,
+                        segment.min_alloc()
+                    )));
+                    continue;
+                }
+                (None, Some(segment)) => {
+                    segments.push(Change::Added(format!(
+                        "segment {} (0x{:04X} bytes)",
+                        number,
+                        segment.min_alloc()
+                    )));
+                    continue;
+                }
+                (Some(old), Some(new)) => (old, new),
+                (None, None) => unreachable!(),
+            };
+            if old.header.flags != new.header.flags {
+                segments.push(Change::Changed(format!(
+                    "segment {} flags: {} -> {}",
+                    number, old.header.flags, new.header.flags
+                )));
+            }
+            if old.min_alloc() != new.min_alloc() {
+                segments.push(Change::Changed(format!(
+                    "segment {} size: 0x{:04X} -> 0x{:04X}",
+                    number,
+                    old.min_alloc(),
+                    new.min_alloc()
+                )));
+            }
+            let relocation_count = |segment: &NeSegment| {
+                segment
+                    .relocations
+                    .as_ref()
+                    .map_or(0, |relocations| relocations.entries.len())
+            };
+            if relocation_count(old) != relocation_count(new) {
+                segments.push(Change::Changed(format!(
+                    "segment {} relocations: {} -> {}",
+                    number,
+                    relocation_count(old),
+                    relocation_count(new)
+                )));
+            }
+            let old_data = old.data.as_deref().unwrap_or_default();
+            let new_data = new.data.as_deref().unwrap_or_default();
+            if let Some(difference) = diff_bytes(old_data, new_data) {
+                segments.push(Change::Changed(format!(
+                    "segment {} data: {}",
+                    number, difference
+                )));
+            }
+        }
+
+        let exports = |exe: &NeExecutable| {
+            exe.exports()
+                .into_iter()
+                .map(|export| {
+                    let name = export.name.map(|name| codepage.decode(&name.name));
+                    (export.ordinal, (name, export.segment, export.offset))
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let exports = diff_maps(
+            &exports(self),
+            &exports(other),
+            |ordinal, (name, segment, offset)| {
+                format!(
+                    "@{} {} at {:04X}:{:04X}",
+                    ordinal,
+                    name.as_deref().unwrap_or("<unnamed>"),
+                    segment,
+                    offset
+                )
+            },
+        );
+
+        let old_imports = self.normalized_imports();
+        let new_imports = other.normalized_imports();
+        let mut imports = old_imports
+            .iter()
+            .filter(|import| !new_imports.contains(import))
+            .map(|import| Change::Removed(import.clone()))
+            .collect::<Vec<_>>();
+        imports.extend(
+            new_imports
+                .iter()
+                .filter(|import| !old_imports.contains(import))
+                .map(|import| Change::Added(import.clone())),
+        );
+
+        let old_resources = self.resource_data(codepage);
+        let new_resources = other.resource_data(codepage);
+        let mut resources = Vec::new();
+        let keys = old_resources
+            .keys()
+            .chain(new_resources.keys())
+            .collect::<BTreeSet<_>>();
+        for key in keys {
+            let (type_name, id) = key;
+            match (old_resources.get(key), new_resources.get(key)) {
+                (Some(data), None) => resources.push(Change::Removed(format!(
+                    "{} {} ({} bytes)",
+                    type_name,
+                    id,
+                    data.len()
+                ))),
+                (None, Some(data)) => resources.push(Change::Added(format!(
+                    "{} {} ({} bytes)",
+                    type_name,
+                    id,
+                    data.len()
+                ))),
+                (Some(old), Some(new)) => {
+                    if let Some(difference) = diff_bytes(old, new) {
+                        resources.push(Change::Changed(format!(
+                            "{} {}: {}",
+                            type_name, id, difference
+                        )));
+                    }
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+
+        vec![
+            DiffSection {
+                title: "Header",
+                changes: header,
+            },
+            DiffSection {
+                title: "Segments",
+                changes: segments,
+            },
+            DiffSection {
+                title: "Exports",
+                changes: exports,
+            },
+            DiffSection {
+                title: "Imports",
+                changes: imports,
+            },
+            DiffSection {
+                title: "Resources",
+                changes: resources,
+            },
+        ]
+    }
+
+    /// Prints the changes from this executable to `other`, by section.
+    pub(crate) fn describe_diff<W: Write>(
+        &self,
+        w: &mut W,
+        other: &NeExecutable,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let sections = self.diff(other, codepage);
+        if sections.iter().all(|section| section.changes.is_empty()) {
+            writeln!(w, "No differences")?;
+            return Ok(());
+        }
+        for section in sections {
+            if section.changes.is_empty() {
+                continue;
+            }
+            writeln!(w, "{}:", section.title)?;
+            for change in &section.changes {
+                writeln!(w, "  {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::{RelocationEntry, RelocationTarget, SOURCE_SEGMENT};
+    use super::super::tests::{sample, sample_exe, set_code};
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = sample_exe();
+        let mut data = sample();
+        data[0x40 + 0x0C] = 0x02;
+        let mut new = NeExecutable::read(&mut io::Cursor::new(data)).unwrap();
+        set_code(
+            &mut new,
+            b"\xFF\xFF\xEC\x5D\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_SEGMENT,
+                flags: 0,
+                offset: 0,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        new.retarget(&"1:0000=KERNEL.3".parse().unwrap()).unwrap();
+
+        let sections = old.diff(&new, Codepage::default());
+        let changes = |title| {
+            sections
+                .iter()
+                .find(|section| section.title == title)
+                .unwrap()
+                .changes
+                .clone()
+        };
+        assert_eq!(
+            changes("Header"),
+            [Change::Changed(
+                "flags: 0x0000 (0) -> 0x0002 (MULTIPLEDATA)".to_owned()
+            )]
+        );
+        assert!(changes("Segments")
+            .contains(&Change::Changed("segment 1 relocations: 0 -> 1".to_owned())));
+        assert!(changes("Segments").contains(&Change::Changed(
+            "segment 1 data: 2 bytes differ, first at 0x0000".to_owned()
+        )));
+        assert_eq!(
+            changes("Imports"),
+            [Change::Added("kernel.ord3".to_owned())]
+        );
+        assert!(changes("Exports").is_empty());
+        assert!(old
+            .diff(&old, Codepage::default())
+            .iter()
+            .all(|section| section.changes.is_empty()));
+    }
+
+    #[test]
+    fn test_diff_bytes() {
+        assert_eq!(diff_bytes(b"abc", b"abc"), None);
+        assert_eq!(
+            diff_bytes(b"abcd", b"xbcy"),
+            Some("2 bytes differ, first at 0x0000".to_owned())
+        );
+        assert_eq!(
+            diff_bytes(b"ab", b"abcd"),
+            Some("2 bytes differ, first at 0x0002".to_owned())
+        );
+    }
+}
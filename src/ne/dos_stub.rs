@@ -0,0 +1,223 @@
+use std::io::{self, Write};
+
+use super::header::{self, NeHeader};
+use super::NeExecutable;
+use crate::mz::DosHeader;
+use crate::x86::{self, BackendKind, Immediate, Symbols};
+
+/// The code of stubs that linkers put in by default.
+const KNOWN_STUBS: &[(&str, &[u8])] = &[
+    (
+        "Microsoft LINK",
+        b"\x0E\x1F\xBA\x0E\x00\xB4\x09\xCD\x21\xB8\x01\x4C\xCD\x21",
+    ),
+    (
+        "Borland TLINK",
+        b"\xBA\x10\x00\x0E\x1F\xB4\x09\xCD\x21\xB8\x01\x4C\xCD\x21\x90\x90",
+    ),
+];
+
+/// What the DOS stub does, as told by [`NeExecutable::classify_stub`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StubKind {
+    /// Prints a message and exits.
+    Standard {
+        /// The linker whose default stub this is, if known.
+        linker: Option<&'static str>,
+        /// The message, without the terminating `$`.
+        message: Vec<u8>,
+    },
+    /// Does anything else; `size` is that of the load module.
+    Custom { size: usize },
+}
+
+impl NeExecutable {
+    /// Returns the DOS stub as a standalone DOS executable.
+    ///
+    /// The program is cut at the NE header, or where its header says it
+    /// ends if that's before, and its size and `lfanew` are updated to match.
+    pub fn extract_stub(&self) -> Vec<u8> {
+        let mut header = *self.dos_header;
+        let header_size = header.cparhdr.value() as u64 * 0x10;
+        let mut end = self.dos_stub.len() as u64;
+        if (header_size..end).contains(&header.image_size()) {
+            end = header.image_size();
+        }
+        let mut stub = self.dos_stub[..end as usize].to_vec();
+        header.set_image_size(end);
+        header.lfanew = 0.into();
+        let header_len = stub.len().min(0x40);
+        stub[..header_len].copy_from_slice(&bytemuck::bytes_of(&header)[..header_len]);
+        stub
+    }
+
+    /// Returns the file with its DOS stub replaced by another DOS program.
+    ///
+    /// The NE image is moved as a whole, including appended data, and the
+    /// stub is padded so that it moves by a multiple of the segment and
+    /// resource alignments. The file offsets of the segments, resources and
+    /// nonresident names are updated; offsets relative to the NE header
+    /// stay as they are.
+    pub fn replace_stub(&self, stub: &[u8]) -> io::Result<Vec<u8>> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let stub_header = stub
+            .get(..0x40)
+            .map(bytemuck::pod_read_unaligned::<DosHeader>)
+            .ok_or_else(|| invalid("the stub is shorter than a DOS header"))?;
+        stub_header.check_magic()?;
+        let header_size = stub_header.cparhdr.value() as u64 * 0x10;
+        let stub = match stub_header.image_size() {
+            size if (header_size.max(0x40)..=stub.len() as u64).contains(&size) => {
+                &stub[..size as usize]
+            }
+            _ => stub,
+        };
+
+        let old_lfanew = self.dos_header.lfanew.value() as i64;
+        let segment_shift = self.ne_header.alignment_shift();
+        let resource_shift = self.resource_table.header.alignment_shift_count.min(15);
+        // Without resources the shift count may be garbage from the next table.
+        let has_resources = self
+            .resource_table
+            .resource_types
+            .iter()
+            .any(|resource_type| !resource_type.resources.is_empty());
+        let alignment = 1i64 << segment_shift.max(if has_resources { resource_shift } else { 0 });
+        let stub_len = stub.len() as i64;
+        let lfanew = stub_len + (old_lfanew - stub_len).rem_euclid(alignment);
+        let delta = lfanew - old_lfanew;
+
+        let mut file = stub.to_vec();
+        file.resize(lfanew as usize, 0);
+        file[0x3C..0x40].copy_from_slice(&(lfanew as u32).to_le_bytes());
+        file.extend_from_slice(&self.file_data[old_lfanew as usize..]);
+
+        let too_far = || invalid("the image moves too far for the shifted offsets");
+        let shifted = |offset: u16, shift: u16| -> io::Result<u16> {
+            u16::try_from(offset as i64 + (delta >> shift)).map_err(|_| too_far())
+        };
+        let put = |file: &mut Vec<u8>, offset: i64, bytes: &[u8]| {
+            let offset = offset as usize;
+            file[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+
+        let mut ne_header: NeHeader = *self.ne_header;
+        let nonresident = ne_header.non_resident_names_table_offset.value();
+        if nonresident != 0 {
+            let moved = u32::try_from(nonresident as i64 + delta).map_err(|_| too_far())?;
+            ne_header.non_resident_names_table_offset = moved.into();
+        }
+        let segment_table = lfanew + ne_header.segment_table_offset.value() as i64;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let offset = segment.header.data_offset_shifted;
+            if offset != 0 {
+                let moved = shifted(offset, segment_shift)?;
+                put(
+                    &mut file,
+                    segment_table + 8 * i as i64,
+                    &moved.to_le_bytes(),
+                );
+            }
+        }
+        // The resource headers follow the alignment and each type header.
+        let mut pos = lfanew + ne_header.resource_table_offset.value() as i64 + 2;
+        for resource_type in &self.resource_table.resource_types {
+            pos += 8;
+            for resource in &resource_type.resources {
+                let offset = resource.header.data_offset_shifted;
+                if offset != 0 {
+                    put(
+                        &mut file,
+                        pos,
+                        &shifted(offset, resource_shift)?.to_le_bytes(),
+                    );
+                }
+                pos += 12;
+            }
+        }
+        put(&mut file, lfanew, bytemuck::bytes_of(&ne_header));
+        if ne_header.file_load_crc.value() != 0 {
+            let crc = header::file_load_crc(&file, lfanew as usize);
+            put(&mut file, lfanew + 0x08, &crc.to_le_bytes());
+        }
+        Ok(file)
+    }
+
+    /// Returns the load module of the DOS stub, which follows the
+    /// `cparhdr * 16` bytes of the header, and its start in the file.
+    pub(super) fn stub_program(&self) -> (&[u8], usize) {
+        let header = &self.dos_header;
+        let start = (header.cparhdr.value() as usize * 0x10).min(self.dos_stub.len());
+        let mut end = self.dos_stub.len();
+        if (start as u64..end as u64).contains(&header.image_size()) {
+            end = header.image_size() as usize;
+        }
+        (&self.dos_stub[start..end], start)
+    }
+
+    /// Returns the offset of the stub's entry point in its load module.
+    pub(super) fn stub_entry(&self) -> usize {
+        self.dos_header.cs.value() as usize * 0x10 + self.dos_header.ip.value() as usize
+    }
+
+    /// Tells whether the DOS stub only prints a message and exits, and if
+    /// so, which linker's stub it is.
+    pub fn classify_stub(&self) -> StubKind {
+        let (code, _) = self.stub_program();
+        let entry = self.stub_entry();
+        let custom = StubKind::Custom { size: code.len() };
+        if entry >= code.len() {
+            return custom;
+        }
+        let insts = x86::decode_reachable(code, &[entry]);
+        let mut message_offset = None;
+        for inst in &insts {
+            match (inst.opcode, inst.immediate) {
+                // push %cs; pop %ds; mov $imm, %ah / %ax
+                (0x0E | 0x1F | 0x90 | 0xB4 | 0xB8, _) => {}
+                (0xBA, Immediate::Word(offset)) => message_offset = Some(offset as usize),
+                (0xCD, Immediate::Byte(0x20 | 0x21)) => {}
+                _ => return custom,
+            }
+        }
+        // The message is addressed relative to CS, as DS is set from it.
+        let message = message_offset
+            .and_then(|offset| code.get(self.dos_header.cs.value() as usize * 0x10 + offset..))
+            .and_then(|text| Some(text[..text.iter().position(|&byte| byte == b'$')?].to_vec()))
+            .unwrap_or_default();
+        let linker = KNOWN_STUBS
+            .iter()
+            .find(|(_, stub)| code[entry..].starts_with(stub))
+            .map(|&(linker, _)| linker);
+        StubKind::Standard { linker, message }
+    }
+
+    /// Prints the listing of the real-mode program in the DOS stub, from its
+    /// entry point. Offsets are relative to the start of the load module.
+    pub(crate) fn describe_stub_disassembly<W: Write>(
+        &self,
+        w: &mut W,
+        backend: BackendKind,
+    ) -> io::Result<()> {
+        let backend = backend.backend().unwrap_or(&x86::Builtin);
+        let (code, start) = self.stub_program();
+        let entry = self.stub_entry();
+        writeln!(
+            w,
+            "DOS stub: {} bytes of code at 0x{:X}, entry point {:04X}:{:04X}",
+            code.len(),
+            start,
+            self.dos_header.cs.value(),
+            self.dos_header.ip.value()
+        )?;
+        if entry >= code.len() {
+            writeln!(w, "    The entry point is outside the program.")?;
+            return Ok(());
+        }
+        let mut symbols = Symbols::default();
+        symbols.labels.insert(entry, "start".to_owned());
+        let insts = x86::decode_reachable(code, &[entry]);
+        x86::write_listing(w, code, 0, &insts, &symbols, backend)?;
+        Ok(())
+    }
+}
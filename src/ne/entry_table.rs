@@ -1,14 +1,25 @@
 use std::convert::TryInto;
-use std::io::{self, Read};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use bitflags::bitflags;
+use log::debug;
+
+use super::header::write_flag_names;
 
 #[derive(Debug, Clone)]
 pub struct EntryTable {
     pub entries: Vec<SegmentEntry>,
+    /// The position of each entry relative to the start of the table.
+    pub offsets: Vec<u16>,
 }
 
 impl EntryTable {
-    pub fn read<R: Read>(r: &mut R, mut length: u16) -> io::Result<Self> {
+    pub fn read<R: Read>(r: &mut R, length: u16) -> io::Result<Self> {
+        let total_length = length;
+        let mut length = length;
         let mut entries = Vec::new();
+        let mut offsets = Vec::new();
         while length > 0 {
             let num = {
                 let mut buf = [0];
@@ -16,11 +27,9 @@ impl EntryTable {
                 buf[0]
             };
             if num == 0 {
+                // Early linkers count padding after the terminator in the length.
                 if length != 1 {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "Inexact length for entry table",
-                    ));
+                    debug!("entry table ends {} byte(s) before its length", length - 1);
                 }
                 break;
             }
@@ -43,8 +52,12 @@ impl EntryTable {
                     "Inexact length for entry table",
                 ));
             }
+            let entry_size = (bundle_size - 2) / num as u16;
+            let mut offset = total_length - length + 2;
             length -= bundle_size;
             for _ in 0..num {
+                offsets.push(offset);
+                offset += entry_size;
                 entries.push(if segment == 0 {
                     SegmentEntry::Unused
                 } else if segment < 0xFF {
@@ -54,7 +67,36 @@ impl EntryTable {
                 });
             }
         }
-        Ok(Self { entries })
+        Ok(Self { entries, offsets })
+    }
+
+    /// Writes the entries, bundling runs of unused entries, fixed entries in
+    /// the same segment and moveable entries, and the terminating zero.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let indicator = |entry: &SegmentEntry| match entry {
+            SegmentEntry::Unused => 0,
+            SegmentEntry::Fixed(entry) => entry.segment,
+            SegmentEntry::Moveable(_) => 0xFF,
+        };
+        let mut pos = 0;
+        while pos < self.entries.len() {
+            let segment = indicator(&self.entries[pos]);
+            let num = self.entries[pos..]
+                .iter()
+                .take(0xFF)
+                .take_while(|entry| indicator(entry) == segment)
+                .count();
+            w.write_all(&[num as u8, segment])?;
+            for entry in &self.entries[pos..pos + num] {
+                match entry {
+                    SegmentEntry::Unused => {}
+                    SegmentEntry::Fixed(entry) => entry.write(w)?,
+                    SegmentEntry::Moveable(entry) => entry.write(w)?,
+                }
+            }
+            pos += num;
+        }
+        w.write_all(&[0])
     }
 }
 
@@ -65,10 +107,56 @@ pub enum SegmentEntry {
     Moveable(MoveableSegmentEntry),
 }
 
+bitflags! {
+    /// The flags byte of an entry point.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct EntryFlags: u8 {
+        /// The entry point is exported.
+        const EXPORTED = 0x01;
+        /// The entry point uses a global (shared) data segment.
+        const SHARED_DATA = 0x02;
+        /// The number of parameter words copied on a ring transition.
+        const PARAM_WORDS = 0xF8;
+    }
+}
+
+impl EntryFlags {
+    pub fn is_exported(self) -> bool {
+        self.contains(Self::EXPORTED)
+    }
+
+    pub fn is_shared_data(self) -> bool {
+        self.contains(Self::SHARED_DATA)
+    }
+
+    pub fn param_words(self) -> u8 {
+        (self & Self::PARAM_WORDS).bits() >> 3
+    }
+}
+
+impl fmt::Display for EntryFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = Vec::new();
+        if self.is_exported() {
+            names.push("EXPORTED".to_owned());
+        }
+        if self.is_shared_data() {
+            names.push("SHARED_DATA".to_owned());
+        }
+        if (self.bits() & 0x04) != 0 {
+            names.push("0x04".to_owned());
+        }
+        if self.param_words() != 0 {
+            names.push(format!("PARAM_WORDS({})", self.param_words()));
+        }
+        write_flag_names(f, &names)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FixedSegmentEntry {
     pub segment: u8,
-    pub flags: u8,
+    pub flags: EntryFlags,
     pub offset: u16,
 }
 
@@ -78,15 +166,20 @@ impl FixedSegmentEntry {
         r.read_exact(&mut buf)?;
         Ok(Self {
             segment,
-            flags: buf[0],
+            flags: EntryFlags::from_bits_retain(buf[0]),
             offset: u16::from_le_bytes(buf[1..3].try_into().unwrap()),
         })
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&[self.flags.bits()])?;
+        w.write_all(&self.offset.to_le_bytes())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct MoveableSegmentEntry {
-    pub flags: u8,
+    pub flags: EntryFlags,
     pub magic: [u8; 2],
     pub segment: u8,
     pub offset: u16,
@@ -97,10 +190,63 @@ impl MoveableSegmentEntry {
         let mut buf = [0; 6];
         r.read_exact(&mut buf)?;
         Ok(Self {
-            flags: buf[0],
+            flags: EntryFlags::from_bits_retain(buf[0]),
             magic: [buf[1], buf[2]],
             segment: buf[3],
             offset: u16::from_le_bytes(buf[4..6].try_into().unwrap()),
         })
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&[
+            self.flags.bits(),
+            self.magic[0],
+            self.magic[1],
+            self.segment,
+        ])?;
+        w.write_all(&self.offset.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_table_padded() {
+        let buf = b"\x01\x01\x03\x00\x00\x00\x00\x00";
+        let table = EntryTable::read(&mut Cursor::new(buf), buf.len() as u16).unwrap();
+        assert_eq!(table.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_table_write() {
+        let buf = b"\x02\x01\x03\x00\x00\x01\x10\x00\x01\x00\x01\xFF\x01\xCD\x3F\x02\x04\x00\x00";
+        let table = EntryTable::read(&mut Cursor::new(buf), buf.len() as u16).unwrap();
+        assert_eq!(table.entries.len(), 4);
+        let mut written = Vec::new();
+        table.write(&mut written).unwrap();
+        assert_eq!(written, buf);
+    }
+
+    #[test]
+    fn test_entry_flags() {
+        let flags = EntryFlags::from_bits_retain(0x1B);
+        assert!(flags.is_exported() && flags.is_shared_data());
+        assert_eq!(flags.param_words(), 3);
+        assert_eq!(flags.to_string(), "EXPORTED | SHARED_DATA | PARAM_WORDS(3)");
+        assert_eq!(EntryFlags::from_bits_retain(0x04).to_string(), "0x04");
+
+        let buf = b"\x01\x01\x03\x34\x12\x00";
+        let table = EntryTable::read(&mut Cursor::new(buf), buf.len() as u16).unwrap();
+        match &table.entries[0] {
+            SegmentEntry::Fixed(entry) => {
+                assert_eq!(entry.flags, EntryFlags::EXPORTED | EntryFlags::SHARED_DATA);
+                assert_eq!(entry.offset, 0x1234);
+            }
+            entry => panic!("unexpected entry {:?}", entry),
+        }
+    }
 }
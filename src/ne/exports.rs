@@ -0,0 +1,414 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use super::entry_table::{
+    EntryFlags, EntryTable, FixedSegmentEntry, MoveableSegmentEntry, SegmentEntry,
+};
+use super::nonresident_name_table::NonresidentNameEntry;
+use super::resident_name_table::ResidentNameEntry;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// An entry point of the module, joined with its name if it has one.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// The 1-based position in the entry table.
+    pub ordinal: u16,
+    pub name: Option<ExportName>,
+    pub segment: u8,
+    pub offset: u16,
+    pub flags: EntryFlags,
+    pub moveable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportName {
+    pub name: Vec<u8>,
+    /// Whether the name is in the resident name table (as opposed to the nonresident one).
+    pub resident: bool,
+}
+
+/// A new export: `NAME[@ORDINAL]=SEG:OFF`, with the address in hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSpec {
+    pub name: Vec<u8>,
+    /// The ordinal to use, or `None` for the one after the last entry.
+    pub ordinal: Option<u16>,
+    pub segment: u8,
+    pub offset: u16,
+}
+
+impl FromStr for ExportSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, address) = s.split_once('=').ok_or("expected NAME[@ORDINAL]=SEG:OFF")?;
+        let (name, ordinal) = match name.split_once('@') {
+            Some((name, ordinal)) => (
+                name,
+                Some(
+                    ordinal
+                        .parse::<u16>()
+                        .map_err(|e| format!("{}: {}", ordinal, e))?,
+                ),
+            ),
+            None => (name, None),
+        };
+        if name.is_empty() || ordinal == Some(0) {
+            return Err("expected a name and a nonzero ordinal".to_owned());
+        }
+        let (segment, offset) = address.split_once(':').ok_or("expected SEG:OFF")?;
+        Ok(Self {
+            name: name.as_bytes().to_vec(),
+            ordinal,
+            segment: u8::from_str_radix(segment, 16).map_err(|e| format!("{}: {}", segment, e))?,
+            offset: u16::from_str_radix(offset, 16).map_err(|e| format!("{}: {}", offset, e))?,
+        })
+    }
+}
+
+/// A renamed export: `OLD=NEW`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRename {
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl FromStr for ExportRename {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => Ok(Self {
+                old: old.as_bytes().to_vec(),
+                new: new.as_bytes().to_vec(),
+            }),
+            _ => Err("expected OLD=NEW".to_owned()),
+        }
+    }
+}
+
+impl NeExecutable {
+    /// Lists the used entry points along with their names.
+    pub fn exports(&self) -> Vec<Export> {
+        let mut exports = self
+            .entry_table
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let (segment, offset, flags, moveable) = match entry {
+                    SegmentEntry::Unused => return None,
+                    SegmentEntry::Fixed(entry) => (entry.segment, entry.offset, entry.flags, false),
+                    SegmentEntry::Moveable(entry) => {
+                        (entry.segment, entry.offset, entry.flags, true)
+                    }
+                };
+                Some(Export {
+                    ordinal: i as u16 + 1,
+                    name: None,
+                    segment,
+                    offset,
+                    flags,
+                    moveable,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The first entry of each name table is the module name or description.
+        let resident_names = self
+            .resident_name_table
+            .entries
+            .iter()
+            .skip(1)
+            .map(|entry| (entry.index, &entry.name, true));
+        let nonresident_names = self
+            .nonresident_name_table
+            .entries
+            .iter()
+            .skip(1)
+            .map(|entry| (entry.index, &entry.name, false));
+        for (index, name, resident) in resident_names.chain(nonresident_names) {
+            let pos = exports.binary_search_by_key(&index, |export| export.ordinal);
+            if let Ok(pos) = pos {
+                let export = &mut exports[pos];
+                if export.name.is_none() {
+                    export.name = Some(ExportName {
+                        name: name.clone(),
+                        resident,
+                    });
+                }
+            }
+        }
+        exports
+    }
+
+    /// Adds an entry point and its name to the resident or nonresident name
+    /// table. Returns the ordinal.
+    ///
+    /// The entry table is padded with unused entries up to the ordinal.
+    /// Entries in moveable segments are written as moveable entries.
+    pub fn add_export(&mut self, spec: &ExportSpec, resident: bool) -> io::Result<u16> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let segment = match self
+            .segment_entries
+            .get((spec.segment as usize).wrapping_sub(1))
+        {
+            Some(segment) => segment,
+            None => return Err(invalid(format!("no segment {}", spec.segment))),
+        };
+        if self.exports().iter().any(|export| {
+            export
+                .name
+                .as_ref()
+                .is_some_and(|name| name.name == spec.name)
+        }) {
+            return Err(invalid(format!(
+                "{} is already exported",
+                String::from_utf8_lossy(&spec.name)
+            )));
+        }
+        let entries = &mut self.entry_table.entries;
+        let ordinal = spec.ordinal.unwrap_or(entries.len() as u16 + 1);
+        let index = ordinal as usize - 1;
+        if index < entries.len() && !matches!(entries[index], SegmentEntry::Unused) {
+            return Err(invalid(format!("ordinal {} is already used", ordinal)));
+        }
+        if index >= entries.len() {
+            entries.resize(index + 1, SegmentEntry::Unused);
+        }
+        let flags = EntryFlags::EXPORTED;
+        entries[index] = if segment.is_moveable() {
+            self.ne_header.movable_entry_point_count =
+                (self.ne_header.movable_entry_point_count.value() + 1).into();
+            SegmentEntry::Moveable(MoveableSegmentEntry {
+                flags,
+                // INT 3Fh, the call into the loader that each moveable entry starts as.
+                magic: [0xCD, 0x3F],
+                segment: spec.segment,
+                offset: spec.offset,
+            })
+        } else {
+            SegmentEntry::Fixed(FixedSegmentEntry {
+                segment: spec.segment,
+                flags,
+                offset: spec.offset,
+            })
+        };
+        // Re-read the table to update the entry offsets.
+        let mut table = Vec::new();
+        self.entry_table.write(&mut table)?;
+        self.entry_table = EntryTable::read(&mut &table[..], table.len() as u16)?;
+
+        if resident {
+            self.resident_name_table.entries.push(ResidentNameEntry {
+                name: spec.name.clone(),
+                index: ordinal,
+            });
+        } else {
+            let description = self.module_name_entry().unwrap_or(b"").to_vec();
+            let entries = &mut self.nonresident_name_table.entries;
+            // The first entry is the module description, which may have been stripped.
+            if entries.is_empty() {
+                entries.push(NonresidentNameEntry {
+                    name: description,
+                    index: 0,
+                });
+            }
+            entries.push(NonresidentNameEntry {
+                name: spec.name.clone(),
+                index: ordinal,
+            });
+        }
+        Ok(ordinal)
+    }
+
+    /// Renames an export in whichever name table it's in.
+    pub fn rename_export(&mut self, rename: &ExportRename) -> io::Result<()> {
+        let resident_names = self
+            .resident_name_table
+            .entries
+            .iter_mut()
+            .skip(1)
+            .map(|entry| &mut entry.name);
+        let nonresident_names = self
+            .nonresident_name_table
+            .entries
+            .iter_mut()
+            .skip(1)
+            .map(|entry| &mut entry.name);
+        match resident_names
+            .chain(nonresident_names)
+            .find(|name| **name == rename.old)
+        {
+            Some(name) => {
+                *name = rename.new.clone();
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is not exported by name",
+                    String::from_utf8_lossy(&rename.old)
+                ),
+            )),
+        }
+    }
+
+    /// Returns the module name, i.e. the first entry of the resident name table.
+    pub fn module_name_entry(&self) -> Option<&[u8]> {
+        self.resident_name_table
+            .entries
+            .first()
+            .map(|entry| &entry.name[..])
+    }
+
+    pub(crate) fn describe_exports<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        writeln!(w, "Exports of {}:", module_name)?;
+        writeln!(
+            w,
+            "    {:>7}  {:<32} {:<11}  {:<9}  Type",
+            "Ordinal", "Name", "Table", "Address"
+        )?;
+        for export in self.exports() {
+            let (name, table) = match &export.name {
+                Some(name) => (
+                    codepage.decode(&name.name),
+                    if name.resident {
+                        "resident"
+                    } else {
+                        "nonresident"
+                    },
+                ),
+                None => match self
+                    .debug_symbols
+                    .get(&(export.segment as u16, export.offset))
+                {
+                    Some(name) => (codepage.decode(name), "debug info"),
+                    None => ("<unnamed>".to_owned(), ""),
+                },
+            };
+            writeln!(
+                w,
+                "    {:>7}  {:<32} {:<11}  {:04X}:{:04X}  {}",
+                export.ordinal,
+                name,
+                table,
+                export.segment,
+                export.offset,
+                if export.moveable { "moveable" } else { "fixed" }
+            )?;
+        }
+        if !self.debug_symbols.is_empty() {
+            writeln!(w, "Public symbols from debug information:")?;
+            for (&(segment, offset), name) in &self.debug_symbols {
+                writeln!(
+                    w,
+                    "    {:04X}:{:04X}  {}",
+                    segment,
+                    offset,
+                    codepage.decode(name)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    /// The sample with a moveable entry #3 after an unused one, and names
+    /// for entries #1, #3 and the missing #7.
+    fn with_names() -> NeExecutable {
+        let mut exe = sample_exe();
+        let entries = &mut exe.entry_table.entries;
+        entries.push(SegmentEntry::Unused);
+        entries.push(SegmentEntry::Moveable(MoveableSegmentEntry {
+            flags: EntryFlags::EXPORTED,
+            magic: [0xCD, 0x3F],
+            segment: 1,
+            offset: 3,
+        }));
+        let resident = |name: &[u8], index| ResidentNameEntry {
+            name: name.to_vec(),
+            index,
+        };
+        let nonresident = |name: &[u8], index| NonresidentNameEntry {
+            name: name.to_vec(),
+            index,
+        };
+        exe.resident_name_table
+            .entries
+            .extend([resident(b"First", 1), resident(b"Missing", 7)]);
+        exe.nonresident_name_table
+            .entries
+            .extend([nonresident(b"Third", 3), nonresident(b"Other", 1)]);
+        exe
+    }
+
+    #[test]
+    fn test_exports() {
+        let exports = with_names().exports();
+        let summary = exports
+            .iter()
+            .map(|export| {
+                let name = export.name.as_ref().unwrap();
+                (
+                    export.ordinal,
+                    name.name.clone(),
+                    name.resident,
+                    export.moveable,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (1, b"First".to_vec(), true, false),
+                (3, b"Third".to_vec(), false, true),
+            ]
+        );
+        assert_eq!((exports[1].segment, exports[1].offset), (1, 3));
+        assert!(exports[1].flags.is_exported());
+    }
+
+    #[test]
+    fn test_describe_exports() {
+        let mut exe = with_names();
+        exe.nonresident_name_table.entries.truncate(1);
+        exe.debug_symbols.insert((1, 3), b"_third".to_vec());
+        let mut output = Vec::new();
+        exe.describe_exports(&mut output, Codepage::default())
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Exports of TEST:");
+        assert_eq!(
+            lines[2],
+            format!(
+                "    {:>7}  {:<32} {:<11}  0001:0000  fixed",
+                1, "First", "resident"
+            )
+        );
+        assert_eq!(
+            lines[3],
+            format!(
+                "    {:>7}  {:<32} {:<11}  0001:0003  moveable",
+                3, "_third", "debug info"
+            )
+        );
+        assert_eq!(lines[4], "Public symbols from debug information:");
+        assert_eq!(lines[5], "    0001:0003  _third");
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+
+/// Where a segment or resource lies relative to the fast-load area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Inside,
+    Partly,
+    Outside,
+}
+
+impl Placement {
+    fn of(range: &Range<u64>, area: &Range<u64>) -> Self {
+        if area.start <= range.start && range.end <= area.end {
+            Placement::Inside
+        } else if range.start < area.end && area.start < range.end {
+            Placement::Partly
+        } else {
+            Placement::Outside
+        }
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Placement::Inside => "inside",
+            Placement::Partly => "partly inside",
+            Placement::Outside => "outside",
+        })
+    }
+}
+
+/// A segment or resource and where it lies relative to the fast-load area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastLoadItem {
+    /// `Segment N` or the resource type and ID.
+    pub name: String,
+    pub preload: bool,
+    pub placement: Placement,
+}
+
+impl NeExecutable {
+    /// Returns where each segment, with its relocations, and each resource
+    /// lies relative to the fast-load area, or `None` without one or with an
+    /// empty one.
+    pub fn fast_load_items(&self) -> Option<Vec<FastLoadItem>> {
+        let (offset, length) = self
+            .ne_header
+            .fast_load_area()
+            .filter(|&(_, length)| length != 0)?;
+        let area = offset..offset + length;
+        let mut items = Vec::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            let relocations_size = segment
+                .relocations
+                .as_ref()
+                .map_or(0, |relocations| relocations.file_size());
+            let start = segment.data_offset();
+            let range = start..start + segment.data_length() + relocations_size;
+            items.push(FastLoadItem {
+                name: format!("Segment {}", i + 1),
+                preload: segment.is_preload(),
+                placement: Placement::of(&range, &area),
+            });
+        }
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                let (start, length) = self.resource_table.data_range(resource);
+                if length == 0 {
+                    continue;
+                }
+                items.push(FastLoadItem {
+                    name: format!(
+                        "{} {}",
+                        describe_resource_id(resource_type.header.type_id, true),
+                        describe_resource_id(resource.header.resource_id, false)
+                    ),
+                    preload: (resource.header.flags & 0x0040) != 0,
+                    placement: Placement::of(&(start..start + length), &area),
+                });
+            }
+        }
+        Some(items)
+    }
+
+    /// Prints where the segments and resources lie relative to the fast-load
+    /// area. Windows 3.x reads the area in one go, so preloaded items outside
+    /// it cost extra reads.
+    pub(crate) fn describe_fast_load<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let items = match self.fast_load_items() {
+            Some(items) => items,
+            None => return Ok(()),
+        };
+        writeln!(w, "Fast-load area contents:")?;
+        for item in &items {
+            writeln!(
+                w,
+                "    {:<24} {}{}",
+                item.name,
+                item.placement,
+                if item.preload { " (preload)" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement() {
+        let area = 0x100..0x200;
+        assert_eq!(Placement::of(&(0x100..0x180), &area), Placement::Inside);
+        assert_eq!(Placement::of(&(0x180..0x280), &area), Placement::Partly);
+        assert_eq!(Placement::of(&(0x200..0x280), &area), Placement::Outside);
+    }
+}
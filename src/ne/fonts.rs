@@ -0,0 +1,190 @@
+use std::io::{self, Write};
+
+use super::NeExecutable;
+use crate::util::bytes::{cstr_at, u16_at, u32_at};
+use crate::util::codepage::Codepage;
+
+/// The integer IDs of the font directory and font resource types.
+pub const RT_FONTDIR: u16 = 0x8007;
+pub const RT_FONT: u16 = 0x8008;
+
+/// The size of the font header copied into each font directory entry.
+const FONT_DIR_ENTRY_SIZE: usize = 0x71;
+
+/// The fields of a font header (`FONTINFO`) that tell fonts apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontInfo {
+    /// The ID of the `FONT` resource.
+    pub id: u16,
+    pub face: Vec<u8>,
+    pub points: u16,
+    pub pixel_height: u16,
+    pub weight: u16,
+    pub italic: bool,
+    pub charset: u8,
+    pub vector: bool,
+}
+
+impl FontInfo {
+    /// Reads a font header, from a `FONT` resource or a font directory
+    /// entry. The face name is looked for at `dfFace` in `face_data`.
+    fn parse(id: u16, header: &[u8], face_data: &[u8]) -> Option<Self> {
+        let face = u32_at(header, 0x69)
+            .and_then(|offset| cstr_at(face_data, offset as usize))
+            .unwrap_or_default();
+        Some(Self {
+            id,
+            face: face.to_vec(),
+            points: u16_at(header, 0x44)?,
+            pixel_height: u16_at(header, 0x58)?,
+            weight: u16_at(header, 0x53)?,
+            italic: *header.get(0x50)? != 0,
+            charset: *header.get(0x55)?,
+            vector: (u16_at(header, 0x42)? & 0x0001) != 0,
+        })
+    }
+}
+
+/// Reads the entries of a `FONTDIR` resource: the font ID, the font
+/// header, the device name and the face name.
+fn read_font_directory(data: &[u8]) -> Vec<FontInfo> {
+    let count = u16_at(data, 0).unwrap_or(0);
+    let mut pos = 2;
+    let mut fonts = Vec::new();
+    for _ in 0..count {
+        let (Some(id), Some(header)) = (
+            u16_at(data, pos),
+            data.get(pos + 2..pos + 2 + FONT_DIR_ENTRY_SIZE),
+        ) else {
+            break;
+        };
+        let names = pos + 2 + FONT_DIR_ENTRY_SIZE;
+        let device = cstr_at(data, names).unwrap_or_default();
+        let face = cstr_at(data, names + device.len() + 1).unwrap_or_default();
+        if let Some(mut font) = FontInfo::parse(id, header, &[]) {
+            font.face = face.to_vec();
+            fonts.push(font);
+        }
+        pos = names + device.len() + face.len() + 2;
+    }
+    fonts
+}
+
+/// Returns the name of a character set.
+pub fn charset_name(charset: u8) -> Option<&'static str> {
+    Some(match charset {
+        0 => "ANSI",
+        1 => "DEFAULT",
+        2 => "SYMBOL",
+        77 => "MAC",
+        128 => "SHIFTJIS",
+        129 => "HANGUL",
+        130 => "JOHAB",
+        134 => "GB2312",
+        136 => "CHINESEBIG5",
+        161 => "GREEK",
+        162 => "TURKISH",
+        163 => "VIETNAMESE",
+        177 => "HEBREW",
+        178 => "ARABIC",
+        186 => "BALTIC",
+        204 => "RUSSIAN",
+        222 => "THAI",
+        238 => "EASTEUROPE",
+        255 => "OEM",
+        _ => return None,
+    })
+}
+
+impl NeExecutable {
+    /// Returns the fonts in the `FONT` resources, falling back to the font
+    /// directory for the fonts whose resource is missing or unreadable.
+    pub fn fonts(&self) -> Vec<FontInfo> {
+        let resources = |type_id| {
+            self.resource_table
+                .resource_types
+                .iter()
+                .filter(move |resource_type| resource_type.header.type_id == type_id)
+                .flat_map(|resource_type| &resource_type.resources)
+        };
+        let mut fonts = resources(RT_FONT)
+            .filter_map(|resource| {
+                let id = resource.header.resource_id & 0x7FFF;
+                FontInfo::parse(id, &resource.data, &resource.data)
+            })
+            .collect::<Vec<_>>();
+        for resource in resources(RT_FONTDIR) {
+            for entry in read_font_directory(&resource.data) {
+                match fonts.iter_mut().find(|font| font.id == entry.id) {
+                    Some(font) if font.face.is_empty() => font.face = entry.face,
+                    Some(_) => {}
+                    None => fonts.push(entry),
+                }
+            }
+        }
+        fonts.sort_by_key(|font| font.id);
+        fonts
+    }
+
+    /// Prints a table of the fonts in a font library.
+    pub(crate) fn describe_fonts<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        writeln!(w, "Fonts:")?;
+        writeln!(
+            w,
+            "    {:>5}  {:<24} {:>6} {:>6} {:>6}  {:<16} Type",
+            "ID", "Face", "Points", "Height", "Weight", "Charset"
+        )?;
+        for font in self.fonts() {
+            let mut face = codepage.decode(&font.face);
+            if font.italic {
+                face.push_str(" Italic");
+            }
+            let charset = match charset_name(font.charset) {
+                Some(name) => format!("{} ({})", font.charset, name),
+                None => font.charset.to_string(),
+            };
+            writeln!(
+                w,
+                "    {:>5}  {:<24} {:>6} {:>6} {:>6}  {:<16} {}",
+                font.id,
+                face,
+                font.points,
+                font.pixel_height,
+                font.weight,
+                charset,
+                if font.vector { "vector" } else { "raster" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_font_directory() {
+        let mut header = vec![0; FONT_DIR_ENTRY_SIZE];
+        header[0x44] = 10;
+        header[0x53..0x55].copy_from_slice(&700_u16.to_le_bytes());
+        header[0x55] = 255;
+        header[0x58] = 16;
+        let mut data = b"\x01\x00\x03\x00".to_vec();
+        data.extend_from_slice(&header);
+        data.extend_from_slice(b"\0Terminal\0");
+        assert_eq!(
+            read_font_directory(&data),
+            vec![FontInfo {
+                id: 3,
+                face: b"Terminal".to_vec(),
+                points: 10,
+                pixel_height: 16,
+                weight: 700,
+                italic: false,
+                charset: 255,
+                vector: false,
+            }]
+        );
+    }
+}
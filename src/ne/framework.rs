@@ -0,0 +1,133 @@
+use super::imports::ImportedSymbol;
+use super::NeExecutable;
+
+/// A class library or runtime recognized by [`NeExecutable::detect_frameworks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameworkDetection {
+    pub name: String,
+    /// What the detection is based on.
+    pub evidence: String,
+}
+
+/// Where a [`RULES`] marker is looked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// The start of a referenced module name, ignoring case.
+    Module,
+    /// Part of an exported or imported function name, e.g. a mangled class name.
+    Symbol,
+    /// Part of a data segment, e.g. a window class name.
+    Data,
+}
+
+const RULES: &[(Source, &[u8], &str)] = &[
+    (Source::Module, b"OWL", "Borland OWL"),
+    (Source::Symbol, b"TApplication", "Borland OWL"),
+    (Source::Symbol, b"TWindow", "Borland OWL"),
+    (Source::Data, b"OWLWindow", "Borland OWL"),
+    (Source::Module, b"MFC", "MFC (DLL)"),
+    (Source::Symbol, b"@CWinApp@@", "MFC"),
+    (Source::Symbol, b"@CWnd@@", "MFC"),
+    (Source::Data, b"AfxWnd", "MFC"),
+    (Source::Data, b"AfxFrameOrView", "MFC"),
+    (Source::Module, b"VBRUN", "Visual Basic runtime"),
+    (
+        Source::Symbol,
+        b"VBINITCC",
+        "Visual Basic custom control (VBX)",
+    ),
+    (Source::Data, b".VBX", "VBX host"),
+];
+
+impl Source {
+    fn matches(self, marker: &[u8], haystack: &[u8]) -> bool {
+        match self {
+            Source::Module => haystack
+                .get(..marker.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(marker)),
+            Source::Symbol | Source::Data => haystack
+                .windows(marker.len())
+                .any(|window| window == marker),
+        }
+    }
+}
+
+impl NeExecutable {
+    /// Looks for the class libraries and runtimes the module is built on,
+    /// from the referenced modules, the names of the exported and imported
+    /// functions and the window class names in the data segments.
+    pub fn detect_frameworks(&self) -> Vec<FrameworkDetection> {
+        let mut haystacks = Vec::<(Source, String, Vec<u8>)>::new();
+        for entry in &self.module_reference_table.entries {
+            let description = format!("imports from {}", String::from_utf8_lossy(&entry.name));
+            haystacks.push((Source::Module, description, entry.name.clone()));
+        }
+        for export in self.exports() {
+            if let Some(name) = export.name {
+                let description = format!("exports {}", String::from_utf8_lossy(&name.name));
+                haystacks.push((Source::Symbol, description, name.name));
+            }
+        }
+        for module in self.imports() {
+            for import in module.symbols {
+                if let ImportedSymbol::Name(name) = import.symbol {
+                    let description = format!(
+                        "imports {}.{}",
+                        String::from_utf8_lossy(&module.name),
+                        String::from_utf8_lossy(&name)
+                    );
+                    haystacks.push((Source::Symbol, description, name));
+                }
+            }
+        }
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.is_code() {
+                continue;
+            }
+            if let Ok(Some(data)) = segment.expanded_data() {
+                haystacks.push((
+                    Source::Data,
+                    format!("segment {}", i + 1),
+                    data.into_owned(),
+                ));
+            }
+        }
+
+        let mut detections = Vec::<FrameworkDetection>::new();
+        for &(source, marker, name) in RULES {
+            if detections.iter().any(|detection| detection.name == name) {
+                continue;
+            }
+            let found = haystacks
+                .iter()
+                .find(|(s, _, haystack)| *s == source && source.matches(marker, haystack));
+            if let Some((_, description, _)) = found {
+                let evidence = match source {
+                    Source::Data => {
+                        format!("{:?} in {}", String::from_utf8_lossy(marker), description)
+                    }
+                    _ => description.clone(),
+                };
+                detections.push(FrameworkDetection {
+                    name: name.to_owned(),
+                    evidence,
+                });
+            }
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_matches() {
+        assert!(Source::Module.matches(b"OWL", b"owl31"));
+        assert!(!Source::Module.matches(b"OWL", b"BOWL"));
+        assert!(Source::Symbol.matches(b"@CWnd@@", b"?Create@CWnd@@RECAHPEBD@Z"));
+        assert!(Source::Data.matches(b"AfxWnd", b"\0AfxWnd\0"));
+        assert!(!Source::Data.matches(b"AfxWnd", b"afxwnd"));
+    }
+}
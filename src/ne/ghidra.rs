@@ -0,0 +1,204 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use super::ida_script::linear_address;
+use super::relocation_table::{self, RelocationTarget};
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The analysis bundle read by `scripts/ghidra/ImportWin16ne.py`.
+///
+/// Addresses are linear, with segment N placed at N0000h as in the IDC export.
+#[derive(Debug, Clone, Serialize)]
+pub struct GhidraBundle {
+    pub module: String,
+    pub entry_point: Option<u32>,
+    pub memory_blocks: Vec<MemoryBlock>,
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryBlock {
+    pub name: String,
+    /// The 1-based segment number.
+    pub segment: u16,
+    pub start: u32,
+    /// The size in memory, at least the size of `bytes`.
+    pub size: u32,
+    pub code: bool,
+    /// The expanded segment data as hexadecimal, without fixups applied.
+    pub bytes: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Symbol {
+    pub address: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reference {
+    /// The fixup site.
+    pub from: u32,
+    /// The address referred to, for references within the module.
+    pub to: Option<u32>,
+    /// What the fixup patches in: `FAR_ADDR`, `OFFSET`, `SEGMENT`, ...
+    pub kind: String,
+    /// The description of the target, e.g. `KERNEL.GETVERSION`.
+    pub target: String,
+}
+
+impl NeExecutable {
+    /// Collects the segments, named addresses and fixups for import into Ghidra.
+    pub fn ghidra_bundle(&self, codepage: Codepage) -> GhidraBundle {
+        let module = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_default();
+        let entry_point = self.ne_header.entry_point.value();
+        let entry_point = Some(linear_address(
+            (entry_point >> 16) as u16,
+            entry_point as u16,
+        ))
+        .filter(|_| entry_point >> 16 != 0);
+
+        let exports = self.exports();
+        let mut memory_blocks = Vec::new();
+        let mut references = Vec::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let number = i as u16 + 1;
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            memory_blocks.push(MemoryBlock {
+                name: format!("SEG{}", number),
+                segment: number,
+                start: linear_address(number, 0),
+                size: segment.min_alloc().max(data.len() as u64).min(0x10000) as u32,
+                code: segment.is_code(),
+                bytes: data.iter().map(|byte| format!("{:02X}", byte)).collect(),
+            });
+
+            let relocations = match &segment.relocations {
+                Some(relocations) => relocations,
+                None => continue,
+            };
+            for entry in &relocations.entries {
+                let is_segment = entry.source_type == relocation_table::SOURCE_SEGMENT;
+                let to = match entry.target {
+                    RelocationTarget::InternalFixed { segment, offset } => {
+                        Some((segment as u16, offset))
+                    }
+                    RelocationTarget::InternalMoveable { ordinal } => self
+                        .entry_address(ordinal)
+                        .map(|(segment, offset)| (segment as u16 + 1, offset as u16)),
+                    _ => None,
+                }
+                .map(|(segment, offset)| {
+                    linear_address(segment, if is_segment { 0 } else { offset })
+                });
+                let kind = relocation_table::source_type_name(entry.source_type)
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("0x{:02X}", entry.source_type));
+                let target = self.describe_fixup(entry, &exports, codepage);
+                for site in entry.sites(&data).unwrap_or_else(|_| vec![entry.offset]) {
+                    references.push(Reference {
+                        from: linear_address(number, site),
+                        to,
+                        kind: kind.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+        }
+
+        let symbols = self
+            .named_addresses(codepage)
+            .into_iter()
+            .map(|((segment, offset), name)| Symbol {
+                address: linear_address(segment, offset),
+                name,
+            })
+            .collect();
+        GhidraBundle {
+            module,
+            entry_point,
+            memory_blocks,
+            symbols,
+            references,
+        }
+    }
+
+    /// Prints the [`GhidraBundle`] as JSON.
+    pub(crate) fn describe_ghidra_json<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let bundle = self.ghidra_bundle(codepage);
+        writeln!(
+            w,
+            "{}",
+            serde_json::to_string_pretty(&bundle).expect("the bundle is serializable")
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::{RelocationEntry, SOURCE_FAR_ADDR};
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    #[test]
+    fn test_ghidra_bundle() {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 5,
+                },
+            }],
+        );
+        let bundle = exe.ghidra_bundle(Codepage::default());
+        assert_eq!(bundle.module, "TEST");
+        assert_eq!(bundle.entry_point, Some(0x10000));
+        let [block] = &bundle.memory_blocks[..] else {
+            panic!("expected one memory block");
+        };
+        assert_eq!(
+            (
+                &block.name[..],
+                block.segment,
+                block.start,
+                block.size,
+                block.code
+            ),
+            ("SEG1", 1, 0x10000, 6, true)
+        );
+        assert_eq!(block.bytes, "9AFFFF0000CB");
+        let symbols = bundle
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.address, &symbol.name[..]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            vec![(0x10000, "entry_1"), (0x10005, "sub_0001_0005")]
+        );
+        let [reference] = &bundle.references[..] else {
+            panic!("expected one reference");
+        };
+        assert_eq!(reference.from, 0x10001);
+        assert_eq!(reference.to, Some(0x10005));
+        assert_eq!(reference.kind, "FAR_ADDR");
+        assert_eq!(reference.target, "0001:0005");
+    }
+}
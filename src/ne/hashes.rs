@@ -0,0 +1,220 @@
+use std::io::{self, Write};
+
+use md5::Md5;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+use crate::util::spamsum;
+
+/// Which hashes [`NeExecutable::hashes`] computes besides SHA-256.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashOptions {
+    pub md5: bool,
+    /// The spamsum (ssdeep) hash of the code, see [`NeExecutable::code_image`].
+    pub fuzzy: bool,
+}
+
+/// The hashes of a piece of the file, in hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Digests {
+    pub sha256: String,
+    /// Only computed on request, for databases that still key on MD5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+}
+
+impl Digests {
+    pub fn of(data: &[u8], md5: bool) -> Self {
+        Self {
+            sha256: format!("{:x}", Sha256::digest(data)),
+            md5: md5.then(|| format!("{:x}", Md5::digest(data))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentHashes {
+    /// The 1-based segment number.
+    pub segment: u16,
+    pub offset: u64,
+    /// The length of the data in the file, which is less than the size of
+    /// iterated segments in memory.
+    pub length: u64,
+    #[serde(flatten)]
+    pub digests: Digests,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceHashes {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub id: String,
+    pub offset: u64,
+    /// The length of the data including the padding to the alignment.
+    pub length: u64,
+    #[serde(flatten)]
+    pub digests: Digests,
+}
+
+/// The hashes of the whole file and of each segment's raw data and each
+/// resource.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileHashes {
+    #[serde(flatten)]
+    pub file: Digests,
+    /// The spamsum (ssdeep) hash of the code with the fixups zeroed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_ssdeep: Option<String>,
+    pub segments: Vec<SegmentHashes>,
+    pub resources: Vec<ResourceHashes>,
+}
+
+impl NeExecutable {
+    /// Returns the code segments as loaded, one after another, with the
+    /// locations patched by fixups as `None`.
+    pub fn code_with_fixups(&self) -> Vec<Option<u8>> {
+        let mut code = Vec::new();
+        for segment in self
+            .segment_entries
+            .iter()
+            .filter(|segment| segment.is_code())
+        {
+            let Ok(Some(data)) = segment.expanded_data() else {
+                continue;
+            };
+            let mut bytes = data.iter().copied().map(Some).collect::<Vec<_>>();
+            for entry in segment
+                .relocations
+                .iter()
+                .flat_map(|relocations| &relocations.entries)
+            {
+                for site in entry.sites(&data).unwrap_or_default() {
+                    let start = (site as usize).min(bytes.len());
+                    let end = (start + entry.source_size()).min(bytes.len());
+                    bytes[start..end].fill(None);
+                }
+            }
+            code.extend(bytes);
+        }
+        code
+    }
+
+    /// Returns the code segments with the locations patched by fixups
+    /// zeroed, so that the same code linked at other addresses or against
+    /// other modules gives the same bytes.
+    pub fn code_image(&self) -> Vec<u8> {
+        self.code_with_fixups()
+            .into_iter()
+            .map(|byte| byte.unwrap_or(0))
+            .collect()
+    }
+
+    /// Hashes the file, its segments and its resources with SHA-256, and
+    /// with the other algorithms chosen in `options`.
+    pub fn hashes(&self, options: HashOptions) -> FileHashes {
+        let md5 = options.md5;
+        let segments = self
+            .segment_entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, segment)| {
+                let data = segment.data.as_ref()?;
+                Some(SegmentHashes {
+                    segment: i as u16 + 1,
+                    offset: segment.data_offset(),
+                    length: data.len() as u64,
+                    digests: Digests::of(data, md5),
+                })
+            })
+            .collect();
+        let resources = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| {
+                resource_type.resources.iter().map(move |resource| {
+                    let (offset, _) = self.resource_table.data_range(resource);
+                    ResourceHashes {
+                        type_name: describe_resource_id(resource_type.header.type_id, true),
+                        id: describe_resource_id(resource.header.resource_id, false),
+                        offset,
+                        length: resource.data.len() as u64,
+                        digests: Digests::of(&resource.data, md5),
+                    }
+                })
+            })
+            .collect();
+        FileHashes {
+            file: Digests::of(&self.file_data, md5),
+            code_ssdeep: options.fuzzy.then(|| spamsum::hash(&self.code_image())),
+            segments,
+            resources,
+        }
+    }
+
+    /// Prints the hashes in the tagged format of `sha256sum --tag`.
+    pub(crate) fn describe_hashes<W: Write>(
+        &self,
+        w: &mut W,
+        options: HashOptions,
+    ) -> io::Result<()> {
+        let print = |w: &mut W, label: &str, digests: &Digests| -> io::Result<()> {
+            writeln!(w, "SHA256 ({}) = {}", label, digests.sha256)?;
+            if let Some(md5) = &digests.md5 {
+                writeln!(w, "MD5 ({}) = {}", label, md5)?;
+            }
+            Ok(())
+        };
+        let hashes = self.hashes(options);
+        print(w, "file", &hashes.file)?;
+        if let Some(ssdeep) = &hashes.code_ssdeep {
+            writeln!(w, "SSDEEP (code) = {}", ssdeep)?;
+        }
+        for segment in &hashes.segments {
+            print(w, &format!("segment {}", segment.segment), &segment.digests)?;
+        }
+        for resource in &hashes.resources {
+            print(
+                w,
+                &format!("resource {} {}", resource.type_name, resource.id),
+                &resource.digests,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Prints the [`FileHashes`] as JSON.
+    pub(crate) fn describe_hashes_json<W: Write>(
+        &self,
+        w: &mut W,
+        options: HashOptions,
+    ) -> io::Result<()> {
+        writeln!(
+            w,
+            "{}",
+            serde_json::to_string_pretty(&self.hashes(options))
+                .expect("the hashes are serializable")
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(
+            Digests::of(b"abc", true),
+            Digests {
+                sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                    .to_owned(),
+                md5: Some("900150983cd24fb0d6963f7d28e17f72".to_owned()),
+            }
+        );
+        assert_eq!(Digests::of(b"", false).md5, None);
+    }
+}
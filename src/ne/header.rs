@@ -1,5 +1,8 @@
+use std::fmt;
 use std::io::{self, Read};
+use std::str::FromStr;
 
+use bitflags::bitflags;
 use bytemuck::{Pod, Zeroable};
 
 use crate::util::endian::{Lu16, Lu32};
@@ -40,13 +43,291 @@ pub struct NeHeader {
     pub expected_win_ver: [u8; 2],
 }
 
+bitflags! {
+    /// The flags of the NE header.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct NeFlags: u16 {
+        /// One shared data segment.
+        const SINGLEDATA = 0x0001;
+        /// A data segment for each instance.
+        const MULTIPLEDATA = 0x0002;
+        /// The library is initialized for each process (OS/2).
+        const GLOBAL_INIT = 0x0004;
+        /// The application may only run in protected mode.
+        const PROTECTED_MODE_ONLY = 0x0008;
+        /// The module contains 8086 instructions.
+        const CPU_8086 = 0x0010;
+        /// The module contains 80286 instructions.
+        const CPU_80286 = 0x0020;
+        /// The module contains 80386 instructions.
+        const CPU_80386 = 0x0040;
+        /// The module contains 8087 instructions.
+        const CPU_8087 = 0x0080;
+        /// The OS/2 application type; see [`NeFlags::app_type_name`].
+        const APP_TYPE = 0x0300;
+        /// The first segment loads the others.
+        const SELF_LOADING = 0x0800;
+        /// The linker reported errors, so the image is not loadable.
+        const LINK_ERROR = 0x2000;
+        /// The module is a non-conforming program (OS/2).
+        const NON_CONFORMING = 0x4000;
+        /// The module is a library (DLL) rather than a task.
+        const LIBRARY = 0x8000;
+    }
+}
+
+impl NeFlags {
+    /// Returns the name of the OS/2 application type, if one is set.
+    pub fn app_type_name(self) -> Option<&'static str> {
+        match (self & Self::APP_TYPE).bits() >> 8 {
+            1 => Some("NOTWINDOWCOMPAT"),
+            2 => Some("WINDOWCOMPAT"),
+            3 => Some("WINDOWAPI"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NeFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = Vec::new();
+        for shift in 0..16 {
+            let mask = 1 << shift;
+            if (mask & Self::APP_TYPE.bits()) != 0 {
+                // The two bits of the application type form one field.
+                if mask == 0x0100 {
+                    names.extend(self.app_type_name().map(str::to_owned));
+                }
+                continue;
+            }
+            if (self.bits() & mask) == 0 {
+                continue;
+            }
+            names.push(
+                match Self::from_bits(mask).and_then(|flag| flag.iter_names().next()) {
+                    Some((name, _)) => name.to_owned(),
+                    None => format!("0x{:04x}", mask),
+                },
+            );
+        }
+        write_flag_names(f, &names)
+    }
+}
+
+bitflags! {
+    /// The OS/2 flags byte of the header, which Windows uses as well.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Os2Flags: u8 {
+        /// The module supports long file names (OS/2).
+        const LONG_FILENAMES = 0x01;
+        /// A Windows 2.x application that runs in protected mode.
+        const PROTECTED_MODE = 0x02;
+        /// A Windows 2.x application that supports proportional fonts.
+        const PROPORTIONAL_FONTS = 0x04;
+        /// The module has a fast-load (gangload) area.
+        const FAST_LOAD = 0x08;
+    }
+}
+
+impl fmt::Display for Os2Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = self
+            .iter_names()
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<_>>();
+        let unknown = self.bits() & !Self::all().bits();
+        if unknown != 0 {
+            names.push(format!("0x{:02X}", unknown));
+        }
+        write_flag_names(f, &names)
+    }
+}
+
+/// Writes the names of the flags that are set joined by `|`, or `0`.
+pub(crate) fn write_flag_names(f: &mut fmt::Formatter, names: &[String]) -> fmt::Result {
+    if names.is_empty() {
+        write!(f, "0")
+    } else {
+        write!(f, "{}", names.join(" | "))
+    }
+}
+
+/// A change to a header field, written `FIELD=VALUE` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderEdit {
+    /// `expected-win-ver=3.10`
+    ExpectedWinVer { major: u8, minor: u8 },
+    /// `heap-size=4096`
+    HeapSize(u16),
+    /// `stack-size=0x2000`
+    StackSize(u16),
+    /// `library=yes` or `library=no`
+    Library(bool),
+    /// `flags=0x0302`, replacing all the flags
+    Flags(u16),
+}
+
+/// Parses a decimal number or a hexadecimal one prefixed with `0x`.
+fn parse_u16(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("{}: {}", s, e))
+}
+
+impl FromStr for HeaderEdit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, value) = s.split_once('=').ok_or("expected FIELD=VALUE")?;
+        Ok(match field {
+            "expected-win-ver" => {
+                let (major, minor) = value.split_once('.').ok_or("expected MAJOR.MINOR")?;
+                let parse = |s: &str| s.parse::<u8>().map_err(|e| format!("{}: {}", s, e));
+                HeaderEdit::ExpectedWinVer {
+                    major: parse(major)?,
+                    minor: parse(minor)?,
+                }
+            }
+            "heap-size" => HeaderEdit::HeapSize(parse_u16(value)?),
+            "stack-size" => HeaderEdit::StackSize(parse_u16(value)?),
+            "library" => HeaderEdit::Library(match value {
+                "yes" => true,
+                "no" => false,
+                _ => return Err(format!("expected yes or no, got {:?}", value)),
+            }),
+            "flags" => HeaderEdit::Flags(parse_u16(value)?),
+            _ => {
+                return Err(format!(
+                    "unknown field {:?}; expected expected-win-ver, heap-size, stack-size, library or flags",
+                    field
+                ))
+            }
+        })
+    }
+}
+
+/// Returns the name of a value of the target OS byte.
+pub fn target_os_name(target_os: u8) -> Option<&'static str> {
+    Some(match target_os {
+        0 => "unspecified",
+        1 => "OS/2",
+        2 => "Windows",
+        3 => "European MS-DOS 4.x",
+        4 => "Windows/386",
+        5 => "Borland Operating System Services",
+        0x81 => "PharLap 286|DOS-Extender, OS/2",
+        0x82 => "PharLap 286|DOS-Extender, Windows",
+        _ => return None,
+    })
+}
+
+/// Computes the file load CRC of an executable whose NE header is at
+/// `lfanew`: the sum of the file as little-endian dwords, the last one
+/// padded with zeros, with the CRC field itself counted as zero.
+pub fn file_load_crc(file: &[u8], lfanew: usize) -> u32 {
+    let field = lfanew + 0x08..lfanew + 0x0C;
+    file.chunks(4)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut dword = [0; 4];
+            for (j, &byte) in chunk.iter().enumerate() {
+                if !field.contains(&(i * 4 + j)) {
+                    dword[j] = byte;
+                }
+            }
+            u32::from_le_bytes(dword)
+        })
+        .fold(0, u32::wrapping_add)
+}
+
 impl NeHeader {
+    pub fn apply(&mut self, edit: HeaderEdit) {
+        match edit {
+            HeaderEdit::ExpectedWinVer { major, minor } => self.expected_win_ver = [minor, major],
+            HeaderEdit::HeapSize(size) => self.init_heap_size = size.into(),
+            HeaderEdit::StackSize(size) => self.init_stack_size = size.into(),
+            HeaderEdit::Library(is_library) => {
+                let mut flags = self.ne_flags();
+                flags.set(NeFlags::LIBRARY, is_library);
+                self.flags = flags.bits().into();
+            }
+            HeaderEdit::Flags(flags) => self.flags = flags.into(),
+        }
+    }
+
     pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
         let mut buf = [0; 0x40];
         r.read_exact(&mut buf)?;
         Ok(bytemuck::cast(buf))
     }
 
+    /// Returns the file alignment shift count in effect: early linkers leave
+    /// it zero, which the loader takes as 9 (512-byte sectors).
+    pub fn alignment_shift(&self) -> u16 {
+        match self.file_alignment_shift_count.value() {
+            0 => 9,
+            shift => shift.min(15),
+        }
+    }
+
+    pub fn ne_flags(&self) -> NeFlags {
+        NeFlags::from_bits_retain(self.flags.value())
+    }
+
+    pub fn os2_flags(&self) -> Os2Flags {
+        Os2Flags::from_bits_retain(self.os2_exe_flags)
+    }
+
+    /// Returns the file offset and length of the fast-load area, if the
+    /// flags say there is one. With the fast-load flag, the words after the
+    /// flags byte hold its offset and length in alignment units rather than
+    /// the OS/2 thunk offsets.
+    pub fn fast_load_area(&self) -> Option<(u64, u64)> {
+        if !self.os2_flags().contains(Os2Flags::FAST_LOAD) {
+            return None;
+        }
+        let shift = self.alignment_shift();
+        Some((
+            (self.return_thunk_offset.value() as u64) << shift,
+            (self.segment_reference_thunk_offset.value() as u64) << shift,
+        ))
+    }
+
+    /// Tells whether the header is in the format of Windows 1.x and 2.x
+    /// linkers, which predate the expected Windows version field.
+    pub fn is_early_format(&self) -> bool {
+        self.expected_win_ver == [0, 0]
+    }
+
+    /// Describes in a line the platform the module is meant for, by the
+    /// target OS and the expected Windows version.
+    pub fn platform(&self) -> String {
+        let windows = if self.is_early_format() {
+            "Windows 1.x/2.x".to_owned()
+        } else {
+            format!(
+                "Windows {}.{} or later",
+                self.expected_win_ver[1], self.expected_win_ver[0]
+            )
+        };
+        match self.target_os {
+            // Windows 1.x and 2.x linkers often leave the target OS unset.
+            0 => format!("{} (target OS unset)", windows),
+            1 => "OS/2".to_owned(),
+            2 => windows,
+            4 => format!("{}, 386 enhanced mode", windows),
+            0x81 | 0x82 if !self.is_early_format() => {
+                format!("{} ({})", target_os_name(self.target_os).unwrap(), windows)
+            }
+            target_os => match target_os_name(target_os) {
+                Some(name) => name.to_owned(),
+                None => format!("unknown target OS {}", target_os),
+            },
+        }
+    }
+
     pub fn check_magic(&self) -> io::Result<()> {
         if self.magic != *b"NE" {
             return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid magic"));
@@ -132,6 +413,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fast_load_area() {
+        let mut h = NeHeader::zeroed();
+        h.file_alignment_shift_count = 4.into();
+        h.return_thunk_offset = 0x20.into();
+        h.segment_reference_thunk_offset = 0x10.into();
+        assert_eq!(h.fast_load_area(), None);
+        h.os2_exe_flags = (Os2Flags::FAST_LOAD | Os2Flags::PROTECTED_MODE).bits();
+        assert_eq!(h.fast_load_area(), Some((0x200, 0x100)));
+        assert_eq!(h.os2_flags().to_string(), "PROTECTED_MODE | FAST_LOAD");
+    }
+
+    #[test]
+    fn test_alignment_shift() {
+        let mut h = NeHeader::zeroed();
+        assert_eq!(h.alignment_shift(), 9);
+        h.file_alignment_shift_count = 4.into();
+        assert_eq!(h.alignment_shift(), 4);
+    }
+
+    #[test]
+    fn test_header_edit() {
+        let mut h = NeHeader::zeroed();
+        for edit in ["expected-win-ver=3.10", "heap-size=0x400", "library=yes"] {
+            h.apply(edit.parse().unwrap());
+        }
+        assert_eq!(h.expected_win_ver, [10, 3]);
+        assert_eq!(h.init_heap_size.value(), 0x400);
+        assert_eq!(h.ne_flags(), NeFlags::LIBRARY);
+        assert!("heap-size=65536".parse::<HeaderEdit>().is_err());
+        assert!("stack=1".parse::<HeaderEdit>().is_err());
+    }
+
     #[test]
     fn test_ne_header_size() {
         assert_eq!(std::mem::size_of::<NeHeader>(), 0x40);
@@ -182,4 +496,38 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_ne_flags_display() {
+        assert_eq!(NeFlags::empty().to_string(), "0");
+        assert_eq!(
+            NeFlags::from_bits_retain(0x0312).to_string(),
+            "MULTIPLEDATA | CPU_8086 | WINDOWAPI"
+        );
+        assert_eq!(
+            NeFlags::from_bits_retain(0xE109).to_string(),
+            "SINGLEDATA | PROTECTED_MODE_ONLY | NOTWINDOWCOMPAT | LINK_ERROR | NON_CONFORMING | LIBRARY"
+        );
+        assert_eq!(NeFlags::from_bits_retain(0x1000).to_string(), "0x1000");
+    }
+
+    #[test]
+    fn test_platform() {
+        let mut h = NeHeader::zeroed();
+        h.target_os = 2;
+        h.expected_win_ver = [10, 3];
+        assert_eq!(h.platform(), "Windows 3.10 or later");
+        h.target_os = 0;
+        h.expected_win_ver = [0, 0];
+        assert_eq!(h.platform(), "Windows 1.x/2.x (target OS unset)");
+        h.target_os = 0x82;
+        assert_eq!(h.platform(), "PharLap 286|DOS-Extender, Windows");
+        h.expected_win_ver = [0, 3];
+        assert_eq!(
+            h.platform(),
+            "PharLap 286|DOS-Extender, Windows (Windows 3.0 or later)"
+        );
+        h.target_os = 0x42;
+        assert_eq!(h.platform(), "unknown target OS 66");
+    }
 }
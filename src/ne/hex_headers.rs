@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+
+use super::header::{target_os_name, NeFlags, Os2Flags};
+use super::NeExecutable;
+
+/// Decodes the bytes of a header field, named by its path from
+/// [`NeExecutable::spans`].
+pub(super) fn decode_field(path: &str, bytes: &[u8]) -> String {
+    let value = bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, &byte| value << 8 | byte as u64);
+    match (path, bytes.len()) {
+        ("dos_header.magic" | "ne_header.magic", _) => {
+            format!("\"{}\"", String::from_utf8_lossy(bytes).escape_default())
+        }
+        ("ne_header.flags", 2) => {
+            format!(
+                "0x{:04X} ({})",
+                value,
+                NeFlags::from_bits_retain(value as u16)
+            )
+        }
+        ("ne_header.os2_exe_flags", 1) => {
+            format!(
+                "0x{:02X} ({})",
+                value,
+                Os2Flags::from_bits_retain(value as u8)
+            )
+        }
+        ("ne_header.target_os", 1) => match target_os_name(value as u8) {
+            Some(name) => format!("{} ({})", value, name),
+            None => value.to_string(),
+        },
+        ("ne_header.expected_win_ver", 2) => format!("{}.{:02}", bytes[1], bytes[0]),
+        ("ne_header.entry_point" | "ne_header.init_stack", 4) => {
+            format!("{:04X}:{:04X}", value >> 16, value & 0xFFFF)
+        }
+        ("ne_header.file_load_crc", 4) => format!("0x{:08X}", value),
+        (_, 1 | 2 | 4) => format!("0x{:0width$X} ({})", value, value, width = 2 * bytes.len()),
+        _ => bytes
+            .chunks(2)
+            .map(|word| format!("{:02X}{:02X}", word.get(1).unwrap_or(&0), word[0]))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+impl NeExecutable {
+    /// Prints the DOS and NE headers field by field: the offset, the raw
+    /// bytes, the name and the decoded value.
+    pub(crate) fn describe_hex_headers<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut section = "";
+        for field in self.spans() {
+            let (title, name) = if let Some(name) = field.path.strip_prefix("dos_header.") {
+                ("DOS header", name)
+            } else if let Some(name) = field.path.strip_prefix("ne_header.") {
+                ("NE header", name)
+            } else {
+                continue;
+            };
+            if section != title {
+                if !section.is_empty() {
+                    writeln!(w)?;
+                }
+                writeln!(w, "{}:", title)?;
+                section = title;
+            }
+            let range = field.span.range();
+            let bytes = self
+                .file_data
+                .get(range.start as usize..range.end as usize)
+                .unwrap_or_default();
+            // Long fields continue on further lines, 8 bytes each.
+            let mut lines = bytes.chunks(8).map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            });
+            writeln!(
+                w,
+                "  {:08X}  {:<23}  {:<31}  {}",
+                range.start,
+                lines.next().unwrap_or_default(),
+                name,
+                decode_field(&field.path, bytes)
+            )?;
+            for (i, hex) in lines.enumerate() {
+                writeln!(w, "  {:08X}  {}", range.start + 8 * (i as u64 + 1), hex)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_field() {
+        assert_eq!(decode_field("ne_header.magic", b"NE"), "\"NE\"");
+        assert_eq!(
+            decode_field("ne_header.segment_count", &[3, 1]),
+            "0x0103 (259)"
+        );
+        assert_eq!(decode_field("ne_header.expected_win_ver", &[10, 3]), "3.10");
+        assert_eq!(
+            decode_field("ne_header.entry_point", &[0x10, 0, 1, 0]),
+            "0001:0010"
+        );
+        assert_eq!(
+            decode_field("dos_header.res", &[1, 0, 0x34, 0x12, 0, 0, 0, 0]),
+            "0001 1234 0000 0000"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// Returns the linear address segment `number` (1-based) is placed at in
+/// exported databases: one 64KiB block per segment.
+pub(crate) fn linear_address(segment: u16, offset: u16) -> u32 {
+    (segment as u32) << 16 | offset as u32
+}
+
+/// Quotes a string for IDC.
+fn idc_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl NeExecutable {
+    /// Prints an IDC script that recreates the segments, names and fixup
+    /// comments in an empty IDA database.
+    ///
+    /// Segment N is placed at N0000h. The segment data is loaded from `path`,
+    /// except for iterated segments, whose expanded data is patched in byte by
+    /// byte.
+    pub(crate) fn describe_idc<W: Write>(
+        &self,
+        w: &mut W,
+        path: &Path,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        writeln!(
+            w,
+            "// IDC script for {}, generated by win16ne.",
+            module_name
+        )?;
+        writeln!(
+            w,
+            "// Run it on an empty database; segment N is placed at N0000h."
+        )?;
+        writeln!(w, "#include <idc.idc>")?;
+        writeln!(w)?;
+        writeln!(w, "static main() {{")?;
+        writeln!(w, "    auto file;")?;
+        writeln!(
+            w,
+            "    file = fopen({}, \"rb\");",
+            idc_string(&path.display().to_string())
+        )?;
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let number = i as u16 + 1;
+            let start = linear_address(number, 0);
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let size = segment.min_alloc().max(data.len() as u64).min(0x10000);
+            writeln!(w)?;
+            writeln!(
+                w,
+                "    add_segm_ex(0x{:X}, 0x{:X}, 0x{:X}, 0, saRelPara, scPub, ADDSEG_NOSREG);",
+                start,
+                start as u64 + size,
+                start >> 4
+            )?;
+            writeln!(w, "    set_segm_name(0x{:X}, \"SEG{}\");", start, number)?;
+            writeln!(
+                w,
+                "    set_segm_class(0x{:X}, \"{}\");",
+                start,
+                if segment.is_code() { "CODE" } else { "DATA" }
+            )?;
+            if data.is_empty() {
+                continue;
+            }
+            if segment.is_iterated() {
+                for (offset, byte) in data.iter().enumerate() {
+                    writeln!(
+                        w,
+                        "    patch_byte(0x{:X}, 0x{:02X});",
+                        start as usize + offset,
+                        byte
+                    )?;
+                }
+            } else {
+                writeln!(
+                    w,
+                    "    loadfile(file, 0x{:X}, 0x{:X}, 0x{:X});",
+                    segment.data_offset(),
+                    start,
+                    data.len()
+                )?;
+            }
+        }
+        writeln!(w)?;
+        writeln!(w, "    fclose(file);")?;
+
+        writeln!(w)?;
+        for export in self.exports() {
+            if let Some(name) = &export.name {
+                if (1..=self.segment_entries.len()).contains(&(export.segment as usize)) {
+                    writeln!(
+                        w,
+                        "    add_entry({}, 0x{:X}, {}, {});",
+                        export.ordinal,
+                        linear_address(export.segment as u16, export.offset),
+                        idc_string(&codepage.decode(&name.name)),
+                        self.segment_entries[export.segment as usize - 1].is_code() as u8
+                    )?;
+                }
+            }
+        }
+        let entry_point = self.ne_header.entry_point.value();
+        if entry_point >> 16 != 0 {
+            let ea = linear_address((entry_point >> 16) as u16, entry_point as u16);
+            writeln!(w, "    add_entry(0x{:X}, 0x{:X}, \"start\", 1);", ea, ea)?;
+        }
+        for ((segment, offset), name) in self.named_addresses(codepage) {
+            writeln!(
+                w,
+                "    set_name(0x{:X}, {}, SN_NOWARN);",
+                linear_address(segment, offset),
+                idc_string(&name)
+            )?;
+        }
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            for (offset, comment) in self.fixup_annotations(segment, &data, codepage) {
+                writeln!(
+                    w,
+                    "    set_cmt(0x{:X}, {}, 0);",
+                    linear_address(i as u16 + 1, offset as u16),
+                    idc_string(&comment)
+                )?;
+            }
+        }
+        writeln!(w, "    auto_wait();")?;
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idc_string() {
+        assert_eq!(idc_string(r#"C:\WIN\"A".EXE"#), r#""C:\\WIN\\\"A\".EXE""#);
+        assert_eq!(linear_address(2, 0x10), 0x20010);
+    }
+}
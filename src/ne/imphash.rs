@@ -0,0 +1,46 @@
+use md5::{Digest, Md5};
+
+use super::imports::ImportedSymbol;
+use super::NeExecutable;
+
+impl NeExecutable {
+    /// Returns the imports as `module.symbol` strings, lowercase, sorted and
+    /// without duplicates. Functions imported by ordinal are written `ordN`.
+    pub fn normalized_imports(&self) -> Vec<String> {
+        let mut imports = self
+            .imports()
+            .into_iter()
+            .flat_map(|module| {
+                let module_name = String::from_utf8_lossy(&module.name).to_ascii_lowercase();
+                module
+                    .symbols
+                    .into_iter()
+                    .map(move |import| match import.symbol {
+                        ImportedSymbol::Ordinal(ordinal) => {
+                            format!("{}.ord{}", module_name, ordinal)
+                        }
+                        ImportedSymbol::Name(name) => format!(
+                            "{}.{}",
+                            module_name,
+                            String::from_utf8_lossy(&name).to_ascii_lowercase()
+                        ),
+                    })
+            })
+            .collect::<Vec<_>>();
+        imports.sort();
+        imports.dedup();
+        imports
+    }
+
+    /// Computes an import hash in the manner of the PE imphash: the MD5 of
+    /// the normalized imports joined by commas, in hex. Modules that import
+    /// the same functions get the same hash whatever their order in the
+    /// relocation records. Returns `None` if nothing is imported.
+    pub fn imphash(&self) -> Option<String> {
+        let imports = self.normalized_imports();
+        if imports.is_empty() {
+            return None;
+        }
+        Some(format!("{:x}", Md5::digest(imports.join(",").as_bytes())))
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use super::module_reference_table::ModuleReferenceTable;
+use super::resource_table::name_len;
+
+/// The imported names table.
+///
+/// The table has no stated length, so only the names referred to from
+/// relocation records are read, keyed by their offset in the table.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedNameTable {
+    pub names: BTreeMap<u16, Vec<u8>>,
+}
+
+impl ImportedNameTable {
+    pub fn read_names<R, I>(&mut self, r: &mut R, offset: u64, name_offsets: I) -> io::Result<()>
+    where
+        R: Read + Seek,
+        I: IntoIterator<Item = u16>,
+    {
+        for name_offset in name_offsets {
+            if self.names.contains_key(&name_offset) {
+                continue;
+            }
+            r.seek(SeekFrom::Start(offset + name_offset as u64))?;
+            let len = {
+                let mut len = 0;
+                r.read_exact(std::slice::from_mut(&mut len))?;
+                len
+            };
+            let mut name = vec![0; len as usize];
+            r.read_exact(&mut name)?;
+            self.names.insert(name_offset, name);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name_offset: u16) -> Option<&[u8]> {
+        self.names.get(&name_offset).map(|name| &name[..])
+    }
+
+    /// Writes the table with the imported names and the module names at
+    /// their offsets. Gaps, such as the customary empty name at offset 0,
+    /// are filled with zeros.
+    pub fn write<W: Write>(
+        &self,
+        w: &mut W,
+        module_reference_table: &ModuleReferenceTable,
+    ) -> io::Result<()> {
+        let module_names = module_reference_table
+            .entries
+            .iter()
+            .map(|entry| (entry.header.offset, &entry.name));
+        let mut names = self
+            .names
+            .iter()
+            .map(|(&offset, name)| (offset, name))
+            .collect::<Vec<_>>();
+        names.extend(module_names);
+        names.sort();
+        names.dedup();
+
+        let mut table = Vec::new();
+        for (offset, name) in names {
+            let offset = offset as usize;
+            let mut encoded = vec![name_len(name)?];
+            encoded.extend_from_slice(name);
+            if offset < table.len() {
+                if table.get(offset..offset + encoded.len()) != Some(&encoded[..]) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("imported names overlap at 0x{:04X}", offset),
+                    ));
+                }
+                continue;
+            }
+            table.resize(offset, 0);
+            table.extend_from_slice(&encoded);
+        }
+        w.write_all(&table)
+    }
+}
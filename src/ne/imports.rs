@@ -0,0 +1,341 @@
+use log::{debug, warn};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::relocation_table::RelocationTarget;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// A function imported from another module.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImportedSymbol {
+    Ordinal(u16),
+    Name(Vec<u8>),
+}
+
+/// The functions imported from one referenced module.
+#[derive(Debug, Clone)]
+pub struct ImportedModule {
+    /// The 1-based index in the module reference table.
+    pub module: u16,
+    pub name: Vec<u8>,
+    pub symbols: Vec<Import>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub symbol: ImportedSymbol,
+    /// The number of locations patched to refer to the symbol.
+    pub references: usize,
+}
+
+impl NeExecutable {
+    /// Collects the imported functions from the relocation records of all segments.
+    pub fn imports(&self) -> Vec<ImportedModule> {
+        let mut symbols = BTreeMap::<u16, BTreeMap<ImportedSymbol, usize>>::new();
+        for segment in &self.segment_entries {
+            let relocations = if let Some(relocations) = &segment.relocations {
+                relocations
+            } else {
+                continue;
+            };
+            let data = segment.expanded_data().ok().flatten();
+            for entry in &relocations.entries {
+                let (module, symbol) = match self.imported_symbol(entry.target) {
+                    Some(import) => import,
+                    None => continue,
+                };
+                let references = data
+                    .as_ref()
+                    .and_then(|data| entry.sites(data).ok())
+                    .map_or(1, |sites| sites.len());
+                *symbols
+                    .entry(module)
+                    .or_default()
+                    .entry(symbol)
+                    .or_default() += references;
+            }
+        }
+
+        // Keep references to modules missing from the table so they aren't lost.
+        let unknown_modules = symbols
+            .keys()
+            .copied()
+            .filter(|&module| self.module_name(module).is_none())
+            .collect::<Vec<_>>();
+        (1..=self.module_reference_table.entries.len() as u16)
+            .chain(unknown_modules)
+            .map(|module| ImportedModule {
+                module,
+                name: self.module_name(module).unwrap_or_default().to_owned(),
+                symbols: symbols
+                    .remove(&module)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(symbol, references)| Import { symbol, references })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the 1-based module index and the symbol a relocation imports, if any.
+    pub fn imported_symbol(&self, target: RelocationTarget) -> Option<(u16, ImportedSymbol)> {
+        match target {
+            RelocationTarget::ImportOrdinal { module, ordinal } => {
+                Some((module, ImportedSymbol::Ordinal(ordinal)))
+            }
+            RelocationTarget::ImportName {
+                module,
+                name_offset,
+            } => {
+                let name = self
+                    .imported_name_table
+                    .get(name_offset)
+                    .unwrap_or_default();
+                Some((module, ImportedSymbol::Name(name.to_owned())))
+            }
+            _ => None,
+        }
+    }
+
+    /// Locates the referenced modules in the given directories and loads their
+    /// export tables, so that functions imported by ordinal can be named.
+    ///
+    /// Returns the names of the modules that weren't found.
+    pub fn load_imported_modules(&mut self, search_path: &[PathBuf]) -> Vec<Vec<u8>> {
+        let mut missing = Vec::new();
+        for (i, entry) in self.module_reference_table.entries.iter().enumerate() {
+            let path = if let Some(path) = find_module(&entry.name, search_path) {
+                path
+            } else {
+                missing.push(entry.name.clone());
+                continue;
+            };
+            debug!("loading {}", path.display());
+            let module =
+                match File::open(&path).and_then(|f| NeExecutable::read(&mut BufReader::new(f))) {
+                    Ok(module) => module,
+                    Err(e) => {
+                        warn!("{}: {}", path.display(), e);
+                        missing.push(entry.name.clone());
+                        continue;
+                    }
+                };
+            let exports = module
+                .exports()
+                .into_iter()
+                .filter_map(|export| Some((export.ordinal, export.name?.name)))
+                .collect();
+            self.imported_module_exports.insert(i as u16 + 1, exports);
+        }
+        missing
+    }
+
+    pub(crate) fn describe_imports<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        writeln!(w, "Imports of {}:", module_name)?;
+        for module in self.imports() {
+            if module.name.is_empty() {
+                writeln!(w, "    <module {}>", module.module)?;
+            } else {
+                writeln!(w, "    {}", codepage.decode(&module.name))?;
+            }
+            for import in &module.symbols {
+                let symbol = match &import.symbol {
+                    ImportedSymbol::Ordinal(ordinal) => {
+                        match self.imported_ordinal_name(module.module, *ordinal) {
+                            Some(name) => format!("{} (@{})", codepage.decode(name), ordinal),
+                            None => format!("@{}", ordinal),
+                        }
+                    }
+                    ImportedSymbol::Name(name) => codepage.decode(name),
+                };
+                writeln!(w, "        {:<32} {:>5} ref(s)", symbol, import.references)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The extensions tried when looking for a module file, in order of preference.
+const MODULE_EXTENSIONS: [&str; 4] = ["DLL", "EXE", "DRV", ""];
+
+/// Finds the file of a module, e.g. `VBRUN300.DLL` for `VBRUN300`.
+fn find_module(name: &[u8], search_path: &[PathBuf]) -> Option<PathBuf> {
+    for dir in search_path {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("{}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        let found = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter_map(|path| Some((module_extension_rank(&path, name)?, path)))
+            .min();
+        if let Some((_, path)) = found {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Returns the preference of the file if it can hold the named module.
+fn module_extension_rank(path: &Path, name: &[u8]) -> Option<usize> {
+    let stem = path.file_stem()?.to_string_lossy();
+    if !stem.as_bytes().eq_ignore_ascii_case(name) {
+        return None;
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_uppercase())
+        .unwrap_or_default();
+    MODULE_EXTENSIONS.iter().position(|&ext| ext == extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::{RelocationEntry, SOURCE_FAR_ADDR};
+    use super::super::resident_name_table::ResidentNameEntry;
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    /// The sample calling `USER.MessageBox` once and `KERNEL.3` twice, and
+    /// ordinal 1 of a module missing from the table.
+    fn with_imports() -> NeExecutable {
+        let mut exe = sample_exe();
+        // Three far calls, linked in a chain from 0001 through 0006 to 000B.
+        set_code(
+            &mut exe,
+            b"\x9A\x06\x00\x00\x00\x9A\x0B\x00\x00\x00\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        for retarget in [
+            "1:0006=USER.MessageBox",
+            "1:0001=KERNEL.3",
+            "1:000B=KERNEL.3",
+        ] {
+            exe.retarget(&retarget.parse().unwrap()).unwrap();
+        }
+        let relocations = exe.segment_entries[0].relocations.as_mut().unwrap();
+        relocations.entries.push(RelocationEntry {
+            source_type: SOURCE_FAR_ADDR,
+            flags: 4,
+            offset: 0x0F,
+            target: RelocationTarget::ImportOrdinal {
+                module: 5,
+                ordinal: 1,
+            },
+        });
+        exe
+    }
+
+    #[test]
+    fn test_imports() {
+        let imports = with_imports()
+            .imports()
+            .into_iter()
+            .map(|module| {
+                let symbols = module
+                    .symbols
+                    .into_iter()
+                    .map(|import| (import.symbol, import.references))
+                    .collect::<Vec<_>>();
+                (module.module, module.name, symbols)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            imports,
+            [
+                (
+                    1,
+                    b"USER".to_vec(),
+                    vec![(ImportedSymbol::Name(b"MessageBox".to_vec()), 1)]
+                ),
+                (2, b"KERNEL".to_vec(), vec![(ImportedSymbol::Ordinal(3), 2)]),
+                (5, Vec::new(), vec![(ImportedSymbol::Ordinal(1), 1)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_imports() {
+        let mut output = Vec::new();
+        with_imports()
+            .describe_imports(&mut output, Codepage::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            [
+                "Imports of TEST:",
+                "    USER",
+                "        MessageBox                           1 ref(s)",
+                "    KERNEL",
+                "        GETVERSION (@3)                      2 ref(s)",
+                "    <module 5>",
+                "        @1                                   1 ref(s)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_load_imported_modules() {
+        let dir = std::env::temp_dir().join(format!("win16ne-imports-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // USER.DLL exports MessageBox as ordinal 1; USER.EXE is less preferred.
+        let mut user = sample_exe();
+        user.resident_name_table.entries[0].name = b"USER".to_vec();
+        user.resident_name_table.entries.push(ResidentNameEntry {
+            name: b"MessageBox".to_vec(),
+            index: 1,
+        });
+        let mut data = Vec::new();
+        user.write(&mut io::Cursor::new(&mut data)).unwrap();
+        fs::write(dir.join("user.dll"), &data).unwrap();
+        fs::write(dir.join("USER.EXE"), b"not an executable").unwrap();
+
+        let mut exe = with_imports();
+        exe.retarget(&"1:0006=USER.1".parse().unwrap()).unwrap();
+        let missing = exe.load_imported_modules(std::slice::from_ref(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(missing, [b"KERNEL".to_vec()]);
+        // The loaded names come before the built-in ones, which fill the gaps.
+        assert_eq!(exe.imported_ordinal_name(1, 1), Some(&b"MessageBox"[..]));
+        assert_eq!(
+            exe.imported_ordinal_name(1, 2),
+            Some(&b"OLDEXITWINDOWS"[..])
+        );
+    }
+
+    #[test]
+    fn test_module_extension_rank() {
+        let rank = |path: &str| module_extension_rank(Path::new(path), b"VBRUN300");
+        assert_eq!(rank("dir/vbrun300.dll"), Some(0));
+        assert_eq!(rank("VBRUN300.EXE"), Some(1));
+        assert_eq!(rank("VBRUN300"), Some(3));
+        assert_eq!(rank("VBRUN300.TXT"), None);
+        assert_eq!(rank("VBRUN200.DLL"), None);
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// What a range of the file holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionKind {
+    /// A structure of the format, named.
+    Structure(String),
+    /// Zeros between structures, as left by alignment.
+    Padding,
+    /// Nonzero bytes that no structure accounts for.
+    Unknown,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionKind::Structure(name) => f.write_str(name),
+            RegionKind::Padding => f.write_str("padding"),
+            RegionKind::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<u64>,
+    pub kind: RegionKind,
+}
+
+/// Bytes claimed by two structures at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    pub range: Range<u64>,
+    pub first: String,
+    pub second: String,
+}
+
+/// The map of the file from [`NeExecutable::layout`].
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    /// The structures and the gaps between them, ordered by offset.
+    pub regions: Vec<Region>,
+    pub overlaps: Vec<Overlap>,
+}
+
+impl NeExecutable {
+    /// Returns the ranges of the file taken by each structure, whether or
+    /// not they lie within the file.
+    pub fn structures(&self, codepage: Codepage) -> Vec<(Range<u64>, String)> {
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let ne_header = &self.ne_header;
+        let table = |offset: u16| lfanew + offset as u64;
+        let mut structures = Vec::new();
+        let mut add = |start: u64, length: u64, name: String| {
+            if length != 0 {
+                structures.push((start..start + length, name));
+            }
+        };
+
+        let dos_header_end = lfanew.min(0x40);
+        add(0, dos_header_end, "DOS header".to_owned());
+        add(
+            dos_header_end,
+            lfanew - dos_header_end,
+            "DOS stub".to_owned(),
+        );
+        add(lfanew, 0x40, "NE header".to_owned());
+        add(
+            table(ne_header.segment_table_offset.value()),
+            8 * self.segment_entries.len() as u64,
+            "segment table".to_owned(),
+        );
+        let resource_table = &self.resource_table;
+        if !resource_table.resource_types.is_empty() || !resource_table.names.is_empty() {
+            let mut data = Vec::new();
+            if resource_table.write(&mut data).is_ok() {
+                add(
+                    table(ne_header.resource_table_offset.value()),
+                    data.len() as u64,
+                    "resource table".to_owned(),
+                );
+            }
+        }
+        let mut data = Vec::new();
+        if self.resident_name_table.write(&mut data).is_ok() {
+            add(
+                table(ne_header.resident_names_table_offset.value()),
+                data.len() as u64,
+                "resident name table".to_owned(),
+            );
+        }
+        add(
+            table(ne_header.module_reference_table_offset.value()),
+            2 * self.module_reference_table.entries.len() as u64,
+            "module reference table".to_owned(),
+        );
+        // The imported names have no length of their own; they run up to the
+        // entry table that follows them in linker output.
+        add(
+            table(ne_header.import_name_table_offset.value()),
+            ne_header
+                .entry_table_offset
+                .value()
+                .saturating_sub(ne_header.import_name_table_offset.value()) as u64,
+            "imported name table".to_owned(),
+        );
+        add(
+            table(ne_header.entry_table_offset.value()),
+            ne_header.entry_table_length.value() as u64,
+            "entry table".to_owned(),
+        );
+        add(
+            ne_header.non_resident_names_table_offset.value() as u64,
+            ne_header.non_resident_names_size.value() as u64,
+            "nonresident name table".to_owned(),
+        );
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            let data_end = segment.data_offset() + segment.data_length();
+            add(
+                segment.data_offset(),
+                segment.data_length(),
+                format!("segment {}", i + 1),
+            );
+            if let Some(relocations) = &segment.relocations {
+                add(
+                    data_end,
+                    relocations.file_size(),
+                    format!("segment {} relocations", i + 1),
+                );
+            }
+        }
+        let resource_name = |id, is_type| match resource_table.names.get(&id) {
+            Some(name) if (id & 0x8000) == 0 => codepage.decode(name),
+            _ => describe_resource_id(id, is_type),
+        };
+        for resource_type in &resource_table.resource_types {
+            for resource in &resource_type.resources {
+                let (offset, length) = resource_table.data_range(resource);
+                add(
+                    offset,
+                    length,
+                    format!(
+                        "resource {} {}",
+                        resource_name(resource_type.header.type_id, true),
+                        resource_name(resource.header.resource_id, false)
+                    ),
+                );
+            }
+        }
+        if let Some(overlay) = self.overlay() {
+            let name = match self.overlay_kind() {
+                Some(kind) => format!("overlay ({})", kind),
+                None => "overlay".to_owned(),
+            };
+            add(overlay.start, overlay.end - overlay.start, name);
+        }
+        structures.sort_by_key(|(range, _)| (range.start, range.end));
+        structures
+    }
+
+    /// Maps every byte of the file to the structure that owns it, calling
+    /// the gaps between structures padding if they're zeros and unknown
+    /// otherwise, and noting where structures overlap.
+    pub fn layout(&self, codepage: Codepage) -> Layout {
+        let file_size = self.file_data.len() as u64;
+        let mut layout = Layout::default();
+        let gap = |layout: &mut Layout, range: Range<u64>| {
+            let bytes = &self.file_data[range.start as usize..range.end as usize];
+            let kind = if bytes.iter().all(|&byte| byte == 0) {
+                RegionKind::Padding
+            } else {
+                RegionKind::Unknown
+            };
+            layout.regions.push(Region { range, kind });
+        };
+        // The end of the structures so far, and the one reaching furthest.
+        let mut covered = 0;
+        let mut furthest = String::new();
+        for (range, name) in self.structures(codepage) {
+            let gap_end = range.start.min(file_size);
+            if covered < gap_end {
+                gap(&mut layout, covered..gap_end);
+            }
+            if range.start < covered {
+                layout.overlaps.push(Overlap {
+                    range: range.start..range.end.min(covered),
+                    first: furthest.clone(),
+                    second: name.clone(),
+                });
+            }
+            if range.end > covered {
+                covered = range.end;
+                furthest = name.clone();
+            }
+            layout.regions.push(Region {
+                range,
+                kind: RegionKind::Structure(name),
+            });
+        }
+        if covered < file_size {
+            gap(&mut layout, covered..file_size);
+        }
+        layout
+    }
+
+    /// Prints the [`Layout`], one range per line.
+    pub(crate) fn describe_layout<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let file_size = self.file_data.len() as u64;
+        let layout = self.layout(codepage);
+        for region in &layout.regions {
+            let Range { start, end } = region.range;
+            write!(
+                w,
+                "0x{:08X}..0x{:08X} {:>8}  {}",
+                start,
+                end,
+                end - start,
+                region.kind
+            )?;
+            if end > file_size {
+                write!(w, " (past the end of file)")?;
+            }
+            writeln!(w)?;
+        }
+        for overlap in &layout.overlaps {
+            writeln!(
+                w,
+                "Overlap: 0x{:08X}..0x{:08X} is in both {} and {}",
+                overlap.range.start, overlap.range.end, overlap.first, overlap.second
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::string_table::RT_STRING;
+    use super::super::tests::{rewrite, sample, sample_exe, set_resources};
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let exe = sample_exe();
+        let layout = exe.layout(Codepage::default());
+        let regions = layout
+            .regions
+            .iter()
+            .map(|region| (region.range.clone(), region.kind.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(regions[0], (0..0x40, "DOS header".to_owned()));
+        assert_eq!(regions[1], (0x40..0x80, "NE header".to_owned()));
+        assert!(regions.contains(&(0x96..0x9E, "nonresident name table".to_owned())));
+        assert!(regions.contains(&(0x9E..0xA0, "unknown".to_owned())));
+        assert!(regions.contains(&(0xA0..0xA5, "segment 1".to_owned())));
+        assert_eq!(
+            layout.regions.last().unwrap().kind,
+            RegionKind::Structure("overlay".to_owned())
+        );
+        assert!(layout.overlaps.is_empty());
+
+        // Move the nonresident name table over the end of the entry table.
+        let mut data = sample();
+        data[0x40 + 0x2C] = 0x94;
+        let exe = NeExecutable::read(&mut io::Cursor::new(data)).unwrap();
+        let overlaps = exe.layout(Codepage::default()).overlaps;
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].range, 0x94..0x96);
+        assert_eq!(overlaps[0].first, "entry table");
+        assert_eq!(overlaps[0].second, "nonresident name table");
+    }
+
+    #[test]
+    fn test_layout_resource_names() {
+        let mut exe = sample_exe();
+        // The name follows the table: the shift, one type, one resource and the terminator.
+        set_resources(&mut exe, &[(RT_STRING, 0x18, b"\x05Hello")]);
+        exe.resource_table.names.insert(0x18, b"HELLO".to_vec());
+        let exe = rewrite(&exe);
+        let mut output = Vec::new();
+        exe.describe_layout(&mut output, Codepage::default())
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output
+            .lines()
+            .any(|line| line.ends_with("  resource STRING HELLO")));
+    }
+}
@@ -0,0 +1,259 @@
+use std::io::{self, Write};
+
+use super::symbol_list::CONSTANT_SEGMENT;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The segment names and public symbols of a linker map (`.MAP`), as written
+/// by Microsoft LINK or Watcom WLINK.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapFile {
+    /// Segment numbers, offsets and names, from the segment list.
+    pub segments: Vec<(u16, u16, Vec<u8>)>,
+    /// Segment numbers, offsets and names of the public symbols, without
+    /// absolute and imported symbols.
+    pub publics: Vec<(u16, u16, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Other,
+    Segments,
+    Publics,
+}
+
+/// Parses a `SSSS:OOOO` address, ignoring the markers WLINK appends (`+`, `*`, ...).
+fn parse_address(token: &[u8]) -> Option<(u16, u16)> {
+    let end = token
+        .iter()
+        .rposition(u8::is_ascii_hexdigit)
+        .map_or(0, |pos| pos + 1);
+    let text = std::str::from_utf8(&token[..end]).ok()?;
+    let (segment, offset) = text.split_once(':')?;
+    if segment.len() != 4 || offset.len() != 4 {
+        return None;
+    }
+    Some((
+        u16::from_str_radix(segment, 16).ok()?,
+        u16::from_str_radix(offset, 16).ok()?,
+    ))
+}
+
+impl MapFile {
+    pub fn parse(data: &[u8]) -> Self {
+        let mut map = Self::default();
+        let mut section = Section::Other;
+        for line in data.split(|&byte| byte == b'\n') {
+            let tokens = line
+                .split(u8::is_ascii_whitespace)
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>();
+            let has = |word: &[u8]| tokens.contains(&word);
+            if has(b"Publics") || (has(b"Address") && has(b"Symbol")) {
+                section = Section::Publics;
+                continue;
+            }
+            if (has(b"Start") && has(b"Length")) || (has(b"Segment") && has(b"Class")) {
+                section = Section::Segments;
+                continue;
+            }
+            let first = match tokens.first() {
+                Some(first) => first,
+                None => continue,
+            };
+            match section {
+                Section::Segments => {
+                    // LINK: START LENGTH NAME CLASS; WLINK: NAME CLASS GROUP ADDRESS SIZE.
+                    let segment = match parse_address(first) {
+                        Some(address) => tokens.get(2).map(|name| (address, name)),
+                        None => tokens
+                            .iter()
+                            .find_map(|token| parse_address(token))
+                            .map(|address| (address, first)),
+                    };
+                    if let Some(((segment, offset), name)) = segment {
+                        map.segments.push((segment, offset, name.to_vec()));
+                    }
+                }
+                Section::Publics => {
+                    let address = match parse_address(first) {
+                        Some(address) => address,
+                        None => continue,
+                    };
+                    match tokens.get(1) {
+                        Some(&(b"Abs" | b"Imp")) | None => {}
+                        Some(name) => map.publics.push((address.0, address.1, name.to_vec())),
+                    }
+                }
+                Section::Other => {}
+            }
+        }
+        map
+    }
+}
+
+impl NeExecutable {
+    /// Names addresses and segments after a linker map.
+    ///
+    /// Segments listed at offset 0 give their names to the segments.
+    pub fn load_map_file(&mut self, map: &MapFile) {
+        for (segment, offset, name) in &map.segments {
+            if *offset == 0 {
+                self.segment_names.insert(*segment, name.clone());
+            }
+        }
+        for (segment, offset, name) in &map.publics {
+            self.debug_symbols.insert((*segment, *offset), name.clone());
+        }
+    }
+
+    /// Prints a linker map in the format of Microsoft LINK: the segment list,
+    /// the public symbols by name and by value, and the entry point.
+    ///
+    /// Segments are listed by number at offset 0, so `SSSS:OOOO` addresses
+    /// are segment numbers as elsewhere. Only exported names and debug
+    /// symbols are listed; exported constants are marked `Abs`.
+    pub(crate) fn describe_map<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_default();
+        writeln!(w)?;
+        writeln!(w, " {}", module_name)?;
+        writeln!(w)?;
+        writeln!(w, " Start     Length     Name                   Class")?;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let number = i as u16 + 1;
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let length = segment.min_alloc().max(data.len() as u64).min(0x10000);
+            let name = self
+                .segment_names
+                .get(&number)
+                .map(|name| codepage.decode(name))
+                .unwrap_or_else(|| format!("SEG{}", number));
+            let class = if segment.is_code() { "CODE" } else { "DATA" };
+            writeln!(
+                w,
+                " {:04X}:0000 {:05X}H     {:<22} {}",
+                number, length, name, class
+            )?;
+        }
+
+        let mut publics = self
+            .public_names(codepage)
+            .into_iter()
+            .map(|((segment, offset), name)| (segment, offset, false, name))
+            .collect::<Vec<_>>();
+        for export in self.exports() {
+            if let (Some(name), CONSTANT_SEGMENT) = (&export.name, export.segment) {
+                publics.push((0, export.offset, true, codepage.decode(&name.name)));
+            }
+        }
+        let print = |w: &mut W, publics: &[(u16, u16, bool, String)]| -> io::Result<()> {
+            for (segment, offset, is_constant, name) in publics {
+                let kind = if *is_constant { "Abs" } else { "" };
+                writeln!(w, " {:04X}:{:04X}  {:<3}  {}", segment, offset, kind, name)?;
+            }
+            Ok(())
+        };
+
+        publics.sort_by_key(|public| (public.3.to_uppercase(), public.0, public.1));
+        writeln!(w)?;
+        writeln!(w, "  Address         Publics by Name")?;
+        writeln!(w)?;
+        print(w, &publics)?;
+
+        publics.sort();
+        writeln!(w)?;
+        writeln!(w, "  Address         Publics by Value")?;
+        writeln!(w)?;
+        print(w, &publics)?;
+
+        let entry_point = self.ne_header.entry_point.value();
+        if entry_point >> 16 != 0 {
+            writeln!(w)?;
+            writeln!(
+                w,
+                "Program entry point at {:04X}:{:04X}",
+                entry_point >> 16,
+                entry_point & 0xFFFF
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = b"
+ TESTAPP
+
+ Start     Length     Name                   Class
+ 0001:0000 00016H     _TEXT                  CODE
+ 0002:0000 00100H     _DATA                  DATA
+
+  Address         Publics by Value
+
+ 0000:0000  Abs  __acrtused
+ 0000:0000  Imp  GETVERSION           (KERNEL.3)
+ 0001:0000       _main
+ 0001:0010+      _helper
+
+Program entry point at 0001:0000
+";
+        let map = MapFile::parse(text);
+        assert_eq!(
+            map.segments,
+            vec![(1, 0, b"_TEXT".to_vec()), (2, 0, b"_DATA".to_vec())]
+        );
+        assert_eq!(
+            map.publics,
+            vec![(1, 0, b"_main".to_vec()), (1, 0x10, b"_helper".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_describe_map() {
+        let mut exe = sample_exe();
+        exe.segment_names.insert(1, b"_TEXT".to_vec());
+        exe.debug_symbols.insert((1, 3), b"_leave".to_vec());
+        exe.debug_symbols.insert((1, 0), b"_main".to_vec());
+        let mut output = Vec::new();
+        exe.describe_map(&mut output, Codepage::default()).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(
+            text,
+            [
+                "",
+                " TEST",
+                "",
+                " Start     Length     Name                   Class",
+                " 0001:0000 00005H     _TEXT                  CODE",
+                "",
+                "  Address         Publics by Name",
+                "",
+                " 0001:0003       _leave",
+                " 0001:0000       _main",
+                "",
+                "  Address         Publics by Value",
+                "",
+                " 0001:0000       _main",
+                " 0001:0003       _leave",
+                "",
+                "Program entry point at 0001:0000",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // The map reads back.
+        let map = MapFile::parse(text.as_bytes());
+        assert_eq!(map.segments, vec![(1, 0, b"_TEXT".to_vec())]);
+        assert!(map.publics.contains(&(1, 3, b"_leave".to_vec())));
+    }
+}
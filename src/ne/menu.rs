@@ -0,0 +1,108 @@
+use crate::util::bytes::{cstr_at, u16_at};
+
+/// The integer ID of the menu resource type.
+pub const RT_MENU: u16 = 0x8004;
+
+/// The item opens a submenu, whose items follow it.
+pub const MF_POPUP: u16 = 0x0010;
+/// The item is the last of its menu.
+pub const MF_END: u16 = 0x0080;
+
+/// An item of a `MENU` resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuItem {
+    /// The `MF_` flags, including [`MF_POPUP`] and [`MF_END`].
+    pub flags: u16,
+    /// The command ID, which popups don't have.
+    pub id: Option<u16>,
+    /// The text, with `&` before the mnemonic and `\t` before the shortcut.
+    /// Separators have none.
+    pub text: Vec<u8>,
+    pub children: Vec<MenuItem>,
+}
+
+fn read_items(data: &[u8], pos: &mut usize) -> Option<Vec<MenuItem>> {
+    let mut items = Vec::new();
+    loop {
+        let flags = u16_at(data, *pos)?;
+        *pos += 2;
+        let id = if flags & MF_POPUP == 0 {
+            let id = u16_at(data, *pos)?;
+            *pos += 2;
+            Some(id)
+        } else {
+            None
+        };
+        let text = cstr_at(data, *pos)?.to_vec();
+        *pos += text.len() + 1;
+        let children = if flags & MF_POPUP != 0 {
+            read_items(data, pos)?
+        } else {
+            Vec::new()
+        };
+        items.push(MenuItem {
+            flags,
+            id,
+            text,
+            children,
+        });
+        if flags & MF_END != 0 {
+            return Some(items);
+        }
+    }
+}
+
+/// Reads a `MENU` resource: a header of two zero words, then the items with
+/// the items of each popup after it.
+pub fn read_menu(data: &[u8]) -> Option<Vec<MenuItem>> {
+    let mut pos = 4;
+    read_items(data, &mut pos)
+}
+
+fn write_items(items: &[MenuItem], data: &mut Vec<u8>) {
+    for (i, item) in items.iter().enumerate() {
+        let mut flags = item.flags & !MF_END;
+        if i + 1 == items.len() {
+            flags |= MF_END;
+        }
+        data.extend_from_slice(&flags.to_le_bytes());
+        if let Some(id) = item.id {
+            data.extend_from_slice(&id.to_le_bytes());
+        }
+        data.extend_from_slice(&item.text);
+        data.push(0);
+        write_items(&item.children, data);
+    }
+}
+
+/// Writes a `MENU` resource.
+pub fn write_menu(items: &[MenuItem]) -> Vec<u8> {
+    let mut data = vec![0; 4];
+    write_items(items, &mut data);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu() {
+        let data = b"\x00\x00\x00\x00\
+            \x10\x00&File\x00\
+                \x00\x00\x01\x00&Open...\tCtrl+O\x00\
+                \x00\x08\x00\x00\x00\
+                \x80\x00\x02\x00E&xit\x00\
+            \x90\x00&Help\x00\
+                \x80\x00\x03\x00&About\x00";
+        let menu = read_menu(data).unwrap();
+        assert_eq!(menu.len(), 2);
+        assert_eq!(menu[0].text, b"&File");
+        assert_eq!(menu[0].id, None);
+        assert_eq!(menu[0].children.len(), 3);
+        assert_eq!(menu[0].children[0].text, b"&Open...\tCtrl+O");
+        assert_eq!(menu[0].children[1].text, b"");
+        assert_eq!(menu[1].children[0].id, Some(3));
+        assert_eq!(write_menu(&menu), data);
+    }
+}
@@ -1,44 +1,181 @@
 use log::debug;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 
-use self::entry_table::EntryTable;
+use self::entry_table::{EntryTable, SegmentEntry};
+use self::exports::Export;
 use self::header::NeHeader;
+use self::imported_name_table::ImportedNameTable;
 use self::module_reference_table::ModuleReferenceTable;
 use self::nonresident_name_table::NonresidentNameTable;
+use self::relocation_table::{RelocationEntry, RelocationTarget};
 use self::resident_name_table::ResidentNameTable;
+use self::resource_table::name_len;
 use self::resource_table::NeResourceTable;
-use self::segment_table::NeSegment;
+use self::segment_table::{NeSegment, NeSegmentHeader, SegmentFlags};
+use self::turbo_debugger::TurboDebuggerInfo;
 use crate::mz::DosHeader;
+use crate::ordinals;
+use crate::util::codepage::Codepage;
+use crate::x86::{self, BackendKind, Symbols};
 
+pub mod assembly;
+pub mod build;
+pub mod carve;
+pub mod catalog;
+pub mod classification;
+pub mod code_flow;
+pub mod control_flow_graph;
+pub mod dependency_graph;
+pub mod dialog;
+pub mod diff;
+pub mod dos_stub;
 pub mod entry_table;
+pub mod exports;
+pub mod fast_load;
+pub mod fonts;
+pub mod framework;
+pub mod ghidra;
+pub mod hashes;
 pub mod header;
+pub mod hex_headers;
+pub mod ida_script;
+pub mod imphash;
+pub mod imported_name_table;
+pub mod imports;
+pub mod layout;
+pub mod map_file;
+pub mod menu;
+pub mod module_definition;
 pub mod module_reference_table;
 pub mod nonresident_name_table;
+pub mod omf;
+pub mod overlay;
+pub mod packer;
+pub mod patch;
+pub mod radare2;
+pub mod relocation_table;
+pub mod repack;
 pub mod resident_name_table;
 pub mod resource_table;
+pub mod search;
 pub mod segment_table;
+pub mod self_loading;
+pub mod signatures;
+pub mod span;
+pub mod statistics;
+pub mod string_table;
+pub mod strings;
+pub mod summary;
+pub mod symbol_file;
+pub mod symbol_list;
+pub mod toolchain;
+pub mod translation;
+pub mod turbo_debugger;
+pub mod verify;
+pub mod visual_basic;
+pub mod wine_spec;
+pub mod xrefs;
+pub mod yara;
+
+/// Collects what a writer function writes.
+fn to_bytes(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Writes zeros up to a file offset.
+fn pad_to<W: Write + Seek>(file: &mut W, offset: u64) -> io::Result<()> {
+    let pos = file.stream_position()?;
+    if pos > offset {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("data overlaps at 0x{:08X}", offset),
+        ));
+    }
+    io::copy(&mut io::repeat(0).take(offset - pos), file)?;
+    Ok(())
+}
+
+/// How [`NeExecutable::describe`] disassembles the code segments.
+#[derive(Debug, Clone, Default)]
+pub struct DisassemblyOptions {
+    /// Only list the code reachable from the entry points.
+    pub recursive: bool,
+    /// List the code function by function.
+    pub functions: bool,
+    /// The decoder used for the listing.
+    pub backend: BackendKind,
+    /// Only list this segment (1-based), even if it isn't a code segment.
+    pub segment: Option<u16>,
+    /// Only list this range of offsets in a segment (1-based).
+    pub range: Option<(u16, Range<usize>)>,
+}
+
+/// Which parts of the tables [`NeExecutable::describe`] leaves out, for
+/// files whose full description is too long to read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DescribeFilter {
+    /// Only print the header and the module name and description.
+    pub header_only: bool,
+    /// Only print the number of relocations of each segment.
+    pub no_relocations: bool,
+    /// Only print the number of entry points.
+    pub no_entries: bool,
+}
 
 /// The parsed New Executable binary.
 #[derive(Debug, Clone)]
 pub struct NeExecutable {
     pub dos_header: Box<DosHeader>,
+    /// The DOS program preceding the NE header, including the DOS header.
+    pub dos_stub: Vec<u8>,
     pub ne_header: Box<NeHeader>,
     pub segment_entries: Vec<NeSegment>,
     pub resource_table: NeResourceTable,
     pub resident_name_table: ResidentNameTable,
     pub module_reference_table: ModuleReferenceTable,
+    pub imported_name_table: ImportedNameTable,
     pub entry_table: EntryTable,
     pub nonresident_name_table: NonresidentNameTable,
+    /// Names exported by referenced modules, keyed by module index and then ordinal.
+    /// Filled by [`NeExecutable::load_imported_modules`].
+    pub imported_module_exports: BTreeMap<u16, BTreeMap<u16, Vec<u8>>>,
+    /// Public symbols from debug information or a symbol file and the names of
+    /// library functions matched by signature, keyed by 1-based segment and offset.
+    pub debug_symbols: BTreeMap<(u16, u16), Vec<u8>>,
+    /// Segment names from a symbol file, keyed by 1-based segment number.
+    /// Filled by [`NeExecutable::load_symbol_file`].
+    pub segment_names: BTreeMap<u16, Vec<u8>>,
+    /// The whole file as read, for [`NeExecutable::write_preserving`].
+    pub file_data: Vec<u8>,
 }
 
 impl NeExecutable {
     pub fn read<R: Read + Seek>(file: &mut R) -> io::Result<Self> {
+        Self::read_with(file, false)
+    }
+
+    /// Reads the executable like [`NeExecutable::read`], but accepts segments
+    /// extending past the end of file, for [`NeExecutable::verify`] to report.
+    pub fn read_lenient<R: Read + Seek>(file: &mut R) -> io::Result<Self> {
+        Self::read_with(file, true)
+    }
+
+    fn read_with<R: Read + Seek>(file: &mut R, lenient: bool) -> io::Result<Self> {
         let dos_header = DosHeader::read(file)?;
         debug!("dos_header = {:?}", dos_header);
         dos_header.check_magic()?;
 
         let lfanew = dos_header.lfanew.value() as u64;
 
+        file.seek(SeekFrom::Start(0))?;
+        let mut file_data = Vec::new();
+        file.read_to_end(&mut file_data)?;
+        let dos_stub = file_data[..(lfanew as usize).min(file_data.len())].to_vec();
+
         file.seek(SeekFrom::Start(lfanew))?;
 
         let ne_header = NeHeader::read(file)?;
@@ -50,17 +187,24 @@ impl NeExecutable {
         ))?;
 
         let mut segment_entries = (0..ne_header.segment_count.value())
-            .map(|_| NeSegment::read(file, ne_header.file_alignment_shift_count.value()))
+            .map(|_| NeSegment::read(file, ne_header.alignment_shift()))
             .collect::<Result<Vec<_>, _>>()?;
         debug!("segment_entries = {:#?}", segment_entries);
 
         let rt_offset = lfanew + ne_header.resource_table_offset.value() as u64;
         file.seek(SeekFrom::Start(rt_offset))?;
-        let resource_table = if ne_header.resource_table_entries.value() == 0xFFFF {
+        // Early linkers leave the resource table out, pointing at the next table.
+        let mut resource_table = if ne_header.resource_table_offset
+            == ne_header.resident_names_table_offset
+            && ne_header.resource_table_entries.value() == 0
+        {
+            NeResourceTable::empty(ne_header.alignment_shift())
+        } else if ne_header.resource_table_entries.value() == 0xFFFF {
             NeResourceTable::read_variadic(file)?
         } else {
             NeResourceTable::read(file, ne_header.resource_table_entries.value())?
         };
+        resource_table.read_data(file, rt_offset)?;
         debug!("resource_table = {:#?}", resource_table);
 
         let rnt_offset = lfanew + ne_header.resident_names_table_offset.value() as u64;
@@ -88,235 +232,1915 @@ impl NeExecutable {
         debug!("nonresident_name_table = {:#?}", nonresident_name_table);
 
         for segment in &mut segment_entries {
-            segment.read_data(file)?;
+            if lenient {
+                segment.read_available_data(file)?;
+            } else {
+                segment.read_data(file)?;
+            }
         }
 
-        Ok(Self {
+        let mut imported_name_table = ImportedNameTable::default();
+        let import_name_offsets = segment_entries
+            .iter()
+            .filter_map(|segment| segment.relocations.as_ref())
+            .flat_map(|relocations| &relocations.entries)
+            .filter_map(|entry| match entry.target {
+                RelocationTarget::ImportName { name_offset, .. } => Some(name_offset),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        imported_name_table.read_names(file, int_offset, import_name_offsets)?;
+        debug!("imported_name_table = {:#?}", imported_name_table);
+
+        let mut exe = Self {
             dos_header: Box::new(dos_header),
+            dos_stub,
             ne_header: Box::new(ne_header),
             segment_entries,
             resource_table,
             resident_name_table,
             module_reference_table,
+            imported_name_table,
+            imported_module_exports: BTreeMap::new(),
             entry_table,
             nonresident_name_table,
-        })
+            debug_symbols: BTreeMap::new(),
+            segment_names: BTreeMap::new(),
+            file_data,
+        };
+
+        // TLINK /v appends Turbo Debugger information after the image, possibly aligned.
+        let image_end = exe.image_end();
+        let alignment = 1 << exe.ne_header.alignment_shift();
+        for offset in [image_end, image_end.next_multiple_of(alignment)] {
+            file.seek(SeekFrom::Start(offset))?;
+            match TurboDebuggerInfo::read(file) {
+                Ok(info) => {
+                    debug!("Turbo Debugger information at 0x{:08X}", offset);
+                    exe.debug_symbols = info
+                        .public_symbols()
+                        .into_iter()
+                        .map(|(segment, offset, name)| ((segment, offset), name))
+                        .collect();
+                    break;
+                }
+                Err(e) => debug!("no Turbo Debugger information at 0x{:08X}: {}", offset, e),
+            }
+        }
+
+        Ok(exe)
     }
 
-    pub(crate) fn describe(&self, show_data: bool, disassemble: bool) {
-        let Self {
-            ne_header,
-            segment_entries,
-            ..
-        } = self;
+    /// Writes the executable: the DOS stub, the NE header and the tables in
+    /// the usual order, then the segments with their relocations and the
+    /// resources at their alignments.
+    ///
+    /// The offsets, counts and lengths in the headers are recomputed from the
+    /// contents, as is the file load CRC unless it's zero. Data appended after
+    /// the image, such as debug information, isn't written.
+    pub fn write<W: Write + Seek>(&self, output: &mut W) -> io::Result<()> {
+        // The image is collected first for the file load CRC, if there is one.
+        let file = &mut io::Cursor::new(Vec::new());
+        let too_large =
+            |what: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{} too large", what));
+        let mut exe = self.clone();
+        let lfanew = exe.dos_stub.len().max(0x40) as u64;
+        exe.dos_header.lfanew = (lfanew as u32).into();
 
-        println!("File Type: Windows New Executable");
-        println!("Header:");
-        println!(
-            "    Linker version: {}.{}",
-            ne_header.major_linker_version, ne_header.minor_linker_version
-        );
-        print!("    Flags: ");
-        {
-            let mut flag_found = false;
-            for shift in 0..16 {
-                let mask = 1 << shift;
-                if (mask & ne_header.flags.value()) == 0 {
+        let mut resident_name_table = Vec::new();
+        exe.resident_name_table.write(&mut resident_name_table)?;
+        let mut module_reference_table = Vec::new();
+        exe.module_reference_table
+            .write(&mut module_reference_table)?;
+        let mut imported_name_table = Vec::new();
+        exe.imported_name_table
+            .write(&mut imported_name_table, &exe.module_reference_table)?;
+        let mut entry_table = Vec::new();
+        exe.entry_table.write(&mut entry_table)?;
+        let mut nonresident_name_table = Vec::new();
+        exe.nonresident_name_table
+            .write(&mut nonresident_name_table)?;
+        let has_resources =
+            !exe.resource_table.resource_types.is_empty() || !exe.resource_table.names.is_empty();
+        let resource_table_size = if has_resources {
+            let mut table = Vec::new();
+            exe.resource_table.write(&mut table)?;
+            table.len()
+        } else {
+            0
+        };
+
+        // Offsets of the tables relative to the NE header.
+        let header = &mut exe.ne_header;
+        let mut pos = 0x40;
+        let mut place = |size: usize, what: &str| -> io::Result<u16> {
+            let offset = u16::try_from(pos).map_err(|_| too_large(what))?;
+            pos += size;
+            Ok(offset)
+        };
+        header.segment_table_offset = place(8 * exe.segment_entries.len(), "segment table")?.into();
+        header.resource_table_offset = place(resource_table_size, "resource table")?.into();
+        header.resident_names_table_offset =
+            place(resident_name_table.len(), "resident name table")?.into();
+        header.module_reference_table_offset =
+            place(module_reference_table.len(), "module reference table")?.into();
+        header.import_name_table_offset =
+            place(imported_name_table.len(), "imported name table")?.into();
+        header.entry_table_offset = place(entry_table.len(), "entry table")?.into();
+        header.non_resident_names_table_offset = ((lfanew + pos as u64) as u32).into();
+        let image_start = lfanew + pos as u64 + nonresident_name_table.len() as u64;
+
+        header.entry_table_length = u16::try_from(entry_table.len())
+            .map_err(|_| too_large("entry table"))?
+            .into();
+        header.non_resident_names_size = u16::try_from(nonresident_name_table.len())
+            .map_err(|_| too_large("nonresident name table"))?
+            .into();
+        header.segment_count = u16::try_from(exe.segment_entries.len())
+            .map_err(|_| too_large("segment table"))?
+            .into();
+        header.module_references = u16::try_from(exe.module_reference_table.entries.len())
+            .map_err(|_| too_large("module reference table"))?
+            .into();
+        let moveable_entries = exe
+            .entry_table
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, SegmentEntry::Moveable(_)))
+            .count();
+        header.movable_entry_point_count = (moveable_entries as u16).into();
+        if header.resource_table_entries.value() != 0xFFFF {
+            header.resource_table_entries = (exe.resource_table.resource_types.len() as u16).into();
+        }
+
+        // Segment and resource data, each starting at a multiple of the alignment.
+        let mut end = image_start;
+        let shift = header.alignment_shift();
+        header.file_alignment_shift_count = shift.into();
+        for segment in &mut exe.segment_entries {
+            segment.shift_count = shift;
+            let data = match &segment.data {
+                Some(data) => data,
+                None => continue,
+            };
+            let offset = end.next_multiple_of(1 << shift);
+            segment.header.data_offset_shifted =
+                u16::try_from(offset >> shift).map_err(|_| too_large("file"))?;
+            segment.header.data_length = match data.len() {
+                0x10000 => 0,
+                len => u16::try_from(len).map_err(|_| too_large("segment"))?,
+            };
+            let relocations_size = match &segment.relocations {
+                Some(relocations) => {
+                    segment.header.flags |= SegmentFlags::RELOCINFO;
+                    relocations.file_size()
+                }
+                None => {
+                    segment.header.flags -= SegmentFlags::RELOCINFO;
+                    0
+                }
+            };
+            end = offset + data.len() as u64 + relocations_size;
+        }
+        let resource_shift = exe.resource_table.header.alignment_shift_count.min(15);
+        for resource_type in &mut exe.resource_table.resource_types {
+            for resource in &mut resource_type.resources {
+                if resource.data.is_empty() {
+                    resource.header.data_offset_shifted = 0;
+                    resource.header.data_length = 0;
                     continue;
                 }
-                if flag_found {
-                    print!(" | ");
+                let offset = end.next_multiple_of(1 << resource_shift);
+                resource.header.data_offset_shifted =
+                    u16::try_from(offset >> resource_shift).map_err(|_| too_large("file"))?;
+                let length = (resource.data.len() as u64).div_ceil(1 << resource_shift);
+                resource.header.data_length =
+                    u16::try_from(length).map_err(|_| too_large("resource"))?;
+                end = offset + (length << resource_shift);
+            }
+        }
+
+        let mut dos_stub = exe.dos_stub.clone();
+        dos_stub.resize(lfanew as usize, 0);
+        dos_stub[..0x40].copy_from_slice(bytemuck::bytes_of(&*exe.dos_header));
+        file.write_all(&dos_stub)?;
+        file.write_all(bytemuck::bytes_of(&*exe.ne_header))?;
+        for segment in &exe.segment_entries {
+            segment.header.write(file)?;
+        }
+        if has_resources {
+            exe.resource_table.write(file)?;
+        }
+        file.write_all(&resident_name_table)?;
+        file.write_all(&module_reference_table)?;
+        file.write_all(&imported_name_table)?;
+        file.write_all(&entry_table)?;
+        file.write_all(&nonresident_name_table)?;
+        for segment in &exe.segment_entries {
+            if let Some(data) = &segment.data {
+                pad_to(file, segment.data_offset())?;
+                file.write_all(data)?;
+                if let Some(relocations) = &segment.relocations {
+                    relocations.write(file)?;
+                }
+            }
+        }
+        for resource_type in &exe.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                if !resource.data.is_empty() {
+                    let (offset, length) = exe.resource_table.data_range(resource);
+                    pad_to(file, offset)?;
+                    file.write_all(&resource.data)?;
+                    pad_to(file, offset + length)?;
                 }
-                flag_found = true;
-                if mask == 0x1 {
-                    print!("SINGLEDATA");
-                } else if mask == 0x2 {
-                    print!("MULTIPLEDATA");
-                } else if mask == 0x2000 {
-                    print!("LINK_ERROR");
-                } else if mask == 0x8000 {
-                    print!("LIBRARY");
+            }
+        }
+        let mut image = std::mem::take(file.get_mut());
+        if exe.ne_header.file_load_crc.value() != 0 {
+            let crc = header::file_load_crc(&image, lfanew as usize);
+            let field = lfanew as usize + 0x08;
+            image[field..field + 4].copy_from_slice(&crc.to_le_bytes());
+        }
+        output.write_all(&image)
+    }
+
+    /// Writes the executable like [`NeExecutable::write`], followed by the
+    /// data appended to the file it was read from, for edits that don't fit
+    /// in place.
+    pub fn write_with_overlay<W: Write + Seek>(&self, output: &mut W) -> io::Result<()> {
+        let original = NeExecutable::read(&mut io::Cursor::new(&self.file_data[..]))?;
+        self.write(output)?;
+        output.write_all(original.overlay_data())
+    }
+
+    /// Writes the executable over the file it was read from, keeping every
+    /// structure at its original offset.
+    ///
+    /// Parts that are unchanged keep their original bytes, so an unmodified
+    /// executable is written byte for byte, including padding, reserved
+    /// fields and appended data. Changed tables and segment or resource data
+    /// are written in place and must fit in the space of the original;
+    /// adding segments, modules or resources isn't possible. A nonzero file
+    /// load CRC is recomputed if anything changed.
+    pub fn write_preserving<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        let no_room = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the {} doesn't fit in its original place", what),
+            )
+        };
+        let original = NeExecutable::read(&mut io::Cursor::new(&self.file_data[..]))?;
+        let mut image = self.file_data.clone();
+        let put = |image: &mut Vec<u8>, offset: u64, bytes: &[u8]| {
+            let offset = offset as usize;
+            if image.len() < offset + bytes.len() {
+                image.resize(offset + bytes.len(), 0);
+            }
+            image[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+
+        let lfanew = original.dos_header.lfanew.value() as u64;
+        if self.dos_header.lfanew != original.dos_header.lfanew
+            || self.dos_stub.len() != original.dos_stub.len()
+        {
+            return Err(no_room("DOS stub"));
+        }
+        if self.segment_entries.len() != original.segment_entries.len() {
+            return Err(no_room("segment table"));
+        }
+        if self.module_reference_table.entries.len()
+            != original.module_reference_table.entries.len()
+        {
+            return Err(no_room("module reference table"));
+        }
+        put(&mut image, 0, &self.dos_stub);
+        if self.dos_header != original.dos_header {
+            put(&mut image, 0, bytemuck::bytes_of(&*self.dos_header));
+        }
+
+        // The layout fields come from the original; the rest may have been edited.
+        let mut header = *self.ne_header;
+        let layout = &original.ne_header;
+        header.entry_table_offset = layout.entry_table_offset;
+        header.segment_table_offset = layout.segment_table_offset;
+        header.resource_table_offset = layout.resource_table_offset;
+        header.resident_names_table_offset = layout.resident_names_table_offset;
+        header.module_reference_table_offset = layout.module_reference_table_offset;
+        header.import_name_table_offset = layout.import_name_table_offset;
+        header.non_resident_names_table_offset = layout.non_resident_names_table_offset;
+        header.segment_count = layout.segment_count;
+        header.module_references = layout.module_references;
+
+        // Segment and resource data.
+        let mut segment_headers = Vec::new();
+        for (segment, old) in self.segment_entries.iter().zip(&original.segment_entries) {
+            let mut segment_header = NeSegmentHeader {
+                data_offset_shifted: old.header.data_offset_shifted,
+                ..segment.header
+            };
+            let relocations = to_bytes(|w| match &segment.relocations {
+                Some(relocations) => relocations.write(w),
+                None => Ok(()),
+            })?;
+            let old_relocations = to_bytes(|w| match &old.relocations {
+                Some(relocations) => relocations.write(w),
+                None => Ok(()),
+            })?;
+            if segment.data != old.data || relocations != old_relocations {
+                let data = segment.data.as_deref().unwrap_or_default();
+                let alignment = 1 << old.shift_count.min(15);
+                let room = if old.data.is_some() {
+                    (old.data_length() + old_relocations.len() as u64).next_multiple_of(alignment)
+                } else {
+                    0
+                };
+                if (data.len() + relocations.len()) as u64 > room {
+                    return Err(no_room("segment data"));
+                }
+                put(&mut image, old.data_offset(), data);
+                put(
+                    &mut image,
+                    old.data_offset() + data.len() as u64,
+                    &relocations,
+                );
+                segment_header.data_length = data.len() as u16;
+                if segment.relocations.is_some() {
+                    segment_header.flags |= SegmentFlags::RELOCINFO;
                 } else {
-                    print!("0x{:04x}", mask);
+                    segment_header.flags -= SegmentFlags::RELOCINFO;
                 }
             }
-            if !flag_found {
-                print!("0");
+            segment_headers.push(segment_header);
+        }
+        let segment_table = to_bytes(|w| {
+            segment_headers
+                .iter()
+                .try_for_each(|segment_header| segment_header.write(w))
+        })?;
+        put(
+            &mut image,
+            lfanew + layout.segment_table_offset.value() as u64,
+            &segment_table,
+        );
+
+        let mut resource_table = self.resource_table.clone();
+        let old_resources = original
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| &resource_type.resources)
+            .collect::<Vec<_>>();
+        let resources = resource_table
+            .resource_types
+            .iter_mut()
+            .flat_map(|resource_type| &mut resource_type.resources)
+            .collect::<Vec<_>>();
+        if resources.len() > old_resources.len() {
+            return Err(no_room("resource table"));
+        }
+        for (resource, old) in resources.into_iter().zip(old_resources) {
+            resource.header.data_offset_shifted = old.header.data_offset_shifted;
+            resource.header.data_length = old.header.data_length;
+            if resource.data != old.data {
+                let (offset, length) = original.resource_table.data_range(old);
+                if resource.data.len() as u64 > length {
+                    return Err(no_room("resource data"));
+                }
+                let mut data = resource.data.clone();
+                data.resize(length as usize, 0);
+                put(&mut image, offset, &data);
+            }
+        }
+
+        // The tables between the NE header and the segments; each may use the
+        // space up to the next one, and the entry table its original length
+        // if it's the last.
+        let entry_table_length = layout.entry_table_length.value() as u64;
+        let tables = [
+            (
+                layout.resource_table_offset.value(),
+                "resource table",
+                to_bytes(|w| resource_table.write(w))?,
+                to_bytes(|w| original.resource_table.write(w))?,
+                0,
+            ),
+            (
+                layout.resident_names_table_offset.value(),
+                "resident name table",
+                to_bytes(|w| self.resident_name_table.write(w))?,
+                to_bytes(|w| original.resident_name_table.write(w))?,
+                0,
+            ),
+            (
+                layout.module_reference_table_offset.value(),
+                "module reference table",
+                to_bytes(|w| self.module_reference_table.write(w))?,
+                to_bytes(|w| original.module_reference_table.write(w))?,
+                0,
+            ),
+            (
+                layout.import_name_table_offset.value(),
+                "imported name table",
+                Vec::new(),
+                Vec::new(),
+                0,
+            ),
+            (
+                layout.entry_table_offset.value(),
+                "entry table",
+                to_bytes(|w| self.entry_table.write(w))?,
+                to_bytes(|w| original.entry_table.write(w))?,
+                entry_table_length,
+            ),
+        ];
+        let room = |index: usize| {
+            let (start, .., fallback) = tables[index];
+            tables
+                .iter()
+                .enumerate()
+                // An empty table shares its offset with the one after it.
+                .filter(|&(other, table)| table.0 > start || (table.0 == start && other > index))
+                .map(|(_, table)| (table.0 - start) as u64)
+                .min()
+                .unwrap_or(fallback)
+        };
+        for (index, (offset, what, table, old_table, _)) in tables.iter().enumerate() {
+            if table == old_table {
+                continue;
+            }
+            if table.len() as u64 > room(index) {
+                return Err(no_room(what));
+            }
+            put(&mut image, lfanew + *offset as u64, table);
+            if *what == "entry table" {
+                header.entry_table_length = (table.len() as u16).into();
+            }
+        }
+
+        // Imported and module names keep their offsets; only those are rewritten.
+        let int_offset = layout.import_name_table_offset.value();
+        let int_room = room(3);
+        let module_names = self
+            .module_reference_table
+            .entries
+            .iter()
+            .map(|entry| (entry.header.offset, &entry.name));
+        for (name_offset, name) in self
+            .imported_name_table
+            .names
+            .iter()
+            .map(|(&offset, name)| (offset, name))
+            .chain(module_names)
+        {
+            let encoded = [&[name_len(name)?][..], name].concat();
+            if name_offset as u64 + encoded.len() as u64 > int_room {
+                return Err(no_room("imported name table"));
+            }
+            put(
+                &mut image,
+                lfanew + (int_offset + name_offset) as u64,
+                &encoded,
+            );
+        }
+
+        let nonresident_name_table = to_bytes(|w| self.nonresident_name_table.write(w))?;
+        if nonresident_name_table != to_bytes(|w| original.nonresident_name_table.write(w))? {
+            if nonresident_name_table.len() > layout.non_resident_names_size.value() as usize {
+                return Err(no_room("nonresident name table"));
             }
+            put(
+                &mut image,
+                layout.non_resident_names_table_offset.value() as u64,
+                &nonresident_name_table,
+            );
+            header.non_resident_names_size = (nonresident_name_table.len() as u16).into();
+        }
+
+        put(&mut image, lfanew, bytemuck::bytes_of(&header));
+        if header.file_load_crc.value() != 0 && image != self.file_data {
+            let crc = header::file_load_crc(&image, lfanew as usize);
+            put(&mut image, lfanew + 0x08, &crc.to_le_bytes());
+        }
+        file.write_all(&image)
+    }
+
+    /// Removes the nonresident names, including the module description.
+    ///
+    /// Returns the number of names that were exported, which are now only
+    /// exported by ordinal. Appended debug information is removed by
+    /// [`NeExecutable::write`], which doesn't write it.
+    pub fn strip(&mut self) -> usize {
+        let exported = self
+            .nonresident_name_table
+            .entries
+            .iter()
+            .filter(|entry| entry.index != 0)
+            .count();
+        self.nonresident_name_table.entries.clear();
+        exported
+    }
+
+    /// Sets the alignment of segments and resources to `1 << shift` bytes
+    /// for [`NeExecutable::write`], which recomputes the shifted offsets.
+    ///
+    /// Linkers often used 512-byte alignment; a smaller one saves the
+    /// padding, but the file must then be small enough for the shifted
+    /// 16-bit offsets to reach its end.
+    pub fn realign(&mut self, shift: u16) {
+        self.ne_header.file_alignment_shift_count = shift.into();
+        self.resource_table.header.alignment_shift_count = shift;
+    }
+
+    /// Stores data segments without relocations as iterated data where
+    /// that's smaller. Returns the number of segments compressed.
+    pub fn compress_segments(&mut self) -> usize {
+        let mut count = 0;
+        for segment in &mut self.segment_entries {
+            if segment.is_code() || segment.is_iterated() || segment.relocations.is_some() {
+                continue;
+            }
+            let data = match &segment.data {
+                Some(data) => data,
+                None => continue,
+            };
+            let compressed = segment_table::compress_iterated(data);
+            if compressed.len() >= data.len() {
+                continue;
+            }
+            if segment.min_alloc() < data.len() as u64 {
+                segment.header.min_alloc = data.len() as u16;
+            }
+            segment.data = Some(compressed);
+            segment.header.flags |= SegmentFlags::ITERATED;
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the end of the parts of the file the headers refer to.
+    ///
+    /// Anything beyond it, such as debug information, was appended.
+    pub fn image_end(&self) -> u64 {
+        let mut end = self.segments_end();
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                let (offset, length) = self.resource_table.data_range(resource);
+                end = end.max(offset + length);
+            }
+        }
+        end
+    }
+
+    /// Returns the end of the tables and segments, without the resources.
+    pub(crate) fn segments_end(&self) -> u64 {
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let ne_header = &self.ne_header;
+        let mut end = lfanew
+            + ne_header.entry_table_offset.value() as u64
+            + ne_header.entry_table_length.value() as u64;
+        end = end.max(
+            ne_header.non_resident_names_table_offset.value() as u64
+                + ne_header.non_resident_names_size.value() as u64,
+        );
+        for segment in &self.segment_entries {
+            let relocations_size = segment
+                .relocations
+                .as_ref()
+                .map_or(0, |relocations| relocations.file_size());
+            if segment.header.data_offset_shifted != 0 {
+                end = end.max(segment.data_offset() + segment.data_length() + relocations_size);
+            }
+        }
+        end
+    }
+
+    /// Returns the name of the module referred to by a 1-based module index.
+    pub fn module_name(&self, module: u16) -> Option<&[u8]> {
+        let entry = self
+            .module_reference_table
+            .entries
+            .get((module as usize).checked_sub(1)?)?;
+        Some(&entry.name)
+    }
+
+    /// Returns the name of a function imported by ordinal.
+    ///
+    /// Modules loaded by [`NeExecutable::load_imported_modules`] take
+    /// priority over the built-in tables.
+    pub fn imported_ordinal_name(&self, module: u16, ordinal: u16) -> Option<&[u8]> {
+        if let Some(name) = self
+            .imported_module_exports
+            .get(&module)
+            .and_then(|exports| exports.get(&ordinal))
+        {
+            return Some(name);
+        }
+        ordinals::lookup(self.module_name(module)?, ordinal).map(str::as_bytes)
+    }
+
+    /// Describes what a relocation resolves to, e.g. `KERNEL.GETVERSION` or `0002:0010`.
+    pub fn describe_relocation_target(
+        &self,
+        target: RelocationTarget,
+        codepage: Codepage,
+    ) -> String {
+        let module_name = |module| {
+            self.module_name(module)
+                .map(|name| codepage.decode(name))
+                .unwrap_or_else(|| format!("<module {}>", module))
+        };
+        match target {
+            RelocationTarget::InternalFixed { segment, offset } => {
+                format!("{:04X}:{:04X}", segment, offset)
+            }
+            RelocationTarget::InternalMoveable { ordinal } => format!("entry #{}", ordinal),
+            RelocationTarget::ImportOrdinal { module, ordinal } => {
+                match self.imported_ordinal_name(module, ordinal) {
+                    Some(name) => format!("{}.{}", module_name(module), codepage.decode(name)),
+                    None => format!("{}.{}", module_name(module), ordinal),
+                }
+            }
+            RelocationTarget::ImportName {
+                module,
+                name_offset,
+            } => {
+                let name = self
+                    .imported_name_table
+                    .get(name_offset)
+                    .map(|name| codepage.decode(name))
+                    .unwrap_or_else(|| format!("<name 0x{:04X}>", name_offset));
+                format!("{}.{}", module_name(module), name)
+            }
+            RelocationTarget::OsFixup { kind } => match kind.name() {
+                Some(name) => format!("OSFIXUP {}", name),
+                None => format!("OSFIXUP <type {}>", kind.to_raw()),
+            },
+        }
+    }
+
+    fn describe_relocation(&self, entry: &RelocationEntry, codepage: Codepage) -> String {
+        let source_type = if let Some(name) = relocation_table::source_type_name(entry.source_type)
+        {
+            name.to_owned()
+        } else {
+            format!("<type {}>", entry.source_type)
+        };
+        format!(
+            "0x{:04X} {:<10} {}{}",
+            entry.offset,
+            source_type,
+            self.describe_relocation_target(entry.target, codepage),
+            if entry.is_additive() {
+                " (additive)"
+            } else {
+                ""
+            }
+        )
+    }
+
+    /// Describes what a fixup patches in, for the listing: e.g. `KERNEL.GETVERSION`,
+    /// `seg 0002` or `offset HELPER (0001:0010)`.
+    fn describe_fixup(
+        &self,
+        entry: &RelocationEntry,
+        exports: &[Export],
+        codepage: Codepage,
+    ) -> String {
+        let address = |segment: u8, offset: u16| {
+            let name = exports
+                .iter()
+                .find(|export| export.segment == segment && export.offset == offset)
+                .and_then(|export| export.name.as_ref());
+            match name {
+                Some(name) => format!(
+                    "{} ({:04X}:{:04X})",
+                    codepage.decode(&name.name),
+                    segment,
+                    offset
+                ),
+                None => format!("{:04X}:{:04X}", segment, offset),
+            }
+        };
+        let target = match entry.target {
+            RelocationTarget::InternalFixed { segment, .. }
+                if entry.source_type == relocation_table::SOURCE_SEGMENT =>
+            {
+                format!("{:04X}", segment)
+            }
+            RelocationTarget::InternalFixed { segment, offset } => address(segment, offset),
+            RelocationTarget::InternalMoveable { ordinal } => {
+                match exports.iter().find(|export| export.ordinal == ordinal) {
+                    Some(export) if entry.source_type == relocation_table::SOURCE_SEGMENT => {
+                        format!("{:04X}", export.segment)
+                    }
+                    Some(export) => address(export.segment, export.offset),
+                    None => format!("entry #{}", ordinal),
+                }
+            }
+            target => self.describe_relocation_target(target, codepage),
+        };
+        let kind = match entry.source_type {
+            relocation_table::SOURCE_LOBYTE => "lobyte ",
+            relocation_table::SOURCE_SEGMENT => "seg ",
+            relocation_table::SOURCE_OFFSET | relocation_table::SOURCE_OFFSET32 => "offset ",
+            _ => "",
+        };
+        let additive = if entry.is_additive() {
+            " (additive)"
+        } else {
+            ""
+        };
+        format!("{}{}{}", kind, target, additive)
+    }
+
+    /// Collects a description of every fixup site in the segment, keyed by offset.
+    fn fixup_annotations(
+        &self,
+        segment: &NeSegment,
+        data: &[u8],
+        codepage: Codepage,
+    ) -> BTreeMap<usize, String> {
+        let mut annotations = BTreeMap::new();
+        let relocations = if let Some(relocations) = &segment.relocations {
+            relocations
+        } else {
+            return annotations;
+        };
+        let exports = self.exports();
+        for (index, entry) in relocations.entries.iter().enumerate() {
+            let sites = match entry.sites(data) {
+                Ok(sites) => sites,
+                Err(e) => {
+                    debug!("relocation #{}: {}", index, e);
+                    vec![entry.offset]
+                }
+            };
+            let description = self.describe_fixup(entry, &exports, codepage);
+            for site in sites {
+                annotations.insert(site as usize, description.clone());
+            }
+        }
+        annotations
+    }
+
+    /// Collects the labels and fixups to show in the listing of a segment.
+    fn listing_symbols(
+        &self,
+        index: usize,
+        segment: &NeSegment,
+        data: &[u8],
+        codepage: Codepage,
+    ) -> Symbols {
+        let mut symbols = Symbols {
+            annotations: self.fixup_annotations(segment, data, codepage),
+            ..Symbols::default()
+        };
+        let exports = self.exports();
+        for export in &exports {
+            if export.segment as usize != index + 1 {
+                continue;
+            }
+            let label = match &export.name {
+                Some(name) => codepage.decode(&name.name),
+                None => format!("entry_{}", export.ordinal),
+            };
+            symbols.labels.insert(export.offset as usize, label);
+        }
+        for (&(_, offset), name) in self
+            .debug_symbols
+            .range((index as u16 + 1, 0)..=(index as u16 + 1, 0xFFFF))
+        {
+            symbols
+                .labels
+                .entry(offset as usize)
+                .or_insert_with(|| codepage.decode(name));
+        }
+        if let Some(relocations) = &segment.relocations {
+            for entry in &relocations.entries {
+                if entry.source_type != relocation_table::SOURCE_FAR_ADDR || entry.is_additive() {
+                    continue;
+                }
+                let sites = entry.sites(data).unwrap_or_default();
+                let target = self.describe_fixup(entry, &exports, codepage);
+                for site in sites {
+                    symbols.far_pointers.insert(site as usize, target.clone());
+                }
+            }
+        }
+        symbols
+    }
+
+    fn describe_disassembly<W: Write>(
+        &self,
+        w: &mut W,
+        options: &DisassemblyOptions,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let backend = options.backend.backend().unwrap_or(&x86::Builtin);
+        let reachable = if options.recursive {
+            Some(self.reachable_instructions())
+        } else {
+            None
+        };
+        let functions = if options.functions {
+            self.functions()
+        } else {
+            Vec::new()
+        };
+        let selected = match &options.range {
+            Some((segment, _)) => Some(*segment),
+            None => options.segment,
+        };
+        if let Some(segment) = selected {
+            if segment == 0 || segment as usize > self.segment_entries.len() {
+                writeln!(w, "Segment {} does not exist", segment)?;
+                return Ok(());
+            }
+        }
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            match selected {
+                Some(selected) if selected as usize != i + 1 => continue,
+                None if !segment.is_code() => continue,
+                _ => {}
+            }
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                Ok(None) => continue,
+                Err(e) => {
+                    writeln!(w, "Segment #{}: {}", i, e)?;
+                    continue;
+                }
+            };
+            let symbols = self.listing_symbols(i, segment, &data, codepage);
+            let ranges = if let Some((_, range)) = &options.range {
+                let range = range.start..range.end.min(data.len());
+                let header = format!("{:04X}:{:04X}..{:04X}:", i + 1, range.start, range.end);
+                vec![(header, range)]
+            } else if options.functions {
+                functions
+                    .iter()
+                    .filter(|function| function.segment == i)
+                    .map(|function| {
+                        let header = format!(
+                            "{} ({:04X}:{:04X}, {} bytes):",
+                            function.display_name(codepage),
+                            i + 1,
+                            function.start,
+                            function.size()
+                        );
+                        (header, function.start..function.end)
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                let header = format!(
+                    "Segment {} ({:04X}:0000, {} bytes):",
+                    i + 1,
+                    i + 1,
+                    data.len()
+                );
+                vec![(header, 0..data.len())]
+            };
+            for (header, range) in ranges {
+                writeln!(w, "{}", header)?;
+                let insts = match &reachable {
+                    Some(reachable) => reachable
+                        .get(&i)
+                        .map(|insts| {
+                            insts
+                                .iter()
+                                .filter(|inst| range.contains(&inst.pos))
+                                .map(|inst| backend.decode(&data, inst.pos, false))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    None => backend.decode_range(&data, range, false),
+                };
+                x86::write_listing(w, &data, i as u16 + 1, &insts, &symbols, backend)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn describe_module_name<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        if self.resident_name_table.entries.is_empty() {
+            writeln!(w, "Module name: <no entry>")?;
+        } else {
+            writeln!(
+                w,
+                "Module name: {}",
+                codepage.decode(&self.resident_name_table.entries[0].name)
+            )?;
+        }
+        if self.nonresident_name_table.entries.is_empty() {
+            writeln!(w, "Module description: <no entry>")?;
+        } else {
+            writeln!(
+                w,
+                "Module description: {}",
+                codepage.decode(&self.nonresident_name_table.entries[0].name)
+            )?;
         }
-        println!();
-        println!(
+        Ok(())
+    }
+
+    pub(crate) fn describe<W: Write>(
+        &self,
+        w: &mut W,
+        show_data: bool,
+        disassembly: Option<&DisassemblyOptions>,
+        filter: DescribeFilter,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let Self {
+            ne_header,
+            segment_entries,
+            ..
+        } = self;
+
+        writeln!(w, "Module type: {}", self.classify())?;
+        writeln!(w, "File Type: Windows New Executable")?;
+        writeln!(w, "Header:")?;
+        writeln!(
+            w,
+            "    Linker version: {}.{}",
+            ne_header.major_linker_version, ne_header.minor_linker_version
+        )?;
+        writeln!(w, "    Flags: {}", ne_header.ne_flags())?;
+        writeln!(
+            w,
             "    Auto-data segment: {}",
             ne_header.auto_data_segment_index.value()
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Initial heap size: {}",
             ne_header.init_heap_size.value()
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Initial stack size: {}",
             ne_header.init_stack_size.value()
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Entry point (CS:IP): {:04X}:{:04X}",
             ne_header.entry_point.value() >> 16,
             ne_header.entry_point.value() & 0xFFFF
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Initial stack (SS:SP): {:04X}:{:04X}",
             ne_header.init_stack.value() >> 16,
             ne_header.init_stack.value() & 0xFFFF
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Number of segments: {}",
             ne_header.segment_count.value()
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Number of referenced modules: {}",
             ne_header.module_references.value()
-        );
-        println!(
+        )?;
+        writeln!(
+            w,
             "    Number of movable entry points: {}",
             ne_header.movable_entry_point_count.value()
-        );
-        println!(
-            "    Number of file alignment shifts: {}",
-            ne_header.file_alignment_shift_count.value()
-        );
-        println!(
+        )?;
+        if ne_header.file_alignment_shift_count.value() == 0 {
+            writeln!(
+                w,
+                "    Number of file alignment shifts: 0 (taken as {})",
+                ne_header.alignment_shift()
+            )?;
+        } else {
+            writeln!(
+                w,
+                "    Number of file alignment shifts: {}",
+                ne_header.file_alignment_shift_count.value()
+            )?;
+        }
+        writeln!(
+            w,
             "    Number of resource table entries: {}",
             ne_header.resource_table_entries.value()
-        );
-        print!("    Target os: ");
-        if ne_header.target_os == 2 {
-            print!("Windows");
+        )?;
+        match header::target_os_name(ne_header.target_os) {
+            Some(name) => writeln!(w, "    Target os: {} ({})", name, ne_header.target_os)?,
+            None => writeln!(w, "    Target os: Unknown ({})", ne_header.target_os)?,
+        }
+        writeln!(w, "    OS/2 flags: {}", ne_header.os2_flags())?;
+        match ne_header.fast_load_area() {
+            Some((_, 0)) => writeln!(w, "    Fast-load area: empty")?,
+            Some((offset, length)) => writeln!(
+                w,
+                "    Fast-load area: 0x{:X} bytes at 0x{:X}",
+                length, offset
+            )?,
+            None => {}
+        }
+        if ne_header.is_early_format() {
+            writeln!(
+                w,
+                "    Expected Windows version: none (Windows 1.x/2.x format)"
+            )?;
         } else {
-            print!("Unknown ({})", ne_header.target_os);
+            writeln!(
+                w,
+                "    Expected Windows version: {}.{}",
+                ne_header.expected_win_ver[1], ne_header.expected_win_ver[0]
+            )?;
         }
-        println!();
-        println!(
-            "    Expected Windows version: {}.{}",
-            ne_header.expected_win_ver[1], ne_header.expected_win_ver[0]
-        );
-
-        for (i, segment) in segment_entries.iter().enumerate() {
-            println!("Segment #{}:", i);
-            println!("    Offset on file: 0x{:04X}", segment.data_offset());
-            println!("    Length on file: 0x{:04X}", segment.data_length());
-            println!("    Flags: 0x{:04X}", segment.header.flags);
-            println!("    Allocation: 0x{:04X}", segment.min_alloc());
+        writeln!(w, "    Platform: {}", ne_header.platform())?;
+        let packers = self.detect_packers();
+        if packers.is_empty() {
+            writeln!(w, "    Packer: none detected")?;
+        }
+        for packer in &packers {
+            writeln!(w, "    Packer: {} ({})", packer.name, packer.evidence)?;
+        }
+        for framework in &self.detect_frameworks() {
+            writeln!(
+                w,
+                "    Framework: {} ({})",
+                framework.name, framework.evidence
+            )?;
+        }
+        match self.classify_stub() {
+            dos_stub::StubKind::Standard { linker, message } => writeln!(
+                w,
+                "    DOS stub: standard{} ({:?})",
+                linker
+                    .map(|linker| format!(", {}", linker))
+                    .unwrap_or_default(),
+                codepage.decode(&message).trim_end()
+            )?,
+            dos_stub::StubKind::Custom { size } => {
+                writeln!(w, "    DOS stub: custom ({} bytes)", size)?
+            }
+        }
+        match self.overlay() {
+            Some(range) => writeln!(
+                w,
+                "    Overlay: {} bytes at 0x{:X}{}",
+                range.end - range.start,
+                range.start,
+                self.overlay_kind()
+                    .map(|kind| format!(" ({})", kind))
+                    .unwrap_or_default()
+            )?,
+            None => writeln!(w, "    Overlay: none")?,
+        }
+        let toolchains = self.detect_toolchain();
+        if toolchains.is_empty() {
+            writeln!(w, "    Toolchain: unknown")?;
+        }
+        for toolchain in &toolchains {
+            writeln!(
+                w,
+                "    Toolchain: {} ({})",
+                toolchain.name, toolchain.evidence
+            )?;
+        }
+        if let Some(imphash) = self.imphash() {
+            writeln!(w, "    Import hash: {}", imphash)?;
         }
 
-        if self.resident_name_table.entries.is_empty() {
-            println!("Module name: <no entry>");
-        } else {
-            println!(
-                "Module name: {}",
-                String::from_utf8_lossy(&self.resident_name_table.entries[0].name)
-            );
+        if filter.header_only {
+            return self.describe_module_name(w, codepage);
         }
-        if self.nonresident_name_table.entries.is_empty() {
-            println!("Module description: <no entry>");
-        } else {
-            println!(
-                "Module description: {}",
-                String::from_utf8_lossy(&self.nonresident_name_table.entries[0].name)
-            );
+
+        self.describe_fast_load(w)?;
+        self.describe_self_loading(w)?;
+
+        for (i, segment) in segment_entries.iter().enumerate() {
+            writeln!(w, "Segment #{}:", i)?;
+            if let Some(name) = self.segment_names.get(&(i as u16 + 1)) {
+                writeln!(w, "    Name: {}", codepage.decode(name))?;
+            }
+            writeln!(w, "    Offset on file: 0x{:04X}", segment.data_offset())?;
+            writeln!(w, "    Length on file: 0x{:04X}", segment.data_length())?;
+            writeln!(
+                w,
+                "    Flags: 0x{:04X} ({})",
+                segment.header.flags.bits(),
+                segment.header.flags
+            )?;
+            writeln!(w, "    Allocation: 0x{:04X}", segment.min_alloc())?;
+            match &segment.relocations {
+                Some(relocations) if filter.no_relocations => {
+                    writeln!(w, "    Relocations: {}", relocations.entries.len())?;
+                }
+                Some(relocations) => {
+                    writeln!(w, "    Relocations:")?;
+                    let data = segment.expanded_data().ok().flatten();
+                    for entry in &relocations.entries {
+                        writeln!(w, "        {}", self.describe_relocation(entry, codepage))?;
+                        let sites = data.as_ref().map(|data| entry.sites(data));
+                        match sites {
+                            Some(Ok(sites)) if sites.len() > 1 => {
+                                let sites = sites
+                                    .iter()
+                                    .map(|site| format!("0x{:04X}", site))
+                                    .collect::<Vec<_>>();
+                                writeln!(w, "            Chain: {}", sites.join(", "))?;
+                            }
+                            Some(Err(e)) => writeln!(w, "            <{}>", e)?,
+                            _ => {}
+                        }
+                    }
+                }
+                None => {}
+            }
         }
+
+        self.describe_module_name(w, codepage)?;
         if !self.resident_name_table.entries.is_empty() {
-            println!("Resident names:");
+            writeln!(w, "Resident names:")?;
             for entry in &self.resident_name_table.entries[1..] {
-                println!(
-                    "    {:3} {}",
-                    entry.index,
-                    String::from_utf8_lossy(&entry.name)
-                );
+                writeln!(w, "    {:3} {}", entry.index, codepage.decode(&entry.name))?;
             }
         }
         if !self.nonresident_name_table.entries.is_empty() {
-            println!("Nonresident names:");
+            writeln!(w, "Nonresident names:")?;
             for entry in &self.nonresident_name_table.entries[1..] {
-                println!(
-                    "    {:3} {}",
-                    entry.index,
-                    String::from_utf8_lossy(&entry.name)
-                );
+                writeln!(w, "    {:3} {}", entry.index, codepage.decode(&entry.name))?;
             }
         }
 
-        println!("Module references:");
+        if self.classify().kind == classification::ModuleKind::FontLibrary {
+            self.describe_fonts(w, codepage)?;
+        }
+
+        writeln!(w, "Module references:")?;
         for entry in &self.module_reference_table.entries {
-            println!("    {}", String::from_utf8_lossy(&entry.name));
+            writeln!(w, "    {}", codepage.decode(&entry.name))?;
         }
 
-        for (i, entry) in self.entry_table.entries.iter().enumerate() {
-            use self::entry_table::SegmentEntry::*;
-            match entry {
-                Unused => {
-                    println!("Entry #{}: unused", i + 1);
-                }
-                Fixed(entry) => {
-                    println!("Entry #{}: fixed", i + 1);
-                    println!("    Segment: {}", entry.segment);
-                    println!("    Flags: 0x{:02X}", entry.flags);
-                    println!("    Offset: 0x{:04X}", entry.offset);
-                }
-                Moveable(entry) => {
-                    println!("Entry #{}: moveable", i + 1);
-                    println!("    Flags: 0x{:02X}", entry.flags);
-                    if entry.magic != *b"\xCD\x3F" {
-                        println!(
-                            "    <Invalid magic>: {:02X} {:02X}",
-                            entry.magic[0], entry.magic[1]
-                        );
+        let exports = self.exports();
+        if filter.no_entries {
+            writeln!(w, "Entries: {}", self.entry_table.entries.len())?;
+        } else {
+            for (i, entry) in self.entry_table.entries.iter().enumerate() {
+                use self::entry_table::SegmentEntry::*;
+                let name = exports
+                    .binary_search_by_key(&(i as u16 + 1), |export| export.ordinal)
+                    .ok()
+                    .and_then(|pos| exports[pos].name.as_ref());
+                let describe_name = |w: &mut W| -> io::Result<()> {
+                    if let Some(name) = name {
+                        writeln!(
+                            w,
+                            "    Name: {} ({})",
+                            codepage.decode(&name.name),
+                            if name.resident {
+                                "resident"
+                            } else {
+                                "nonresident"
+                            }
+                        )?;
+                    }
+                    Ok(())
+                };
+                match entry {
+                    Unused => {
+                        writeln!(w, "Entry #{}: unused", i + 1)?;
+                    }
+                    Fixed(entry) => {
+                        writeln!(w, "Entry #{}: fixed", i + 1)?;
+                        describe_name(w)?;
+                        writeln!(w, "    Segment: {}", entry.segment)?;
+                        writeln!(w, "    Flags: {}", entry.flags)?;
+                        writeln!(w, "    Offset: 0x{:04X}", entry.offset)?;
+                    }
+                    Moveable(entry) => {
+                        writeln!(w, "Entry #{}: moveable", i + 1)?;
+                        describe_name(w)?;
+                        writeln!(w, "    Flags: {}", entry.flags)?;
+                        if entry.magic != *b"\xCD\x3F" {
+                            writeln!(
+                                w,
+                                "    <Invalid magic>: {:02X} {:02X}",
+                                entry.magic[0], entry.magic[1]
+                            )?;
+                        }
+                        writeln!(w, "    Segment: 0x{:02X}", entry.segment)?;
+                        writeln!(w, "    Offset: 0x{:04X}", entry.offset)?;
                     }
-                    println!("    Segment: 0x{:02X}", entry.segment);
-                    println!("    Offset: 0x{:04X}", entry.offset);
                 }
             }
         }
 
-        for (_i, segment) in segment_entries.iter().enumerate() {
-            if !disassemble || (segment.header.flags & 7) != 0 {
-                continue;
-            }
-            if let Some(data) = &segment.data {
-                crate::x86::disassemble(data, false);
-            }
+        if let Some(options) = disassembly {
+            self.describe_disassembly(w, options, codepage)?;
         }
 
         for (i, segment) in segment_entries.iter().enumerate() {
             if !show_data {
                 continue;
             }
-            if let Some(data) = &segment.data {
-                println!("Segment #{} data:", i);
-                for (i, chunk) in data.chunks(16).enumerate() {
-                    print!("{:08X} ", i * 16);
-                    for j in 0..16 {
-                        if let Some(x) = chunk.get(j) {
-                            print!(" {:02X}", x);
-                        } else {
-                            print!("   ");
-                        }
-                        if j == 7 {
-                            print!(" ");
-                        }
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                Ok(None) => continue,
+                Err(e) => {
+                    writeln!(w, "Segment #{} data: {}", i, e)?;
+                    continue;
+                }
+            };
+            let annotations = self.fixup_annotations(segment, &data, codepage);
+            writeln!(w, "Segment #{} data:", i)?;
+            for (i, chunk) in data.chunks(16).enumerate() {
+                write!(w, "{:08X} ", i * 16)?;
+                for j in 0..16 {
+                    if let Some(x) = chunk.get(j) {
+                        write!(w, " {:02X}", x)?;
+                    } else {
+                        write!(w, "   ")?;
                     }
-                    print!("  |");
-                    for &byte in chunk {
-                        if 0x20 <= byte && byte < 0x7F {
-                            print!("{}", byte as char);
-                        } else {
-                            print!(".");
-                        }
+                    if j == 7 {
+                        write!(w, " ")?;
+                    }
+                }
+                write!(w, "  |")?;
+                for &byte in chunk {
+                    if (0x20..0x7F).contains(&byte) {
+                        write!(w, "{}", byte as char)?;
+                    } else {
+                        write!(w, ".")?;
                     }
-                    print!("|");
-                    println!();
                 }
-                println!("{:08X}", (data.len() + 15) / 16 * 16);
-                println!();
+                write!(w, "|")?;
+                let row_annotations = annotations
+                    .range(i * 16..i * 16 + 16)
+                    .map(|(offset, description)| format!("{:04X}: {}", offset, description))
+                    .collect::<Vec<_>>();
+                if !row_annotations.is_empty() {
+                    write!(w, "{:1$}", "", 16 - chunk.len())?;
+                    write!(w, "  ; {}", row_annotations.join(", "))?;
+                }
+                writeln!(w)?;
             }
+            writeln!(w, "{:08X}", data.len().div_ceil(16) * 16)?;
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::header::NeFlags;
+    use super::*;
+
+    /// A module with one code segment, an entry point, padding and appended data.
+    pub(super) fn sample() -> Vec<u8> {
+        let mut file = vec![0; 0x40];
+        file[..2].copy_from_slice(b"MZ");
+        file[0x3C] = 0x40;
+        let mut header = NeHeader::zeroed();
+        header.magic = *b"NE";
+        header.segment_table_offset = 0x40.into();
+        header.resource_table_offset = 0x48.into();
+        header.resident_names_table_offset = 0x48.into();
+        header.module_reference_table_offset = 0x50.into();
+        header.import_name_table_offset = 0x50.into();
+        header.entry_table_offset = 0x50.into();
+        header.entry_table_length = 6.into();
+        header.non_resident_names_table_offset = 0x96.into();
+        header.non_resident_names_size = 8.into();
+        header.segment_count = 1.into();
+        header.file_alignment_shift_count = 4.into();
+        header.entry_point = 0x0001_0000.into();
+        file.extend_from_slice(bytemuck::bytes_of(&header));
+        file.extend_from_slice(b"\x0A\x00\x05\x00\x00\x00\x05\x00");
+        file.extend_from_slice(b"\x04TEST\x00\x00\x00");
+        file.extend_from_slice(b"\x01\x01\x01\x00\x00\x00");
+        file.extend_from_slice(b"\x04Test\x00\x00\x00\xAA\xAA");
+        file.extend_from_slice(b"\x55\x8B\xEC\x5D\xCB");
+        file.extend_from_slice(b"appended");
+        file
+    }
+
+    /// Parses [`sample`].
+    pub(super) fn sample_exe() -> NeExecutable {
+        NeExecutable::read(&mut io::Cursor::new(sample())).unwrap()
+    }
+
+    /// Replaces the data and relocations of the first segment.
+    pub(super) fn set_code(exe: &mut NeExecutable, data: &[u8], relocations: Vec<RelocationEntry>) {
+        let segment = &mut exe.segment_entries[0];
+        if !relocations.is_empty() {
+            segment.header.flags |= SegmentFlags::RELOCINFO;
         }
+        segment.data = Some(data.to_vec());
+        segment.relocations = Some(relocation_table::RelocationTable {
+            entries: relocations,
+        });
+    }
+
+    /// Replaces the resources with one resource per `(type_id, resource_id, data)`.
+    pub(super) fn set_resources(exe: &mut NeExecutable, resources: &[(u16, u16, &[u8])]) {
+        use resource_table::{NeResource, NeResourceHeader, NeResourceType, NeResourceTypeHeader};
+
+        exe.resource_table.resource_types = resources
+            .iter()
+            .map(|&(type_id, resource_id, data)| NeResourceType {
+                header: NeResourceTypeHeader {
+                    type_id,
+                    num_resources: 1,
+                    res: [0; 2],
+                },
+                resources: vec![NeResource {
+                    header: NeResourceHeader {
+                        data_offset_shifted: 0,
+                        data_length: 0,
+                        flags: 0,
+                        resource_id,
+                        res: [0; 2],
+                    },
+                    data: data.to_vec(),
+                }],
+            })
+            .collect();
+    }
+
+    /// Writes `exe` out and parses the result.
+    pub(super) fn rewrite(exe: &NeExecutable) -> NeExecutable {
+        let mut data = Vec::new();
+        exe.write(&mut io::Cursor::new(&mut data)).unwrap();
+        NeExecutable::read(&mut io::Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        let data = sample();
+        let exe = NeExecutable::read(&mut io::Cursor::new(&data[..])).unwrap();
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write(&mut written).unwrap();
+        let written = written.into_inner();
+        // The padding is zeroed and the appended data dropped.
+        let mut expected = data[..data.len() - 8].to_vec();
+        expected[0x9E..0xA0].fill(0);
+        assert_eq!(written, expected);
+
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert_eq!(rewritten.module_name_entry(), Some(&b"TEST"[..]));
+        assert_eq!(
+            rewritten.segment_entries[0].data,
+            exe.segment_entries[0].data
+        );
+    }
+
+    #[test]
+    fn test_write_preserving() {
+        let data = sample();
+        let mut exe = NeExecutable::read(&mut io::Cursor::new(&data[..])).unwrap();
+        let mut written = Vec::new();
+        exe.write_preserving(&mut written).unwrap();
+        assert_eq!(written, data);
+
+        exe.ne_header.expected_win_ver = [10, 3];
+        exe.nonresident_name_table.entries[0].name = b"Tst".to_vec();
+        let mut written = Vec::new();
+        exe.write_preserving(&mut written).unwrap();
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert_eq!(rewritten.ne_header.expected_win_ver, [10, 3]);
+        assert_eq!(rewritten.nonresident_name_table.entries[0].name, b"Tst");
+        assert!(written.ends_with(b"appended"));
+
+        exe.nonresident_name_table.entries[0].name = b"Test application".to_vec();
+        assert!(exe.write_preserving(&mut Vec::new()).is_err());
+
+        // The resident name table shares its offset with the empty resource table.
+        let mut exe = NeExecutable::read(&mut io::Cursor::new(&data[..])).unwrap();
+        exe.resident_name_table.entries[0].name = b"TST".to_vec();
+        let mut written = Vec::new();
+        exe.write_preserving(&mut written).unwrap();
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert_eq!(rewritten.module_name_entry(), Some(&b"TST"[..]));
+    }
+
+    #[test]
+    fn test_write_with_overlay() {
+        let mut exe = sample_exe();
+        exe.nonresident_name_table.entries[0].name = b"Test application".to_vec();
+        assert!(exe.write_preserving(&mut Vec::new()).is_err());
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write_with_overlay(&mut written).unwrap();
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(written.into_inner())).unwrap();
+        assert_eq!(
+            rewritten.nonresident_name_table.entries[0].name,
+            b"Test application"
+        );
+        assert_eq!(rewritten.overlay_data(), b"appended");
+    }
+
+    #[test]
+    fn test_strip() {
+        let mut exe = sample_exe();
+        exe.nonresident_name_table
+            .entries
+            .push(nonresident_name_table::NonresidentNameEntry {
+                name: b"Hidden".to_vec(),
+                index: 1,
+            });
+        assert_eq!(exe.strip(), 1);
+        let rewritten = rewrite(&exe);
+        assert!(rewritten.nonresident_name_table.entries.is_empty());
+        // Just the terminating zero.
+        assert_eq!(rewritten.ne_header.non_resident_names_size.value(), 1);
+        assert_eq!(rewritten.overlay(), None);
+        assert_eq!(
+            rewritten.segment_entries[0].data,
+            exe.segment_entries[0].data
+        );
+    }
+
+    #[test]
+    fn test_realign() {
+        let mut exe = sample_exe();
+        set_resources(&mut exe, &[(0x8006, 1, b"Hello")]);
+        exe.realign(1);
+        let rewritten = rewrite(&exe);
+        assert_eq!(rewritten.ne_header.alignment_shift(), 1);
+        assert_eq!(rewritten.resource_table.header.alignment_shift_count, 1);
+        assert_eq!(
+            rewritten.segment_entries[0].data,
+            exe.segment_entries[0].data
+        );
+        let resource = &rewritten.resource_table.resource_types[0].resources[0];
+        assert!(resource.data.starts_with(b"Hello"));
+        exe.realign(4);
+        assert!(rewritten.file_data.len() < rewrite(&exe).file_data.len());
+    }
+
+    #[test]
+    fn test_realign_too_large() {
+        // At 2-byte alignment, offsets can't reach past 128KiB.
+        let mut exe = sample_exe();
+        set_code(&mut exe, &[0x90; 0x10000], Vec::new());
+        let data = [0; 0xFFFF];
+        set_resources(&mut exe, &[(0x8006, 1, &data), (0x800A, 2, &data)]);
+        exe.realign(1);
+        let error = exe.write(&mut io::Cursor::new(Vec::new())).unwrap_err();
+        assert_eq!(error.to_string(), "file too large");
+
+        exe.realign(4);
+        exe.write(&mut io::Cursor::new(Vec::new())).unwrap();
+    }
+
+    #[test]
+    fn test_add_export() {
+        let mut exe = sample_exe();
+        let spec = "Func@3=1:0003".parse::<exports::ExportSpec>().unwrap();
+        assert_eq!(exe.add_export(&spec, false).unwrap(), 3);
+        assert!(exe.add_export(&spec, true).is_err());
+        let rename = "Func=Renamed".parse::<exports::ExportRename>().unwrap();
+        exe.rename_export(&rename).unwrap();
+
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write(&mut written).unwrap();
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(written.into_inner())).unwrap();
+        let exports = rewritten.exports();
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports[1].ordinal, 3);
+        assert_eq!((exports[1].segment, exports[1].offset), (1, 3));
+        assert!(exports[1].flags.is_exported());
+        assert_eq!(exports[1].name.as_ref().unwrap().name, b"Renamed");
+        assert!(rewritten.verify(u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_retarget() {
+        let mut exe = sample_exe();
+        // A chain of two selector fixups at 0000 and 0002.
+        set_code(
+            &mut exe,
+            b"\x02\x00\xFF\xFF\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_SEGMENT,
+                flags: 0,
+                offset: 0,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        let retarget = "1:0002=KERNEL.3".parse::<patch::Retarget>().unwrap();
+        exe.retarget(&retarget).unwrap();
+
+        let segment = &exe.segment_entries[0];
+        assert_eq!(segment.data.as_deref(), Some(&b"\xFF\xFF\xFF\xFF\xCB"[..]));
+        let entries = &segment.relocations.as_ref().unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].offset, 2);
+        assert_eq!(
+            entries[1].target,
+            RelocationTarget::ImportOrdinal {
+                module: 1,
+                ordinal: 3
+            }
+        );
+        assert_eq!(exe.module_name(1), Some(&b"KERNEL"[..]));
+    }
+
+    #[test]
+    fn test_file_load_crc() {
+        let mut exe = sample_exe();
+        exe.ne_header.file_load_crc = 1.into();
+        let mut written = io::Cursor::new(Vec::new());
+        exe.write(&mut written).unwrap();
+        let written = written.into_inner();
+        let crc = header::file_load_crc(&written, 0x40);
+        assert_ne!(crc, 1);
+        assert_eq!(written[0x48..0x4C], crc.to_le_bytes());
+
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&written[..])).unwrap();
+        assert!(rewritten.verify(written.len() as u64).is_empty());
+        let mut corrupted = written.clone();
+        corrupted[0xA0] ^= 1;
+        let corrupted = NeExecutable::read(&mut io::Cursor::new(&corrupted[..])).unwrap();
+        assert_eq!(corrupted.verify(written.len() as u64).len(), 1);
+    }
+
+    #[test]
+    fn test_replace_stub() {
+        let exe = sample_exe();
+        let mut stub = vec![0; 0x45];
+        stub[..2].copy_from_slice(b"MZ");
+        let replaced = exe.replace_stub(&stub).unwrap();
+        // Moved by a multiple of the 16-byte alignment.
+        assert_eq!(replaced[0x3C], 0x50);
+        assert_eq!(replaced.len(), exe.file_data.len() + 0x10);
+
+        let rewritten = NeExecutable::read(&mut io::Cursor::new(&replaced[..])).unwrap();
+        assert_eq!(rewritten.dos_stub[0x40..0x45], stub[0x40..]);
+        assert_eq!(rewritten.module_name_entry(), Some(&b"TEST"[..]));
+        assert_eq!(
+            rewritten.segment_entries[0].data,
+            exe.segment_entries[0].data
+        );
+        assert!(rewritten.verify(replaced.len() as u64).is_empty());
+        assert!(replaced.ends_with(b"appended"));
+    }
+
+    #[test]
+    fn test_classify_stub() {
+        let exe = sample_exe();
+        assert_eq!(exe.classify_stub(), dos_stub::StubKind::Custom { size: 0 });
+
+        let replaced = exe.replace_stub(&build::dos_stub()).unwrap();
+        let exe = NeExecutable::read(&mut io::Cursor::new(&replaced[..])).unwrap();
+        assert_eq!(
+            exe.classify_stub(),
+            dos_stub::StubKind::Standard {
+                linker: Some("Microsoft LINK"),
+                message: b"This program requires Microsoft Windows.\r\n".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let mut exe = sample_exe();
+        assert_eq!(exe.classify().to_string(), "Windows EXE");
+        exe.ne_header.flags = (NeFlags::LIBRARY | NeFlags::PROTECTED_MODE_ONLY)
+            .bits()
+            .into();
+        assert_eq!(
+            exe.classify().to_string(),
+            "Windows DLL, protected mode only"
+        );
+        exe.add_export(&"Enable=1:0".parse().unwrap(), true)
+            .unwrap();
+        assert_eq!(
+            exe.classify().kind,
+            classification::ModuleKind::DeviceDriver
+        );
+        exe.ne_header.target_os = 1;
+        assert_eq!(exe.classify().kind, classification::ModuleKind::Os2Library);
+    }
+
+    #[test]
+    fn test_loader_procedures() {
+        let mut exe = sample_exe();
+        let mut data = vec![0; 0x2C];
+        data[..2].copy_from_slice(b"A0");
+        data[0x08..0x0C].copy_from_slice(b"\x34\x12\x01\x00");
+        exe.segment_entries[0].data = Some(data);
+        assert_eq!(exe.loader_procedures(), None);
+
+        exe.ne_header.flags = NeFlags::SELF_LOADING.bits().into();
+        let procedures = exe.loader_procedures().unwrap();
+        assert_eq!(procedures[0].name, "BootApp");
+        assert_eq!(procedures[0].pointer, (1, 0x1234));
+        assert_eq!(procedures[1].pointer, (0, 0));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let exe = sample_exe();
+        let range = exe.overlay().unwrap();
+        assert_eq!(range.end, exe.file_data.len() as u64);
+        assert_eq!(exe.overlay_data(), b"appended");
+        assert_eq!(exe.overlay_kind(), None);
+
+        // Zeros up to the alignment are padding.
+        let mut data = exe.file_data[..range.start as usize].to_vec();
+        data.extend_from_slice(&[0; 4]);
+        let exe = NeExecutable::read(&mut io::Cursor::new(data)).unwrap();
+        assert_eq!(exe.overlay(), None);
+    }
+
+    #[test]
+    fn test_spans() {
+        let exe = sample_exe();
+        let spans = exe.spans();
+        let header_length = spans
+            .iter()
+            .filter(|field| field.path.starts_with("ne_header."))
+            .map(|field| field.span.length)
+            .sum::<u64>();
+        assert_eq!(header_length, 0x40);
+        assert_eq!(
+            exe.span_of("ne_header.segment_count"),
+            Some(span::Span::new(0x40 + 0x1C, 2))
+        );
+        assert_eq!(
+            exe.span_of("dos_header.lfanew"),
+            Some(span::Span::new(0x3C, 4))
+        );
+        assert_eq!(
+            exe.span_of("resident_name_table.entries[0]"),
+            Some(span::Span::new(0x88, 7))
+        );
+        assert_eq!(
+            exe.span_of("entry_table.entries[0]"),
+            Some(span::Span::new(0x92, 3))
+        );
+        assert_eq!(
+            exe.span_of("segment_entries[0].data"),
+            Some(span::Span::new(0xA0, 5))
+        );
+        assert!(spans
+            .windows(2)
+            .all(|pair| pair[0].span.offset <= pair[1].span.offset));
+    }
+
+    #[test]
+    fn test_carve() {
+        let exe = sample();
+        let mut blob = b"junk MZ junk".to_vec();
+        blob.extend_from_slice(&exe);
+        let images = carve::carve(&blob);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].offset, 12);
+        assert_eq!(images[0].data(&blob), &exe[..exe.len() - b"appended".len()]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let exe = sample_exe();
+        let summary = exe.summary();
+        assert_eq!(summary.module_name, b"TEST");
+        assert_eq!(summary.description, b"Test");
+        assert_eq!(
+            summary.classification.kind,
+            classification::ModuleKind::WindowsProgram
+        );
+        assert_eq!(summary.platform, "Windows 1.x/2.x (target OS unset)");
+        assert_eq!((summary.segment_count, summary.resource_count), (1, 0));
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 5,
+                },
+            }],
+        );
+        let describe = |filter| {
+            let mut output = Vec::new();
+            exe.describe(&mut output, false, None, filter, Codepage::default())
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        let output = describe(DescribeFilter::default());
+        assert!(output.starts_with("Module type: "));
+        assert!(output.contains("\nModule name: TEST\nModule description: Test\n"));
+        assert!(output.contains("\nEntry #1: fixed\n"));
+        assert!(output.contains("\n    Relocations:\n        0x0001 FAR_ADDR   0001:0005\n"));
+
+        let header = describe(DescribeFilter {
+            header_only: true,
+            ..Default::default()
+        });
+        assert!(header.ends_with("\nModule name: TEST\nModule description: Test\n"));
+        assert!(!header.contains("Segment #0:"));
+
+        let output = describe(DescribeFilter {
+            no_entries: true,
+            ..Default::default()
+        });
+        assert!(output.contains("\nEntries: 1\n"));
+        assert!(!output.contains("Entry #1"));
+
+        let output = describe(DescribeFilter {
+            no_relocations: true,
+            ..Default::default()
+        });
+        assert!(output.contains("\n    Relocations: 1\n"));
+        assert!(!output.contains("FAR_ADDR"));
+    }
+
+    #[test]
+    fn test_imphash() {
+        use md5::{Digest, Md5};
+
+        let mut exe = sample_exe();
+        assert_eq!(exe.imphash(), None);
+        set_code(
+            &mut exe,
+            b"\x02\x00\xFF\xFF\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_SEGMENT,
+                flags: 0,
+                offset: 0,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        for retarget in ["1:0002=USER.MessageBox", "1:0000=KERNEL.3"] {
+            exe.retarget(&retarget.parse().unwrap()).unwrap();
+        }
+        assert_eq!(exe.normalized_imports(), ["kernel.ord3", "user.messagebox"]);
+        assert_eq!(
+            exe.imphash().unwrap(),
+            format!("{:x}", Md5::digest(b"kernel.ord3,user.messagebox"))
+        );
+    }
+
+    #[test]
+    fn test_code_image() {
+        let mut exe = sample_exe();
+        assert_eq!(exe.code_image(), b"\x55\x8B\xEC\x5D\xCB");
+        set_code(
+            &mut exe,
+            b"\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::ImportOrdinal {
+                    module: 1,
+                    ordinal: 3,
+                },
+            }],
+        );
+        assert_eq!(exe.code_image(), b"\x9A\x00\x00\x00\x00\xCB");
+    }
+
+    /// Lists the code of `exe` with the given options.
+    fn disassembly(exe: &NeExecutable, options: DisassemblyOptions) -> String {
+        let mut output = Vec::new();
+        exe.describe_disassembly(&mut output, &options, Codepage::default())
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// The sample calling `KERNEL.3` far, named `Start`, with a debug symbol
+    /// for the `retf` at 0005.
+    fn with_call() -> NeExecutable {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        exe.retarget(&"1:0001=KERNEL.3".parse().unwrap()).unwrap();
+        exe.resident_name_table
+            .entries
+            .push(resident_name_table::ResidentNameEntry {
+                name: b"Start".to_vec(),
+                index: 1,
+            });
+        exe.debug_symbols.insert((1, 5), b"_done".to_vec());
+        exe
+    }
+
+    #[test]
+    fn test_listing_symbols() {
+        assert_eq!(
+            disassembly(&with_call(), DisassemblyOptions::default()),
+            [
+                "Segment 1 (0001:0000, 6 bytes):",
+                "Start:",
+                "0001:0000:   9A FF FF 00 00          lcall KERNEL.GETVERSION",
+                "_done:",
+                "0001:0005:   CB                      lret",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_listing_fixups() {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\xB8\xFF\xFF\x8E\xD8\xCB",
+            vec![RelocationEntry {
+                source_type: relocation_table::SOURCE_SEGMENT,
+                flags: 0,
+                offset: 1,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        assert_eq!(
+            disassembly(&exe, DisassemblyOptions::default()),
+            [
+                "Segment 1 (0001:0000, 6 bytes):",
+                "entry_1:",
+                "0001:0000:   B8 FF FF                mov $0xffff, %ax                 ; seg 0001",
+                "0001:0003:   8E D8                   mov %ax, %ds",
+                "0001:0005:   CB                      lret",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_listing_segment() {
+        let exe = with_call();
+        let options = |segment| DisassemblyOptions {
+            segment: Some(segment),
+            ..Default::default()
+        };
+        assert_eq!(
+            disassembly(&exe, options(1)),
+            disassembly(&exe, DisassemblyOptions::default())
+        );
+        assert_eq!(disassembly(&exe, options(2)), "Segment 2 does not exist\n");
+    }
+
+    #[test]
+    fn test_listing_range() {
+        let exe = with_call();
+        let options = |range| DisassemblyOptions {
+            range: Some(range),
+            ..Default::default()
+        };
+        assert_eq!(
+            disassembly(&exe, options((1, 5..0x80))),
+            [
+                "0001:0005..0006:",
+                "_done:",
+                "0001:0005:   CB                      lret",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            disassembly(&exe, options((1, 0..1))),
+            [
+                "0001:0000..0001:",
+                "Start:",
+                "0001:0000:   9A FF FF 00 00          lcall KERNEL.GETVERSION",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            disassembly(&exe, options((2, 0..1))),
+            "Segment 2 does not exist\n"
+        );
     }
 }
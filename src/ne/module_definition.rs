@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+
+use super::header::NeFlags;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+impl NeExecutable {
+    /// Prints a module definition (`.def`) file reconstructed from the name and entry tables.
+    pub(crate) fn describe_def<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_default();
+        if self.ne_header.ne_flags().contains(NeFlags::LIBRARY) {
+            writeln!(w, "LIBRARY {}", module_name)?;
+        } else {
+            writeln!(w, "NAME {}", module_name)?;
+        }
+        if let Some(description) = self.nonresident_name_table.entries.first() {
+            let description = codepage.decode(&description.name);
+            writeln!(w, "DESCRIPTION '{}'", description.replace('\'', "''"))?;
+        }
+
+        let exports = self.exports();
+        if exports.is_empty() {
+            return Ok(());
+        }
+        writeln!(w)?;
+        writeln!(w, "EXPORTS")?;
+        for export in exports {
+            let name = if let Some(name) = &export.name {
+                name
+            } else {
+                // A definition file can't export an entry point without a name.
+                writeln!(
+                    w,
+                    "    ; @{} has no name ({:04X}:{:04X})",
+                    export.ordinal, export.segment, export.offset
+                )?;
+                continue;
+            };
+            let mut line = format!("    {} @{}", codepage.decode(&name.name), export.ordinal);
+            if name.resident {
+                line.push_str(" RESIDENTNAME");
+            }
+            if !export.flags.is_shared_data() {
+                line.push_str(" NODATA");
+            }
+            if export.flags.param_words() != 0 {
+                line.push_str(&format!(" {}", export.flags.param_words()));
+            }
+            writeln!(w, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_describe_def() {
+        let mut exe = sample_exe();
+        exe.ne_header.flags = NeFlags::LIBRARY.bits().into();
+        exe.nonresident_name_table.entries[0].name = b"Bob's DLL".to_vec();
+        exe.add_export(&"Func@3=1:0003".parse().unwrap(), false)
+            .unwrap();
+        exe.add_export(&"Init=1:0004".parse().unwrap(), true)
+            .unwrap();
+        let mut output = Vec::new();
+        exe.describe_def(&mut output, Codepage::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            [
+                "LIBRARY TEST",
+                "DESCRIPTION 'Bob''s DLL'",
+                "",
+                "EXPORTS",
+                "    ; @1 has no name (0001:0000)",
+                "    Func @3 NODATA",
+                "    Init @4 RESIDENTNAME NODATA",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 #[derive(Debug, Clone)]
 pub struct ModuleReferenceTable {
@@ -19,6 +19,14 @@ impl ModuleReferenceTable {
         }
         Ok(())
     }
+
+    /// Writes the name offsets; the names go in the imported names table.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            w.write_all(&entry.header.offset.to_le_bytes())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,4 +1,6 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
+
+use super::resource_table::name_len;
 
 #[derive(Debug, Clone)]
 pub struct NonresidentNameTable {
@@ -13,6 +15,14 @@ impl NonresidentNameTable {
         }
         Ok(Self { entries })
     }
+
+    /// Writes the entries and the terminating zero.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            entry.write(w)?;
+        }
+        w.write_all(&[0])
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -43,4 +53,10 @@ impl NonresidentNameEntry {
         };
         Ok(Some(Self { name, index }))
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&[name_len(&self.name)?])?;
+        w.write_all(&self.name)?;
+        w.write_all(&self.index.to_le_bytes())
+    }
 }
@@ -0,0 +1,504 @@
+use std::io;
+
+use super::signatures::Signature;
+use super::NeExecutable;
+
+// Record types; the odd variants have 32-bit fields.
+const THEADR: u8 = 0x80;
+const MODEND: u8 = 0x8A;
+const EXTDEF: u8 = 0x8C;
+const PUBDEF: u8 = 0x90;
+const LNAMES: u8 = 0x96;
+const SEGDEF: u8 = 0x98;
+const FIXUPP: u8 = 0x9C;
+const LEDATA: u8 = 0xA0;
+const LIDATA: u8 = 0xA2;
+const LIB_HEADER: u8 = 0xF0;
+const LIB_END: u8 = 0xF1;
+
+/// A segment defined by an object module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OmfSegment {
+    pub name: Vec<u8>,
+    pub class: Vec<u8>,
+    pub length: u32,
+    /// The contents from LEDATA and LIDATA records, with `None` for bytes
+    /// that aren't initialized or are patched by fixups. It may be shorter
+    /// than `length`.
+    pub data: Vec<Option<u8>>,
+}
+
+impl OmfSegment {
+    pub fn is_code(&self) -> bool {
+        self.class.ends_with(b"CODE")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OmfPublic {
+    pub name: Vec<u8>,
+    /// The 0-based index in [`OmfModule::segments`], or `None` for absolute symbols.
+    pub segment: Option<usize>,
+    pub offset: u32,
+}
+
+/// The target of a fixup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OmfTarget {
+    /// A 0-based index in [`OmfModule::segments`].
+    Segment(usize),
+    /// A 0-based group index.
+    Group(usize),
+    /// A 0-based index in [`OmfModule::externals`].
+    External(usize),
+    Frame(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OmfFixup {
+    /// The 0-based segment index and offset of the patched bytes.
+    pub segment: usize,
+    pub offset: u32,
+    /// The location type: 0 (low byte), 1 (offset), 2 (base), 3 (pointer), ...
+    pub location: u8,
+    pub segment_relative: bool,
+    pub target: Option<OmfTarget>,
+}
+
+impl OmfFixup {
+    /// Returns the number of bytes patched.
+    pub fn size(&self) -> usize {
+        match self.location {
+            0 | 4 => 1,
+            3 | 9 | 13 => 4,
+            11 => 6,
+            _ => 2,
+        }
+    }
+}
+
+/// An object module, read from an `.OBJ` file or a member of a `.LIB` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OmfModule {
+    /// The name from THEADR, usually the source file name.
+    pub name: Vec<u8>,
+    pub segments: Vec<OmfSegment>,
+    pub publics: Vec<OmfPublic>,
+    pub externals: Vec<Vec<u8>>,
+    pub fixups: Vec<OmfFixup>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Reads the fields of a record.
+struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+    is_32: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid("record too short"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("record too short"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn word(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// A 16-bit field, or a 32-bit one in 32-bit records.
+    fn offset(&mut self) -> io::Result<u32> {
+        if self.is_32 {
+            let bytes = self.bytes(4)?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        } else {
+            Ok(self.word()? as u32)
+        }
+    }
+
+    /// An index of one or two bytes.
+    fn index(&mut self) -> io::Result<usize> {
+        let first = self.byte()?;
+        if first & 0x80 == 0 {
+            Ok(first as usize)
+        } else {
+            Ok(((first as usize & 0x7F) << 8) | self.byte()? as usize)
+        }
+    }
+
+    fn name(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.byte()? as usize;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// Expands an iterated data block of an LIDATA record.
+fn expand_block(fields: &mut Fields, output: &mut Vec<u8>) -> io::Result<()> {
+    let repeat = fields.offset()? as usize;
+    let blocks = fields.word()?;
+    let start = output.len();
+    if blocks == 0 {
+        let len = fields.byte()? as usize;
+        output.extend_from_slice(fields.bytes(len)?);
+    } else {
+        for _ in 0..blocks {
+            expand_block(fields, output)?;
+        }
+    }
+    let block = output[start..].to_vec();
+    for _ in 1..repeat {
+        if output.len() > 0x10_0000 {
+            return Err(invalid("iterated data too large"));
+        }
+        output.extend_from_slice(&block);
+    }
+    Ok(())
+}
+
+/// Returns whether the data looks like an object module or a library.
+pub fn is_omf(data: &[u8]) -> bool {
+    matches!(data.first(), Some(&(THEADR | LIB_HEADER)))
+}
+
+/// Parses an object file or a library into its modules.
+pub fn parse(data: &[u8]) -> io::Result<Vec<OmfModule>> {
+    let mut modules = Vec::new();
+    let mut module = OmfModule::default();
+    let mut names = Vec::<Vec<u8>>::new();
+    let mut page_size = 0;
+    let mut last_data = None::<(usize, u32)>;
+    let mut target_threads = [None::<(u8, usize)>; 4];
+    let mut pos = 0;
+    while pos + 3 <= data.len() {
+        let record_type = data[pos];
+        let length = u16::from_le_bytes([data[pos + 1], data[pos + 2]]) as usize;
+        let contents = data
+            .get(pos + 3..pos + 3 + length.saturating_sub(1))
+            .ok_or_else(|| invalid("record extends past the end of the file"))?;
+        let mut fields = Fields {
+            data: contents,
+            pos: 0,
+            is_32: record_type & 1 != 0,
+        };
+        pos += 3 + length;
+
+        match record_type & !1 {
+            LIB_HEADER => {
+                page_size = length + 3;
+                continue;
+            }
+            LIB_END => break,
+            THEADR => module.name = fields.name()?,
+            LNAMES => {
+                while !fields.is_empty() {
+                    names.push(fields.name()?);
+                }
+            }
+            SEGDEF => {
+                let acbp = fields.byte()?;
+                if acbp >> 5 == 0 {
+                    // Absolute segment: frame number and offset.
+                    fields.bytes(3)?;
+                }
+                let mut length = fields.offset()?;
+                if acbp & 0x02 != 0 && !fields.is_32 {
+                    length = 0x10000;
+                }
+                let name = fields.index()?;
+                let class = fields.index()?;
+                let name_of = |index: usize| {
+                    index
+                        .checked_sub(1)
+                        .and_then(|index| names.get(index))
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                module.segments.push(OmfSegment {
+                    name: name_of(name),
+                    class: name_of(class),
+                    length,
+                    data: Vec::new(),
+                });
+            }
+            PUBDEF => {
+                let _group = fields.index()?;
+                let segment = fields.index()?.checked_sub(1);
+                if segment.is_none() {
+                    fields.word()?;
+                }
+                while !fields.is_empty() {
+                    let name = fields.name()?;
+                    let offset = fields.offset()?;
+                    let _type = fields.index()?;
+                    module.publics.push(OmfPublic {
+                        name,
+                        segment,
+                        offset,
+                    });
+                }
+            }
+            EXTDEF => {
+                while !fields.is_empty() {
+                    module.externals.push(fields.name()?);
+                    let _type = fields.index()?;
+                }
+            }
+            LEDATA | LIDATA => {
+                let segment = fields
+                    .index()?
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("data for segment 0"))?;
+                let offset = fields.offset()?;
+                let bytes = if record_type & !1 == LEDATA {
+                    fields.bytes(contents.len() - fields.pos)?.to_vec()
+                } else {
+                    let mut bytes = Vec::new();
+                    while !fields.is_empty() {
+                        expand_block(&mut fields, &mut bytes)?;
+                    }
+                    bytes
+                };
+                let target = module
+                    .segments
+                    .get_mut(segment)
+                    .ok_or_else(|| invalid("data for an undefined segment"))?;
+                let end = offset as usize + bytes.len();
+                if target.data.len() < end {
+                    target.data.resize(end, None);
+                }
+                for (slot, &byte) in target.data[offset as usize..end].iter_mut().zip(&bytes) {
+                    *slot = Some(byte);
+                }
+                last_data = Some((segment, offset));
+            }
+            FIXUPP => {
+                while !fields.is_empty() {
+                    let first = fields.byte()?;
+                    if first & 0x80 == 0 {
+                        // THREAD: remember targets for later fixups; skip frames.
+                        let method = (first >> 2) & 7;
+                        let datum = if method < 3 { fields.index()? } else { 0 };
+                        if first & 0x40 == 0 {
+                            target_threads[(first & 3) as usize] = Some((method, datum));
+                        }
+                        continue;
+                    }
+                    let locat = u16::from_be_bytes([first, fields.byte()?]);
+                    let fixdat = fields.byte()?;
+                    if fixdat & 0x80 == 0 {
+                        let frame_method = (fixdat >> 4) & 7;
+                        if frame_method < 3 {
+                            fields.index()?;
+                        }
+                    }
+                    let (method, datum) = if fixdat & 0x08 == 0 {
+                        let method = fixdat & 3;
+                        let datum = if method == 3 {
+                            fields.word()? as usize
+                        } else {
+                            fields.index()?
+                        };
+                        (method, datum)
+                    } else {
+                        target_threads[(fixdat & 3) as usize].unwrap_or((3, 0))
+                    };
+                    if fixdat & 0x04 == 0 {
+                        fields.offset()?;
+                    }
+                    let target = match method & 3 {
+                        0 => datum.checked_sub(1).map(OmfTarget::Segment),
+                        1 => datum.checked_sub(1).map(OmfTarget::Group),
+                        2 => datum.checked_sub(1).map(OmfTarget::External),
+                        _ => Some(OmfTarget::Frame(datum as u16)),
+                    };
+                    if let Some((segment, offset)) = last_data {
+                        module.fixups.push(OmfFixup {
+                            segment,
+                            offset: offset + (locat & 0x3FF) as u32,
+                            location: ((locat >> 10) & 0xF) as u8,
+                            segment_relative: locat & 0x4000 != 0,
+                            target,
+                        });
+                    }
+                }
+            }
+            MODEND => {
+                for fixup in &module.fixups {
+                    if let Some(segment) = module.segments.get_mut(fixup.segment) {
+                        let start = (fixup.offset as usize).min(segment.data.len());
+                        let end = (start + fixup.size()).min(segment.data.len());
+                        segment.data[start..end].fill(None);
+                    }
+                }
+                modules.push(std::mem::take(&mut module));
+                names.clear();
+                last_data = None;
+                target_threads = [None; 4];
+                if page_size > 0 {
+                    pos = pos.next_multiple_of(page_size);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(modules)
+}
+
+impl OmfModule {
+    /// Makes signatures of the public functions in code segments, each
+    /// extending to the next public symbol or the end of the segment.
+    pub fn signatures(&self) -> Vec<Signature> {
+        let mut signatures = Vec::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            if !segment.is_code() {
+                continue;
+            }
+            let mut publics = self
+                .publics
+                .iter()
+                .filter(|public| public.segment == Some(index))
+                .collect::<Vec<_>>();
+            publics.sort_by_key(|public| public.offset);
+            for (i, public) in publics.iter().enumerate() {
+                let end = publics
+                    .get(i + 1)
+                    .map_or(segment.data.len(), |next| next.offset as usize);
+                if let Some(code) = segment.data.get(public.offset as usize..end) {
+                    if !code.is_empty() {
+                        signatures.push(Signature::from_code(&public.name, code));
+                    }
+                }
+            }
+        }
+        signatures
+    }
+}
+
+/// Returns where the module's segment data occurs in the masked segment data,
+/// treating `None` on either side as matching anything.
+fn find_segment(haystack: &[Option<u8>], needle: &[Option<u8>]) -> Option<usize> {
+    if needle.iter().flatten().count() < 8 || needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&start| {
+        haystack[start..]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.is_none() || b.is_none() || a == b)
+    })
+}
+
+impl NeExecutable {
+    /// Finds the segments of the object modules in the executable, and names
+    /// the addresses of their public symbols and the segments they are in.
+    ///
+    /// Returns the number of symbols named.
+    pub fn apply_omf_modules(&mut self, modules: &[OmfModule]) -> usize {
+        let masked = (0..self.segment_entries.len())
+            .map(|index| self.masked_segment_data(index))
+            .collect::<Vec<_>>();
+        let mut count = 0;
+        for module in modules {
+            for (index, segment) in module.segments.iter().enumerate() {
+                let found = masked.iter().enumerate().find_map(|(ne_index, haystack)| {
+                    find_segment(haystack, &segment.data).map(|base| (ne_index, base))
+                });
+                let (ne_index, base) = match found {
+                    Some(found) => found,
+                    None => continue,
+                };
+                let number = ne_index as u16 + 1;
+                self.segment_names
+                    .entry(number)
+                    .or_insert_with(|| segment.name.clone());
+                for public in &module.publics {
+                    if public.segment != Some(index) {
+                        continue;
+                    }
+                    let offset = base + public.offset as usize;
+                    if let Ok(offset) = u16::try_from(offset) {
+                        if let std::collections::btree_map::Entry::Vacant(entry) =
+                            self.debug_symbols.entry((number, offset))
+                        {
+                            entry.insert(public.name.clone());
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_type: u8, contents: &[u8]) -> Vec<u8> {
+        let mut record = vec![record_type];
+        record.extend_from_slice(&(contents.len() as u16 + 1).to_le_bytes());
+        record.extend_from_slice(contents);
+        record.push(0);
+        record
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut data = Vec::new();
+        data.extend(record(THEADR, b"\x05a.asm"));
+        data.extend(record(LNAMES, b"\x00\x05_TEXT\x04CODE"));
+        // Byte-aligned, public, 12 bytes, named _TEXT, class CODE.
+        data.extend(record(SEGDEF, b"\x28\x0C\x00\x02\x03\x01"));
+        data.extend(record(EXTDEF, b"\x0AGETVERSION\x00"));
+        data.extend(record(
+            PUBDEF,
+            b"\x00\x01\x05_main\x00\x00\x00\x07_helper\x08\x00\x00",
+        ));
+        data.extend(record(
+            LEDATA,
+            b"\x01\x00\x00\x55\x8B\xEC\x9A\x00\x00\x00\x00\xCD\x21\x5D\xCB",
+        ));
+        // A pointer fixup at 4 to external 1.
+        data.extend(record(FIXUPP, b"\xCC\x04\x56\x01"));
+        data.extend(record(MODEND, b"\x00"));
+
+        let modules = parse(&data).unwrap();
+        assert_eq!(modules.len(), 1);
+        let module = &modules[0];
+        assert_eq!(module.name, b"a.asm");
+        assert_eq!(module.segments[0].name, b"_TEXT");
+        assert!(module.segments[0].is_code());
+        assert_eq!(module.externals, vec![b"GETVERSION".to_vec()]);
+        assert_eq!(module.fixups[0].target, Some(OmfTarget::External(0)));
+        assert_eq!(module.segments[0].data[3], Some(0x9A));
+        assert_eq!(module.segments[0].data[4..8], [None; 4]);
+        assert_eq!(module.publics[1].offset, 8);
+
+        let signatures = module.signatures();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].name, b"_main");
+        assert_eq!(signatures[0].length, 8);
+    }
+}
@@ -0,0 +1,50 @@
+use std::ops::Range;
+
+use super::turbo_debugger::TD_MAGIC;
+use super::NeExecutable;
+use crate::util::bytes::u16_at;
+
+impl NeExecutable {
+    /// Returns the range of the data appended after the image, if any.
+    ///
+    /// Zeros up to the next segment or resource alignment are taken as
+    /// padding rather than appended data.
+    pub fn overlay(&self) -> Option<Range<u64>> {
+        let file_size = self.file_data.len() as u64;
+        let image_end = self.image_end().min(file_size);
+        let shift = self
+            .ne_header
+            .alignment_shift()
+            .max(self.resource_table.header.alignment_shift_count.min(15));
+        let padding_end = image_end.next_multiple_of(1 << shift).min(file_size);
+        let start = self.file_data[image_end as usize..padding_end as usize]
+            .iter()
+            .position(|&byte| byte != 0)
+            .map_or(padding_end, |pos| image_end + pos as u64);
+        (start < file_size).then_some(start..file_size)
+    }
+
+    /// Returns the appended data, or an empty slice if there is none.
+    pub fn overlay_data(&self) -> &[u8] {
+        match self.overlay() {
+            Some(range) => &self.file_data[range.start as usize..],
+            None => &[],
+        }
+    }
+
+    /// Names the kind of the appended data if it's recognized.
+    pub fn overlay_kind(&self) -> Option<&'static str> {
+        let data = self.overlay_data();
+        if u16_at(data, 0) == Some(TD_MAGIC) {
+            Some("Turbo Debugger information")
+        } else if data.starts_with(b"NB0") || data.starts_with(b"NB1") {
+            Some("CodeView information")
+        } else if data.starts_with(b"PK\x03\x04") {
+            Some("ZIP archive")
+        } else if data.starts_with(b"MSCF") {
+            Some("cabinet")
+        } else {
+            None
+        }
+    }
+}
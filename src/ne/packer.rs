@@ -0,0 +1,199 @@
+use super::search::BytePattern;
+use super::statistics::ByteStatistics;
+use super::NeExecutable;
+
+/// A packer or protector recognized by [`NeExecutable::detect_packers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackerDetection {
+    pub name: String,
+    /// What the detection is based on.
+    pub evidence: String,
+}
+
+/// Marker strings left by packers in their loader code, matched case-insensitively
+/// in the segments the loader runs from.
+const NE_MARKERS: &[(&str, &[u8])] = &[
+    ("WinLite", b"winlite"),
+    ("PackWin", b"packwin"),
+    ("Shrinker", b"shrinker"),
+    ("Shrinker", b"blinkinc"),
+];
+
+/// Marker strings of DOS executable packers, matched in the DOS stub.
+const STUB_MARKERS: &[(&str, &[u8])] = &[("PKLITE", b"pklite"), ("LZEXE", b"lzexe")];
+
+/// The decompressors of DOS executable packers, matched at the entry point
+/// (CS:IP) of the DOS stub. `??` stands for bytes that vary between files.
+const STUB_SIGNATURES: &[(&str, &str)] = &[
+    // mov $seg, %ax; mov $seg, %dx; mov %ds, %bx; add %ax, %bx; cmp 0x2, %bx; jae
+    ("PKLITE", "B8 ?? ?? BA ?? ?? 8C DB 03 D8 3B 1E 02 00 73"),
+    // push %es; push %cs; pop %ds; mov 0xc, %cx; ...; mov %bx, %es
+    (
+        "LZEXE",
+        "06 0E 1F 8B 0E 0C 00 8B F1 4E 89 F7 8C DB 03 1E 0A 00 8E C3",
+    ),
+];
+
+/// Entropy (in bits per byte) above which a segment's data looks compressed or encrypted.
+const PACKED_ENTROPY: f64 = 7.2;
+
+/// Segments smaller than this are too short for their entropy to mean much.
+const MIN_ENTROPY_SIZE: usize = 256;
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window.eq_ignore_ascii_case(needle))
+}
+
+impl NeExecutable {
+    /// Looks for signs of packers and protectors: marker strings in the
+    /// loader code and in the DOS stub, the decompressor at the entry point
+    /// of the DOS stub, the self-loading flag, and segments whose data looks
+    /// compressed.
+    pub fn detect_packers(&self) -> Vec<PackerDetection> {
+        let mut detections = Vec::new();
+        // Packers put their loader at the entry point, or at the start of a
+        // self-loading application; the strings of other segments don't count.
+        let entry_segment = ((self.ne_header.entry_point.value() >> 16) as usize).checked_sub(1);
+        let is_loader = |i: usize| entry_segment == Some(i) || (i == 0 && self.is_self_loading());
+
+        let stub = self.dos_stub.get(0x40..).unwrap_or_default();
+        for &(name, marker) in STUB_MARKERS {
+            if contains_ignore_case(stub, marker) {
+                detections.push(PackerDetection {
+                    name: format!("{} (DOS stub)", name),
+                    evidence: "marker string in the DOS stub".to_owned(),
+                });
+            }
+        }
+        let (code, _) = self.stub_program();
+        let entry_code = code.get(self.stub_entry()..).unwrap_or_default();
+        for &(name, signature) in STUB_SIGNATURES {
+            let pattern = signature.parse::<BytePattern>().unwrap();
+            let prefix = &entry_code[..pattern.0.len().min(entry_code.len())];
+            if pattern.find_all(prefix) == [0] {
+                detections.push(PackerDetection {
+                    name: format!("{} (DOS stub)", name),
+                    evidence: format!(
+                        "decompressor at the entry point {:04X}:{:04X}",
+                        self.dos_header.cs.value(),
+                        self.dos_header.ip.value()
+                    ),
+                });
+            }
+        }
+        // LZEXE writes its version at 0x1C of the header.
+        if let Some(version @ (b"LZ09" | b"LZ91")) = self.dos_stub.get(0x1C..0x20) {
+            detections.push(PackerDetection {
+                name: "LZEXE (DOS stub)".to_owned(),
+                evidence: format!("{} at 0x1C", String::from_utf8_lossy(version)),
+            });
+        }
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            if segment.is_code() && is_loader(i) {
+                for &(name, marker) in NE_MARKERS {
+                    let seen = detections.iter().any(|detection| detection.name == name);
+                    if !seen && contains_ignore_case(&data, marker) {
+                        detections.push(PackerDetection {
+                            name: name.to_owned(),
+                            evidence: format!(
+                                "marker string {:?} in segment {}",
+                                String::from_utf8_lossy(marker),
+                                i + 1
+                            ),
+                        });
+                    }
+                }
+            }
+            if data.len() >= MIN_ENTROPY_SIZE {
+                let statistics = ByteStatistics::new(&data);
+                if statistics.entropy >= PACKED_ENTROPY {
+                    detections.push(PackerDetection {
+                        name: "unknown packer or encryption".to_owned(),
+                        evidence: format!(
+                            "segment {} has entropy {:.2} bits/byte",
+                            i + 1,
+                            statistics.entropy
+                        ),
+                    });
+                }
+            }
+        }
+
+        if !detections.is_empty() && self.is_self_loading() {
+            detections.push(PackerDetection {
+                name: "self-loading application".to_owned(),
+                evidence: "its first segment loads the others, as packer loaders do".to_owned(),
+            });
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_contains_ignore_case() {
+        assert!(contains_ignore_case(b"xxWinLite v1.0", b"winlite"));
+        assert!(!contains_ignore_case(b"WinLit", b"winlite"));
+    }
+
+    #[test]
+    fn test_stub_signatures() {
+        for (name, code) in [
+            (
+                "PKLITE (DOS stub)",
+                &b"\xB8\x3A\x01\xBA\x50\x02\x8C\xDB\x03\xD8\x3B\x1E\x02\x00\x73\x0A"[..],
+            ),
+            (
+                "LZEXE (DOS stub)",
+                b"\x06\x0E\x1F\x8B\x0E\x0C\x00\x8B\xF1\x4E\x89\xF7\x8C\xDB\x03\x1E\x0A\x00\x8E\xC3\xFD",
+            ),
+        ] {
+            // The code follows the 0x40 bytes of the header.
+            let mut exe = sample_exe();
+            exe.dos_stub.extend_from_slice(&[0xCC; 0x12]);
+            exe.dos_stub.extend_from_slice(code);
+            exe.dos_header.cparhdr = 4.into();
+            exe.dos_header.cp = 1.into();
+            exe.dos_header.cblp = (exe.dos_stub.len() as u16).into();
+            exe.dos_header.cs = 1.into();
+            exe.dos_header.ip = 2.into();
+            assert_eq!(
+                exe.detect_packers(),
+                [PackerDetection {
+                    name: name.to_owned(),
+                    evidence: "decompressor at the entry point 0001:0002".to_owned(),
+                }]
+            );
+            // Elsewhere in the stub, it's just data.
+            exe.dos_header.ip = 0.into();
+            assert_eq!(exe.detect_packers(), []);
+        }
+    }
+
+    #[test]
+    fn test_detect_packers_loader_segment() {
+        let mut exe = sample_exe();
+        exe.segment_entries[0].data = Some(b"\xCBWinLite".to_vec());
+        assert_eq!(
+            exe.detect_packers(),
+            [PackerDetection {
+                name: "WinLite".to_owned(),
+                evidence: "marker string \"winlite\" in segment 1".to_owned(),
+            }]
+        );
+        // Without the entry point there, the segment isn't the loader.
+        exe.ne_header.entry_point = 0.into();
+        assert_eq!(exe.detect_packers(), []);
+    }
+}
@@ -0,0 +1,459 @@
+use std::io;
+use std::str::FromStr;
+
+use super::entry_table::SegmentEntry;
+use super::module_reference_table::{ModuleReferenceEntry, ModuleReferenceEntryHeader};
+use super::relocation_table::{RelocationEntry, RelocationTarget};
+use super::resource_table::name_len;
+use super::search::BytePattern;
+use super::NeExecutable;
+
+/// Bytes to write over a segment: `SEG:OFF=HEXBYTES`, with the address in hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub segment: u16,
+    pub offset: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl FromStr for Patch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, bytes) = s.split_once('=').ok_or("expected SEG:OFF=HEXBYTES")?;
+        let (segment, offset) = address.split_once(':').ok_or("expected SEG:OFF")?;
+        let parse = |s: &str| u16::from_str_radix(s, 16).map_err(|e| format!("{}: {}", s, e));
+        let bytes = bytes
+            .parse::<BytePattern>()?
+            .0
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or("wildcards can't be written")?;
+        Ok(Self {
+            segment: parse(segment)?,
+            offset: parse(offset)?,
+            bytes,
+        })
+    }
+}
+
+/// A new target for a relocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewTarget {
+    /// `MODULE.ORDINAL`
+    ImportOrdinal { module: Vec<u8>, ordinal: u16 },
+    /// `MODULE.NAME`
+    ImportName { module: Vec<u8>, name: Vec<u8> },
+    /// `SEG:OFF` in hex, in a fixed segment or at a moveable entry point.
+    Internal { segment: u8, offset: u16 },
+}
+
+impl FromStr for NewTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((segment, offset)) = s.split_once(':') {
+            return Ok(NewTarget::Internal {
+                segment: u8::from_str_radix(segment, 16)
+                    .map_err(|e| format!("{}: {}", segment, e))?,
+                offset: u16::from_str_radix(offset, 16)
+                    .map_err(|e| format!("{}: {}", offset, e))?,
+            });
+        }
+        match s.split_once('.') {
+            Some((module, function)) if !module.is_empty() && !function.is_empty() => {
+                let module = module.as_bytes().to_vec();
+                Ok(match function.parse::<u16>() {
+                    Ok(ordinal) => NewTarget::ImportOrdinal { module, ordinal },
+                    Err(_) => NewTarget::ImportName {
+                        module,
+                        name: function.as_bytes().to_vec(),
+                    },
+                })
+            }
+            _ => Err("expected MODULE.ORDINAL, MODULE.NAME or SEG:OFF".to_owned()),
+        }
+    }
+}
+
+/// A relocation to redirect: `SEG:OFF=TARGET`, where `SEG:OFF` is one of
+/// the locations it patches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retarget {
+    pub segment: u16,
+    pub offset: u16,
+    pub target: NewTarget,
+}
+
+impl FromStr for Retarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (site, target) = s.split_once('=').ok_or("expected SEG:OFF=TARGET")?;
+        let (segment, offset) = site.split_once(':').ok_or("expected SEG:OFF")?;
+        let parse = |s: &str| u16::from_str_radix(s, 16).map_err(|e| format!("{}: {}", s, e));
+        Ok(Self {
+            segment: parse(segment)?,
+            offset: parse(offset)?,
+            target: target.parse()?,
+        })
+    }
+}
+
+/// A new segment size: `SEG=LENGTH`, with the length in decimal or `0x` hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentResize {
+    pub segment: u16,
+    pub length: u32,
+}
+
+impl FromStr for SegmentResize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (segment, length) = s.split_once('=').ok_or("expected SEG=LENGTH")?;
+        let length = match length
+            .strip_prefix("0x")
+            .or_else(|| length.strip_prefix("0X"))
+        {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => length.parse(),
+        }
+        .map_err(|e| format!("{}: {}", length, e))?;
+        if length > 0x10000 {
+            return Err(format!("{} is larger than a segment", length));
+        }
+        Ok(Self {
+            segment: segment.parse().map_err(|e| format!("{}: {}", segment, e))?,
+            length,
+        })
+    }
+}
+
+impl NeExecutable {
+    /// Returns the offset of a module in the module reference table,
+    /// adding it (and its name to the imported names) if it's not there.
+    pub(crate) fn module_reference(&mut self, module: &[u8]) -> io::Result<u16> {
+        let entries = &self.module_reference_table.entries;
+        if let Some(pos) = entries
+            .iter()
+            .position(|entry| entry.name.eq_ignore_ascii_case(module))
+        {
+            return Ok(pos as u16 + 1);
+        }
+        let offset = self.imported_name(module)?;
+        self.module_reference_table
+            .entries
+            .push(ModuleReferenceEntry {
+                header: ModuleReferenceEntryHeader { offset },
+                name: module.to_vec(),
+            });
+        Ok(self.module_reference_table.entries.len() as u16)
+    }
+
+    /// Returns the offset of a name in the imported name table, appending
+    /// it if it's not there.
+    fn imported_name(&mut self, name: &[u8]) -> io::Result<u16> {
+        let module_names = self
+            .module_reference_table
+            .entries
+            .iter()
+            .map(|entry| (entry.header.offset, &entry.name));
+        let mut end = 1;
+        for (offset, existing) in self
+            .imported_name_table
+            .names
+            .iter()
+            .map(|(&o, n)| (o, n))
+            .chain(module_names)
+        {
+            if existing == name {
+                return Ok(offset);
+            }
+            end = end.max(offset as usize + 1 + existing.len());
+        }
+        let offset = u16::try_from(end).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "imported name table too large")
+        })?;
+        name_len(name)?;
+        self.imported_name_table.names.insert(offset, name.to_vec());
+        Ok(offset)
+    }
+
+    /// Resolves a new target, adding modules and imported names as needed.
+    pub(crate) fn relocation_target(&mut self, target: &NewTarget) -> io::Result<RelocationTarget> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        Ok(match target {
+            NewTarget::ImportOrdinal { module, ordinal } => RelocationTarget::ImportOrdinal {
+                module: self.module_reference(module)?,
+                ordinal: *ordinal,
+            },
+            NewTarget::ImportName { module, name } => RelocationTarget::ImportName {
+                module: self.module_reference(module)?,
+                name_offset: self.imported_name(name)?,
+            },
+            &NewTarget::Internal { segment, offset } => {
+                let entry = self
+                    .segment_entries
+                    .get((segment as usize).wrapping_sub(1))
+                    .ok_or_else(|| invalid(format!("no segment {}", segment)))?;
+                if !entry.is_moveable() {
+                    RelocationTarget::InternalFixed { segment, offset }
+                } else {
+                    // Moveable segments can only be referred to through their entry points.
+                    let ordinal = self
+                        .entry_table
+                        .entries
+                        .iter()
+                        .position(|entry| {
+                            matches!(entry, SegmentEntry::Moveable(entry)
+                                if entry.segment == segment && entry.offset == offset)
+                        })
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "{:04X}:{:04X} is in a moveable segment but isn't an entry point",
+                                segment, offset
+                            ))
+                        })?;
+                    RelocationTarget::InternalMoveable {
+                        ordinal: ordinal as u16 + 1,
+                    }
+                }
+            }
+        })
+    }
+
+    /// Redirects the relocation that patches a location to a new target.
+    ///
+    /// If the relocation patches other locations as well, the location is
+    /// unlinked from its chain and gets a relocation of its own. Additive
+    /// relocations keep the value to add.
+    pub fn retarget(&mut self, retarget: &Retarget) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let address = format!("{:04X}:{:04X}", retarget.segment, retarget.offset);
+        let target = self.relocation_target(&retarget.target)?;
+        let segment = (retarget.segment as usize)
+            .checked_sub(1)
+            .and_then(|index| self.segment_entries.get_mut(index))
+            .ok_or_else(|| invalid(format!("no segment {}", retarget.segment)))?;
+        let (data, relocations) = match (&mut segment.data, &mut segment.relocations) {
+            (Some(data), Some(relocations)) => (data, relocations),
+            _ => return Err(invalid(format!("no relocation patches {}", address))),
+        };
+        let index = relocations
+            .sites(data)?
+            .iter()
+            .find(|site| site.offset == retarget.offset)
+            .map(|site| site.entry)
+            .ok_or_else(|| invalid(format!("no relocation patches {}", address)))?;
+        let entry = &mut relocations.entries[index];
+        if let RelocationTarget::OsFixup { .. } = entry.target {
+            return Err(invalid(format!("{} is patched by an OS fixup", address)));
+        }
+        let sites = entry.sites(data)?;
+        if sites.len() == 1 {
+            entry.target = target;
+            return Ok(());
+        }
+
+        // Unlink the location from the chain.
+        let pos = sites
+            .iter()
+            .position(|&site| site == retarget.offset)
+            .unwrap();
+        let next = sites.get(pos + 1).copied().unwrap_or(0xFFFF);
+        if pos == 0 {
+            entry.offset = next;
+        } else {
+            let previous = sites[pos - 1] as usize;
+            data[previous..previous + 2].copy_from_slice(&next.to_le_bytes());
+        }
+        let site = retarget.offset as usize;
+        data[site..site + 2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        let new_entry = RelocationEntry {
+            offset: retarget.offset,
+            target,
+            ..*entry
+        };
+        relocations.entries.push(new_entry);
+        Ok(())
+    }
+
+    /// Grows or shrinks the data of a segment at its end, filling new bytes
+    /// with zeros, e.g. to make room for a code cave. The allocation grows
+    /// along with it.
+    ///
+    /// Shrinking is refused if it would cut off relocation fixup sites or
+    /// entry points. The segments after it move when the file is written.
+    pub fn resize_segment(&mut self, resize: SegmentResize) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let number = resize.segment;
+        let index = (number as usize)
+            .checked_sub(1)
+            .filter(|&index| index < self.segment_entries.len())
+            .ok_or_else(|| invalid(format!("no segment {}", number)))?;
+        let length = resize.length as usize;
+        let segment = &self.segment_entries[index];
+        if let Some(relocations) = &segment.relocations {
+            let data = segment.expanded_data()?.unwrap_or_default();
+            for entry in &relocations.entries {
+                if entry
+                    .sites(&data)?
+                    .iter()
+                    .any(|&site| site as usize + entry.source_size() > length)
+                {
+                    return Err(invalid(format!(
+                        "segment {} has relocation fixup sites past 0x{:X}",
+                        number, length
+                    )));
+                }
+            }
+        }
+        if self
+            .exports()
+            .iter()
+            .any(|export| export.segment as u16 == number && export.offset as usize >= length)
+        {
+            return Err(invalid(format!(
+                "segment {} has entry points past 0x{:X}",
+                number, length
+            )));
+        }
+
+        let segment = &mut self.segment_entries[index];
+        if segment.is_iterated() {
+            return Err(invalid(format!("segment {} is iterated", number)));
+        }
+        segment.data.get_or_insert_with(Vec::new).resize(length, 0);
+        if segment.min_alloc() < length as u64 {
+            segment.header.min_alloc = length as u16;
+        }
+        Ok(())
+    }
+
+    /// Returns the file offset of a logical address, if it's in the
+    /// segment's data in the file. Iterated segments have no such mapping.
+    pub fn file_offset(&self, segment: u16, offset: u16) -> Option<u64> {
+        let entry = self
+            .segment_entries
+            .get((segment as usize).checked_sub(1)?)?;
+        let data = entry.data.as_ref()?;
+        if entry.is_iterated() || offset as usize >= data.len() {
+            return None;
+        }
+        Some(entry.data_offset() + offset as u64)
+    }
+
+    /// Overwrites bytes of a segment.
+    ///
+    /// Bytes patched by relocations are refused unless `force` is set, as
+    /// the loader would overwrite them (or follow them as a fixup chain).
+    pub fn patch(&mut self, patch: &Patch, force: bool) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let address = format!("{:04X}:{:04X}", patch.segment, patch.offset);
+        if self.file_offset(patch.segment, patch.offset).is_none() {
+            return Err(invalid(format!("{} isn't backed by the file", address)));
+        }
+        let index = patch.segment as usize - 1;
+        let range = patch.offset as usize..patch.offset as usize + patch.bytes.len();
+        let masked = self.masked_segment_data(index);
+        let covered = match masked.get(range.clone()) {
+            Some(covered) => covered,
+            None => {
+                return Err(invalid(format!(
+                    "the patch at {} runs past the segment",
+                    address
+                )))
+            }
+        };
+        if !force && covered.contains(&None) {
+            return Err(invalid(format!(
+                "the patch at {} overlaps a relocation fixup site",
+                address
+            )));
+        }
+        self.segment_entries[index].data.as_mut().unwrap()[range].copy_from_slice(&patch.bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::SOURCE_FAR_ADDR;
+    use super::super::tests::{rewrite, sample_exe, set_code};
+    use super::*;
+
+    #[test]
+    fn test_parse_patch() {
+        let patch = "1:001A=90 90".parse::<Patch>().unwrap();
+        assert_eq!((patch.segment, patch.offset), (1, 0x1A));
+        assert_eq!(patch.bytes, [0x90, 0x90]);
+        assert!("1:001A=9?".parse::<Patch>().is_err());
+        assert!("1:001A=??".parse::<Patch>().is_err());
+    }
+
+    #[test]
+    fn test_parse_retarget() {
+        let retarget = "1:0003=SHIM.5".parse::<Retarget>().unwrap();
+        assert_eq!((retarget.segment, retarget.offset), (1, 3));
+        assert_eq!(
+            retarget.target,
+            NewTarget::ImportOrdinal {
+                module: b"SHIM".to_vec(),
+                ordinal: 5
+            }
+        );
+        assert_eq!(
+            "SHIM.MyFunc".parse::<NewTarget>().unwrap(),
+            NewTarget::ImportName {
+                module: b"SHIM".to_vec(),
+                name: b"MyFunc".to_vec()
+            }
+        );
+        assert_eq!(
+            "2:0010".parse::<NewTarget>().unwrap(),
+            NewTarget::Internal {
+                segment: 2,
+                offset: 0x10
+            }
+        );
+    }
+
+    #[test]
+    fn test_resize_segment() {
+        let mut exe = sample_exe();
+        let resize = |length| SegmentResize { segment: 1, length };
+        exe.resize_segment(resize(0x20)).unwrap();
+        let exe = rewrite(&exe);
+        assert_eq!(exe.segment_entries[0].data.as_ref().unwrap().len(), 0x20);
+        assert!(exe.verify(exe.file_data.len() as u64).is_empty());
+
+        // An additive far pointer at 2..6 keeps the segment from shrinking below 6.
+        let mut exe = exe;
+        let data = exe.segment_entries[0].data.clone().unwrap();
+        set_code(
+            &mut exe,
+            &data,
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 4,
+                offset: 2,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        assert!(exe.resize_segment(resize(5)).is_err());
+        exe.resize_segment(resize(6)).unwrap();
+        let exe = rewrite(&exe);
+        assert_eq!(exe.segment_entries[0].data.as_ref().unwrap().len(), 6);
+        assert!(exe.verify(exe.file_data.len() as u64).is_empty());
+
+        // A chain leading out of the segment is an error rather than no sites.
+        let mut exe = exe;
+        exe.segment_entries[0].relocations.as_mut().unwrap().entries[0].flags = 0;
+        exe.segment_entries[0].data.as_mut().unwrap()[2..4].copy_from_slice(&[0x10, 0x00]);
+        assert!(exe.resize_segment(resize(6)).is_err());
+    }
+}
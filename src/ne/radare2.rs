@@ -0,0 +1,122 @@
+use std::io::{self, Write};
+
+use super::ida_script::linear_address;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// Makes a name usable as a radare2 flag.
+fn flag_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Removes the characters radare2 would take as command separators or
+/// temporary seeks from a comment.
+fn comment_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if ";@|>`~".contains(c) { ' ' } else { c })
+        .collect()
+}
+
+impl NeExecutable {
+    /// Prints radare2 commands that map the segments and apply the names and
+    /// fixup comments, for `r2 -i script.r2 FILE.EXE`.
+    ///
+    /// Segment N is mapped at N0000h. Iterated segments are written in
+    /// expanded form to the io cache.
+    pub(crate) fn describe_r2_script<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        writeln!(
+            w,
+            "# radare2 script for {}, generated by win16ne.",
+            module_name
+        )?;
+        writeln!(w, "e asm.arch=x86")?;
+        writeln!(w, "e asm.bits=16")?;
+        writeln!(w, "e io.cache=true")?;
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let number = i as u16 + 1;
+            let start = linear_address(number, 0);
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let size = segment.min_alloc().max(data.len() as u64).min(0x10000);
+            let perms = if segment.is_code() { "r-x" } else { "rw-" };
+            if segment.is_iterated() {
+                writeln!(
+                    w,
+                    "S 0 0x{:X} 0 0x{:X} SEG{} {}",
+                    start, size, number, perms
+                )?;
+                let bytes = data
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                writeln!(w, "wx {} @ 0x{:X}", bytes, start)?;
+            } else {
+                writeln!(
+                    w,
+                    "S 0x{:X} 0x{:X} 0x{:X} 0x{:X} SEG{} {}",
+                    segment.data_offset(),
+                    start,
+                    data.len(),
+                    size,
+                    number,
+                    perms
+                )?;
+            }
+        }
+
+        for ((segment, offset), name) in self.named_addresses(codepage) {
+            writeln!(
+                w,
+                "f sym.{} 1 @ 0x{:X}",
+                flag_name(&name),
+                linear_address(segment, offset)
+            )?;
+        }
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            for (offset, comment) in self.fixup_annotations(segment, &data, codepage) {
+                writeln!(
+                    w,
+                    "CCu {} @ 0x{:X}",
+                    comment_text(&comment),
+                    linear_address(i as u16 + 1, offset as u16)
+                )?;
+            }
+        }
+        let entry_point = self.ne_header.entry_point.value();
+        if entry_point >> 16 != 0 {
+            let start = linear_address((entry_point >> 16) as u16, entry_point as u16);
+            writeln!(w, "f entry0 1 @ 0x{:X}", start)?;
+            writeln!(w, "s 0x{:X}", start)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_name() {
+        assert_eq!(flag_name("?Create@CWnd@@"), "_Create_CWnd__");
+        assert_eq!(flag_name("KERNEL.GETVERSION"), "KERNEL.GETVERSION");
+        assert_eq!(comment_text("a;b@c"), "a b c");
+    }
+}
@@ -0,0 +1,368 @@
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+/// The relocation records following a segment's data.
+#[derive(Debug, Clone)]
+pub struct RelocationTable {
+    pub entries: Vec<RelocationEntry>,
+}
+
+impl RelocationTable {
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let num = {
+            let mut buf = [0; 2];
+            r.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf)
+        };
+        let entries = (0..num)
+            .map(|_| RelocationEntry::read(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let num = u16::try_from(self.entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many relocations"))?;
+        w.write_all(&num.to_le_bytes())?;
+        for entry in &self.entries {
+            entry.write(w)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the table on file.
+    pub fn file_size(&self) -> u64 {
+        2 + 8 * self.entries.len() as u64
+    }
+
+    /// Lists every location patched by this table, ordered by offset.
+    pub fn sites(&self, data: &[u8]) -> io::Result<Vec<FixupSite>> {
+        let mut sites = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            for offset in entry.sites(data)? {
+                sites.push(FixupSite {
+                    offset,
+                    entry: index,
+                });
+            }
+        }
+        sites.sort_by_key(|site| (site.offset, site.entry));
+        Ok(sites)
+    }
+}
+
+/// A location patched by a relocation entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixupSite {
+    pub offset: u16,
+    /// The index of the relocation entry in its table.
+    pub entry: usize,
+}
+
+/// Low byte at the specified offset.
+pub const SOURCE_LOBYTE: u8 = 0;
+/// 16-bit selector.
+pub const SOURCE_SEGMENT: u8 = 2;
+/// 32-bit pointer (selector:offset).
+pub const SOURCE_FAR_ADDR: u8 = 3;
+/// 16-bit offset.
+pub const SOURCE_OFFSET: u8 = 5;
+/// 48-bit pointer (selector:offset32).
+pub const SOURCE_FAR_ADDR48: u8 = 11;
+/// 32-bit offset.
+pub const SOURCE_OFFSET32: u8 = 13;
+
+pub fn source_type_name(source_type: u8) -> Option<&'static str> {
+    Some(match source_type {
+        SOURCE_LOBYTE => "LOBYTE",
+        SOURCE_SEGMENT => "SEGMENT",
+        SOURCE_FAR_ADDR => "FAR_ADDR",
+        SOURCE_OFFSET => "OFFSET",
+        SOURCE_FAR_ADDR48 => "FAR_ADDR48",
+        SOURCE_OFFSET32 => "OFFSET32",
+        _ => return None,
+    })
+}
+
+/// Returns the number of bytes patched at each site.
+pub fn source_size(source_type: u8) -> usize {
+    match source_type {
+        SOURCE_LOBYTE => 1,
+        SOURCE_SEGMENT | SOURCE_OFFSET => 2,
+        SOURCE_FAR_ADDR | SOURCE_OFFSET32 => 4,
+        SOURCE_FAR_ADDR48 => 6,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RelocationEntry {
+    pub source_type: u8,
+    pub flags: u8,
+    pub offset: u16,
+    pub target: RelocationTarget,
+}
+
+impl RelocationEntry {
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut buf = [0; 8];
+        r.read_exact(&mut buf)?;
+        let get_u16 = |pos| u16::from_le_bytes(buf[pos..pos + 2].try_into().unwrap());
+
+        let flags = buf[1];
+        let target = match flags & 3 {
+            0 if buf[4] == 0xFF => RelocationTarget::InternalMoveable {
+                ordinal: get_u16(6),
+            },
+            0 => RelocationTarget::InternalFixed {
+                segment: buf[4],
+                offset: get_u16(6),
+            },
+            1 => RelocationTarget::ImportOrdinal {
+                module: get_u16(4),
+                ordinal: get_u16(6),
+            },
+            2 => RelocationTarget::ImportName {
+                module: get_u16(4),
+                name_offset: get_u16(6),
+            },
+            _ => RelocationTarget::OsFixup {
+                kind: OsFixupKind::from_raw(get_u16(4)),
+            },
+        };
+        Ok(Self {
+            source_type: buf[0],
+            flags,
+            offset: get_u16(2),
+            target,
+        })
+    }
+
+    /// Writes the record, setting the target type bits of the flags after the target.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (target_type, first, second) = match self.target {
+            RelocationTarget::InternalFixed { segment, offset } => (0, segment as u16, offset),
+            RelocationTarget::InternalMoveable { ordinal } => (0, 0xFF, ordinal),
+            RelocationTarget::ImportOrdinal { module, ordinal } => (1, module, ordinal),
+            RelocationTarget::ImportName {
+                module,
+                name_offset,
+            } => (2, module, name_offset),
+            RelocationTarget::OsFixup { kind } => (3, kind.to_raw(), 0),
+        };
+        w.write_all(&[self.source_type, (self.flags & !3) | target_type])?;
+        w.write_all(&self.offset.to_le_bytes())?;
+        w.write_all(&first.to_le_bytes())?;
+        w.write_all(&second.to_le_bytes())
+    }
+
+    pub fn is_additive(&self) -> bool {
+        (self.flags & 0x04) != 0
+    }
+
+    pub fn source_size(&self) -> usize {
+        source_size(self.source_type)
+    }
+
+    /// Lists the locations patched by this relocation.
+    ///
+    /// Additive relocations patch a single location, adding to the value there.
+    /// Others start a chain: each location holds the offset of the next one
+    /// until `0xFFFF`.
+    pub fn sites(&self, data: &[u8]) -> io::Result<Vec<u16>> {
+        if self.is_additive() || matches!(self.target, RelocationTarget::OsFixup { .. }) {
+            return Ok(vec![self.offset]);
+        }
+        let mut sites = Vec::new();
+        let mut offset = self.offset;
+        loop {
+            let pos = offset as usize;
+            let next = data.get(pos..pos + 2).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Relocation chain points outside the segment: 0x{:04X}",
+                        offset
+                    ),
+                )
+            })?;
+            sites.push(offset);
+            // A well-formed chain can't visit more locations than there are words.
+            if sites.len() > data.len() / 2 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Relocation chain loops",
+                ));
+            }
+            offset = u16::from_le_bytes(next.try_into().unwrap());
+            if offset == 0xFFFF {
+                break;
+            }
+        }
+        Ok(sites)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationTarget {
+    /// A location in a fixed segment of this module.
+    InternalFixed { segment: u8, offset: u16 },
+    /// A moveable entry point of this module, referred to by its ordinal.
+    InternalMoveable { ordinal: u16 },
+    /// An imported function, referred to by its ordinal.
+    /// The module index is 1-based.
+    ImportOrdinal { module: u16, ordinal: u16 },
+    /// An imported function, referred to by its name in the imported names table.
+    /// The module index is 1-based.
+    ImportName { module: u16, name_offset: u16 },
+    /// A fixup applied by the loader itself, such as floating-point emulation.
+    OsFixup { kind: OsFixupKind },
+}
+
+/// The kind of an OS fixup.
+///
+/// These patch floating-point instructions into calls to the emulator
+/// (WIN87EM) when no coprocessor is present. Each kind covers the pair of
+/// `FIxRQQ`/`FJxRQQ` symbols emitted by the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsFixupKind {
+    /// `FIARQQ`/`FJARQQ`: `WAIT` followed by an ordinary `ESC` instruction.
+    Fiarqq,
+    /// `FISRQQ`/`FJSRQQ`: `WAIT` followed by an `ESC` with an `SS:` override.
+    Fisrqq,
+    /// `FICRQQ`/`FJCRQQ`: `WAIT` followed by an `ESC` with a `CS:` override.
+    Ficrqq,
+    /// `FIERQQ`: `WAIT` followed by an `ESC` with an `ES:` override.
+    Fierqq,
+    /// `FIDRQQ`: `WAIT` followed by an `ESC` with a `DS:` override.
+    Fidrqq,
+    /// `FIWRQQ`: standalone `WAIT`.
+    Fiwrqq,
+    Unknown(u16),
+}
+
+impl OsFixupKind {
+    pub fn from_raw(raw: u16) -> Self {
+        match raw {
+            1 => OsFixupKind::Fiarqq,
+            2 => OsFixupKind::Fisrqq,
+            3 => OsFixupKind::Ficrqq,
+            4 => OsFixupKind::Fierqq,
+            5 => OsFixupKind::Fidrqq,
+            6 => OsFixupKind::Fiwrqq,
+            _ => OsFixupKind::Unknown(raw),
+        }
+    }
+
+    pub fn to_raw(self) -> u16 {
+        match self {
+            OsFixupKind::Fiarqq => 1,
+            OsFixupKind::Fisrqq => 2,
+            OsFixupKind::Ficrqq => 3,
+            OsFixupKind::Fierqq => 4,
+            OsFixupKind::Fidrqq => 5,
+            OsFixupKind::Fiwrqq => 6,
+            OsFixupKind::Unknown(raw) => raw,
+        }
+    }
+
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            OsFixupKind::Fiarqq => "FIARQQ/FJARQQ",
+            OsFixupKind::Fisrqq => "FISRQQ/FJSRQQ",
+            OsFixupKind::Ficrqq => "FICRQQ/FJCRQQ",
+            OsFixupKind::Fierqq => "FIERQQ",
+            OsFixupKind::Fidrqq => "FIDRQQ",
+            OsFixupKind::Fiwrqq => "FIWRQQ",
+            OsFixupKind::Unknown(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_relocation_table() {
+        let buf = b"\
+            \x05\x00\
+            \x03\x01\x04\x00\x01\x00\x03\x00\
+            \x03\x06\x09\x00\x02\x00\x0D\x00\
+            \x02\x00\x0E\x00\x02\x00\x00\x00\
+            \x05\x00\x20\x00\xFF\x00\x05\x00\
+            \x05\x07\x30\x00\x02\x00\x00\x00\
+        ";
+        let table = RelocationTable::read(&mut Cursor::new(buf)).unwrap();
+        let targets = table.entries.iter().map(|e| e.target).collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            vec![
+                RelocationTarget::ImportOrdinal {
+                    module: 1,
+                    ordinal: 3
+                },
+                RelocationTarget::ImportName {
+                    module: 2,
+                    name_offset: 13
+                },
+                RelocationTarget::InternalFixed {
+                    segment: 2,
+                    offset: 0
+                },
+                RelocationTarget::InternalMoveable { ordinal: 5 },
+                RelocationTarget::OsFixup {
+                    kind: OsFixupKind::Fisrqq
+                },
+            ]
+        );
+        assert!(!table.entries[0].is_additive());
+        assert!(table.entries[1].is_additive());
+        assert_eq!(table.entries[3].offset, 0x20);
+    }
+
+    #[test]
+    fn test_os_fixup() {
+        for raw in 1..=7 {
+            let kind = OsFixupKind::from_raw(raw);
+            assert_eq!(kind.to_raw(), raw);
+            assert_eq!(kind.name().is_none(), raw == 7);
+        }
+        assert_eq!(OsFixupKind::Fiwrqq.name(), Some("FIWRQQ"));
+
+        let buf = b"\x05\x03\x12\x00\x09\x00\x00\x00";
+        let entry = RelocationEntry::read(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(
+            entry.target,
+            RelocationTarget::OsFixup {
+                kind: OsFixupKind::Unknown(9)
+            }
+        );
+        let mut written = Vec::new();
+        entry.write(&mut written).unwrap();
+        assert_eq!(written, buf);
+        // The site is patched on its own rather than starting a chain.
+        assert!(!entry.is_additive());
+        assert_eq!(entry.sites(&[0xCD; 0x20]).unwrap(), vec![0x12]);
+    }
+
+    #[test]
+    fn test_relocation_chain() {
+        let data = b"\x9A\x00\x00\x00\x00\x9A\x0B\x00\x00\x00\x9A\xFF\xFF\x00\x00";
+        let entry = |flags, offset| RelocationEntry {
+            source_type: SOURCE_FAR_ADDR,
+            flags,
+            offset,
+            target: RelocationTarget::ImportOrdinal {
+                module: 1,
+                ordinal: 1,
+            },
+        };
+        assert_eq!(entry(0x01, 6).sites(data).unwrap(), vec![6, 11]);
+        assert_eq!(entry(0x05, 6).sites(data).unwrap(), vec![6]);
+        assert!(entry(0x01, 6).sites(&data[..10]).is_err());
+        assert!(entry(0x01, 1).sites(data).is_err());
+    }
+}
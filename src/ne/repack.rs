@@ -0,0 +1,212 @@
+use std::io::{self, Write};
+
+use super::{to_bytes, NeExecutable};
+
+impl NeExecutable {
+    /// Writes the executable like [`NeExecutable::write_preserving`], but
+    /// gives resources whose data has grown the room they need.
+    ///
+    /// If the resource data comes after the segments, as linkers place it,
+    /// the resources are packed there anew in table order; otherwise only
+    /// the grown ones are moved, after the end of the image. The resource
+    /// table offsets are updated, and appended data follows the resources.
+    pub fn write_growing_resources<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        let original = NeExecutable::read(&mut io::Cursor::new(&self.file_data[..]))?;
+        let old_table = &original.resource_table;
+        let shift = old_table.header.alignment_shift_count;
+        let new_lengths = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| &resource_type.resources)
+            .map(|resource| resource.data.len() as u64)
+            .collect::<Vec<_>>();
+        let mut table = old_table.clone();
+        let mut resources = table
+            .resource_types
+            .iter_mut()
+            .flat_map(|resource_type| &mut resource_type.resources)
+            .collect::<Vec<_>>();
+        let grown = resources
+            .iter()
+            .zip(&new_lengths)
+            .map(|(resource, &length)| length > old_table.data_range(resource).1)
+            .collect::<Vec<_>>();
+        if !grown.contains(&true) {
+            return self.write_preserving(file);
+        }
+
+        let file_size = self.file_data.len() as u64;
+        let image_end = original.image_end().min(file_size);
+        let resources_start = resources
+            .iter()
+            .filter(|resource| resource.header.data_offset_shifted != 0)
+            .map(|resource| old_table.data_range(resource).0.min(file_size))
+            .min()
+            .unwrap_or(image_end);
+        let repack_all = original.segments_end() <= resources_start;
+        let section_start = if repack_all {
+            resources_start
+        } else {
+            image_end
+        };
+
+        let too_large =
+            |what: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{} too large", what));
+        let alignment = 1u64 << shift.min(15);
+        let mut image = self.file_data[..section_start as usize].to_vec();
+        for (i, resource) in resources.iter_mut().enumerate() {
+            let moves = if repack_all {
+                resource.header.data_offset_shifted != 0 || grown[i]
+            } else {
+                grown[i]
+            };
+            if !moves {
+                continue;
+            }
+            let (offset, length) = old_table.data_range(resource);
+            let room = length.max(new_lengths[i].next_multiple_of(alignment));
+            image.resize((image.len() as u64).next_multiple_of(alignment) as usize, 0);
+            let start = image.len() as u64;
+            // The old data stays until write_preserving replaces what changed.
+            let old_data = self
+                .file_data
+                .get(offset as usize..)
+                .unwrap_or_default()
+                .iter()
+                .take(length as usize);
+            image.extend(old_data);
+            image.resize((start + room) as usize, 0);
+            resource.header.data_offset_shifted =
+                u16::try_from(start >> shift).map_err(|_| too_large("file"))?;
+            resource.header.data_length =
+                u16::try_from(room >> shift).map_err(|_| too_large("resource"))?;
+        }
+        // The padding after the old image end doesn't come along.
+        let overlay_start = original
+            .overlay()
+            .map_or(file_size, |overlay| overlay.start);
+        image.extend_from_slice(&self.file_data[overlay_start as usize..]);
+
+        let table_offset = original.dos_header.lfanew.value() as usize
+            + original.ne_header.resource_table_offset.value() as usize;
+        let table_bytes = to_bytes(|w| table.write(w))?;
+        image[table_offset..table_offset + table_bytes.len()].copy_from_slice(&table_bytes);
+
+        let mut repacked = self.clone();
+        repacked.file_data = image;
+        repacked.write_preserving(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::super::menu::RT_MENU;
+    use super::super::string_table::RT_STRING;
+    use super::super::tests::{sample_exe, set_resources};
+    use super::*;
+    use crate::util::codepage::Codepage;
+
+    /// A full block of 16 strings, of which the first and the third are set.
+    const STRINGS: [u8; 24] = *b"\x05Hello\x00\x03Bye\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    /// The sample with a menu and a string table after its segment, followed
+    /// by appended data.
+    fn with_resources() -> NeExecutable {
+        let mut exe = sample_exe();
+        set_resources(
+            &mut exe,
+            &[
+                (RT_MENU, 0x8001, b"\0\0\0\0\x90\0&File\0\x80\0\x01\0E&xit\0"),
+                (RT_STRING, 0x8002, &STRINGS),
+            ],
+        );
+        exe.realign(4);
+        let mut data = Vec::new();
+        exe.write(&mut io::Cursor::new(&mut data)).unwrap();
+        data.extend_from_slice(b"appended");
+        NeExecutable::read(&mut io::Cursor::new(data)).unwrap()
+    }
+
+    fn translate(exe: &mut NeExecutable, text: &str) {
+        let translations = BTreeMap::from([("STRING/16".to_owned(), text.to_owned())]);
+        exe.apply_translations(&translations, Codepage::default())
+            .unwrap();
+    }
+
+    fn resource_ranges(exe: &NeExecutable) -> Vec<(u64, u64)> {
+        let table = &exe.resource_table;
+        table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| &resource_type.resources)
+            .map(|resource| table.data_range(resource))
+            .collect()
+    }
+
+    fn write_growing(exe: &NeExecutable) -> NeExecutable {
+        let data = to_bytes(|w| exe.write_growing_resources(w)).unwrap();
+        NeExecutable::read(&mut io::Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn test_write_growing_resources_in_place() {
+        let mut exe = with_resources();
+        translate(&mut exe, "Hallo");
+        let preserved = to_bytes(|w| exe.write_preserving(w)).unwrap();
+        let grown = to_bytes(|w| exe.write_growing_resources(w)).unwrap();
+        assert_eq!(grown, preserved);
+    }
+
+    #[test]
+    fn test_write_growing_resources_repack() {
+        let mut exe = with_resources();
+        let old_ranges = resource_ranges(&exe);
+        translate(&mut exe, "Hallo, Welt, wie geht es?");
+        assert!(exe.write_preserving(&mut Vec::new()).is_err());
+
+        let exe = write_growing(&exe);
+        let texts = exe.translatable_texts(Codepage::default());
+        assert_eq!(texts[2].source, "Hallo, Welt, wie geht es?");
+        assert_eq!(texts[3].source, "Bye");
+        // Packed anew from where the resources started, in table order.
+        let ranges = resource_ranges(&exe);
+        assert_eq!(ranges[0], old_ranges[0]);
+        assert_eq!(ranges[1].0, ranges[0].0 + ranges[0].1);
+        assert!(ranges[1].1 > old_ranges[1].1);
+        assert_eq!(exe.overlay_data(), b"appended");
+        assert!(exe.verify(exe.file_data.len() as u64).is_empty());
+    }
+
+    #[test]
+    fn test_write_growing_resources_before_segments() {
+        // Move the segment after the resources.
+        let exe = with_resources();
+        let image_end = exe.image_end() as usize;
+        let mut data = exe.file_data[..image_end].to_vec();
+        let segment_offset = image_end.next_multiple_of(16);
+        data.resize(segment_offset, 0);
+        data.extend_from_slice(exe.segment_entries[0].data.as_ref().unwrap());
+        data.resize(data.len().next_multiple_of(16), 0);
+        let entry = 0x40 + exe.ne_header.segment_table_offset.value() as usize;
+        data[entry..entry + 2].copy_from_slice(&(segment_offset as u16 >> 4).to_le_bytes());
+        data.extend_from_slice(b"appended");
+        let mut exe = NeExecutable::read(&mut io::Cursor::new(data)).unwrap();
+        let old_ranges = resource_ranges(&exe);
+        let old_image_end = exe.image_end();
+
+        translate(&mut exe, "Hallo, Welt, wie geht es?");
+        let exe = write_growing(&exe);
+        let texts = exe.translatable_texts(Codepage::default());
+        assert_eq!(texts[2].source, "Hallo, Welt, wie geht es?");
+        // Only the grown resource moves, after the end of the image.
+        let ranges = resource_ranges(&exe);
+        assert_eq!(ranges[0], old_ranges[0]);
+        assert_eq!(ranges[1].0, old_image_end.next_multiple_of(16));
+        assert_eq!(exe.segment_entries[0].data_offset(), segment_offset as u64);
+        assert_eq!(exe.overlay_data(), b"appended");
+        assert!(exe.verify(exe.file_data.len() as u64).is_empty());
+    }
+}
@@ -1,10 +1,14 @@
+use std::collections::BTreeMap;
 use std::convert::TryInto;
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 #[derive(Debug, Clone)]
 pub struct NeResourceTable {
     pub header: NeResourceTableHeader,
     pub resource_types: Vec<NeResourceType>,
+    /// The type and resource names, keyed by their offset in the table.
+    /// Filled by [`NeResourceTable::read_data`].
+    pub names: BTreeMap<u16, Vec<u8>>,
 }
 impl NeResourceTable {
     pub fn read<R: Read>(r: &mut R, num_entries: u16) -> io::Result<Self> {
@@ -15,26 +19,121 @@ impl NeResourceTable {
         Ok(Self {
             header,
             resource_types,
+            names: BTreeMap::new(),
         })
     }
 
+    /// Returns a table without resources, for a module that has none.
+    pub fn empty(alignment_shift_count: u16) -> Self {
+        Self {
+            header: NeResourceTableHeader {
+                alignment_shift_count,
+            },
+            resource_types: Vec::new(),
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the names referred to by the table at `offset` and the resource data.
+    ///
+    /// Data extending past the end of the file is truncated.
+    pub fn read_data<R: Read + Seek>(&mut self, r: &mut R, offset: u64) -> io::Result<()> {
+        let name_offsets = self
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| {
+                let resource_ids = resource_type
+                    .resources
+                    .iter()
+                    .map(|resource| resource.header.resource_id);
+                std::iter::once(resource_type.header.type_id).chain(resource_ids)
+            })
+            .filter(|&id| id & 0x8000 == 0)
+            .collect::<Vec<_>>();
+        for name_offset in name_offsets {
+            if self.names.contains_key(&name_offset) {
+                continue;
+            }
+            r.seek(SeekFrom::Start(offset + name_offset as u64))?;
+            let mut len = 0;
+            r.read_exact(std::slice::from_mut(&mut len))?;
+            let mut name = vec![0; len as usize];
+            r.read_exact(&mut name)?;
+            self.names.insert(name_offset, name);
+        }
+
+        let shift = self.header.alignment_shift_count;
+        for resource_type in &mut self.resource_types {
+            for resource in &mut resource_type.resources {
+                let header = &resource.header;
+                r.seek(SeekFrom::Start(
+                    (header.data_offset_shifted as u64) << shift,
+                ))?;
+                r.take((header.data_length as u64) << shift)
+                    .read_to_end(&mut resource.data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the table: the types and resources, a terminating zero, the
+    /// names at their offsets and a final zero byte.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut table = Vec::new();
+        table.extend_from_slice(&self.header.alignment_shift_count.to_le_bytes());
+        for resource_type in &self.resource_types {
+            resource_type.write(&mut table)?;
+        }
+        table.extend_from_slice(&[0; 2]);
+        for (&name_offset, name) in &self.names {
+            let name_offset = name_offset as usize;
+            if name_offset < table.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("resource name overlaps the table: 0x{:04X}", name_offset),
+                ));
+            }
+            table.resize(name_offset, 0);
+            table.push(name_len(name)?);
+            table.extend_from_slice(name);
+        }
+        table.push(0);
+        w.write_all(&table)
+    }
+
+    /// Returns the file offset and length of a resource's data.
+    pub fn data_range(&self, resource: &NeResource) -> (u64, u64) {
+        let shift = self.header.alignment_shift_count;
+        (
+            (resource.header.data_offset_shifted as u64) << shift,
+            (resource.header.data_length as u64) << shift,
+        )
+    }
+
     pub fn read_variadic<R: Read>(r: &mut R) -> io::Result<Self> {
         let header = NeResourceTableHeader::read(r)?;
         let mut resource_types = Vec::new();
-        loop {
-            if let Some(resource_type) = NeResourceType::read_opt(r)? {
-                resource_types.push(resource_type);
-            } else {
-                break;
-            }
+        while let Some(resource_type) = NeResourceType::read_opt(r)? {
+            resource_types.push(resource_type);
         }
         Ok(Self {
             header,
             resource_types,
+            names: BTreeMap::new(),
         })
     }
 }
 
+/// Returns the length byte of a name, which can't exceed 255 bytes.
+pub(crate) fn name_len(name: &[u8]) -> io::Result<u8> {
+    u8::try_from(name.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("name too long: {}", String::from_utf8_lossy(name)),
+        )
+    })
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NeResourceTableHeader {
     pub alignment_shift_count: u16,
@@ -52,6 +151,32 @@ impl NeResourceTableHeader {
     }
 }
 
+/// Describes a resource type or ID: the predefined type name, the number, or
+/// the offset of the name in the resource table.
+pub fn describe_resource_id(id: u16, is_type: bool) -> String {
+    if (id & 0x8000) == 0 {
+        return format!("name@0x{:04X}", id);
+    }
+    let number = id & 0x7FFF;
+    let name = match number {
+        1 if is_type => "CURSOR",
+        2 if is_type => "BITMAP",
+        3 if is_type => "ICON",
+        4 if is_type => "MENU",
+        5 if is_type => "DIALOG",
+        6 if is_type => "STRING",
+        7 if is_type => "FONTDIR",
+        8 if is_type => "FONT",
+        9 if is_type => "ACCELERATOR",
+        10 if is_type => "RCDATA",
+        12 if is_type => "GROUP_CURSOR",
+        14 if is_type => "GROUP_ICON",
+        16 if is_type => "VERSION",
+        _ => return number.to_string(),
+    };
+    name.to_owned()
+}
+
 #[derive(Debug, Clone)]
 pub struct NeResourceType {
     pub header: NeResourceTypeHeader,
@@ -76,6 +201,20 @@ impl NeResourceType {
             .collect::<Result<Vec<_>, _>>()?;
         Ok(Some(Self { header, resources }))
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let num_resources = u16::try_from(self.resources.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many resources"))?;
+        NeResourceTypeHeader {
+            num_resources,
+            ..self.header
+        }
+        .write(w)?;
+        for resource in &self.resources {
+            resource.header.write(w)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -96,16 +235,27 @@ impl NeResourceTypeHeader {
             res: [get_u16(4), get_u16(6)],
         })
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for value in [self.type_id, self.num_resources, self.res[0], self.res[1]] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct NeResource {
     pub header: NeResourceHeader,
+    /// The data, including the padding to the alignment.
+    /// Filled by [`NeResourceTable::read_data`].
+    pub data: Vec<u8>,
 }
 impl NeResource {
     pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
         Ok(Self {
             header: NeResourceHeader::read(r)?,
+            data: Vec::new(),
         })
     }
 }
@@ -132,4 +282,18 @@ impl NeResourceHeader {
             res: [get_u16(8), get_u16(10)],
         })
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for value in [
+            self.data_offset_shifted,
+            self.data_length,
+            self.flags,
+            self.resource_id,
+            self.res[0],
+            self.res[1],
+        ] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
 }
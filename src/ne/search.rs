@@ -0,0 +1,133 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+
+/// A byte pattern, where `None` matches any byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytePattern(pub Vec<Option<u8>>);
+
+impl BytePattern {
+    /// Returns the offsets of all (possibly overlapping) matches.
+    pub fn find_all(&self, data: &[u8]) -> Vec<usize> {
+        if self.0.is_empty() || self.0.len() > data.len() {
+            return Vec::new();
+        }
+        data.windows(self.0.len())
+            .enumerate()
+            .filter(|(_, window)| {
+                self.0
+                    .iter()
+                    .zip(window.iter())
+                    .all(|(pattern, byte)| pattern.is_none_or(|pattern| pattern == *byte))
+            })
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+}
+
+/// Parses hexadecimal bytes such as `55 8B EC ?? 9A`; spaces are optional.
+impl FromStr for BytePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+        if digits.is_empty() {
+            return Err("empty pattern".to_owned());
+        }
+        if digits.len() % 2 != 0 {
+            return Err(format!("odd number of hex digits in {:?}", s));
+        }
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| match pair {
+                ['?', '?'] => Ok(None),
+                _ => {
+                    let pair = pair.iter().collect::<String>();
+                    u8::from_str_radix(&pair, 16)
+                        .map(Some)
+                        .map_err(|_| format!("invalid byte {:?}", pair))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BytePattern(bytes))
+    }
+}
+
+impl fmt::Display for BytePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match byte {
+                Some(byte) => write!(f, "{:02X}", byte)?,
+                None => write!(f, "??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    /// Prints where the pattern occurs in the segments and the resources.
+    pub(crate) fn describe_search<W: Write>(
+        &self,
+        w: &mut W,
+        pattern: &BytePattern,
+    ) -> io::Result<()> {
+        let print = |w: &mut W, location: String, file_offset: Option<u64>| match file_offset {
+            Some(file_offset) => writeln!(w, "{:<24} 0x{:08X}", location, file_offset),
+            None => writeln!(w, "{:<24} -", location),
+        };
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            for offset in pattern.find_all(&data) {
+                // Offsets in iterated data don't correspond to the file.
+                let file_offset =
+                    Some(segment.data_offset() + offset as u64).filter(|_| !segment.is_iterated());
+                print(w, format!("{:04X}:{:04X}", i + 1, offset), file_offset)?;
+            }
+        }
+
+        for resource_type in &self.resource_table.resource_types {
+            let type_name = describe_resource_id(resource_type.header.type_id, true);
+            for resource in &resource_type.resources {
+                let (offset, _) = self.resource_table.data_range(resource);
+                let id = describe_resource_id(resource.header.resource_id, false);
+                for found in pattern.find_all(&resource.data) {
+                    let location = format!("{} {}+{:04X}", type_name, id, found);
+                    print(w, location, Some(offset + found as u64))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_pattern() {
+        let pattern = "55 8B ??".parse::<BytePattern>().unwrap();
+        assert_eq!(pattern, BytePattern(vec![Some(0x55), Some(0x8B), None]));
+        assert_eq!(pattern.to_string(), "55 8B ??");
+        assert_eq!(
+            "558bec".parse(),
+            Ok(BytePattern(vec![Some(0x55), Some(0x8B), Some(0xEC)]))
+        );
+        assert!("55 8".parse::<BytePattern>().is_err());
+        assert!("GG".parse::<BytePattern>().is_err());
+
+        let data = b"\x55\x8B\xEC\x90\x55\x8B\xE5\x55\x8B";
+        assert_eq!(pattern.find_all(data), vec![0, 4]);
+    }
+}
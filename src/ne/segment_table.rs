@@ -1,5 +1,11 @@
+use bitflags::bitflags;
+use std::borrow::Cow;
 use std::convert::TryInto;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use super::header::write_flag_names;
+use super::relocation_table::RelocationTable;
 
 /// The New Executable segment table entry.
 #[derive(Debug, Clone)]
@@ -7,6 +13,7 @@ pub struct NeSegment {
     pub header: NeSegmentHeader,
     pub shift_count: u16,
     pub data: Option<Vec<u8>>,
+    pub relocations: Option<RelocationTable>,
 }
 
 impl NeSegment {
@@ -15,6 +22,7 @@ impl NeSegment {
             header: NeSegmentHeader::read(r)?,
             shift_count,
             data: None,
+            relocations: None,
         })
     }
 
@@ -28,6 +36,27 @@ impl NeSegment {
         let mut data = vec![0; data_length as usize];
         r.read_exact(&mut data)?;
         self.data = Some(data);
+        if self.has_relocations() {
+            self.relocations = Some(RelocationTable::read(r)?);
+        }
+        Ok(())
+    }
+
+    /// Reads the segment data like [`NeSegment::read_data`], but keeps what
+    /// there is of data extending past the end of file, without relocations.
+    pub fn read_available_data<R: Read + Seek>(&mut self, r: &mut R) -> io::Result<()> {
+        let file_size = r.seek(SeekFrom::End(0))?;
+        if self.header.data_offset_shifted == 0
+            || self.data_offset() + self.data_length() <= file_size
+        {
+            return self.read_data(r);
+        }
+        let mut data = Vec::new();
+        if self.data_offset() < file_size {
+            r.seek(SeekFrom::Start(self.data_offset()))?;
+            r.read_to_end(&mut data)?;
+        }
+        self.data = Some(data);
         Ok(())
     }
 
@@ -43,6 +72,44 @@ impl NeSegment {
         }
     }
 
+    pub fn has_relocations(&self) -> bool {
+        self.header.flags.contains(SegmentFlags::RELOCINFO)
+    }
+
+    /// Whether this is a code segment, as opposed to a data segment.
+    pub fn is_code(&self) -> bool {
+        (self.header.flags.bits() & 0x0007) == 0
+    }
+
+    pub fn is_moveable(&self) -> bool {
+        self.header.flags.contains(SegmentFlags::MOVEABLE)
+    }
+
+    pub fn is_preload(&self) -> bool {
+        self.header.flags.contains(SegmentFlags::PRELOAD)
+    }
+
+    pub fn is_iterated(&self) -> bool {
+        self.header.flags.contains(SegmentFlags::ITERATED)
+    }
+
+    /// Returns the segment contents as they would be loaded, expanding iterated data.
+    pub fn expanded_data(&self) -> io::Result<Option<Cow<'_, [u8]>>> {
+        let data = if let Some(data) = &self.data {
+            data
+        } else {
+            return Ok(None);
+        };
+        if self.is_iterated() {
+            Ok(Some(Cow::Owned(expand_iterated(
+                data,
+                self.min_alloc() as usize,
+            )?)))
+        } else {
+            Ok(Some(Cow::Borrowed(data)))
+        }
+    }
+
     pub fn min_alloc(&self) -> u64 {
         if self.header.min_alloc == 0 {
             0x10000
@@ -52,11 +119,132 @@ impl NeSegment {
     }
 }
 
+/// Expands the iteration records of an iterated segment into at most
+/// `length` bytes, the size of the segment in memory.
+///
+/// Each record consists of a repeat count, a block length and the block itself.
+/// The records continue until `length` bytes are produced or the on-file data
+/// is exhausted; a record reaching past `length` is cut off there.
+pub fn expand_iterated(data: &[u8], length: usize) -> io::Result<Vec<u8>> {
+    let mut expanded = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() && expanded.len() < length {
+        if data.len() - pos < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated iteration record",
+            ));
+        }
+        let count = u16::from_le_bytes(data[pos..pos + 2].try_into().unwrap());
+        let size = u16::from_le_bytes(data[pos + 2..pos + 4].try_into().unwrap()) as usize;
+        pos += 4;
+        let block = data.get(pos..pos + size).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Truncated iteration record")
+        })?;
+        pos += size;
+        // A tiny record can repeat a block to gigabytes, so only the part
+        // that fits is produced.
+        let repeated = (count as usize * size).min(length - expanded.len());
+        expanded.extend(block.iter().cycle().take(repeated));
+    }
+    Ok(expanded)
+}
+
+/// Encodes data as iteration records, the inverse of [`expand_iterated`].
+///
+/// Runs of a block of up to 4 bytes repeated long enough to pay for a
+/// record of their own are encoded as such; the rest goes into records
+/// repeated once.
+pub fn compress_iterated(data: &[u8]) -> Vec<u8> {
+    fn record(out: &mut Vec<u8>, count: usize, block: &[u8]) {
+        // A full 64K segment doesn't fit in one block.
+        for block in block.chunks(0xFFFF) {
+            out.extend_from_slice(&(count as u16).to_le_bytes());
+            out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            out.extend_from_slice(block);
+        }
+    }
+    let mut out = Vec::new();
+    let mut literal = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        // The block size that saves the most, counting the record header
+        // and that of the literal record after it.
+        let best = (1..=4)
+            .filter(|&size| pos + size <= data.len())
+            .map(|size| {
+                let block = &data[pos..pos + size];
+                let count = data[pos..]
+                    .chunks_exact(size)
+                    .take(0xFFFF)
+                    .take_while(|chunk| *chunk == block)
+                    .count();
+                (count * size, size, count)
+            })
+            .filter(|&(covered, size, _)| covered > size + 8)
+            .max_by_key(|&(covered, size, _)| covered - size);
+        match best {
+            Some((covered, size, count)) => {
+                if literal < pos {
+                    record(&mut out, 1, &data[literal..pos]);
+                }
+                record(&mut out, count, &data[pos..pos + size]);
+                pos += covered;
+                literal = pos;
+            }
+            None => pos += 1,
+        }
+    }
+    if literal < data.len() {
+        record(&mut out, 1, &data[literal..]);
+    }
+    out
+}
+
+bitflags! {
+    /// The flags of a segment table entry.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct SegmentFlags: u16 {
+        /// A data segment rather than a code segment.
+        const DATA = 0x0001;
+        /// The segment data is iterated (compressed).
+        const ITERATED = 0x0008;
+        const MOVEABLE = 0x0010;
+        const SHAREABLE = 0x0020;
+        const PRELOAD = 0x0040;
+        /// Execute-only for code segments, read-only for data segments.
+        const READ_ONLY = 0x0080;
+        /// The segment data is followed by relocations.
+        const RELOCINFO = 0x0100;
+        /// The descriptor privilege level.
+        const DPL = 0x0C00;
+        const DISCARDABLE = 0x1000;
+    }
+}
+
+impl fmt::Display for SegmentFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = (*self - Self::DPL)
+            .iter_names()
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<_>>();
+        let dpl = (self.bits() & Self::DPL.bits()) >> 10;
+        if dpl != 0 {
+            names.push(format!("DPL({})", dpl));
+        }
+        let unknown = self.bits() & !Self::all().bits();
+        if unknown != 0 {
+            names.push(format!("0x{:04x}", unknown));
+        }
+        write_flag_names(f, &names)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NeSegmentHeader {
     pub data_offset_shifted: u16,
     pub data_length: u16,
-    pub flags: u16,
+    pub flags: SegmentFlags,
     pub min_alloc: u16,
 }
 impl NeSegmentHeader {
@@ -68,8 +256,78 @@ impl NeSegmentHeader {
         Ok(Self {
             data_offset_shifted: get_u16(0),
             data_length: get_u16(2),
-            flags: get_u16(4),
+            flags: SegmentFlags::from_bits_retain(get_u16(4)),
             min_alloc: get_u16(6),
         })
     }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for value in [
+            self.data_offset_shifted,
+            self.data_length,
+            self.flags.bits(),
+            self.min_alloc,
+        ] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_iterated() {
+        let data = b"\x03\x00\x02\x00ab\x01\x00\x03\x00xyz\x02\x00\x00\x00";
+        assert_eq!(expand_iterated(data, 0x10000).unwrap(), b"abababxyz");
+        // The records after the stated length are ignored.
+        assert_eq!(expand_iterated(data, 4).unwrap(), b"abab");
+        assert_eq!(expand_iterated(&data[..8], 6).unwrap(), b"ababab");
+        // A record repeated beyond the segment size is cut off.
+        let mut data = b"\xFF\xFF\x00\x01".to_vec();
+        data.extend_from_slice(&[0xCC; 0x100]);
+        assert_eq!(expand_iterated(&data, 0x10000).unwrap(), [0xCC; 0x10000]);
+    }
+
+    #[test]
+    fn test_expand_iterated_truncated() {
+        assert!(expand_iterated(b"\x03\x00\x02\x00a", 0x10000).is_err());
+        assert!(expand_iterated(b"\x03\x00", 0x10000).is_err());
+    }
+
+    #[test]
+    fn test_compress_iterated() {
+        let mut data = b"header".to_vec();
+        data.extend_from_slice(&[0; 100]);
+        data.extend_from_slice(&b"\x12\x34".repeat(50));
+        data.extend_from_slice(b"tail");
+        let compressed = compress_iterated(&data);
+        assert_eq!(compressed.len(), 10 + 5 + 6 + 8);
+        assert_eq!(expand_iterated(&compressed, data.len()).unwrap(), data);
+        assert_eq!(
+            expand_iterated(&compress_iterated(&[0; 0x10000]), 0x10000)
+                .unwrap()
+                .len(),
+            0x10000
+        );
+        let random = (0..0x10000u32)
+            .map(|i| ((i * 7919) >> 3) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            expand_iterated(&compress_iterated(&random), 0x10000).unwrap(),
+            random
+        );
+    }
+
+    #[test]
+    fn test_segment_flags_display() {
+        assert_eq!(SegmentFlags::empty().to_string(), "0");
+        assert_eq!(
+            SegmentFlags::from_bits_retain(0x1D51).to_string(),
+            "DATA | MOVEABLE | PRELOAD | RELOCINFO | DISCARDABLE | DPL(3)"
+        );
+        assert_eq!(SegmentFlags::from_bits_retain(0x4002).to_string(), "0x4002");
+    }
 }
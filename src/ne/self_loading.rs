@@ -0,0 +1,102 @@
+use std::io::{self, Write};
+
+use super::header::NeFlags;
+use super::NeExecutable;
+
+/// The version word that starts the loader table, `A0`.
+const SIGNATURE: &[u8; 2] = b"A0";
+
+/// The far pointers of the loader table at the start of segment 1, by
+/// offset. The gaps are reserved.
+const PROCEDURES: [(u16, &str); 7] = [
+    (0x08, "BootApp"),
+    (0x0C, "LoadAppSeg"),
+    (0x10, "reserved"),
+    (0x14, "MyAlloc"),
+    (0x18, "EntryAddrProc"),
+    (0x1C, "ExitProc"),
+    (0x28, "SetOwner"),
+];
+
+/// A procedure of the loader table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoaderProcedure {
+    pub name: &'static str,
+    /// The offset of the far pointer in segment 1.
+    pub position: u16,
+    /// The far pointer as stored, segment and offset.
+    pub pointer: (u16, u16),
+    /// The 0-based segment index and offset the pointer is relocated to, if
+    /// a relocation fills in its segment.
+    pub target: Option<(usize, usize)>,
+}
+
+impl NeExecutable {
+    pub fn is_self_loading(&self) -> bool {
+        self.ne_header.ne_flags().contains(NeFlags::SELF_LOADING)
+    }
+
+    /// Reads the loader table at the start of segment 1 of a self-loading
+    /// application, or returns `None` if the flag is clear or the table
+    /// doesn't start with `A0`.
+    pub fn loader_procedures(&self) -> Option<Vec<LoaderProcedure>> {
+        if !self.is_self_loading() {
+            return None;
+        }
+        let data = self.segment_entries.first()?.expanded_data().ok()??;
+        if !data.starts_with(SIGNATURE) {
+            return None;
+        }
+        let targets = self.internal_far_targets(0, &data);
+        let procedures = PROCEDURES
+            .iter()
+            .filter_map(|&(position, name)| {
+                let pos = position as usize;
+                let word =
+                    |pos: usize| Some(u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+                Some(LoaderProcedure {
+                    name,
+                    position,
+                    pointer: (word(pos + 2)?, word(pos)?),
+                    target: targets.get(&pos).copied(),
+                })
+            })
+            .collect();
+        Some(procedures)
+    }
+
+    /// Prints the loader table of a self-loading application.
+    pub(crate) fn describe_self_loading<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if !self.is_self_loading() {
+            return Ok(());
+        }
+        let procedures = match self.loader_procedures() {
+            Some(procedures) => procedures,
+            None => {
+                writeln!(w, "Self-loading application: no loader table in segment 1")?;
+                return Ok(());
+            }
+        };
+        writeln!(w, "Self-loading application, loader table in segment 1:")?;
+        for procedure in procedures {
+            match procedure.target {
+                Some((segment, offset)) => writeln!(
+                    w,
+                    "    {:<14} {:04X}:{:04X}",
+                    procedure.name,
+                    segment + 1,
+                    offset
+                )?,
+                None if procedure.pointer == (0, 0) => {
+                    writeln!(w, "    {:<14} none", procedure.name)?
+                }
+                None => writeln!(
+                    w,
+                    "    {:<14} {:04X}:{:04X} (not relocated)",
+                    procedure.name, procedure.pointer.0, procedure.pointer.1
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
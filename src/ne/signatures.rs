@@ -0,0 +1,304 @@
+use std::io::{self, Write};
+
+use super::search::BytePattern;
+use super::NeExecutable;
+
+/// The number of leading bytes matched by pattern, as in FLAIR.
+pub const PATTERN_LENGTH: usize = 32;
+
+/// The maximum number of bytes covered by the CRC.
+const MAX_CRC_LENGTH: usize = 0xFF;
+
+/// A library function signature: a line of an IDA FLAIR pattern (`.pat`) file.
+///
+/// The function matches if its first bytes match the pattern, the following
+/// `crc_length` bytes have the CRC and it's `length` bytes long.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub pattern: BytePattern,
+    pub crc_length: usize,
+    pub crc: u16,
+    pub length: usize,
+    pub name: Vec<u8>,
+}
+
+/// The CRC-16 of FLAIR (CRC-16/X-25 with the bytes swapped).
+pub fn crc16(data: &[u8]) -> u16 {
+    if data.is_empty() {
+        return 0;
+    }
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            crc = if (crc ^ byte as u16) & 1 != 0 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+            byte >>= 1;
+        }
+    }
+    (!crc).swap_bytes()
+}
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line + 1, message),
+    )
+}
+
+impl Signature {
+    /// Parses a `.pat` file; the line `---` ends it.
+    ///
+    /// Only the first public name of each line is used; referenced names and
+    /// tail bytes are ignored.
+    pub fn parse_pat(text: &str) -> io::Result<Vec<Self>> {
+        let mut signatures = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 6 || !fields[4].starts_with(':') {
+                return Err(invalid(i, "expected PATTERN LEN CRC SIZE :OFFSET NAME"));
+            }
+            let hex = |field: &str| {
+                usize::from_str_radix(field, 16).map_err(|_| invalid(i, "invalid number"))
+            };
+            let pattern = fields[0]
+                .replace("..", "??")
+                .parse::<BytePattern>()
+                .map_err(|e| invalid(i, &e))?;
+            signatures.push(Signature {
+                pattern,
+                crc_length: hex(fields[1])?,
+                crc: hex(fields[2])? as u16,
+                length: hex(fields[3])?,
+                name: fields[5].as_bytes().to_vec(),
+            });
+        }
+        Ok(signatures)
+    }
+
+    /// Makes the signature of a function from its code, where `None` marks fixup bytes.
+    pub fn from_code(name: &[u8], code: &[Option<u8>]) -> Self {
+        let mut pattern = code
+            .iter()
+            .copied()
+            .take(PATTERN_LENGTH)
+            .collect::<Vec<_>>();
+        pattern.resize(PATTERN_LENGTH, None);
+        let crc_bytes = code
+            .iter()
+            .skip(PATTERN_LENGTH)
+            .take(MAX_CRC_LENGTH)
+            .map_while(|&byte| byte)
+            .collect::<Vec<_>>();
+        Self {
+            pattern: BytePattern(pattern),
+            crc_length: crc_bytes.len(),
+            crc: crc16(&crc_bytes),
+            length: code.len(),
+            name: name.to_vec(),
+        }
+    }
+
+    /// Formats the signature as a `.pat` line.
+    pub fn to_pat_line(&self) -> String {
+        let pattern = self
+            .pattern
+            .0
+            .iter()
+            .map(|byte| match byte {
+                Some(byte) => format!("{:02X}", byte),
+                None => "..".to_owned(),
+            })
+            .collect::<String>();
+        format!(
+            "{} {:02X} {:04X} {:04X} :0000 {}",
+            pattern,
+            self.crc_length,
+            self.crc,
+            self.length,
+            String::from_utf8_lossy(&self.name)
+        )
+    }
+
+    /// Checks the signature against code, where `None` marks fixup bytes.
+    pub fn matches(&self, code: &[Option<u8>]) -> bool {
+        // Patterns of short functions are padded with `..`, which may run past the code.
+        if code.len() < self.length {
+            return false;
+        }
+        let pattern_matches =
+            self.pattern
+                .0
+                .iter()
+                .zip(code)
+                .all(|(expected, actual)| match (expected, actual) {
+                    (Some(expected), Some(actual)) => expected == actual,
+                    _ => true,
+                });
+        if !pattern_matches {
+            return false;
+        }
+        if self.crc_length == 0 {
+            return true;
+        }
+        let start = self.pattern.0.len();
+        let crc_bytes = match code.get(start..start + self.crc_length) {
+            Some(bytes) => bytes.iter().copied().collect::<Option<Vec<_>>>(),
+            None => return false,
+        };
+        crc_bytes.is_some_and(|bytes| crc16(&bytes) == self.crc)
+    }
+}
+
+impl NeExecutable {
+    /// Returns the expanded data of a segment (0-based) with the bytes
+    /// patched by fixups replaced by `None`.
+    pub fn masked_segment_data(&self, index: usize) -> Vec<Option<u8>> {
+        let segment = match self.segment_entries.get(index) {
+            Some(segment) => segment,
+            None => return Vec::new(),
+        };
+        let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+        let mut masked = data.iter().copied().map(Some).collect::<Vec<_>>();
+        if let Some(relocations) = &segment.relocations {
+            for entry in &relocations.entries {
+                for site in entry.sites(&data).unwrap_or_default() {
+                    let site = site as usize;
+                    let end = (site + entry.source_size()).min(masked.len());
+                    for byte in masked.get_mut(site..end).unwrap_or_default() {
+                        *byte = None;
+                    }
+                }
+            }
+        }
+        masked
+    }
+
+    /// Makes signatures of the named functions, for identifying them when
+    /// they are linked statically into other programs.
+    pub fn signatures(&self) -> Vec<Signature> {
+        let mut signatures = Vec::new();
+        let functions = self.functions();
+        for index in 0..self.segment_entries.len() {
+            let masked = self.masked_segment_data(index);
+            for function in functions.iter().filter(|f| f.segment == index) {
+                let name = match &function.name {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if let Some(code) = masked.get(function.start..function.end) {
+                    signatures.push(Signature::from_code(name, code));
+                }
+            }
+        }
+        signatures
+    }
+
+    /// Prints the signatures of the named functions as a `.pat` file.
+    pub(crate) fn describe_signatures<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for signature in self.signatures() {
+            writeln!(w, "{}", signature.to_pat_line())?;
+        }
+        writeln!(w, "---")?;
+        Ok(())
+    }
+
+    /// Names the functions that match a signature, unless they have a name
+    /// already. Returns the number of functions named.
+    pub fn apply_signatures(&mut self, signatures: &[Signature]) -> usize {
+        let mut count = 0;
+        let functions = self.functions();
+        for index in 0..self.segment_entries.len() {
+            let masked = self.masked_segment_data(index);
+            for function in functions.iter().filter(|f| f.segment == index) {
+                let address = (index as u16 + 1, function.start as u16);
+                if function.name.is_some() || self.debug_symbols.contains_key(&address) {
+                    continue;
+                }
+                let code = &masked[function.start.min(masked.len())..];
+                if let Some(signature) = signatures.iter().find(|s| s.matches(code)) {
+                    self.debug_symbols.insert(address, signature.name.clone());
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::{RelocationEntry, RelocationTarget, SOURCE_FAR_ADDR};
+    use super::super::resident_name_table::ResidentNameEntry;
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(crc16(b"123456789"), 0x6E90);
+
+        let text = "558BEC9A........ 04 3F0E 0010 :0000 _intdos\n---\n";
+        let signatures = Signature::parse_pat(text).unwrap();
+        assert_eq!(signatures.len(), 1);
+        assert_eq!(signatures[0].to_pat_line(), text.lines().next().unwrap());
+
+        let mut code = [0x55, 0x8B, 0xEC, 0x9A, 0, 0, 0, 0, 0xCD, 0x21, 0x5D, 0xCB]
+            .into_iter()
+            .map(Some)
+            .chain([None; 4])
+            .collect::<Vec<_>>();
+        code[4] = None;
+        assert!(signatures[0].matches(&code));
+        code[9] = Some(0x20);
+        assert!(!signatures[0].matches(&code));
+
+        let signature = Signature::from_code(b"_f", &code);
+        assert_eq!(signature.crc_length, 0);
+        assert_eq!(signature.length, code.len());
+        assert!(signature.matches(&code));
+    }
+
+    #[test]
+    fn test_describe_signatures() {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\x55\x8B\xEC\x9A\xFF\xFF\x00\x00\x5D\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 4,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        exe.resident_name_table.entries.push(ResidentNameEntry {
+            name: b"Recurse".to_vec(),
+            index: 1,
+        });
+        let mut output = Vec::new();
+        exe.describe_signatures(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "558BEC9A........5DCB{} 00 0000 000A :0000 Recurse\n---\n",
+                "..".repeat(22)
+            )
+        );
+        let signatures = Signature::parse_pat(&text).unwrap();
+        assert!(signatures[0].matches(&exe.masked_segment_data(0)));
+    }
+}
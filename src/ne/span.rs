@@ -0,0 +1,263 @@
+use std::ops::Range;
+
+use bytemuck::Zeroable;
+
+use super::entry_table::SegmentEntry;
+use super::header::NeHeader;
+use super::NeExecutable;
+use crate::mz::DosHeader;
+
+/// Where a parsed value lies in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl Span {
+    pub fn new(offset: u64, length: u64) -> Self {
+        Self { offset, length }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.end()
+    }
+}
+
+/// A field or table entry and its span, named by its path from
+/// [`NeExecutable`], such as `ne_header.segment_count` or
+/// `segment_entries[0].relocations.entries[2]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub path: String,
+    pub span: Span,
+}
+
+/// Adds the span of each field of a `Pod` header at `base`.
+macro_rules! header_fields {
+    ($spans:ident, $base:expr, $prefix:literal, $ty:ty { $($field:ident),* $(,)? }) => {{
+        let header = <$ty>::zeroed();
+        $(
+            $spans.push(FieldSpan {
+                path: concat!($prefix, ".", stringify!($field)).to_owned(),
+                span: Span::new(
+                    $base + std::mem::offset_of!($ty, $field) as u64,
+                    std::mem::size_of_val(&header.$field) as u64,
+                ),
+            });
+        )*
+    }};
+}
+
+impl NeExecutable {
+    /// Lists the spans of the header fields and of the entries of each
+    /// table, ordered by offset. The offsets are those the structures were
+    /// read from, as given by the headers.
+    pub fn spans(&self) -> Vec<FieldSpan> {
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let ne_header = &self.ne_header;
+        let mut spans = Vec::new();
+        let mut add = |path: String, offset: u64, length: u64| {
+            spans.push(FieldSpan {
+                path,
+                span: Span::new(offset, length),
+            });
+        };
+        let name_length = |name: &[u8]| 1 + name.len() as u64;
+
+        let table = lfanew + ne_header.segment_table_offset.value() as u64;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            add(
+                format!("segment_entries[{}].header", i),
+                table + 8 * i as u64,
+                8,
+            );
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            let data_end = segment.data_offset() + segment.data_length();
+            add(
+                format!("segment_entries[{}].data", i),
+                segment.data_offset(),
+                segment.data_length(),
+            );
+            if let Some(relocations) = &segment.relocations {
+                add(
+                    format!("segment_entries[{}].relocations.count", i),
+                    data_end,
+                    2,
+                );
+                for j in 0..relocations.entries.len() {
+                    add(
+                        format!("segment_entries[{}].relocations.entries[{}]", i, j),
+                        data_end + 2 + 8 * j as u64,
+                        8,
+                    );
+                }
+            }
+        }
+
+        let resource_table = &self.resource_table;
+        if !resource_table.resource_types.is_empty() || !resource_table.names.is_empty() {
+            let table = lfanew + ne_header.resource_table_offset.value() as u64;
+            add("resource_table.header".to_owned(), table, 2);
+            let mut offset = table + 2;
+            for (i, resource_type) in resource_table.resource_types.iter().enumerate() {
+                add(
+                    format!("resource_table.resource_types[{}].header", i),
+                    offset,
+                    8,
+                );
+                offset += 8;
+                for (j, resource) in resource_type.resources.iter().enumerate() {
+                    let path = format!("resource_table.resource_types[{}].resources[{}]", i, j);
+                    add(format!("{}.header", path), offset, 12);
+                    offset += 12;
+                    let (data_offset, length) = resource_table.data_range(resource);
+                    add(format!("{}.data", path), data_offset, length);
+                }
+            }
+            for (&name_offset, name) in &resource_table.names {
+                add(
+                    format!("resource_table.names[0x{:04X}]", name_offset),
+                    table + name_offset as u64,
+                    name_length(name),
+                );
+            }
+        }
+
+        let mut offset = lfanew + ne_header.resident_names_table_offset.value() as u64;
+        for (i, entry) in self.resident_name_table.entries.iter().enumerate() {
+            let length = name_length(&entry.name) + 2;
+            add(
+                format!("resident_name_table.entries[{}]", i),
+                offset,
+                length,
+            );
+            offset += length;
+        }
+
+        let table = lfanew + ne_header.module_reference_table_offset.value() as u64;
+        let imported_names = lfanew + ne_header.import_name_table_offset.value() as u64;
+        for (i, entry) in self.module_reference_table.entries.iter().enumerate() {
+            let path = format!("module_reference_table.entries[{}]", i);
+            add(format!("{}.header", path), table + 2 * i as u64, 2);
+            add(
+                format!("{}.name", path),
+                imported_names + entry.header.offset as u64,
+                name_length(&entry.name),
+            );
+        }
+        for (&name_offset, name) in &self.imported_name_table.names {
+            add(
+                format!("imported_name_table.names[0x{:04X}]", name_offset),
+                imported_names + name_offset as u64,
+                name_length(name),
+            );
+        }
+
+        let table = lfanew + ne_header.entry_table_offset.value() as u64;
+        for (i, entry) in self.entry_table.entries.iter().enumerate() {
+            let length = match entry {
+                SegmentEntry::Unused => continue,
+                SegmentEntry::Fixed(_) => 3,
+                SegmentEntry::Moveable(_) => 6,
+            };
+            add(
+                format!("entry_table.entries[{}]", i),
+                table + self.entry_table.offsets[i] as u64,
+                length,
+            );
+        }
+
+        let mut offset = ne_header.non_resident_names_table_offset.value() as u64;
+        for (i, entry) in self.nonresident_name_table.entries.iter().enumerate() {
+            let length = name_length(&entry.name) + 2;
+            add(
+                format!("nonresident_name_table.entries[{}]", i),
+                offset,
+                length,
+            );
+            offset += length;
+        }
+
+        header_fields!(
+            spans,
+            0,
+            "dos_header",
+            DosHeader {
+                magic,
+                cblp,
+                cp,
+                crlc,
+                cparhdr,
+                minalloc,
+                maxalloc,
+                ss,
+                sp,
+                csum,
+                ip,
+                cs,
+                lfarlc,
+                ovno,
+                res,
+                oemid,
+                oeminfo,
+                res2,
+                lfanew,
+            }
+        );
+        header_fields!(
+            spans,
+            lfanew,
+            "ne_header",
+            NeHeader {
+                magic,
+                major_linker_version,
+                minor_linker_version,
+                entry_table_offset,
+                entry_table_length,
+                file_load_crc,
+                flags,
+                auto_data_segment_index,
+                init_heap_size,
+                init_stack_size,
+                entry_point,
+                init_stack,
+                segment_count,
+                module_references,
+                non_resident_names_size,
+                segment_table_offset,
+                resource_table_offset,
+                resident_names_table_offset,
+                module_reference_table_offset,
+                import_name_table_offset,
+                non_resident_names_table_offset,
+                movable_entry_point_count,
+                file_alignment_shift_count,
+                resource_table_entries,
+                target_os,
+                os2_exe_flags,
+                return_thunk_offset,
+                segment_reference_thunk_offset,
+                min_code_swap,
+                expected_win_ver,
+            }
+        );
+        spans.sort_by_key(|field| field.span.offset);
+        spans
+    }
+
+    /// Returns the span of the field or table entry at `path`, as named
+    /// by [`NeExecutable::spans`].
+    pub fn span_of(&self, path: &str) -> Option<Span> {
+        self.spans()
+            .into_iter()
+            .find(|field| field.path == path)
+            .map(|field| field.span)
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+
+/// Statistics of the bytes of a segment or a resource.
+#[derive(Debug, Clone)]
+pub struct ByteStatistics {
+    pub size: usize,
+    /// The number of occurrences of each byte value.
+    pub histogram: [usize; 256],
+    /// The Shannon entropy in bits per byte, from 0 to 8.
+    pub entropy: f64,
+    /// The size after DEFLATE compression.
+    pub compressed_size: usize,
+}
+
+impl ByteStatistics {
+    pub fn new(data: &[u8]) -> Self {
+        let mut histogram = [0; 256];
+        for &byte in data {
+            histogram[byte as usize] += 1;
+        }
+        let entropy = histogram
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / data.len() as f64;
+                -p * p.log2()
+            })
+            .sum::<f64>();
+        Self {
+            size: data.len(),
+            histogram,
+            entropy,
+            compressed_size: miniz_oxide::deflate::compress_to_vec(data, 6).len(),
+        }
+    }
+
+    /// The compressed size relative to the original; close to 1 or above
+    /// for data that is already packed or encrypted.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.compressed_size as f64 / self.size as f64
+    }
+
+    /// Returns the most frequent byte values with their counts, most frequent first.
+    pub fn most_common(&self, n: usize) -> Vec<(u8, usize)> {
+        let mut counts = (0..=255u8)
+            .map(|byte| (byte, self.histogram[byte as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect::<Vec<_>>();
+        counts.sort_by_key(|&(byte, count)| (std::cmp::Reverse(count), byte));
+        counts.truncate(n);
+        counts
+    }
+}
+
+impl NeExecutable {
+    /// Prints the size, entropy, compression ratio and most common bytes of
+    /// each segment and resource.
+    pub(crate) fn describe_statistics<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(
+            w,
+            "{:<24} {:>7} {:>7} {:>10}  Most common bytes",
+            "Region", "Size", "Entropy", "Compressed"
+        )?;
+        let print = |w: &mut W, region: String, data: &[u8]| -> io::Result<()> {
+            let statistics = ByteStatistics::new(data);
+            let most_common = statistics
+                .most_common(4)
+                .into_iter()
+                .map(|(byte, count)| {
+                    format!(
+                        "{:02X}({:.0}%)",
+                        byte,
+                        100.0 * count as f64 / data.len() as f64
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                w,
+                "{:<24} {:>7} {:>7.3} {:>9.1}%  {}",
+                region,
+                statistics.size,
+                statistics.entropy,
+                100.0 * statistics.compression_ratio(),
+                most_common
+            )?;
+            Ok(())
+        };
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let kind = if segment.is_code() { "CODE" } else { "DATA" };
+            match segment.expanded_data() {
+                Ok(Some(data)) => print(w, format!("segment {} ({})", i + 1, kind), &data)?,
+                Ok(None) => writeln!(w, "segment {} ({})  no data", i + 1, kind)?,
+                Err(e) => writeln!(w, "segment {} ({})  {}", i + 1, kind, e)?,
+            }
+        }
+        for resource_type in &self.resource_table.resource_types {
+            let type_name = describe_resource_id(resource_type.header.type_id, true);
+            for resource in &resource_type.resources {
+                let (_, length) = self.resource_table.data_range(resource);
+                let id = describe_resource_id(resource.header.resource_id, false);
+                if (resource.data.len() as u64) < length {
+                    writeln!(w, "{} {}  outside the file", type_name, id)?;
+                } else {
+                    print(w, format!("{} {}", type_name, id), &resource.data)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_statistics() {
+        let zeros = ByteStatistics::new(&[0; 256]);
+        assert_eq!(zeros.entropy, 0.0);
+        assert!(zeros.compression_ratio() < 0.2);
+        assert_eq!(zeros.most_common(2), vec![(0, 256)]);
+
+        let all = (0..=255).collect::<Vec<u8>>();
+        let all = ByteStatistics::new(&all);
+        assert_eq!(all.entropy, 8.0);
+        assert_eq!(all.most_common(2), vec![(0, 1), (1, 1)]);
+    }
+}
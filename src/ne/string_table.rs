@@ -0,0 +1,68 @@
+use std::io;
+
+/// The integer ID of the string table resource type.
+pub const RT_STRING: u16 = 0x8006;
+
+/// The number of strings in each `STRING` resource.
+pub const STRINGS_PER_BLOCK: usize = 16;
+
+/// Reads the strings of a `STRING` resource, each prefixed with its length
+/// in a byte. Resource `n` holds the strings with IDs `16 * (n - 1)` to
+/// `16 * n - 1`; unused IDs are empty.
+pub fn read_string_block(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut strings = Vec::new();
+    let mut pos = 0;
+    while strings.len() < STRINGS_PER_BLOCK {
+        let Some(&len) = data.get(pos) else {
+            break;
+        };
+        let string = data
+            .get(pos + 1..pos + 1 + len as usize)
+            .unwrap_or(&data[pos + 1..]);
+        strings.push(string.to_vec());
+        pos += 1 + len as usize;
+    }
+    strings.resize(STRINGS_PER_BLOCK, Vec::new());
+    strings
+}
+
+/// Writes the strings of a `STRING` resource.
+pub fn write_string_block(strings: &[Vec<u8>]) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    for string in strings {
+        let len = u8::try_from(string.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "string too long: {} bytes, at most 255: {}",
+                    string.len(),
+                    String::from_utf8_lossy(string)
+                ),
+            )
+        })?;
+        data.push(len);
+        data.extend_from_slice(string);
+    }
+    Ok(data)
+}
+
+/// Returns the ID of the first string in the resource with the given ID.
+pub fn first_string_id(resource_id: u16) -> u16 {
+    ((resource_id & 0x7FFF).wrapping_sub(1)).wrapping_mul(STRINGS_PER_BLOCK as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_block() {
+        let data = b"\x00\x05Hello\x00\x03Bye\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        let strings = read_string_block(data);
+        assert_eq!(strings.len(), STRINGS_PER_BLOCK);
+        assert_eq!(strings[1], b"Hello");
+        assert_eq!(strings[3], b"Bye");
+        assert_eq!(write_string_block(&strings).unwrap(), data);
+        assert_eq!(first_string_id(0x8002), 16);
+    }
+}
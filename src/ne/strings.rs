@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+
+use super::resource_table::describe_resource_id;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// A run of printable text found by [`find_strings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+    /// The offset of the first byte.
+    pub offset: usize,
+    pub text: String,
+}
+
+/// Scans the data for runs of at least `min_len` printable characters.
+///
+/// Besides ASCII, characters of the codepage are accepted, including
+/// double-byte ones.
+pub fn find_strings(data: &[u8], min_len: usize, codepage: Codepage) -> Vec<FoundString> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    let mut text = String::new();
+    let mut chars = 0;
+    let mut pos = 0;
+    while pos <= data.len() {
+        let char_len = if pos < data.len() {
+            printable_char_len(&data[pos..], codepage)
+        } else {
+            None
+        };
+        match char_len {
+            Some(len) => {
+                if chars == 0 {
+                    start = pos;
+                }
+                text.push_str(&codepage.decode(&data[pos..pos + len]));
+                chars += 1;
+                pos += len;
+            }
+            None => {
+                if chars >= min_len {
+                    strings.push(FoundString {
+                        offset: start,
+                        text: std::mem::take(&mut text),
+                    });
+                }
+                text.clear();
+                chars = 0;
+                pos += 1;
+            }
+        }
+    }
+    strings
+}
+
+/// Returns the length of the printable character at the start of the bytes.
+fn printable_char_len(bytes: &[u8], codepage: Codepage) -> Option<usize> {
+    let byte = bytes[0];
+    if (0x20..0x7F).contains(&byte) || byte == b'\t' {
+        return Some(1);
+    }
+    if byte < 0x80 {
+        return None;
+    }
+    (1..=2).filter(|&len| len <= bytes.len()).find(|&len| {
+        let decoded = codepage.decode(&bytes[..len]);
+        let mut chars = decoded.chars();
+        matches!(
+            (chars.next(), chars.next()),
+            (Some(c), None) if c != char::REPLACEMENT_CHARACTER && !c.is_control()
+        )
+    })
+}
+
+impl NeExecutable {
+    /// Prints the strings in the data segments, the resources and the
+    /// nonresident name table.
+    pub(crate) fn describe_strings<W: Write>(
+        &self,
+        w: &mut W,
+        min_len: usize,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let print = |w: &mut W,
+                     location: String,
+                     file_offset: Option<u64>,
+                     string: &FoundString|
+         -> io::Result<()> {
+            let file_offset = file_offset
+                .map(|offset| format!("0x{:08X}", offset))
+                .unwrap_or_else(|| "-".repeat(10));
+            writeln!(w, "{:<24} {}  {}", location, file_offset, string.text)?;
+            Ok(())
+        };
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.is_code() {
+                continue;
+            }
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            for string in find_strings(&data, min_len, codepage) {
+                // Offsets in iterated data don't correspond to the file.
+                let file_offset = Some(segment.data_offset() + string.offset as u64)
+                    .filter(|_| !segment.is_iterated());
+                let location = format!("{:04X}:{:04X}", i + 1, string.offset);
+                print(w, location, file_offset, &string)?;
+            }
+        }
+
+        for resource_type in &self.resource_table.resource_types {
+            let type_name = describe_resource_id(resource_type.header.type_id, true);
+            for resource in &resource_type.resources {
+                let (offset, _) = self.resource_table.data_range(resource);
+                let id = describe_resource_id(resource.header.resource_id, false);
+                for string in find_strings(&resource.data, min_len, codepage) {
+                    let location = format!("{} {}+{:04X}", type_name, id, string.offset);
+                    print(w, location, Some(offset + string.offset as u64), &string)?;
+                }
+            }
+        }
+
+        let offset = self.ne_header.non_resident_names_table_offset.value() as u64;
+        let length = self.ne_header.non_resident_names_size.value() as u64;
+        if let Some(data) = self
+            .file_data
+            .get(offset as usize..(offset + length) as usize)
+        {
+            for string in find_strings(data, min_len, codepage) {
+                let location = format!("nonresident+{:04X}", string.offset);
+                print(w, location, Some(offset + string.offset as u64), &string)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_strings() {
+        let data = b"\x00\x01Hello\x00ab\x00\xFFWorld\x00";
+        let strings = find_strings(data, 4, Codepage::new(1252).unwrap());
+        assert_eq!(
+            strings,
+            vec![
+                FoundString {
+                    offset: 2,
+                    text: "Hello".to_owned()
+                },
+                FoundString {
+                    offset: 11,
+                    text: "\u{FF}World".to_owned()
+                },
+            ]
+        );
+
+        // "ファイル" in Shift_JIS
+        let data = b"\x00\x83\x74\x83\x40\x83\x43\x83\x8B\x00";
+        let strings = find_strings(data, 4, Codepage::new(932).unwrap());
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings[0].offset, 1);
+        assert_eq!(strings[0].text, "ファイル");
+    }
+}
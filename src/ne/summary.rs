@@ -0,0 +1,77 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::classification::Classification;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The facts about a module listed when scanning directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The first resident name, or empty if there is none.
+    pub module_name: Vec<u8>,
+    /// The first nonresident name, or empty if there is none.
+    pub description: Vec<u8>,
+    pub classification: Classification,
+    pub platform: String,
+    pub segment_count: usize,
+    pub resource_count: usize,
+}
+
+impl Summary {
+    /// Writes the header of the table written by [`Summary::write_row`].
+    pub fn write_header<W: Write>(w: &mut W) -> io::Result<()> {
+        writeln!(
+            w,
+            "{:<12} {:<32} {:<32} {:>4} {:>4}  File",
+            "Module", "Type", "Platform", "Segs", "Rsrc"
+        )?;
+        Ok(())
+    }
+
+    /// Writes the summary as a row of a table.
+    pub fn write_row<W: Write>(
+        &self,
+        w: &mut W,
+        path: &Path,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        writeln!(
+            w,
+            "{:<12} {:<32} {:<32} {:>4} {:>4}  {}",
+            codepage.decode(&self.module_name),
+            self.classification.to_string(),
+            self.platform,
+            self.segment_count,
+            self.resource_count,
+            path.display()
+        )?;
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    pub fn summary(&self) -> Summary {
+        Summary {
+            module_name: self
+                .resident_name_table
+                .entries
+                .first()
+                .map_or_else(Vec::new, |entry| entry.name.clone()),
+            description: self
+                .nonresident_name_table
+                .entries
+                .first()
+                .map_or_else(Vec::new, |entry| entry.name.clone()),
+            classification: self.classify(),
+            platform: self.ne_header.platform(),
+            segment_count: self.segment_entries.len(),
+            resource_count: self
+                .resource_table
+                .resource_types
+                .iter()
+                .map(|resource_type| resource_type.resources.len())
+                .sum(),
+        }
+    }
+}
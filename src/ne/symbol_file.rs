@@ -0,0 +1,156 @@
+use std::io;
+
+use super::NeExecutable;
+
+/// The symbols of one segment in a [`SymbolFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolSegment {
+    pub name: Vec<u8>,
+    /// Offsets and names, in the order of the file (sorted by offset).
+    pub symbols: Vec<(u32, Vec<u8>)>,
+}
+
+/// A Windows symbol file (`.SYM`), as written by MAPSYM and read by WDEB386
+/// and SYMDEB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolFile {
+    pub module_name: Vec<u8>,
+    /// The segments, in the order of the module's segment table.
+    pub segments: Vec<SymbolSegment>,
+}
+
+/// The `bFlags` bit of a segment definition marking 32-bit symbol values.
+const SEGMENT_32BIT: u8 = 0x01;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn word(data: &[u8], pos: usize) -> io::Result<u16> {
+    let bytes = data
+        .get(pos..pos + 2)
+        .ok_or_else(|| invalid("unexpected end of symbol file"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a name preceded by its length.
+fn name(data: &[u8], pos: usize) -> io::Result<Vec<u8>> {
+    let len = *data
+        .get(pos)
+        .ok_or_else(|| invalid("unexpected end of symbol file"))? as usize;
+    let name = data
+        .get(pos + 1..pos + 1 + len)
+        .ok_or_else(|| invalid("unexpected end of symbol file"))?;
+    Ok(name.to_vec())
+}
+
+impl SymbolFile {
+    /// Parses the first map of the file.
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        // MAPDEF: ppNextMap, bFlags, bReserved1, pSegEntry, cConsts, pConstDef,
+        // cSegs, ppSegDef, cbMaxSym, then the module name.
+        let segment_count = word(data, 0x08)?;
+        let mut segment_pos = (word(data, 0x0A)? as usize) << 4;
+        let module_name = name(data, 0x0D)?;
+
+        let mut segments = Vec::new();
+        for _ in 0..segment_count {
+            if segment_pos == 0 {
+                return Err(invalid("fewer segments than declared"));
+            }
+            // SEGDEF: ppNextSeg, cSymbols, pSymDef, 4 reserved words, bFlags,
+            // bReserved1, ppLineDef, 2 reserved bytes, then the segment name.
+            let segment = data
+                .get(segment_pos..)
+                .ok_or_else(|| invalid("segment definition out of range"))?;
+            let next = (word(segment, 0x00)? as usize) << 4;
+            let symbol_count = word(segment, 0x02)?;
+            let pointers = word(segment, 0x04)? as usize;
+            let is_32 = segment
+                .get(0x0E)
+                .is_some_and(|flags| flags & SEGMENT_32BIT != 0);
+            let segment_name = name(segment, 0x14)?;
+
+            let mut symbols = Vec::new();
+            for i in 0..symbol_count as usize {
+                let pos = word(segment, pointers + i * 2)? as usize;
+                let (value, name_pos) = if is_32 {
+                    let low = word(segment, pos)? as u32;
+                    let high = word(segment, pos + 2)? as u32;
+                    (high << 16 | low, pos + 4)
+                } else {
+                    (word(segment, pos)? as u32, pos + 2)
+                };
+                symbols.push((value, name(segment, name_pos)?));
+            }
+            segments.push(SymbolSegment {
+                name: segment_name,
+                symbols,
+            });
+            segment_pos = next;
+        }
+        Ok(Self {
+            module_name,
+            segments,
+        })
+    }
+}
+
+impl NeExecutable {
+    /// Names addresses and segments after a symbol file.
+    ///
+    /// The segments of the symbol file are matched to those of the module by position.
+    pub fn load_symbol_file(&mut self, symbol_file: &SymbolFile) {
+        for (i, segment) in symbol_file.segments.iter().enumerate() {
+            let number = i as u16 + 1;
+            if !segment.name.is_empty() {
+                self.segment_names.insert(number, segment.name.clone());
+            }
+            for (offset, name) in &segment.symbols {
+                if let Ok(offset) = u16::try_from(*offset) {
+                    self.debug_symbols.insert((number, offset), name.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut data = vec![0; 0x10];
+        data[0x08] = 1; // cSegs
+        data[0x0A] = 1; // ppSegDef (0x10)
+        data[0x0C] = 16; // cbMaxSym
+        data[0x0D] = 1;
+        data[0x0E] = b'A';
+
+        let mut segment = vec![0; 0x14];
+        segment[0x02] = 2; // cSymbols
+        segment.push(5);
+        segment.extend_from_slice(b"_TEXT");
+        let pointers = segment.len();
+        segment[0x04] = pointers as u8;
+        segment.extend_from_slice(&[0; 4]);
+        let first = segment.len();
+        segment.extend_from_slice(b"\x00\x00\x05_main");
+        let second = segment.len();
+        segment.extend_from_slice(b"\x10\x00\x07_helper");
+        segment[pointers] = first as u8;
+        segment[pointers + 2] = second as u8;
+        data.extend_from_slice(&segment);
+
+        let symbol_file = SymbolFile::parse(&data).unwrap();
+        assert_eq!(symbol_file.module_name, b"A");
+        assert_eq!(
+            symbol_file.segments,
+            vec![SymbolSegment {
+                name: b"_TEXT".to_vec(),
+                symbols: vec![(0x0000, b"_main".to_vec()), (0x0010, b"_helper".to_vec())],
+            }]
+        );
+    }
+}
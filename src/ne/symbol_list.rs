@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The segment number of entries that are constants rather than addresses.
+pub(crate) const CONSTANT_SEGMENT: u8 = 0xFE;
+
+impl NeExecutable {
+    /// Names the known addresses, keyed by 1-based segment number and offset.
+    ///
+    /// Exported names take priority over debug symbols, which take priority
+    /// over the names made up for unnamed entries and detected functions.
+    pub fn named_addresses(&self, codepage: Codepage) -> BTreeMap<(u16, u16), String> {
+        let mut names = self.public_names(codepage);
+        let exports = self.exports();
+        for export in &exports {
+            if export.segment != CONSTANT_SEGMENT {
+                names
+                    .entry((export.segment as u16, export.offset))
+                    .or_insert_with(|| format!("entry_{}", export.ordinal));
+            }
+        }
+        let entry_point = self.ne_header.entry_point.value();
+        if entry_point >> 16 != 0 {
+            names
+                .entry(((entry_point >> 16) as u16, entry_point as u16))
+                .or_insert_with(|| "start".to_owned());
+        }
+        for function in self.functions() {
+            names
+                .entry((function.segment as u16 + 1, function.start as u16))
+                .or_insert_with(|| function.display_name(codepage));
+        }
+        names
+    }
+
+    /// Names the addresses that have a name in the file or in debug
+    /// information, leaving out made-up names.
+    pub fn public_names(&self, codepage: Codepage) -> BTreeMap<(u16, u16), String> {
+        let mut names = BTreeMap::new();
+        for export in &self.exports() {
+            if let Some(name) = &export.name {
+                if export.segment != CONSTANT_SEGMENT {
+                    names.insert(
+                        (export.segment as u16, export.offset),
+                        codepage.decode(&name.name),
+                    );
+                }
+            }
+        }
+        for (&address, name) in &self.debug_symbols {
+            names
+                .entry(address)
+                .or_insert_with(|| codepage.decode(name));
+        }
+        names
+    }
+
+    /// Prints the named addresses as `SSSS:OOOO NAME` lines, the symbol
+    /// format emulator debuggers such as DOSBox-X's load.
+    ///
+    /// `SSSS` is the segment number; the selector or paragraph it is loaded
+    /// at has to be substituted at run time.
+    pub(crate) fn describe_symbol_list<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        for ((segment, offset), name) in self.named_addresses(codepage) {
+            writeln!(w, "{:04X}:{:04X} {}", segment, offset, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_describe_symbol_list() {
+        let mut exe = sample_exe();
+        exe.debug_symbols.insert((1, 3), b"_leave".to_vec());
+        let mut output = Vec::new();
+        exe.describe_symbol_list(&mut output, Codepage::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "0001:0000 entry_1\n0001:0003 _leave\n"
+        );
+
+        // Debug symbols take priority over made-up names.
+        exe.debug_symbols.insert((1, 0), b"_main".to_vec());
+        let mut output = Vec::new();
+        exe.describe_symbol_list(&mut output, Codepage::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "0001:0000 _main\n0001:0003 _leave\n"
+        );
+    }
+}
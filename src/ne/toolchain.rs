@@ -0,0 +1,189 @@
+use super::NeExecutable;
+
+/// A compiler or linker recognized by [`NeExecutable::detect_toolchain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolchainDetection {
+    pub name: String,
+    /// What the detection is based on.
+    pub evidence: String,
+}
+
+/// Toolchains by the first year of their copyright notices.
+type NoticeYears = &'static [(u16, &'static str)];
+
+/// Copyright notices linked in by the runtime libraries, with the toolchain
+/// implied by the year that follows.
+const RUNTIME_NOTICES: &[(&[u8], NoticeYears)] = &[
+    (
+        b"MS Run-Time Library - Copyright (c) ",
+        &[
+            (0, "Microsoft C 6"),
+            (1992, "Microsoft C/C++ 7"),
+            (1993, "Visual C++ 1.x"),
+        ],
+    ),
+    (
+        b"Borland C++ - Copyright ",
+        &[(0, "Borland C++ 3.x"), (1993, "Borland C++ 4.x")],
+    ),
+    (
+        b"Portions Copyright (c) 1983,",
+        &[
+            (0, "Turbo Pascal for Windows"),
+            (1992, "Turbo Pascal for Windows 1.5 or Borland Pascal 7"),
+        ],
+    ),
+    (b"WATCOM C", &[(0, "Watcom C/C++")]),
+];
+
+/// Modules that only programs built with a particular toolchain import.
+const MODULE_HINTS: &[(&[u8], &str)] = &[
+    (b"BWCC", "Borland C++ or Pascal"),
+    (b"VBRUN100", "Visual Basic 1.0"),
+    (b"VBRUN200", "Visual Basic 2.0"),
+    (b"VBRUN300", "Visual Basic 3.0"),
+];
+
+/// Versions written by Microsoft LINK.
+const LINKER_VERSIONS: &[((u8, u8), &str)] = &[
+    ((5, 10), "Microsoft C 6"),
+    ((5, 15), "Microsoft C 6"),
+    ((5, 30), "Microsoft C/C++ 7"),
+    ((5, 31), "Microsoft C/C++ 7"),
+    ((5, 50), "Visual C++ 1.0"),
+    ((5, 60), "Visual C++ 1.5"),
+];
+
+/// Code at the entry point of C programs: `xor bp, bp; push bp; lcall INITTASK`.
+const C_STARTUP: &[u8] = &[0x33, 0xED, 0x55, 0x9A];
+
+/// Returns the toolchain named by a runtime notice starting at the beginning of `text`.
+fn notice_toolchain(text: &[u8]) -> Option<&'static str> {
+    let &(prefix, versions) = RUNTIME_NOTICES
+        .iter()
+        .find(|(prefix, _)| text.starts_with(prefix))?;
+    let digits = text[prefix.len()..]
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .fold(String::new(), |mut digits, &byte| {
+            digits.push(byte as char);
+            digits
+        });
+    let year = match (digits.len(), digits.parse::<u16>()) {
+        (2, Ok(year)) => 1900 + year,
+        (4, Ok(year)) => year,
+        _ => 0,
+    };
+    versions
+        .iter()
+        .rev()
+        .find(|&&(since, _)| since <= year)
+        .map(|&(_, name)| name)
+}
+
+impl NeExecutable {
+    /// Guesses the compiler and linker from the copyright notices of the
+    /// runtime libraries, the startup code, the referenced modules and the
+    /// linker version.
+    ///
+    /// The weaker hints are only used when the runtime notices say nothing.
+    pub fn detect_toolchain(&self) -> Vec<ToolchainDetection> {
+        let mut detections = Vec::<ToolchainDetection>::new();
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            for pos in 0..data.len() {
+                let name = match notice_toolchain(&data[pos..]) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if detections.iter().any(|detection| detection.name == name) {
+                    continue;
+                }
+                let notice = data[pos..]
+                    .iter()
+                    .take_while(|&&byte| (0x20..0x7F).contains(&byte))
+                    .take(64)
+                    .map(|&byte| byte as char)
+                    .collect::<String>();
+                detections.push(ToolchainDetection {
+                    name: name.to_owned(),
+                    evidence: format!("runtime notice {:?} in segment {}", notice, i + 1),
+                });
+            }
+        }
+        if !detections.is_empty() {
+            return detections;
+        }
+
+        for entry in &self.module_reference_table.entries {
+            if let Some(&(module, name)) = MODULE_HINTS
+                .iter()
+                .find(|(module, _)| entry.name.eq_ignore_ascii_case(module))
+            {
+                detections.push(ToolchainDetection {
+                    name: name.to_owned(),
+                    evidence: format!("imports from {}", String::from_utf8_lossy(module)),
+                });
+            }
+        }
+
+        let version = (
+            self.ne_header.major_linker_version,
+            self.ne_header.minor_linker_version,
+        );
+        if let Some(&(_, name)) = LINKER_VERSIONS.iter().find(|&&(v, _)| v == version) {
+            detections.push(ToolchainDetection {
+                name: name.to_owned(),
+                evidence: format!("Microsoft LINK version {}.{}", version.0, version.1),
+            });
+        }
+
+        let entry_point = self.ne_header.entry_point.value();
+        let startup = ((entry_point >> 16) as usize)
+            .checked_sub(1)
+            .and_then(|segment| self.segment_entries.get(segment))
+            .and_then(|segment| segment.expanded_data().ok().flatten())
+            .and_then(|data| {
+                data.get((entry_point & 0xFFFF) as usize..)
+                    .map(<[u8]>::to_vec)
+            })
+            .unwrap_or_default();
+        if detections.is_empty() && startup.starts_with(C_STARTUP) {
+            detections.push(ToolchainDetection {
+                name: "unidentified C compiler".to_owned(),
+                evidence: "C runtime startup code at the entry point".to_owned(),
+            });
+        }
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notice_toolchain() {
+        assert_eq!(
+            notice_toolchain(b"MS Run-Time Library - Copyright (c) 1992, Microsoft Corp"),
+            Some("Microsoft C/C++ 7")
+        );
+        assert_eq!(
+            notice_toolchain(b"Borland C++ - Copyright 1991 Borland Intl."),
+            Some("Borland C++ 3.x")
+        );
+        assert_eq!(
+            notice_toolchain(b"Portions Copyright (c) 1983,92 Borland"),
+            Some("Turbo Pascal for Windows 1.5 or Borland Pascal 7")
+        );
+        assert_eq!(
+            notice_toolchain(b"WATCOM C/C++16 Run-Time system."),
+            Some("Watcom C/C++")
+        );
+        assert_eq!(notice_toolchain(b"Copyright"), None);
+    }
+}
@@ -0,0 +1,594 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::dialog::{read_dialog, write_dialog, NameOrOrdinal, RT_DIALOG};
+use super::menu::{read_menu, write_menu, MenuItem, RT_MENU};
+use super::string_table::{first_string_id, read_string_block, write_string_block, RT_STRING};
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// A text of the resources to translate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationEntry {
+    /// Names the text by the resource and its place in it, so that it stays
+    /// the same across builds: `STRING/<id>`, `MENU/<resource>/<i.j>` for
+    /// the `j`th item of the `i`th popup, `DIALOG/<resource>/caption` and
+    /// `DIALOG/<resource>/<control index>`.
+    pub key: String,
+    pub source: String,
+}
+
+/// The file formats for the texts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationFormat {
+    /// A gettext PO file, with the keys as contexts.
+    Po,
+    /// A CSV file with the columns `key`, `source` and `translation`.
+    Csv,
+}
+
+impl TranslationFormat {
+    /// Chooses the format by the extension of the file name.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "po" | "pot" => Some(Self::Po),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn menu_entries(
+    items: &[MenuItem],
+    prefix: &str,
+    path: &str,
+    entries: &mut Vec<(String, Vec<u8>)>,
+) {
+    for (i, item) in items.iter().enumerate() {
+        let path = if path.is_empty() {
+            i.to_string()
+        } else {
+            format!("{}.{}", path, i)
+        };
+        if !item.text.is_empty() {
+            entries.push((format!("{}/{}", prefix, path), item.text.clone()));
+        }
+        menu_entries(&item.children, prefix, &path, entries);
+    }
+}
+
+/// Replaces the texts of the menu items that have translations, returning
+/// how many were replaced.
+fn translate_menu(
+    items: &mut [MenuItem],
+    prefix: &str,
+    path: &str,
+    translations: &BTreeMap<String, Vec<u8>>,
+) -> usize {
+    let mut count = 0;
+    for (i, item) in items.iter_mut().enumerate() {
+        let path = if path.is_empty() {
+            i.to_string()
+        } else {
+            format!("{}.{}", path, i)
+        };
+        if let Some(text) = translations.get(&format!("{}/{}", prefix, path)) {
+            item.text = text.clone();
+            count += 1;
+        }
+        count += translate_menu(&mut item.children, prefix, &path, translations);
+    }
+    count
+}
+
+impl NeExecutable {
+    /// Names a resource in translation keys: its number, or its name.
+    fn resource_key(&self, id: u16, codepage: Codepage) -> String {
+        match self.resource_table.names.get(&id) {
+            Some(name) if (id & 0x8000) == 0 => codepage.decode(name),
+            _ => (id & 0x7FFF).to_string(),
+        }
+    }
+
+    /// Returns the texts of the string tables, menus and dialogs as raw
+    /// bytes, keyed as in [`TranslationEntry`].
+    fn resource_texts(&self, codepage: Codepage) -> Vec<(String, Vec<u8>)> {
+        let mut entries = Vec::new();
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                let id = resource.header.resource_id;
+                match resource_type.header.type_id {
+                    RT_STRING => {
+                        let first = first_string_id(id);
+                        for (i, string) in read_string_block(&resource.data).into_iter().enumerate()
+                        {
+                            if !string.is_empty() {
+                                entries.push((
+                                    format!("STRING/{}", first.wrapping_add(i as u16)),
+                                    string,
+                                ));
+                            }
+                        }
+                    }
+                    RT_MENU => {
+                        let prefix = format!("MENU/{}", self.resource_key(id, codepage));
+                        if let Some(items) = read_menu(&resource.data) {
+                            menu_entries(&items, &prefix, "", &mut entries);
+                        }
+                    }
+                    RT_DIALOG => {
+                        let prefix = format!("DIALOG/{}", self.resource_key(id, codepage));
+                        let Some(dialog) = read_dialog(&resource.data) else {
+                            continue;
+                        };
+                        if !dialog.caption.is_empty() {
+                            entries.push((format!("{}/caption", prefix), dialog.caption));
+                        }
+                        for (i, control) in dialog.controls.into_iter().enumerate() {
+                            match control.text {
+                                NameOrOrdinal::Name(text) if !text.is_empty() => {
+                                    entries.push((format!("{}/{}", prefix, i), text));
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        entries
+    }
+
+    /// Lists the texts of the string tables, menus and dialogs.
+    pub fn translatable_texts(&self, codepage: Codepage) -> Vec<TranslationEntry> {
+        self.resource_texts(codepage)
+            .into_iter()
+            .map(|(key, text)| TranslationEntry {
+                key,
+                source: codepage.decode(&text),
+            })
+            .collect()
+    }
+
+    /// Replaces the texts of the string tables, menus and dialogs with their
+    /// translations, keyed as in [`TranslationEntry`], and rebuilds the
+    /// resources. Returns the number of texts replaced.
+    pub fn apply_translations(
+        &mut self,
+        translations: &BTreeMap<String, String>,
+        codepage: Codepage,
+    ) -> io::Result<usize> {
+        let encoded = translations
+            .iter()
+            .map(|(key, text)| {
+                let bytes = codepage.encode(text).ok_or_else(|| {
+                    invalid(format!(
+                        "{}: the translation can't be written in codepage {}",
+                        key, codepage
+                    ))
+                })?;
+                Ok((key.clone(), bytes))
+            })
+            .collect::<io::Result<BTreeMap<_, _>>>()?;
+        let keys = self
+            .resource_table
+            .resource_types
+            .iter()
+            .map(|resource_type| {
+                resource_type
+                    .resources
+                    .iter()
+                    .map(|resource| self.resource_key(resource.header.resource_id, codepage))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut count = 0;
+        for (resource_type, keys) in self.resource_table.resource_types.iter_mut().zip(keys) {
+            let type_id = resource_type.header.type_id;
+            for (resource, key) in resource_type.resources.iter_mut().zip(keys) {
+                let id = resource.header.resource_id;
+                let data = match type_id {
+                    RT_STRING => {
+                        let first = first_string_id(id);
+                        let mut strings = read_string_block(&resource.data);
+                        let mut replaced = 0;
+                        for (i, string) in strings.iter_mut().enumerate() {
+                            let key = format!("STRING/{}", first.wrapping_add(i as u16));
+                            if let Some(text) = encoded.get(&key) {
+                                *string = text.clone();
+                                replaced += 1;
+                            }
+                        }
+                        if replaced == 0 {
+                            continue;
+                        }
+                        count += replaced;
+                        write_string_block(&strings)?
+                    }
+                    RT_MENU => {
+                        let Some(mut items) = read_menu(&resource.data) else {
+                            continue;
+                        };
+                        let replaced =
+                            translate_menu(&mut items, &format!("MENU/{}", key), "", &encoded);
+                        if replaced == 0 {
+                            continue;
+                        }
+                        count += replaced;
+                        write_menu(&items)
+                    }
+                    RT_DIALOG => {
+                        let Some(mut dialog) = read_dialog(&resource.data) else {
+                            continue;
+                        };
+                        let prefix = format!("DIALOG/{}", key);
+                        let mut replaced = 0;
+                        if let Some(text) = encoded.get(&format!("{}/caption", prefix)) {
+                            dialog.caption = text.clone();
+                            replaced += 1;
+                        }
+                        for (i, control) in dialog.controls.iter_mut().enumerate() {
+                            if let NameOrOrdinal::Name(name) = &mut control.text {
+                                if let Some(text) = encoded.get(&format!("{}/{}", prefix, i)) {
+                                    *name = text.clone();
+                                    replaced += 1;
+                                }
+                            }
+                        }
+                        if replaced == 0 {
+                            continue;
+                        }
+                        count += replaced;
+                        write_dialog(&dialog)
+                    }
+                    _ => continue,
+                };
+                resource.data = data;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Escapes a string for a PO file.
+fn po_quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn po_unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        unquoted.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            c => c,
+        });
+    }
+    Some(unquoted)
+}
+
+/// Writes the texts as a PO template, the keys as message contexts.
+pub fn write_po<W: Write>(w: &mut W, entries: &[TranslationEntry]) -> io::Result<()> {
+    writeln!(w, "msgid \"\"")?;
+    writeln!(w, "msgstr \"\"")?;
+    writeln!(w, "\"Content-Type: text/plain; charset=UTF-8\\n\"")?;
+    for entry in entries {
+        writeln!(w)?;
+        writeln!(w, "msgctxt {}", po_quote(&entry.key))?;
+        writeln!(w, "msgid {}", po_quote(&entry.source))?;
+        writeln!(w, "msgstr \"\"")?;
+    }
+    Ok(())
+}
+
+/// Reads the translations of a PO file, keyed by message context. Untranslated
+/// and fuzzy messages are left out.
+pub fn read_po(text: &str) -> io::Result<BTreeMap<String, String>> {
+    #[derive(Default)]
+    struct Message {
+        context: Option<String>,
+        translation: String,
+        fuzzy: bool,
+    }
+    let mut translations = BTreeMap::new();
+    let mut flush = |message: &mut Message| {
+        let message = std::mem::take(message);
+        if let Some(context) = message.context {
+            if !message.fuzzy && !message.translation.is_empty() {
+                translations.insert(context, message.translation);
+            }
+        }
+    };
+    let mut message = Message::default();
+    // The field continued by lines holding only a string.
+    let mut field = "";
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error = || invalid(format!("line {}: invalid PO syntax: {}", i + 1, line));
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("#,") {
+            if field == "msgstr" {
+                flush(&mut message);
+                field = "";
+            }
+            message.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let (keyword, string) = match line.split_once(char::is_whitespace) {
+            Some((keyword, string)) if !line.starts_with('"') => (keyword, string.trim()),
+            _ => ("", line),
+        };
+        let string = po_unquote(string).ok_or_else(error)?;
+        match keyword {
+            "" => match field {
+                "msgctxt" => message.context.get_or_insert_default().push_str(&string),
+                "msgstr" => message.translation.push_str(&string),
+                "msgid" => {}
+                _ => return Err(error()),
+            },
+            "msgctxt" | "msgid" => {
+                if field == "msgstr" {
+                    flush(&mut message);
+                }
+                if keyword == "msgctxt" {
+                    message.context = Some(string);
+                }
+                field = keyword;
+            }
+            "msgstr" => {
+                message.translation = string;
+                field = keyword;
+            }
+            _ => return Err(error()),
+        }
+    }
+    flush(&mut message);
+    Ok(translations)
+}
+
+fn csv_quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Writes the texts as CSV with an empty translation column.
+pub fn write_csv<W: Write>(w: &mut W, entries: &[TranslationEntry]) -> io::Result<()> {
+    writeln!(w, "key,source,translation")?;
+    for entry in entries {
+        writeln!(w, "{},{},", csv_quote(&entry.key), csv_quote(&entry.source))?;
+    }
+    Ok(())
+}
+
+/// Splits CSV text into records of fields.
+fn parse_csv(text: &str) -> io::Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(invalid("unterminated quoted field in CSV".to_owned()));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Reads the translations of a CSV file with the columns `key`, `source` and
+/// `translation` in the first row. Empty translations are left out.
+pub fn read_csv(text: &str) -> io::Result<BTreeMap<String, String>> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim() == name)
+            .ok_or_else(|| invalid(format!("the CSV has no {} column", name)))
+    };
+    let (key, translation) = (column("key")?, column("translation")?);
+    Ok(records
+        .filter_map(|record| {
+            let translation = record.get(translation).filter(|text| !text.is_empty())?;
+            Some((record.get(key)?.clone(), translation.clone()))
+        })
+        .collect())
+}
+
+/// Reads the translations of a file in the given format.
+pub fn read_translations(
+    text: &str,
+    format: TranslationFormat,
+) -> io::Result<BTreeMap<String, String>> {
+    match format {
+        TranslationFormat::Po => read_po(text),
+        TranslationFormat::Csv => read_csv(text),
+    }
+}
+
+/// Writes the texts in the given format.
+pub fn write_translations<W: Write>(
+    w: &mut W,
+    entries: &[TranslationEntry],
+    format: TranslationFormat,
+) -> io::Result<()> {
+    match format {
+        TranslationFormat::Po => write_po(w, entries),
+        TranslationFormat::Csv => write_csv(w, entries),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{rewrite, sample_exe, set_resources};
+    use super::*;
+
+    fn entries() -> Vec<TranslationEntry> {
+        vec![
+            TranslationEntry {
+                key: "STRING/1".to_owned(),
+                source: "Say \"hi\", then\tgo".to_owned(),
+            },
+            TranslationEntry {
+                key: "MENU/1/0.0".to_owned(),
+                source: "Line\none".to_owned(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_po() {
+        let mut po = Vec::new();
+        write_po(&mut po, &entries()).unwrap();
+        let po = String::from_utf8(po).unwrap();
+        assert!(po.contains("msgid \"Say \\\"hi\\\", then\\tgo\""));
+        assert!(read_po(&po).unwrap().is_empty());
+
+        let translated = concat!(
+            "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n\n",
+            "#. a comment\nmsgctxt \"STRING/1\"\nmsgid \"Say\"\nmsgstr \"Sag\"\n\"s\"\n\n",
+            "#, fuzzy\nmsgctxt \"MENU/1/0.0\"\nmsgid \"Line\"\nmsgstr \"Zeile\"\n\n",
+            "msgctxt \"MENU/1/0.1\"\nmsgid \"Exit\"\nmsgstr \"\"\n",
+        );
+        let translations = read_po(translated).unwrap();
+        assert_eq!(translations.len(), 1);
+        assert_eq!(translations["STRING/1"], "Sags");
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &entries()).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv,
+            "key,source,translation\nSTRING/1,\"Say \"\"hi\"\", then\tgo\",\nMENU/1/0.0,\"Line\none\",\n"
+        );
+        assert!(read_csv(&csv).unwrap().is_empty());
+        let translations = read_csv(&csv.replace("\"Line\none\",", "x,\"Zeile\neins\"")).unwrap();
+        assert_eq!(translations["MENU/1/0.0"], "Zeile\neins");
+    }
+
+    #[test]
+    fn test_translation() {
+        let mut exe = sample_exe();
+        set_resources(
+            &mut exe,
+            &[
+                (RT_MENU, 0x8001, b"\0\0\0\0\x90\0&File\0\x80\0\x01\0E&xit\0"),
+                (RT_STRING, 0x8002, b"\x05Hello\x00\x03Bye"),
+            ],
+        );
+        let texts = exe
+            .translatable_texts(Codepage::default())
+            .into_iter()
+            .map(|entry| (entry.key, entry.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                ("MENU/1/0".to_owned(), "&File".to_owned()),
+                ("MENU/1/0.0".to_owned(), "E&xit".to_owned()),
+                ("STRING/16".to_owned(), "Hello".to_owned()),
+                ("STRING/18".to_owned(), "Bye".to_owned()),
+            ]
+        );
+
+        let translations = [("MENU/1/0.0", "&Beenden"), ("STRING/16", "Hallo")]
+            .into_iter()
+            .map(|(key, text)| (key.to_owned(), text.to_owned()))
+            .collect();
+        assert_eq!(
+            exe.apply_translations(&translations, Codepage::default())
+                .unwrap(),
+            2
+        );
+        let exe = rewrite(&exe);
+        let texts = exe.translatable_texts(Codepage::default());
+        assert_eq!(texts[1].source, "&Beenden");
+        assert_eq!(texts[2].source, "Hallo");
+        assert_eq!(texts[3].source, "Bye");
+    }
+
+    #[test]
+    fn test_translation_malformed_resource() {
+        let mut exe = sample_exe();
+        // The popup's items are cut off before the last one.
+        let menu = b"\0\0\0\0\x90\0&File\0\x00\0\x01\0E&xit\0";
+        set_resources(
+            &mut exe,
+            &[(RT_MENU, 0x8001, menu), (RT_STRING, 0x8002, b"\x05Hello")],
+        );
+        let texts = exe.translatable_texts(Codepage::default());
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].key, "STRING/16");
+
+        let translations = [("MENU/1/0", "&Datei"), ("STRING/16", "Hallo")]
+            .into_iter()
+            .map(|(key, text)| (key.to_owned(), text.to_owned()))
+            .collect();
+        assert_eq!(
+            exe.apply_translations(&translations, Codepage::default())
+                .unwrap(),
+            1
+        );
+        assert_eq!(exe.resource_table.resource_types[0].resources[0].data, menu);
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::util::endian::{Lu16, Lu32};
+
+/// The signature of Turbo Debugger symbol information (`FB 52`).
+pub const TD_MAGIC: u16 = 0x52FB;
+
+/// The size of a symbol record.
+const SYMBOL_SIZE: usize = 9;
+
+/// Symbols of this class are static variables and functions.
+const CLASS_STATIC: u8 = 0;
+
+/// The header of the Turbo Debugger symbol information that TLINK `/v`
+/// appends to the executable, or writes to a `.TDS` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct TdInfoHeader {
+    pub magic: Lu16,
+    pub version: Lu16,
+    pub names_size: Lu32,
+    pub names_count: Lu16,
+    pub types_count: Lu16,
+    pub members_count: Lu16,
+    pub symbols_count: Lu16,
+    pub globals_count: Lu16,
+    pub modules_count: Lu16,
+    pub locals_count: Lu16,
+    pub scopes_count: Lu16,
+    pub line_numbers_count: Lu16,
+    pub source_files_count: Lu16,
+    pub segments_count: Lu16,
+    pub correlations_count: Lu16,
+    pub image_size: Lu32,
+    pub basic_info_offset: Lu32,
+    pub debug_flags: u8,
+    pub reserved: [u8; 5],
+    pub extension_size: Lu16,
+}
+
+impl TdInfoHeader {
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut buf = [0; 0x30];
+        r.read_exact(&mut buf)?;
+        Ok(bytemuck::cast(buf))
+    }
+
+    pub fn check_magic(&self) -> io::Result<()> {
+        if self.magic.value() != TD_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TdSymbol {
+    /// The 1-based index in the name pool, or 0.
+    pub name_index: u16,
+    pub type_index: u16,
+    pub offset: u16,
+    /// The 1-based segment number for NE files.
+    pub segment: u16,
+    /// The storage class in the lower 3 bits.
+    pub flags: u8,
+}
+
+impl TdSymbol {
+    fn parse(record: &[u8]) -> Self {
+        let word = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+        Self {
+            name_index: word(0),
+            type_index: word(2),
+            offset: word(4),
+            segment: word(6),
+            flags: record[8],
+        }
+    }
+
+    pub fn class(&self) -> u8 {
+        self.flags & 7
+    }
+}
+
+/// The parts of the Turbo Debugger symbol information used to name addresses.
+#[derive(Debug, Clone)]
+pub struct TurboDebuggerInfo {
+    pub header: TdInfoHeader,
+    pub symbols: Vec<TdSymbol>,
+    /// The name pool, which ends the symbol information.
+    pub names: Vec<Vec<u8>>,
+}
+
+impl TurboDebuggerInfo {
+    /// Reads the symbol information starting at the current position and
+    /// extending to the end of the file.
+    pub fn read<R: Read + Seek>(r: &mut R) -> io::Result<Self> {
+        let header = TdInfoHeader::read(r)?;
+        header.check_magic()?;
+        r.seek(SeekFrom::Current(header.extension_size.value() as i64))?;
+
+        let mut records = vec![0; header.symbols_count.value() as usize * SYMBOL_SIZE];
+        r.read_exact(&mut records)?;
+        let symbols = records.chunks(SYMBOL_SIZE).map(TdSymbol::parse).collect();
+
+        r.seek(SeekFrom::End(-(header.names_size.value() as i64)))?;
+        let mut pool = Vec::new();
+        r.take(header.names_size.value() as u64)
+            .read_to_end(&mut pool)?;
+        let names = pool
+            .split(|&byte| byte == 0)
+            .take(header.names_count.value() as usize)
+            .map(<[u8]>::to_vec)
+            .collect();
+        Ok(Self {
+            header,
+            symbols,
+            names,
+        })
+    }
+
+    pub fn name(&self, index: u16) -> Option<&[u8]> {
+        let name = self.names.get((index as usize).checked_sub(1)?)?;
+        Some(name)
+    }
+
+    /// Lists the global static symbols as (segment, offset, name).
+    pub fn public_symbols(&self) -> Vec<(u16, u16, Vec<u8>)> {
+        self.symbols
+            .iter()
+            .take(self.header.globals_count.value() as usize)
+            .filter(|symbol| symbol.class() == CLASS_STATIC)
+            .filter_map(|symbol| {
+                let name = self.name(symbol.name_index)?;
+                Some((symbol.segment, symbol.offset, name.to_vec()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let names = b"_main\0_helper\0_local\0";
+        let mut header = TdInfoHeader::zeroed();
+        header.magic = TD_MAGIC.into();
+        header.names_size = (names.len() as u32).into();
+        header.names_count = 3.into();
+        header.symbols_count = 3.into();
+        header.globals_count = 2.into();
+        header.extension_size = 2.into();
+
+        let mut data = bytemuck::bytes_of(&header).to_vec();
+        data.extend_from_slice(&[0, 0]);
+        // _main at 0001:0000, _helper at 0001:0010 (a typedef), _local at 0002:0004
+        data.extend_from_slice(&[1, 0, 0, 0, 0x00, 0x00, 1, 0, 0]);
+        data.extend_from_slice(&[2, 0, 0, 0, 0x10, 0x00, 1, 0, 6]);
+        data.extend_from_slice(&[3, 0, 0, 0, 0x04, 0x00, 2, 0, 0]);
+        data.extend_from_slice(names);
+
+        let info = TurboDebuggerInfo::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(info.name(2), Some(&b"_helper"[..]));
+        assert_eq!(info.public_symbols(), vec![(1, 0, b"_main".to_vec())]);
+    }
+}
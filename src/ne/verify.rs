@@ -0,0 +1,436 @@
+use std::fmt;
+
+use super::entry_table::SegmentEntry;
+use super::header::file_load_crc;
+use super::relocation_table::RelocationTarget;
+use super::NeExecutable;
+
+/// An inconsistency found by [`NeExecutable::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The file offset of the offending structure.
+    pub offset: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:08X}: {}", self.offset, self.message)
+    }
+}
+
+impl NeExecutable {
+    /// Checks that the offsets and references in the executable are consistent.
+    pub fn verify(&self, file_size: u64) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut report = |offset: u64, message: String| {
+            violations.push(Violation { offset, message });
+        };
+
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let ne_header = &self.ne_header;
+        let segment_count = ne_header.segment_count.value();
+        let num_entries = self.entry_table.entries.len();
+
+        let entry_cs = ne_header.entry_point.value() >> 16;
+        if entry_cs > segment_count as u32 {
+            report(
+                lfanew + 0x14,
+                format!("entry point segment {} does not exist", entry_cs),
+            );
+        }
+        let init_ss = ne_header.init_stack.value() >> 16;
+        if init_ss > segment_count as u32 {
+            report(
+                lfanew + 0x18,
+                format!("initial stack segment {} does not exist", init_ss),
+            );
+        }
+        let crc = ne_header.file_load_crc.value();
+        if crc != 0 {
+            let computed = file_load_crc(&self.file_data, lfanew as usize);
+            if crc != computed {
+                report(
+                    lfanew + 0x08,
+                    format!(
+                        "file load CRC 0x{:08X} does not match the contents (0x{:08X})",
+                        crc, computed
+                    ),
+                );
+            }
+        }
+        let auto_data_segment = ne_header.auto_data_segment_index.value();
+        if auto_data_segment > segment_count {
+            report(
+                lfanew + 0x0E,
+                format!("auto-data segment {} does not exist", auto_data_segment),
+            );
+        }
+
+        let segment_table_offset = lfanew + ne_header.segment_table_offset.value() as u64;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let header_offset = segment_table_offset + 8 * i as u64;
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            let data_end = segment.data_offset() + segment.data_length();
+            if data_end > file_size {
+                report(
+                    header_offset,
+                    format!(
+                        "segment #{}: data 0x{:X}..0x{:X} extends past the end of file (0x{:X})",
+                        i,
+                        segment.data_offset(),
+                        data_end,
+                        file_size
+                    ),
+                );
+            }
+            if !segment.is_iterated() && segment.data_length() > segment.min_alloc() {
+                report(
+                    header_offset,
+                    format!(
+                        "segment #{}: length on file 0x{:X} exceeds allocation 0x{:X}",
+                        i,
+                        segment.data_length(),
+                        segment.min_alloc()
+                    ),
+                );
+            }
+
+            let relocations = if let Some(relocations) = &segment.relocations {
+                relocations
+            } else {
+                continue;
+            };
+            let data = match segment.expanded_data() {
+                Ok(data) => data.unwrap_or_default(),
+                Err(e) => {
+                    report(segment.data_offset(), format!("segment #{}: {}", i, e));
+                    continue;
+                }
+            };
+            let segment_length = data.len() as u64;
+            for (j, entry) in relocations.entries.iter().enumerate() {
+                let entry_offset = data_end + 2 + 8 * j as u64;
+                match entry.sites(&data) {
+                    Ok(sites) => {
+                        for site in sites {
+                            if site as u64 + entry.source_size() as u64 > segment_length {
+                                report(
+                                    entry_offset,
+                                    format!(
+                                        "segment #{}: relocation #{} patches 0x{:04X} past the end (0x{:X})",
+                                        i, j, site, segment_length
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        report(
+                            entry_offset,
+                            format!("segment #{}: relocation #{}: {}", i, j, e),
+                        );
+                    }
+                }
+                let problem = match entry.target {
+                    RelocationTarget::InternalFixed { segment, .. }
+                        if segment == 0 || segment as u16 > segment_count =>
+                    {
+                        Some(format!("refers to nonexistent segment {}", segment))
+                    }
+                    RelocationTarget::InternalMoveable { ordinal }
+                        if ordinal == 0 || ordinal as usize > num_entries =>
+                    {
+                        Some(format!("refers to nonexistent entry #{}", ordinal))
+                    }
+                    RelocationTarget::ImportOrdinal { module, .. }
+                    | RelocationTarget::ImportName { module, .. }
+                        if self.module_name(module).is_none() =>
+                    {
+                        Some(format!("refers to nonexistent module {}", module))
+                    }
+                    _ => None,
+                };
+                if let Some(problem) = problem {
+                    report(
+                        entry_offset,
+                        format!("segment #{}: relocation #{} {}", i, j, problem),
+                    );
+                }
+            }
+        }
+
+        let entry_table_offset = lfanew + ne_header.entry_table_offset.value() as u64;
+        for (i, entry) in self.entry_table.entries.iter().enumerate() {
+            let offset = entry_table_offset + self.entry_table.offsets[i] as u64;
+            let (segment, entry_offset) = match entry {
+                SegmentEntry::Unused => continue,
+                SegmentEntry::Fixed(entry) => (entry.segment, entry.offset),
+                SegmentEntry::Moveable(entry) => {
+                    if entry.magic != *b"\xCD\x3F" {
+                        report(
+                            offset,
+                            format!("entry #{}: invalid moveable entry magic", i + 1),
+                        );
+                    }
+                    (entry.segment, entry.offset)
+                }
+            };
+            if segment == 0 || segment as u16 > segment_count {
+                report(
+                    offset,
+                    format!(
+                        "entry #{}: refers to nonexistent segment {}",
+                        i + 1,
+                        segment
+                    ),
+                );
+                continue;
+            }
+            let target = &self.segment_entries[segment as usize - 1];
+            if entry_offset as u64 >= target.min_alloc() {
+                report(
+                    offset,
+                    format!(
+                        "entry #{}: offset 0x{:04X} is outside segment {}",
+                        i + 1,
+                        entry_offset,
+                        segment
+                    ),
+                );
+            }
+        }
+
+        let name_tables = [
+            (
+                "resident",
+                lfanew + ne_header.resident_names_table_offset.value() as u64,
+                self.resident_name_table
+                    .entries
+                    .iter()
+                    .map(|entry| (entry.name.len(), entry.index))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "nonresident",
+                ne_header.non_resident_names_table_offset.value() as u64,
+                self.nonresident_name_table
+                    .entries
+                    .iter()
+                    .map(|entry| (entry.name.len(), entry.index))
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        for (kind, table_offset, entries) in name_tables {
+            let mut offset = table_offset;
+            for (i, (name_len, index)) in entries.into_iter().enumerate() {
+                // The first entry is the module name or description and has no ordinal.
+                if i > 0 {
+                    let entry = self
+                        .entry_table
+                        .entries
+                        .get((index as usize).wrapping_sub(1));
+                    if entry.is_none() {
+                        report(
+                            offset,
+                            format!(
+                                "{} name #{}: refers to nonexistent entry #{}",
+                                kind, i, index
+                            ),
+                        );
+                    } else if let Some(SegmentEntry::Unused) = entry {
+                        report(
+                            offset,
+                            format!("{} name #{}: refers to unused entry #{}", kind, i, index),
+                        );
+                    }
+                }
+                offset += 1 + name_len as u64 + 2;
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::super::entry_table::{EntryFlags, FixedSegmentEntry, MoveableSegmentEntry};
+    use super::super::relocation_table::{RelocationEntry, RelocationTable, SOURCE_FAR_ADDR};
+    use super::super::segment_table::SegmentFlags;
+    use super::super::tests::{sample, sample_exe};
+    use super::*;
+
+    fn messages(exe: &NeExecutable) -> Vec<String> {
+        exe.verify(exe.file_data.len() as u64)
+            .into_iter()
+            .map(|violation| violation.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_sample() {
+        let exe = sample_exe();
+        assert_eq!(messages(&exe), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_verify_header() {
+        let mut exe = sample_exe();
+        exe.ne_header.entry_point = 0x0002_0000.into();
+        exe.ne_header.init_stack = 0x0003_0000.into();
+        exe.ne_header.auto_data_segment_index = 4.into();
+        exe.ne_header.file_load_crc = 0x12345678.into();
+        let computed = file_load_crc(&exe.file_data, 0x40);
+        assert_eq!(
+            messages(&exe),
+            [
+                "entry point segment 2 does not exist".to_string(),
+                "initial stack segment 3 does not exist".to_string(),
+                format!(
+                    "file load CRC 0x12345678 does not match the contents (0x{:08X})",
+                    computed
+                ),
+                "auto-data segment 4 does not exist".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_segment_past_end() {
+        let mut data = sample();
+        data.truncate(0xA3);
+        assert!(NeExecutable::read(&mut Cursor::new(&data[..])).is_err());
+        let exe = NeExecutable::read_lenient(&mut Cursor::new(&data[..])).unwrap();
+        let violations = exe.verify(data.len() as u64);
+        assert_eq!(
+            violations,
+            [Violation {
+                offset: 0x80,
+                message: "segment #0: data 0xA0..0xA5 extends past the end of file (0xA3)"
+                    .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_verify_segment_allocation() {
+        let mut exe = sample_exe();
+        exe.segment_entries[0].header.min_alloc = 2;
+        assert_eq!(
+            messages(&exe),
+            ["segment #0: length on file 0x5 exceeds allocation 0x2".to_string()]
+        );
+        exe.segment_entries[0].header.min_alloc = 5;
+        exe.segment_entries[0].header.flags |= SegmentFlags::ITERATED;
+        exe.segment_entries[0].relocations = Some(RelocationTable {
+            entries: Vec::new(),
+        });
+        assert_eq!(
+            messages(&exe),
+            ["segment #0: Truncated iteration record".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_verify_relocations() {
+        let mut exe = sample_exe();
+        let entry = |flags, offset, target| RelocationEntry {
+            source_type: SOURCE_FAR_ADDR,
+            flags,
+            offset,
+            target,
+        };
+        let entries = vec![
+            entry(
+                4,
+                3,
+                RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            ),
+            entry(
+                0,
+                0x10,
+                RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            ),
+            entry(
+                4,
+                0,
+                RelocationTarget::InternalFixed {
+                    segment: 2,
+                    offset: 0,
+                },
+            ),
+            entry(4, 0, RelocationTarget::InternalMoveable { ordinal: 2 }),
+            entry(
+                4,
+                0,
+                RelocationTarget::ImportOrdinal {
+                    module: 1,
+                    ordinal: 1,
+                },
+            ),
+        ];
+        exe.segment_entries[0].relocations = Some(RelocationTable { entries });
+        assert_eq!(
+            messages(&exe),
+            [
+                "segment #0: relocation #0 patches 0x0003 past the end (0x5)",
+                "segment #0: relocation #1: Relocation chain points outside the segment: 0x0010",
+                "segment #0: relocation #2 refers to nonexistent segment 2",
+                "segment #0: relocation #3 refers to nonexistent entry #2",
+                "segment #0: relocation #4 refers to nonexistent module 1",
+            ]
+            .map(str::to_string)
+        );
+    }
+
+    #[test]
+    fn test_verify_entries() {
+        let mut exe = sample_exe();
+        let entries = &mut exe.entry_table.entries;
+        entries.push(SegmentEntry::Moveable(MoveableSegmentEntry {
+            flags: EntryFlags::EXPORTED,
+            magic: *b"\xCD\x20",
+            segment: 1,
+            offset: 0,
+        }));
+        entries.push(SegmentEntry::Fixed(FixedSegmentEntry {
+            segment: 2,
+            flags: EntryFlags::EXPORTED,
+            offset: 0,
+        }));
+        entries.push(SegmentEntry::Fixed(FixedSegmentEntry {
+            segment: 1,
+            flags: EntryFlags::EXPORTED,
+            offset: 5,
+        }));
+        entries.push(SegmentEntry::Unused);
+        exe.entry_table.offsets.extend([6, 12, 16, 20]);
+        let names = &mut exe.resident_name_table.entries;
+        names.push(names[0].clone());
+        names[1].index = 5;
+        names.push(names[0].clone());
+        names[2].index = 9;
+        assert_eq!(
+            messages(&exe),
+            [
+                "entry #2: invalid moveable entry magic",
+                "entry #3: refers to nonexistent segment 2",
+                "entry #4: offset 0x0005 is outside segment 1",
+                "resident name #1: refers to unused entry #5",
+                "resident name #2: refers to nonexistent entry #9",
+            ]
+            .map(str::to_string)
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The runtime DLLs and the Visual Basic versions they belong to.
+const RUNTIMES: &[(&[u8], &str)] = &[
+    (b"VBRUN100", "Visual Basic 1.0"),
+    (b"VBRUN200", "Visual Basic 2.0"),
+    (b"VBRUN300", "Visual Basic 3.0"),
+    (b"VB40016", "Visual Basic 4.0 (16-bit)"),
+];
+
+/// Returns the file names of custom controls (`*.VBX`) mentioned in the data.
+fn vbx_names(data: &[u8]) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for (pos, window) in data.windows(4).enumerate() {
+        if !window.eq_ignore_ascii_case(b".VBX") {
+            continue;
+        }
+        let start = data[..pos]
+            .iter()
+            .rposition(|&byte| !(byte.is_ascii_alphanumeric() || b"_-~$:\\".contains(&byte)))
+            .map_or(0, |i| i + 1);
+        let path = String::from_utf8_lossy(&data[start..pos + 4]);
+        let name = path.rsplit(['\\', ':']).next().unwrap_or_default();
+        if name.len() > 4 {
+            names.insert(name.to_ascii_uppercase());
+        }
+    }
+    names
+}
+
+impl NeExecutable {
+    /// Prints what is known about the Visual Basic program: the runtime it
+    /// needs and the custom controls it loads.
+    ///
+    /// The layout of the form and control records isn't decoded, so the
+    /// forms and their standard controls aren't listed; class names such as
+    /// `Data` or `Line` are too common to be told apart from other strings.
+    pub(crate) fn describe_vb_info<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_else(|| "<no name>".to_owned());
+        let runtimes = self
+            .module_reference_table
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                RUNTIMES
+                    .iter()
+                    .find(|(runtime, _)| entry.name.eq_ignore_ascii_case(runtime))
+            })
+            .collect::<Vec<_>>();
+        if runtimes.is_empty() {
+            writeln!(w, "{} does not use the Visual Basic runtime", module_name)?;
+            return Ok(());
+        }
+
+        writeln!(w, "Visual Basic program {}:", module_name)?;
+        for (runtime, version) in runtimes {
+            writeln!(
+                w,
+                "    Runtime: {} ({})",
+                String::from_utf8_lossy(runtime),
+                version
+            )?;
+        }
+
+        let mut custom_controls = BTreeSet::new();
+        for segment in &self.segment_entries {
+            if segment.is_code() {
+                continue;
+            }
+            let data = match segment.expanded_data() {
+                Ok(Some(data)) => data,
+                _ => continue,
+            };
+            custom_controls.extend(vbx_names(&data));
+        }
+        writeln!(w, "    Custom controls:")?;
+        if custom_controls.is_empty() {
+            writeln!(w, "        none found")?;
+        }
+        for name in &custom_controls {
+            writeln!(w, "        {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::module_reference_table::{ModuleReferenceEntry, ModuleReferenceEntryHeader};
+    use super::super::segment_table::SegmentFlags;
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_describe_vb_info() {
+        let mut exe = sample_exe();
+        let describe = |exe: &NeExecutable| {
+            let mut output = Vec::new();
+            exe.describe_vb_info(&mut output, Codepage::default())
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(
+            describe(&exe),
+            "TEST does not use the Visual Basic runtime\n"
+        );
+
+        exe.module_reference_table
+            .entries
+            .push(ModuleReferenceEntry {
+                header: ModuleReferenceEntryHeader { offset: 0 },
+                name: b"VBRUN300".to_vec(),
+            });
+        let mut data = exe.segment_entries[0].clone();
+        data.header.flags = SegmentFlags::DATA;
+        // Strings that happen to be class names aren't controls.
+        data.data = Some(b"Data\0Line\0Menu\0THREED.VBX\0".to_vec());
+        exe.segment_entries.push(data);
+        assert_eq!(
+            describe(&exe),
+            [
+                "Visual Basic program TEST:",
+                "    Runtime: VBRUN300 (Visual Basic 3.0)",
+                "    Custom controls:",
+                "        THREED.VBX",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_vbx_names() {
+        let data = b"\0C:\\VB\\THREED.VBX\0grid.vbx\0.VBX\0";
+        assert_eq!(
+            vbx_names(data).into_iter().collect::<Vec<_>>(),
+            vec!["GRID.VBX".to_owned(), "THREED.VBX".to_owned()]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The segment number of entries that are constants rather than addresses.
+const CONSTANT_SEGMENT: u8 = 0xFE;
+
+impl NeExecutable {
+    /// Prints a Wine `.spec` file for reimplementing the module as a 16-bit DLL.
+    ///
+    /// The argument lists can't be recovered from the executable, so entries
+    /// in code segments become `pascal` functions with unknown arguments
+    /// unless the entry records a parameter word count.
+    pub(crate) fn describe_wine_spec<W: Write>(
+        &self,
+        w: &mut W,
+        codepage: Codepage,
+    ) -> io::Result<()> {
+        let module_name = self
+            .module_name_entry()
+            .map(|name| codepage.decode(name))
+            .unwrap_or_default();
+        for export in self.exports() {
+            let name = match &export.name {
+                Some(name) => codepage.decode(&name.name),
+                None => {
+                    writeln!(
+                        w,
+                        "{} stub {}_{}",
+                        export.ordinal, module_name, export.ordinal
+                    )?;
+                    continue;
+                }
+            };
+            if export.segment == CONSTANT_SEGMENT {
+                writeln!(w, "{} equate {} {}", export.ordinal, name, export.offset)?;
+                continue;
+            }
+            let is_code = self
+                .segment_entries
+                .get((export.segment as usize).wrapping_sub(1))
+                .is_none_or(|segment| segment.is_code());
+            if !is_code {
+                writeln!(
+                    w,
+                    "{} variable {}(0x00000000) # {:04X}:{:04X}",
+                    export.ordinal, name, export.segment, export.offset
+                )?;
+            } else if export.flags.param_words() != 0 {
+                let args = vec!["word"; export.flags.param_words() as usize];
+                writeln!(
+                    w,
+                    "{} pascal {}({}) {}16",
+                    export.ordinal,
+                    name,
+                    args.join(" "),
+                    name
+                )?;
+            } else {
+                writeln!(
+                    w,
+                    "{} pascal {}() {}16 # arguments unknown",
+                    export.ordinal, name, name
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::entry_table::{EntryFlags, FixedSegmentEntry, SegmentEntry};
+    use super::super::tests::sample_exe;
+    use super::*;
+
+    #[test]
+    fn test_describe_wine_spec() {
+        let mut exe = sample_exe();
+        exe.add_export(&"Func@2=1:0003".parse().unwrap(), true)
+            .unwrap();
+        exe.add_export(&"Draw@3=1:0004".parse().unwrap(), true)
+            .unwrap();
+        exe.add_export(&"Answer@4=1:002A".parse().unwrap(), true)
+            .unwrap();
+        let entries = &mut exe.entry_table.entries;
+        if let SegmentEntry::Fixed(entry) = &mut entries[2] {
+            entry.flags |= EntryFlags::from_bits_retain(2 << 3);
+        }
+        entries[3] = SegmentEntry::Fixed(FixedSegmentEntry {
+            segment: CONSTANT_SEGMENT,
+            flags: EntryFlags::EXPORTED,
+            offset: 42,
+        });
+        let mut output = Vec::new();
+        exe.describe_wine_spec(&mut output, Codepage::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            [
+                "1 stub TEST_1",
+                "2 pascal Func() Func16 # arguments unknown",
+                "3 pascal Draw(word word) Draw16",
+                "4 equate Answer 42",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::code_flow::Function;
+use super::imports::ImportedSymbol;
+use super::relocation_table::{RelocationTarget, SOURCE_SEGMENT};
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+use crate::x86::{self, Flow};
+
+/// What is referred to by an [`Xref`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum XrefTarget {
+    /// A location in this module, as a 0-based segment index and offset.
+    Address { segment: usize, offset: usize },
+    /// A segment of this module as a whole, e.g. `mov $seg, %ax`.
+    Segment(usize),
+    /// A function imported from another module. The module index is 1-based.
+    Import { module: u16, symbol: ImportedSymbol },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrefKind {
+    Call,
+    /// A jump or a conditional branch.
+    Jump,
+    /// Any other use, such as loading a pointer or a selector.
+    Load,
+}
+
+impl XrefKind {
+    fn name(self) -> &'static str {
+        match self {
+            XrefKind::Call => "call",
+            XrefKind::Jump => "jump",
+            XrefKind::Load => "load",
+        }
+    }
+}
+
+/// An instruction referring to an [`XrefTarget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xref {
+    /// The 0-based segment index of the instruction.
+    pub segment: usize,
+    /// The offset of the instruction.
+    pub offset: usize,
+    pub kind: XrefKind,
+}
+
+impl NeExecutable {
+    /// Collects the references made by the instructions of each function,
+    /// both direct ones and those through relocations.
+    pub fn xrefs(&self) -> BTreeMap<XrefTarget, Vec<Xref>> {
+        let mut xrefs = BTreeMap::<XrefTarget, Vec<Xref>>::new();
+        for function in self.functions() {
+            let segment = &self.segment_entries[function.segment];
+            let data = segment.expanded_data().ok().flatten().unwrap_or_default();
+            let fixups = self.fixup_targets(function.segment, &data);
+            for inst in x86::decode_range(&data, function.start..function.end, false) {
+                let kind = match inst.flow() {
+                    Flow::Call(_) | Flow::FarCall => XrefKind::Call,
+                    Flow::Jump(_) | Flow::Branch(_) | Flow::FarJump => XrefKind::Jump,
+                    Flow::Next | Flow::Stop => XrefKind::Load,
+                };
+                let xref = Xref {
+                    segment: function.segment,
+                    offset: inst.pos,
+                    kind,
+                };
+                if let Flow::Call(target) | Flow::Jump(target) | Flow::Branch(target) = inst.flow()
+                {
+                    let target = XrefTarget::Address {
+                        segment: function.segment,
+                        offset: target as usize,
+                    };
+                    xrefs.entry(target).or_default().push(xref);
+                }
+                // A far call's fixup covers the operand, a `mov` of a selector's the immediate.
+                for (_, target) in fixups.range(inst.pos..inst.end()) {
+                    xrefs.entry(target.clone()).or_default().push(xref);
+                }
+            }
+        }
+        xrefs
+    }
+
+    /// Maps the fixup sites of a segment to what they resolve to.
+    fn fixup_targets(&self, segment: usize, data: &[u8]) -> BTreeMap<usize, XrefTarget> {
+        let mut targets = BTreeMap::new();
+        let relocations = match &self.segment_entries[segment].relocations {
+            Some(relocations) => relocations,
+            None => return targets,
+        };
+        for entry in &relocations.entries {
+            let target = match entry.target {
+                RelocationTarget::InternalFixed { segment, .. }
+                    if entry.source_type == SOURCE_SEGMENT =>
+                {
+                    (segment as usize).checked_sub(1).map(XrefTarget::Segment)
+                }
+                RelocationTarget::InternalFixed { segment, offset } => (segment as usize)
+                    .checked_sub(1)
+                    .map(|segment| XrefTarget::Address {
+                        segment,
+                        offset: offset as usize,
+                    }),
+                RelocationTarget::InternalMoveable { ordinal } => self
+                    .entry_address(ordinal)
+                    .map(|(segment, offset)| XrefTarget::Address { segment, offset }),
+                target => self
+                    .imported_symbol(target)
+                    .map(|(module, symbol)| XrefTarget::Import { module, symbol }),
+            };
+            if let Some(target) = target {
+                for site in entry.sites(data).unwrap_or_default() {
+                    targets.insert(site as usize, target.clone());
+                }
+            }
+        }
+        targets
+    }
+
+    /// Describes an xref target, e.g. `KERNEL.GETVERSION`, `MAINPROC` or `0002:0010`.
+    fn describe_xref_target(
+        &self,
+        target: &XrefTarget,
+        functions: &[Function],
+        codepage: Codepage,
+    ) -> String {
+        match target {
+            XrefTarget::Address { segment, offset } => {
+                match functions
+                    .iter()
+                    .find(|function| function.segment == *segment && function.start == *offset)
+                {
+                    Some(function) => format!(
+                        "{} ({:04X}:{:04X})",
+                        function.display_name(codepage),
+                        segment + 1,
+                        offset
+                    ),
+                    None => format!("{:04X}:{:04X}", segment + 1, offset),
+                }
+            }
+            XrefTarget::Segment(segment) => format!("segment {}", segment + 1),
+            XrefTarget::Import { module, symbol } => {
+                let module_name = self
+                    .module_name(*module)
+                    .map(|name| codepage.decode(name))
+                    .unwrap_or_else(|| format!("<module {}>", module));
+                let symbol = match symbol {
+                    ImportedSymbol::Ordinal(ordinal) => {
+                        match self.imported_ordinal_name(*module, *ordinal) {
+                            Some(name) => codepage.decode(name),
+                            None => ordinal.to_string(),
+                        }
+                    }
+                    ImportedSymbol::Name(name) => codepage.decode(name),
+                };
+                format!("{}.{}", module_name, symbol)
+            }
+        }
+    }
+
+    /// Prints the callers of each function and imported symbol, and the other
+    /// references to this module's addresses and segments.
+    pub(crate) fn describe_xrefs<W: Write>(&self, w: &mut W, codepage: Codepage) -> io::Result<()> {
+        let functions = self.functions();
+        let xrefs = self.xrefs();
+        let print_target = |w: &mut W, target: &XrefTarget| -> io::Result<()> {
+            writeln!(
+                w,
+                "    {}",
+                self.describe_xref_target(target, &functions, codepage)
+            )?;
+            for xref in &xrefs[target] {
+                let caller = functions
+                    .iter()
+                    .find(|function| {
+                        function.segment == xref.segment
+                            && (function.start..function.end).contains(&xref.offset)
+                    })
+                    .map(|function| function.display_name(codepage))
+                    .unwrap_or_default();
+                writeln!(
+                    w,
+                    "        {:04X}:{:04X}  {}  in {}",
+                    xref.segment + 1,
+                    xref.offset,
+                    xref.kind.name(),
+                    caller
+                )?;
+            }
+            Ok(())
+        };
+
+        // Jumps within a function are better read from the listing.
+        let is_internal_jump = |target: &XrefTarget| match target {
+            XrefTarget::Address { segment, offset } => xrefs[target].iter().all(|xref| {
+                xref.kind == XrefKind::Jump
+                    && functions.iter().any(|function| {
+                        function.segment == *segment
+                            && (function.start..function.end).contains(offset)
+                            && (function.start..function.end).contains(&xref.offset)
+                            && function.segment == xref.segment
+                    })
+            }),
+            _ => false,
+        };
+
+        writeln!(w, "Local references:")?;
+        for target in xrefs.keys() {
+            if !matches!(target, XrefTarget::Import { .. }) && !is_internal_jump(target) {
+                print_target(w, target)?;
+            }
+        }
+        writeln!(w, "Imported references:")?;
+        for target in xrefs.keys() {
+            if matches!(target, XrefTarget::Import { .. }) {
+                print_target(w, target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::relocation_table::{RelocationEntry, SOURCE_FAR_ADDR};
+    use super::super::tests::{sample_exe, set_code};
+    use super::*;
+
+    /// The sample calling a near helper, which calls `KERNEL.3` far.
+    fn with_calls() -> NeExecutable {
+        let mut exe = sample_exe();
+        set_code(
+            &mut exe,
+            b"\xE8\x01\x00\xCB\x9A\xFF\xFF\x00\x00\xCB",
+            vec![RelocationEntry {
+                source_type: SOURCE_FAR_ADDR,
+                flags: 0,
+                offset: 5,
+                target: RelocationTarget::InternalFixed {
+                    segment: 1,
+                    offset: 0,
+                },
+            }],
+        );
+        exe.retarget(&"1:0005=KERNEL.3".parse().unwrap()).unwrap();
+        exe
+    }
+
+    #[test]
+    fn test_xrefs() {
+        let xrefs = with_calls().xrefs();
+        assert_eq!(
+            xrefs.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    XrefTarget::Address {
+                        segment: 0,
+                        offset: 4
+                    },
+                    vec![Xref {
+                        segment: 0,
+                        offset: 0,
+                        kind: XrefKind::Call
+                    }]
+                ),
+                (
+                    XrefTarget::Import {
+                        module: 1,
+                        symbol: ImportedSymbol::Ordinal(3)
+                    },
+                    vec![Xref {
+                        segment: 0,
+                        offset: 4,
+                        kind: XrefKind::Call
+                    }]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_xrefs() {
+        let mut output = Vec::new();
+        with_calls()
+            .describe_xrefs(&mut output, Codepage::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            [
+                "Local references:",
+                "    sub_0001_0004 (0001:0004)",
+                "        0001:0000  call  in sub_0001_0000",
+                "Imported references:",
+                "    KERNEL.GETVERSION",
+                "        0001:0004  call  in sub_0001_0004",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
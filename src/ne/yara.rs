@@ -0,0 +1,219 @@
+use std::fmt::Write as _;
+
+use super::hashes::Digests;
+use super::strings::find_strings;
+use super::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// The most strings of each kind put in a rule.
+const MAX_STRINGS: usize = 4;
+/// The length of the code sequences put in a rule.
+const CODE_SEQUENCE_LEN: usize = 16;
+/// The shortest resource string put in a rule.
+const MIN_RESOURCE_STRING_LEN: usize = 8;
+
+/// Writes bytes as a YARA text string, escaping what isn't printable ASCII.
+fn yara_text(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7E => text.push(byte as char),
+            _ => write!(text, "\\x{:02X}", byte).unwrap(),
+        }
+    }
+    text
+}
+
+/// Turns a module name into a rule identifier.
+fn rule_name(module_name: &[u8]) -> String {
+    let name = module_name
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_alphanumeric() {
+                byte as char
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() {
+        "ne_module".to_owned()
+    } else {
+        format!("ne_{}", name)
+    }
+}
+
+/// Picks up to `count` non-overlapping sequences of the code with the most
+/// distinct byte values. Fixups are `None`, and sequences with more than a
+/// quarter of them are passed over.
+fn distinctive_sequences(code: &[Option<u8>], count: usize) -> Vec<&[Option<u8>]> {
+    let mut candidates = code
+        .windows(CODE_SEQUENCE_LEN)
+        .enumerate()
+        .step_by(2)
+        .filter(|(_, window)| {
+            window.iter().filter(|byte| byte.is_none()).count() <= CODE_SEQUENCE_LEN / 4
+        })
+        .map(|(offset, window)| {
+            let mut seen = [false; 256];
+            for byte in window.iter().flatten() {
+                seen[*byte as usize] = true;
+            }
+            (seen.iter().filter(|&&seen| seen).count(), offset)
+        })
+        .collect::<Vec<_>>();
+    // The most distinct first, then the earliest.
+    candidates.sort_by_key(|&(distinct, offset)| (std::cmp::Reverse(distinct), offset));
+    let mut picked = Vec::<usize>::new();
+    for (_, offset) in candidates {
+        if picked.len() == count {
+            break;
+        }
+        if picked
+            .iter()
+            .all(|&other| offset.abs_diff(other) >= CODE_SEQUENCE_LEN)
+        {
+            picked.push(offset);
+        }
+    }
+    picked.sort_unstable();
+    picked
+        .into_iter()
+        .map(|offset| &code[offset..offset + CODE_SEQUENCE_LEN])
+        .collect()
+}
+
+impl NeExecutable {
+    /// Writes a YARA rule matching this module: the NE header fields as
+    /// conditions, and the module description, strings from the resources
+    /// and distinctive code sequences as strings. It's meant as a start to
+    /// be edited rather than a finished rule.
+    pub fn yara_rule(&self, codepage: Codepage) -> String {
+        let summary = self.summary();
+        let mut rule = String::new();
+        writeln!(rule, "rule {}", rule_name(&summary.module_name)).unwrap();
+        writeln!(rule, "{{").unwrap();
+        writeln!(rule, "    meta:").unwrap();
+        let meta = [
+            ("module", Some(codepage.decode(&summary.module_name))),
+            ("description", Some(codepage.decode(&summary.description))),
+            ("type", Some(summary.classification.to_string())),
+            ("sha256", Some(Digests::of(&self.file_data, false).sha256)),
+            ("imphash", self.imphash()),
+        ];
+        for (key, value) in meta {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                writeln!(
+                    rule,
+                    "        {} = \"{}\"",
+                    key,
+                    yara_text(value.as_bytes())
+                )
+                .unwrap();
+            }
+        }
+
+        let mut strings = Vec::new();
+        if !summary.description.is_empty() {
+            strings.push(format!(
+                "$description = \"{}\" ascii",
+                yara_text(&summary.description)
+            ));
+        }
+        let mut resource_strings = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|resource_type| &resource_type.resources)
+            .flat_map(|resource| find_strings(&resource.data, MIN_RESOURCE_STRING_LEN, codepage))
+            .map(|string| string.text)
+            .filter(|text| text.is_ascii())
+            .collect::<Vec<_>>();
+        // The longest strings are the least likely to occur elsewhere.
+        resource_strings.sort_by_key(|text| std::cmp::Reverse(text.len()));
+        resource_strings.dedup();
+        for (i, text) in resource_strings.iter().take(MAX_STRINGS).enumerate() {
+            strings.push(format!(
+                "$resource{} = \"{}\" ascii wide",
+                i + 1,
+                yara_text(text.as_bytes())
+            ));
+        }
+        let code = self.code_with_fixups();
+        for (i, sequence) in distinctive_sequences(&code, MAX_STRINGS)
+            .into_iter()
+            .enumerate()
+        {
+            let hex = sequence
+                .iter()
+                .map(|byte| match byte {
+                    Some(byte) => format!("{:02X}", byte),
+                    None => "??".to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            strings.push(format!("$code{} = {{ {} }}", i + 1, hex));
+        }
+        if !strings.is_empty() {
+            writeln!(rule, "    strings:").unwrap();
+            for string in &strings {
+                writeln!(rule, "        {}", string).unwrap();
+            }
+        }
+
+        let ne_header = &self.ne_header;
+        writeln!(rule, "    condition:").unwrap();
+        writeln!(rule, "        uint16(0) == 0x5A4D and").unwrap();
+        writeln!(rule, "        uint16(uint32(0x3C)) == 0x454E and").unwrap();
+        writeln!(
+            rule,
+            "        uint16(uint32(0x3C) + 0x0C) == 0x{:04X} and",
+            ne_header.flags.value()
+        )
+        .unwrap();
+        writeln!(
+            rule,
+            "        uint16(uint32(0x3C) + 0x1C) == {} and",
+            ne_header.segment_count.value()
+        )
+        .unwrap();
+        write!(
+            rule,
+            "        uint8(uint32(0x3C) + 0x36) == 0x{:02X}",
+            ne_header.target_os
+        )
+        .unwrap();
+        if strings.is_empty() {
+            writeln!(rule).unwrap();
+        } else {
+            writeln!(rule, " and").unwrap();
+            writeln!(rule, "        {} of them", strings.len().div_ceil(2)).unwrap();
+        }
+        writeln!(rule, "}}").unwrap();
+        rule
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yara_text() {
+        assert_eq!(yara_text(b"a\"b\\c\x01"), "a\\\"b\\\\c\\x01");
+        assert_eq!(rule_name(b"MY-DLL"), "ne_MY_DLL");
+    }
+
+    #[test]
+    fn test_distinctive_sequences() {
+        let mut code = vec![Some(0x90); 64];
+        for (i, byte) in code[20..36].iter_mut().enumerate() {
+            *byte = Some(i as u8);
+        }
+        code[22] = None;
+        let sequences = distinctive_sequences(&code, 1);
+        assert_eq!(sequences, [&code[20..36]]);
+    }
+}
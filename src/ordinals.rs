@@ -0,0 +1,83 @@
+//! Names of the functions exported by the core Windows 3.x DLLs.
+//!
+//! Applications usually import system functions by ordinal only, so the
+//! executable itself doesn't tell which function is called. The tables
+//! follow the export definitions of the DLLs shipped with Windows 3.1, and
+//! use the uppercase names found in their resident name tables.
+
+#[cfg(feature = "ordinals")]
+mod commdlg;
+#[cfg(feature = "ordinals")]
+mod gdi;
+#[cfg(feature = "ordinals")]
+mod kernel;
+#[cfg(feature = "ordinals")]
+mod keyboard;
+#[cfg(feature = "ordinals")]
+mod shell;
+#[cfg(feature = "ordinals")]
+mod sound;
+#[cfg(feature = "ordinals")]
+mod toolhelp;
+#[cfg(feature = "ordinals")]
+mod user;
+#[cfg(feature = "ordinals")]
+mod win87em;
+
+/// Looks up the name of a function exported by a well-known system DLL.
+///
+/// The module name is compared case-insensitively. Always returns `None`
+/// when built without the `ordinals` feature.
+#[cfg(feature = "ordinals")]
+pub fn lookup(module: &[u8], ordinal: u16) -> Option<&'static str> {
+    let table = match &module.to_ascii_uppercase()[..] {
+        b"KERNEL" => kernel::ORDINALS,
+        b"USER" => user::ORDINALS,
+        b"GDI" => gdi::ORDINALS,
+        b"KEYBOARD" => keyboard::ORDINALS,
+        b"SOUND" => sound::ORDINALS,
+        b"WIN87EM" => win87em::ORDINALS,
+        b"COMMDLG" => commdlg::ORDINALS,
+        b"SHELL" => shell::ORDINALS,
+        b"TOOLHELP" => toolhelp::ORDINALS,
+        _ => return None,
+    };
+    let pos = table.binary_search_by_key(&ordinal, |&(o, _)| o).ok()?;
+    Some(table[pos].1)
+}
+
+#[cfg(not(feature = "ordinals"))]
+pub fn lookup(_module: &[u8], _ordinal: u16) -> Option<&'static str> {
+    None
+}
+
+#[cfg(all(test, feature = "ordinals"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup(b"USER", 1), Some("MESSAGEBOX"));
+        assert_eq!(lookup(b"kernel", 3), Some("GETVERSION"));
+        assert_eq!(lookup(b"GDI", 45), Some("SELECTOBJECT"));
+        assert_eq!(lookup(b"USER", 9), None);
+        assert_eq!(lookup(b"VBRUN300", 1), None);
+    }
+
+    #[test]
+    fn test_tables_sorted() {
+        for table in [
+            kernel::ORDINALS,
+            user::ORDINALS,
+            gdi::ORDINALS,
+            keyboard::ORDINALS,
+            sound::ORDINALS,
+            win87em::ORDINALS,
+            commdlg::ORDINALS,
+            shell::ORDINALS,
+            toolhelp::ORDINALS,
+        ] {
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "GETOPENFILENAME"),
+    (2, "GETSAVEFILENAME"),
+    (5, "CHOOSECOLOR"),
+    (6, "FILEOPENDLGPROC"),
+    (7, "FILESAVEDLGPROC"),
+    (8, "COLORDLGPROC"),
+    (11, "FINDTEXT"),
+    (12, "REPLACETEXT"),
+    (13, "FINDTEXTDLGPROC"),
+    (14, "REPLACETEXTDLGPROC"),
+    (15, "CHOOSEFONT"),
+    (16, "FORMATCHARDLGPROC"),
+    (18, "FONTSTYLEENUMPROC"),
+    (19, "FONTFAMILYENUMPROC"),
+    (20, "PRINTDLG"),
+    (21, "PRINTDLGPROC"),
+    (22, "PRINTSETUPDLGPROC"),
+    (23, "EDITINTEGERONLY"),
+    (25, "WANTARROWS"),
+    (26, "COMMDLGEXTENDEDERROR"),
+    (27, "GETFILETITLE"),
+    (28, "WEP"),
+    (29, "DWLBSUBCLASS"),
+    (30, "DWUPARROWHACK"),
+    (31, "DWOKSUBCLASS"),
+];
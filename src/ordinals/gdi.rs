@@ -0,0 +1,244 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "SETBKCOLOR"),
+    (2, "SETBKMODE"),
+    (3, "SETMAPMODE"),
+    (4, "SETROP2"),
+    (5, "SETRELABS"),
+    (6, "SETPOLYFILLMODE"),
+    (7, "SETSTRETCHBLTMODE"),
+    (8, "SETTEXTCHARACTEREXTRA"),
+    (9, "SETTEXTCOLOR"),
+    (10, "SETTEXTJUSTIFICATION"),
+    (11, "SETWINDOWORG"),
+    (12, "SETWINDOWEXT"),
+    (13, "SETVIEWPORTORG"),
+    (14, "SETVIEWPORTEXT"),
+    (15, "OFFSETWINDOWORG"),
+    (16, "SCALEWINDOWEXT"),
+    (17, "OFFSETVIEWPORTORG"),
+    (18, "SCALEVIEWPORTEXT"),
+    (19, "LINETO"),
+    (20, "MOVETO"),
+    (21, "EXCLUDECLIPRECT"),
+    (22, "INTERSECTCLIPRECT"),
+    (23, "ARC"),
+    (24, "ELLIPSE"),
+    (25, "FLOODFILL"),
+    (26, "PIE"),
+    (27, "RECTANGLE"),
+    (28, "ROUNDRECT"),
+    (29, "PATBLT"),
+    (30, "SAVEDC"),
+    (31, "SETPIXEL"),
+    (32, "OFFSETCLIPRGN"),
+    (33, "TEXTOUT"),
+    (34, "BITBLT"),
+    (35, "STRETCHBLT"),
+    (36, "POLYGON"),
+    (37, "POLYLINE"),
+    (38, "ESCAPE"),
+    (39, "RESTOREDC"),
+    (40, "FILLRGN"),
+    (41, "FRAMERGN"),
+    (42, "INVERTRGN"),
+    (43, "PAINTRGN"),
+    (44, "SELECTCLIPRGN"),
+    (45, "SELECTOBJECT"),
+    (47, "COMBINERGN"),
+    (48, "CREATEBITMAP"),
+    (49, "CREATEBITMAPINDIRECT"),
+    (50, "CREATEBRUSHINDIRECT"),
+    (51, "CREATECOMPATIBLEBITMAP"),
+    (52, "CREATECOMPATIBLEDC"),
+    (53, "CREATEDC"),
+    (54, "CREATEELLIPTICRGN"),
+    (55, "CREATEELLIPTICRGNINDIRECT"),
+    (56, "CREATEFONT"),
+    (57, "CREATEFONTINDIRECT"),
+    (58, "CREATEHATCHBRUSH"),
+    (60, "CREATEPATTERNBRUSH"),
+    (61, "CREATEPEN"),
+    (62, "CREATEPENINDIRECT"),
+    (63, "CREATEPOLYGONRGN"),
+    (64, "CREATERECTRGN"),
+    (65, "CREATERECTRGNINDIRECT"),
+    (66, "CREATESOLIDBRUSH"),
+    (67, "DPTOLP"),
+    (68, "DELETEDC"),
+    (69, "DELETEOBJECT"),
+    (70, "ENUMFONTS"),
+    (71, "ENUMOBJECTS"),
+    (72, "EQUALRGN"),
+    (73, "EXCLUDEVISRECT"),
+    (74, "GETBITMAPBITS"),
+    (75, "GETBKCOLOR"),
+    (76, "GETBKMODE"),
+    (77, "GETCLIPBOX"),
+    (78, "GETCURRENTPOSITION"),
+    (79, "GETDCORG"),
+    (80, "GETDEVICECAPS"),
+    (81, "GETMAPMODE"),
+    (82, "GETOBJECT"),
+    (83, "GETPIXEL"),
+    (84, "GETPOLYFILLMODE"),
+    (85, "GETROP2"),
+    (86, "GETRELABS"),
+    (87, "GETSTOCKOBJECT"),
+    (88, "GETSTRETCHBLTMODE"),
+    (89, "GETTEXTCHARACTEREXTRA"),
+    (90, "GETTEXTCOLOR"),
+    (91, "GETTEXTEXTENT"),
+    (92, "GETTEXTFACE"),
+    (93, "GETTEXTMETRICS"),
+    (94, "GETVIEWPORTEXT"),
+    (95, "GETVIEWPORTORG"),
+    (96, "GETWINDOWEXT"),
+    (97, "GETWINDOWORG"),
+    (98, "INTERSECTVISRECT"),
+    (99, "LPTODP"),
+    (100, "LINEDDA"),
+    (101, "OFFSETRGN"),
+    (102, "OFFSETVISRGN"),
+    (103, "PTVISIBLE"),
+    (104, "RECTVISIBLEOLD"),
+    (105, "SELECTVISRGN"),
+    (106, "SETBITMAPBITS"),
+    (117, "SETDCORG"),
+    (119, "ADDFONTRESOURCE"),
+    (121, "DEATH"),
+    (122, "RESURRECTION"),
+    (123, "PLAYMETAFILE"),
+    (124, "GETMETAFILE"),
+    (125, "CREATEMETAFILE"),
+    (126, "CLOSEMETAFILE"),
+    (127, "DELETEMETAFILE"),
+    (128, "MULDIV"),
+    (129, "SAVEVISRGN"),
+    (130, "RESTOREVISRGN"),
+    (131, "INQUIREVISRGN"),
+    (132, "SETENVIRONMENT"),
+    (133, "GETENVIRONMENT"),
+    (134, "GETRGNBOX"),
+    (136, "REMOVEFONTRESOURCE"),
+    (148, "SETBRUSHORG"),
+    (149, "GETBRUSHORG"),
+    (150, "UNREALIZEOBJECT"),
+    (151, "COPYMETAFILE"),
+    (153, "CREATEIC"),
+    (154, "GETNEARESTCOLOR"),
+    (156, "CREATEDISCARDABLEBITMAP"),
+    (161, "PTINREGION"),
+    (162, "GETBITMAPDIMENSION"),
+    (163, "SETBITMAPDIMENSION"),
+    (169, "ISDCDIRTY"),
+    (170, "SETDCSTATUS"),
+    (172, "SETRECTRGN"),
+    (173, "GETCLIPRGN"),
+    (175, "ENUMMETAFILE"),
+    (176, "PLAYMETAFILERECORD"),
+    (179, "GETDCSTATE"),
+    (180, "SETDCSTATE"),
+    (181, "RECTINREGIONOLD"),
+    (190, "SETDCHOOK"),
+    (191, "GETDCHOOK"),
+    (192, "SETHOOKFLAGS"),
+    (193, "SETBOUNDSRECT"),
+    (194, "GETBOUNDSRECT"),
+    (240, "OPENJOB"),
+    (241, "WRITESPOOL"),
+    (242, "WRITEDIALOG"),
+    (243, "CLOSEJOB"),
+    (244, "DELETEJOB"),
+    (245, "GETSPOOLJOB"),
+    (246, "STARTSPOOLPAGE"),
+    (247, "ENDSPOOLPAGE"),
+    (248, "QUERYJOB"),
+    (250, "COPY"),
+    (253, "DELETESPOOLPAGE"),
+    (254, "SPOOLFILE"),
+    (307, "GETCHARABCWIDTHS"),
+    (308, "GETOUTLINETEXTMETRICS"),
+    (309, "GETGLYPHOUTLINE"),
+    (310, "CREATESCALABLEFONTRESOURCE"),
+    (311, "GETFONTDATA"),
+    (313, "GETRASTERIZERCAPS"),
+    (330, "ENUMFONTFAMILIES"),
+    (332, "GETKERNINGPAIRS"),
+    (345, "GETTEXTALIGN"),
+    (346, "SETTEXTALIGN"),
+    (348, "CHORD"),
+    (349, "SETMAPPERFLAGS"),
+    (350, "GETCHARWIDTH"),
+    (351, "EXTTEXTOUT"),
+    (352, "GETPHYSICALFONTHANDLE"),
+    (353, "GETASPECTRATIOFILTER"),
+    (354, "SHRINKGDIHEAP"),
+    (360, "CREATEPALETTE"),
+    (361, "GDISELECTPALETTE"),
+    (362, "GDIREALIZEPALETTE"),
+    (363, "GETPALETTEENTRIES"),
+    (364, "SETPALETTEENTRIES"),
+    (365, "REALIZEDEFAULTPALETTE"),
+    (366, "UPDATECOLORS"),
+    (367, "ANIMATEPALETTE"),
+    (368, "RESIZEPALETTE"),
+    (370, "GETNEARESTPALETTEINDEX"),
+    (372, "EXTFLOODFILL"),
+    (373, "SETSYSTEMPALETTEUSE"),
+    (374, "GETSYSTEMPALETTEUSE"),
+    (375, "GETSYSTEMPALETTEENTRIES"),
+    (376, "RESETDC"),
+    (377, "STARTDOC"),
+    (378, "ENDDOC"),
+    (379, "STARTPAGE"),
+    (380, "ENDPAGE"),
+    (381, "SETABORTPROC"),
+    (382, "ABORTDOC"),
+    (400, "FASTWINDOWFRAME"),
+    (401, "GDIMOVEBITMAP"),
+    (403, "GDIINIT2"),
+    (405, "FINALGDIINIT"),
+    (407, "CREATEUSERBITMAP"),
+    (409, "CREATEUSERDISCARDABLEBITMAP"),
+    (410, "ISVALIDMETAFILE"),
+    (411, "GETCURLOGFONT"),
+    (412, "ISDCCURRENTPALETTE"),
+    (439, "STRETCHDIBITS"),
+    (440, "SETDIBITS"),
+    (441, "GETDIBITS"),
+    (442, "CREATEDIBITMAP"),
+    (443, "SETDIBITSTODEVICE"),
+    (444, "CREATEROUNDRECTRGN"),
+    (445, "CREATEDIBPATTERNBRUSH"),
+    (449, "DEVICECOLORMATCH"),
+    (450, "POLYPOLYGON"),
+    (451, "CREATEPOLYPOLYGONRGN"),
+    (452, "GDISEEGDIDO"),
+    (460, "GDITASKTERMINATION"),
+    (461, "SETOBJECTOWNER"),
+    (462, "ISGDIOBJECT"),
+    (463, "MAKEOBJECTPRIVATE"),
+    (464, "FIXUPBOGUSPUBLISHERMETAFILE"),
+    (465, "RECTVISIBLE"),
+    (466, "RECTINREGION"),
+    (467, "UNICODETOANSI"),
+    (468, "GETBITMAPDIMENSIONEX"),
+    (469, "GETBRUSHORGEX"),
+    (470, "GETCURRENTPOSITIONEX"),
+    (471, "GETTEXTEXTENTPOINT"),
+    (472, "GETVIEWPORTEXTEX"),
+    (473, "GETVIEWPORTORGEX"),
+    (474, "GETWINDOWEXTEX"),
+    (475, "GETWINDOWORGEX"),
+    (476, "OFFSETVIEWPORTORGEX"),
+    (477, "OFFSETWINDOWORGEX"),
+    (478, "SETBITMAPDIMENSIONEX"),
+    (479, "SETVIEWPORTEXTEX"),
+    (480, "SETVIEWPORTORGEX"),
+    (481, "SETWINDOWEXTEX"),
+    (482, "SETWINDOWORGEX"),
+    (483, "MOVETOEX"),
+    (484, "SCALEVIEWPORTEXTEX"),
+    (485, "SCALEWINDOWEXTEX"),
+    (486, "GETASPECTRATIOFILTEREX"),
+];
@@ -0,0 +1,22 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "INQUIRE"),
+    (2, "ENABLE"),
+    (3, "DISABLE"),
+    (4, "TOASCII"),
+    (5, "ANSITOOEM"),
+    (6, "OEMTOANSI"),
+    (7, "SETSPEED"),
+    (100, "SCREENSWITCHENABLE"),
+    (126, "GETTABLESEG"),
+    (127, "NEWTABLE"),
+    (128, "OEMKEYSCAN"),
+    (129, "VKKEYSCAN"),
+    (130, "GETKEYBOARDTYPE"),
+    (131, "MAPVIRTUALKEY"),
+    (132, "GETKBCODEPAGE"),
+    (133, "GETKEYNAMETEXT"),
+    (134, "ANSITOOEMBUFF"),
+    (135, "OEMTOANSIBUFF"),
+    (136, "ENABLEKBSYSREQ"),
+    (137, "GETBIOSKEYPROC"),
+];
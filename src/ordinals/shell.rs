@@ -0,0 +1,24 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "REGOPENKEY"),
+    (2, "REGCREATEKEY"),
+    (3, "REGCLOSEKEY"),
+    (4, "REGDELETEKEY"),
+    (5, "REGSETVALUE"),
+    (6, "REGQUERYVALUE"),
+    (7, "REGENUMKEY"),
+    (9, "DRAGACCEPTFILES"),
+    (11, "DRAGQUERYFILE"),
+    (12, "DRAGFINISH"),
+    (13, "DRAGQUERYPOINT"),
+    (20, "SHELLEXECUTE"),
+    (21, "FINDEXECUTABLE"),
+    (22, "SHELLABOUT"),
+    (33, "ABOUTDLGPROC"),
+    (34, "EXTRACTICON"),
+    (36, "EXTRACTASSOCIATEDICON"),
+    (37, "DOENVIRONMENTSUBST"),
+    (38, "FINDENVIRONMENTSTRING"),
+    (39, "INTERNALEXTRACTICON"),
+    (102, "REGISTERSHELLHOOK"),
+    (103, "SHELLHOOKPROC"),
+];
@@ -0,0 +1,19 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "OPENSOUND"),
+    (2, "CLOSESOUND"),
+    (3, "SETVOICEQUEUESIZE"),
+    (4, "SETVOICENOTE"),
+    (5, "SETVOICEACCENT"),
+    (6, "SETVOICEENVELOPE"),
+    (7, "SETSOUNDNOISE"),
+    (8, "SETVOICESOUND"),
+    (9, "STARTSOUND"),
+    (10, "STOPSOUND"),
+    (11, "WAITSOUNDSTATE"),
+    (12, "SYNCALLVOICES"),
+    (13, "COUNTVOICENOTES"),
+    (14, "GETTHRESHOLDEVENT"),
+    (15, "GETTHRESHOLDSTATUS"),
+    (16, "SETVOICETHRESHOLD"),
+    (17, "DOBEEP"),
+];
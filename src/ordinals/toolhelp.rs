@@ -0,0 +1,39 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (50, "GLOBALHANDLETOSEL"),
+    (51, "GLOBALFIRST"),
+    (52, "GLOBALNEXT"),
+    (53, "GLOBALINFO"),
+    (54, "GLOBALENTRYHANDLE"),
+    (55, "GLOBALENTRYMODULE"),
+    (56, "LOCALINFO"),
+    (57, "LOCALFIRST"),
+    (58, "LOCALNEXT"),
+    (59, "MODULEFIRST"),
+    (60, "MODULENEXT"),
+    (61, "MODULEFINDNAME"),
+    (62, "MODULEFINDHANDLE"),
+    (63, "TASKFIRST"),
+    (64, "TASKNEXT"),
+    (65, "TASKFINDHANDLE"),
+    (66, "STACKTRACEFIRST"),
+    (67, "STACKTRACECSIPFIRST"),
+    (68, "STACKTRACENEXT"),
+    (69, "CLASSFIRST"),
+    (70, "CLASSNEXT"),
+    (71, "SYSTEMHEAPINFO"),
+    (72, "MEMMANINFO"),
+    (73, "NOTIFYREGISTER"),
+    (74, "NOTIFYUNREGISTER"),
+    (75, "INTERRUPTREGISTER"),
+    (76, "INTERRUPTUNREGISTER"),
+    (77, "TERMINATEAPP"),
+    (78, "MEMORYREAD"),
+    (79, "MEMORYWRITE"),
+    (80, "TIMERCOUNT"),
+    (81, "TASKSETCSIP"),
+    (82, "TASKGETCSIP"),
+    (83, "TASKSWITCH"),
+    (84, "LOCAL32INFO"),
+    (85, "LOCAL32FIRST"),
+    (86, "LOCAL32NEXT"),
+];
@@ -0,0 +1,373 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "MESSAGEBOX"),
+    (2, "OLDEXITWINDOWS"),
+    (3, "ENABLEOEMLAYER"),
+    (4, "DISABLEOEMLAYER"),
+    (5, "INITAPP"),
+    (6, "POSTQUITMESSAGE"),
+    (7, "EXITWINDOWS"),
+    (10, "SETTIMER"),
+    (11, "SETSYSTEMTIMER"),
+    (12, "KILLTIMER"),
+    (13, "GETTICKCOUNT"),
+    (14, "GETTIMERRESOLUTION"),
+    (15, "GETCURRENTTIME"),
+    (16, "CLIPCURSOR"),
+    (17, "GETCURSORPOS"),
+    (18, "SETCAPTURE"),
+    (19, "RELEASECAPTURE"),
+    (20, "SETDOUBLECLICKTIME"),
+    (21, "GETDOUBLECLICKTIME"),
+    (22, "SETFOCUS"),
+    (23, "GETFOCUS"),
+    (24, "REMOVEPROP"),
+    (25, "GETPROP"),
+    (26, "SETPROP"),
+    (27, "ENUMPROPS"),
+    (28, "CLIENTTOSCREEN"),
+    (29, "SCREENTOCLIENT"),
+    (30, "WINDOWFROMPOINT"),
+    (31, "ISICONIC"),
+    (32, "GETWINDOWRECT"),
+    (33, "GETCLIENTRECT"),
+    (34, "ENABLEWINDOW"),
+    (35, "ISWINDOWENABLED"),
+    (36, "GETWINDOWTEXT"),
+    (37, "SETWINDOWTEXT"),
+    (38, "GETWINDOWTEXTLENGTH"),
+    (39, "BEGINPAINT"),
+    (40, "ENDPAINT"),
+    (41, "CREATEWINDOW"),
+    (42, "SHOWWINDOW"),
+    (43, "CLOSEWINDOW"),
+    (44, "OPENICON"),
+    (45, "BRINGWINDOWTOTOP"),
+    (46, "GETPARENT"),
+    (47, "ISWINDOW"),
+    (48, "ISCHILD"),
+    (49, "ISWINDOWVISIBLE"),
+    (50, "FINDWINDOW"),
+    (52, "ANYPOPUP"),
+    (53, "DESTROYWINDOW"),
+    (54, "ENUMWINDOWS"),
+    (55, "ENUMCHILDWINDOWS"),
+    (56, "MOVEWINDOW"),
+    (57, "REGISTERCLASS"),
+    (58, "GETCLASSNAME"),
+    (59, "SETACTIVEWINDOW"),
+    (60, "GETACTIVEWINDOW"),
+    (61, "SCROLLWINDOW"),
+    (62, "SETSCROLLPOS"),
+    (63, "GETSCROLLPOS"),
+    (64, "SETSCROLLRANGE"),
+    (65, "GETSCROLLRANGE"),
+    (66, "GETDC"),
+    (67, "GETWINDOWDC"),
+    (68, "RELEASEDC"),
+    (69, "SETCURSOR"),
+    (70, "SETCURSORPOS"),
+    (71, "SHOWCURSOR"),
+    (72, "SETRECT"),
+    (73, "SETRECTEMPTY"),
+    (74, "COPYRECT"),
+    (75, "ISRECTEMPTY"),
+    (76, "PTINRECT"),
+    (77, "OFFSETRECT"),
+    (78, "INFLATERECT"),
+    (79, "INTERSECTRECT"),
+    (80, "UNIONRECT"),
+    (81, "FILLRECT"),
+    (82, "INVERTRECT"),
+    (83, "FRAMERECT"),
+    (84, "DRAWICON"),
+    (85, "DRAWTEXT"),
+    (87, "DIALOGBOX"),
+    (88, "ENDDIALOG"),
+    (89, "CREATEDIALOG"),
+    (90, "ISDIALOGMESSAGE"),
+    (91, "GETDLGITEM"),
+    (92, "SETDLGITEMTEXT"),
+    (93, "GETDLGITEMTEXT"),
+    (94, "SETDLGITEMINT"),
+    (95, "GETDLGITEMINT"),
+    (96, "CHECKRADIOBUTTON"),
+    (97, "CHECKDLGBUTTON"),
+    (98, "ISDLGBUTTONCHECKED"),
+    (99, "DLGDIRSELECT"),
+    (100, "DLGDIRLIST"),
+    (101, "SENDDLGITEMMESSAGE"),
+    (102, "ADJUSTWINDOWRECT"),
+    (103, "MAPDIALOGRECT"),
+    (104, "MESSAGEBEEP"),
+    (105, "FLASHWINDOW"),
+    (106, "GETKEYSTATE"),
+    (107, "DEFWINDOWPROC"),
+    (108, "GETMESSAGE"),
+    (109, "PEEKMESSAGE"),
+    (110, "POSTMESSAGE"),
+    (111, "SENDMESSAGE"),
+    (112, "WAITMESSAGE"),
+    (113, "TRANSLATEMESSAGE"),
+    (114, "DISPATCHMESSAGE"),
+    (115, "REPLYMESSAGE"),
+    (116, "POSTAPPMESSAGE"),
+    (118, "REGISTERWINDOWMESSAGE"),
+    (119, "GETMESSAGEPOS"),
+    (120, "GETMESSAGETIME"),
+    (121, "SETWINDOWSHOOK"),
+    (122, "CALLWINDOWPROC"),
+    (123, "CALLMSGFILTER"),
+    (124, "UPDATEWINDOW"),
+    (125, "INVALIDATERECT"),
+    (126, "INVALIDATERGN"),
+    (127, "VALIDATERECT"),
+    (128, "VALIDATERGN"),
+    (129, "GETCLASSWORD"),
+    (130, "SETCLASSWORD"),
+    (131, "GETCLASSLONG"),
+    (132, "SETCLASSLONG"),
+    (133, "GETWINDOWWORD"),
+    (134, "SETWINDOWWORD"),
+    (135, "GETWINDOWLONG"),
+    (136, "SETWINDOWLONG"),
+    (137, "OPENCLIPBOARD"),
+    (138, "CLOSECLIPBOARD"),
+    (139, "EMPTYCLIPBOARD"),
+    (140, "GETCLIPBOARDOWNER"),
+    (141, "SETCLIPBOARDDATA"),
+    (142, "GETCLIPBOARDDATA"),
+    (143, "COUNTCLIPBOARDFORMATS"),
+    (144, "ENUMCLIPBOARDFORMATS"),
+    (145, "REGISTERCLIPBOARDFORMAT"),
+    (146, "GETCLIPBOARDFORMATNAME"),
+    (147, "SETCLIPBOARDVIEWER"),
+    (148, "GETCLIPBOARDVIEWER"),
+    (149, "CHANGECLIPBOARDCHAIN"),
+    (150, "LOADMENU"),
+    (151, "CREATEMENU"),
+    (152, "DESTROYMENU"),
+    (153, "CHANGEMENU"),
+    (154, "CHECKMENUITEM"),
+    (155, "ENABLEMENUITEM"),
+    (156, "GETSYSTEMMENU"),
+    (157, "GETMENU"),
+    (158, "SETMENU"),
+    (159, "GETSUBMENU"),
+    (160, "DRAWMENUBAR"),
+    (161, "GETMENUSTRING"),
+    (162, "HILITEMENUITEM"),
+    (163, "CREATECARET"),
+    (164, "DESTROYCARET"),
+    (165, "SETCARETPOS"),
+    (166, "HIDECARET"),
+    (167, "SHOWCARET"),
+    (168, "SETCARETBLINKTIME"),
+    (169, "GETCARETBLINKTIME"),
+    (170, "ARRANGEICONICWINDOWS"),
+    (171, "WINHELP"),
+    (172, "SWITCHTOTHISWINDOW"),
+    (173, "LOADCURSOR"),
+    (174, "LOADICON"),
+    (175, "LOADBITMAP"),
+    (176, "LOADSTRING"),
+    (177, "LOADACCELERATORS"),
+    (178, "TRANSLATEACCELERATOR"),
+    (179, "GETSYSTEMMETRICS"),
+    (180, "GETSYSCOLOR"),
+    (181, "SETSYSCOLORS"),
+    (183, "GETCARETPOS"),
+    (184, "QUERYSENDMESSAGE"),
+    (185, "GRAYSTRING"),
+    (186, "SWAPMOUSEBUTTON"),
+    (187, "ENDMENU"),
+    (188, "SETSYSMODALWINDOW"),
+    (189, "GETSYSMODALWINDOW"),
+    (190, "GETUPDATERECT"),
+    (191, "CHILDWINDOWFROMPOINT"),
+    (192, "INSENDMESSAGE"),
+    (193, "ISCLIPBOARDFORMATAVAILABLE"),
+    (194, "DLGDIRSELECTCOMBOBOX"),
+    (195, "DLGDIRLISTCOMBOBOX"),
+    (196, "TABBEDTEXTOUT"),
+    (197, "GETTABBEDTEXTEXTENT"),
+    (198, "CASCADECHILDWINDOWS"),
+    (199, "TILECHILDWINDOWS"),
+    (200, "OPENCOMM"),
+    (201, "SETCOMMSTATE"),
+    (202, "GETCOMMSTATE"),
+    (203, "GETCOMMERROR"),
+    (204, "READCOMM"),
+    (205, "WRITECOMM"),
+    (206, "TRANSMITCOMMCHAR"),
+    (207, "CLOSECOMM"),
+    (208, "SETCOMMEVENTMASK"),
+    (209, "GETCOMMEVENTMASK"),
+    (210, "SETCOMMBREAK"),
+    (211, "CLEARCOMMBREAK"),
+    (212, "UNGETCOMMCHAR"),
+    (213, "BUILDCOMMDCB"),
+    (214, "ESCAPECOMMFUNCTION"),
+    (215, "FLUSHCOMM"),
+    (216, "USERSEEUSERDO"),
+    (217, "LOOKUPMENUHANDLE"),
+    (218, "DIALOGBOXINDIRECT"),
+    (219, "CREATEDIALOGINDIRECT"),
+    (220, "LOADMENUINDIRECT"),
+    (221, "SCROLLDC"),
+    (222, "GETKEYBOARDSTATE"),
+    (223, "SETKEYBOARDSTATE"),
+    (224, "GETWINDOWTASK"),
+    (225, "ENUMTASKWINDOWS"),
+    (226, "LOCKINPUT"),
+    (227, "GETNEXTDLGGROUPITEM"),
+    (228, "GETNEXTDLGTABITEM"),
+    (229, "GETTOPWINDOW"),
+    (230, "GETNEXTWINDOW"),
+    (231, "GETSYSTEMDEBUGSTATE"),
+    (232, "SETWINDOWPOS"),
+    (233, "SETPARENT"),
+    (234, "UNHOOKWINDOWSHOOK"),
+    (235, "DEFHOOKPROC"),
+    (236, "GETCAPTURE"),
+    (237, "GETUPDATERGN"),
+    (238, "EXCLUDEUPDATERGN"),
+    (239, "DIALOGBOXPARAM"),
+    (240, "DIALOGBOXINDIRECTPARAM"),
+    (241, "CREATEDIALOGPARAM"),
+    (242, "CREATEDIALOGINDIRECTPARAM"),
+    (243, "GETDIALOGBASEUNITS"),
+    (244, "EQUALRECT"),
+    (245, "ENABLECOMMNOTIFICATION"),
+    (246, "EXITWINDOWSEXEC"),
+    (247, "GETCURSOR"),
+    (248, "GETOPENCLIPBOARDWINDOW"),
+    (249, "GETASYNCKEYSTATE"),
+    (250, "GETMENUSTATE"),
+    (251, "SENDDRIVERMESSAGE"),
+    (252, "OPENDRIVER"),
+    (253, "CLOSEDRIVER"),
+    (254, "GETDRIVERMODULEHANDLE"),
+    (255, "DEFDRIVERPROC"),
+    (256, "GETDRIVERINFO"),
+    (257, "GETNEXTDRIVER"),
+    (258, "MAPWINDOWPOINTS"),
+    (259, "BEGINDEFERWINDOWPOS"),
+    (260, "DEFERWINDOWPOS"),
+    (261, "ENDDEFERWINDOWPOS"),
+    (262, "GETWINDOW"),
+    (263, "GETMENUITEMCOUNT"),
+    (264, "GETMENUITEMID"),
+    (265, "SHOWOWNEDPOPUPS"),
+    (266, "SETMESSAGEQUEUE"),
+    (267, "SHOWSCROLLBAR"),
+    (268, "GLOBALADDATOM"),
+    (269, "GLOBALDELETEATOM"),
+    (270, "GLOBALFINDATOM"),
+    (271, "GLOBALGETATOMNAME"),
+    (272, "ISZOOMED"),
+    (273, "CONTROLPANELINFO"),
+    (274, "GETNEXTQUEUEWINDOW"),
+    (275, "REPAINTSCREEN"),
+    (276, "LOCKMYTASK"),
+    (277, "GETDLGCTRLID"),
+    (278, "GETDESKTOPHWND"),
+    (279, "OLDSETDESKPATTERN"),
+    (280, "SETSYSTEMMENU"),
+    (281, "GETSYSCOLORBRUSH"),
+    (282, "SELECTPALETTE"),
+    (283, "REALIZEPALETTE"),
+    (284, "GETFREESYSTEMRESOURCES"),
+    (286, "GETDESKTOPWINDOW"),
+    (287, "GETLASTACTIVEPOPUP"),
+    (288, "GETMESSAGEEXTRAINFO"),
+    (289, "KEYBD_EVENT"),
+    (290, "REDRAWWINDOW"),
+    (291, "SETWINDOWSHOOKEX"),
+    (292, "UNHOOKWINDOWSHOOKEX"),
+    (293, "CALLNEXTHOOKEX"),
+    (294, "LOCKWINDOWUPDATE"),
+    (299, "MOUSE_EVENT"),
+    (308, "DEFDLGPROC"),
+    (309, "GETCLIPCURSOR"),
+    (314, "SIGNALPROC"),
+    (319, "SCROLLWINDOWEX"),
+    (320, "SYSERRORBOX"),
+    (321, "SETEVENTHOOK"),
+    (322, "WINOLDAPPHACKOMATIC"),
+    (323, "GETMESSAGE2"),
+    (324, "FILLWINDOW"),
+    (325, "PAINTRECT"),
+    (326, "GETCONTROLBRUSH"),
+    (331, "ENABLEHARDWAREINPUT"),
+    (332, "USERYIELD"),
+    (333, "ISUSERIDLE"),
+    (334, "GETQUEUESTATUS"),
+    (335, "GETINPUTSTATE"),
+    (336, "LOADCURSORICONHANDLER"),
+    (337, "GETMOUSEEVENTPROC"),
+    (343, "GETFILEPORTNAME"),
+    (356, "LOADDIBCURSORHANDLER"),
+    (357, "LOADDIBICONHANDLER"),
+    (358, "ISMENU"),
+    (359, "GETDCEX"),
+    (362, "DCHOOK"),
+    (368, "COPYICON"),
+    (369, "COPYCURSOR"),
+    (370, "GETWINDOWPLACEMENT"),
+    (371, "SETWINDOWPLACEMENT"),
+    (372, "GETINTERNALICONHEADER"),
+    (373, "SUBTRACTRECT"),
+    (400, "FINALUSERINIT"),
+    (402, "GETPRIORITYCLIPBOARDFORMAT"),
+    (403, "UNREGISTERCLASS"),
+    (404, "GETCLASSINFO"),
+    (406, "CREATECURSOR"),
+    (407, "CREATEICON"),
+    (408, "CREATECURSORICONINDIRECT"),
+    (410, "INSERTMENU"),
+    (411, "APPENDMENU"),
+    (412, "REMOVEMENU"),
+    (413, "DELETEMENU"),
+    (414, "MODIFYMENU"),
+    (415, "CREATEPOPUPMENU"),
+    (416, "TRACKPOPUPMENU"),
+    (417, "GETMENUCHECKMARKDIMENSIONS"),
+    (418, "SETMENUITEMBITMAPS"),
+    (420, "_WSPRINTF"),
+    (421, "WVSPRINTF"),
+    (422, "DLGDIRSELECTEX"),
+    (423, "DLGDIRSELECTCOMBOBOXEX"),
+    (430, "LSTRCMP"),
+    (431, "ANSIUPPER"),
+    (432, "ANSILOWER"),
+    (433, "ISCHARALPHA"),
+    (434, "ISCHARALPHANUMERIC"),
+    (435, "ISCHARUPPER"),
+    (436, "ISCHARLOWER"),
+    (437, "ANSIUPPERBUFF"),
+    (438, "ANSILOWERBUFF"),
+    (445, "DEFFRAMEPROC"),
+    (447, "DEFMDICHILDPROC"),
+    (451, "TRANSLATEMDISYSACCEL"),
+    (452, "CREATEWINDOWEX"),
+    (454, "ADJUSTWINDOWRECTEX"),
+    (455, "GETICONID"),
+    (456, "LOADICONHANDLER"),
+    (457, "DESTROYICON"),
+    (458, "DESTROYCURSOR"),
+    (459, "DUMPICON"),
+    (460, "GETINTERNALWINDOWPOS"),
+    (461, "SETINTERNALWINDOWPOS"),
+    (462, "CALCCHILDSCROLL"),
+    (463, "SCROLLCHILDREN"),
+    (464, "DRAGOBJECT"),
+    (465, "DRAGDETECT"),
+    (466, "DRAWFOCUSRECT"),
+    (470, "STRINGFUNC"),
+    (471, "LSTRCMPI"),
+    (472, "ANSINEXT"),
+    (473, "ANSIPREV"),
+    (480, "GETUSERLOCALOBJTYPE"),
+    (482, "ENABLESCROLLBAR"),
+    (483, "SYSTEMPARAMETERSINFO"),
+];
@@ -0,0 +1,6 @@
+pub(super) static ORDINALS: &[(u16, &str)] = &[
+    (1, "__FPMATH"),
+    (3, "__WINEM87INFO"),
+    (4, "__WINEM87RESTORE"),
+    (5, "__WINEM87SAVE"),
+];
@@ -0,0 +1,566 @@
+use std::io::{self, Write};
+
+use bytemuck::{Pod, Zeroable};
+use log::warn;
+
+use crate::util::bytes::{cstr_at, u16_at, u32_at, u64_at};
+use crate::util::endian::{Lu16, Lu32};
+
+/// The PE signature and the COFF file header that follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PeHeader {
+    /// `PE\0\0`
+    pub signature: [u8; 4],
+    pub machine: Lu16,
+    pub number_of_sections: Lu16,
+    pub time_date_stamp: Lu32,
+    pub pointer_to_symbol_table: Lu32,
+    pub number_of_symbols: Lu32,
+    pub size_of_optional_header: Lu16,
+    pub characteristics: Lu16,
+}
+
+/// The image is a DLL.
+pub const FILE_DLL: u16 = 0x2000;
+
+/// The magic of the PE32 optional header.
+pub const PE32_MAGIC: u16 = 0x010B;
+/// The magic of the PE32+ optional header.
+pub const PE32_PLUS_MAGIC: u16 = 0x020B;
+
+impl PeHeader {
+    pub fn read(data: &[u8], lfanew: usize) -> io::Result<Self> {
+        let header = data
+            .get(lfanew..lfanew + std::mem::size_of::<Self>())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Truncated PE header"))?;
+        let header = bytemuck::pod_read_unaligned::<Self>(header);
+        if header.signature != *b"PE\0\0" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid magic"));
+        }
+        Ok(header)
+    }
+
+    pub fn is_dll(&self) -> bool {
+        (self.characteristics.value() & FILE_DLL) != 0
+    }
+}
+
+/// Returns the name of a COFF machine type.
+pub fn machine_name(machine: u16) -> Option<&'static str> {
+    Some(match machine {
+        0x014C => "i386",
+        0x0166 => "MIPS R4000",
+        0x0184 => "Alpha",
+        0x01C0 => "ARM",
+        0x01C2 => "Thumb",
+        0x01C4 => "ARMv7",
+        0x01F0 => "PowerPC",
+        0x0200 => "Itanium",
+        0x8664 => "x86-64",
+        0xAA64 => "ARM64",
+        _ => return None,
+    })
+}
+
+/// Returns the name of a PE subsystem.
+pub fn subsystem_name(subsystem: u16) -> Option<&'static str> {
+    Some(match subsystem {
+        1 => "native",
+        2 => "Windows GUI",
+        3 => "Windows console",
+        5 => "OS/2 console",
+        7 => "POSIX console",
+        9 => "Windows CE GUI",
+        10 => "EFI application",
+        _ => return None,
+    })
+}
+
+/// A section header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct SectionHeader {
+    pub name: [u8; 8],
+    pub virtual_size: Lu32,
+    pub virtual_address: Lu32,
+    pub size_of_raw_data: Lu32,
+    pub pointer_to_raw_data: Lu32,
+    pub pointer_to_relocations: Lu32,
+    pub pointer_to_linenumbers: Lu32,
+    pub number_of_relocations: Lu16,
+    pub number_of_linenumbers: Lu16,
+    pub characteristics: Lu32,
+}
+
+impl SectionHeader {
+    /// Returns the name without the padding.
+    pub fn name(&self) -> &[u8] {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(8);
+        &self.name[..len]
+    }
+}
+
+/// The fields of the optional header that are shown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionalHeader {
+    pub magic: u16,
+    pub entry_point: u32,
+    pub image_base: u64,
+    pub subsystem: u16,
+    /// The address and size of each data directory.
+    pub data_directories: Vec<(u32, u32)>,
+}
+
+/// The index of the export directory among the data directories.
+pub const EXPORT_DIRECTORY: usize = 0;
+/// The index of the import directory among the data directories.
+pub const IMPORT_DIRECTORY: usize = 1;
+
+/// A function in the export directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeExport {
+    pub ordinal: u32,
+    pub name: Option<Vec<u8>>,
+    pub address: u32,
+    /// The `MODULE.NAME` the export is forwarded to, if any.
+    pub forwarder: Option<Vec<u8>>,
+}
+
+/// The export directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportDirectory {
+    pub name: Vec<u8>,
+    pub exports: Vec<PeExport>,
+}
+
+/// A function imported by ordinal or by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeImport {
+    Ordinal(u16),
+    Name { hint: u16, name: Vec<u8> },
+}
+
+/// The functions imported from a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportedModule {
+    pub name: Vec<u8>,
+    pub imports: Vec<PeImport>,
+}
+
+/// A Portable Executable, parsed as far as needed to tell what it is and
+/// what it links against.
+#[derive(Debug, Clone)]
+pub struct PeExecutable {
+    pub header: PeHeader,
+    pub optional_header: Option<OptionalHeader>,
+    pub sections: Vec<SectionHeader>,
+    pub exports: Option<ExportDirectory>,
+    pub imported_modules: Vec<ImportedModule>,
+}
+
+impl OptionalHeader {
+    fn read(optional: &[u8]) -> Option<Self> {
+        let magic = u16_at(optional, 0)?;
+        let (image_base, directories_start) = match magic {
+            PE32_MAGIC => (u32_at(optional, 28)? as u64, 96),
+            PE32_PLUS_MAGIC => (u64_at(optional, 24)?, 112),
+            _ => (0, optional.len()),
+        };
+        let count = u32_at(optional, directories_start - 4).unwrap_or(0) as usize;
+        let data_directories = (0..count.min(16))
+            .map_while(|i| {
+                let pos = directories_start + i * 8;
+                Some((u32_at(optional, pos)?, u32_at(optional, pos + 4)?))
+            })
+            .collect();
+        Some(Self {
+            magic,
+            entry_point: u32_at(optional, 16).unwrap_or(0),
+            image_base,
+            subsystem: u16_at(optional, 68).unwrap_or(0),
+            data_directories,
+        })
+    }
+}
+
+impl PeExecutable {
+    pub fn read(data: &[u8], lfanew: usize) -> io::Result<Self> {
+        let header = PeHeader::read(data, lfanew)?;
+        let start = lfanew + std::mem::size_of::<PeHeader>();
+        let size = header.size_of_optional_header.value() as usize;
+        let optional_header = data.get(start..start + size).and_then(OptionalHeader::read);
+
+        let table = start + size;
+        let sections = (0..header.number_of_sections.value() as usize)
+            .map(|i| {
+                let pos = table + i * std::mem::size_of::<SectionHeader>();
+                data.get(pos..pos + std::mem::size_of::<SectionHeader>())
+                    .map(bytemuck::pod_read_unaligned::<SectionHeader>)
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "Truncated section table")
+                    })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut exe = Self {
+            header,
+            optional_header,
+            sections,
+            exports: None,
+            imported_modules: Vec::new(),
+        };
+        if let Some((rva, size)) = exe.data_directory(EXPORT_DIRECTORY) {
+            exe.exports = exe.read_exports(data, rva, size);
+            if exe.exports.is_none() {
+                warn!("export directory at 0x{:08X} is out of the file", rva);
+            }
+        }
+        if let Some((rva, _)) = exe.data_directory(IMPORT_DIRECTORY) {
+            match exe.read_imports(data, rva) {
+                Some(imported_modules) => exe.imported_modules = imported_modules,
+                None => warn!("import directory at 0x{:08X} is out of the file", rva),
+            }
+        }
+        Ok(exe)
+    }
+
+    pub fn is_pe32_plus(&self) -> bool {
+        matches!(&self.optional_header, Some(optional) if optional.magic == PE32_PLUS_MAGIC)
+    }
+
+    /// Returns the address and size of a data directory that is present.
+    pub fn data_directory(&self, index: usize) -> Option<(u32, u32)> {
+        let &(rva, size) = self.optional_header.as_ref()?.data_directories.get(index)?;
+        Some((rva, size)).filter(|_| rva != 0)
+    }
+
+    /// Converts a relative virtual address to a file offset through the
+    /// section that contains it.
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        let section = self.sections.iter().find(|section| {
+            let start = section.virtual_address.value();
+            let size = section
+                .virtual_size
+                .value()
+                .max(section.size_of_raw_data.value());
+            (start..start.saturating_add(size)).contains(&rva)
+        })?;
+        let delta = rva - section.virtual_address.value();
+        if delta >= section.size_of_raw_data.value() {
+            return None;
+        }
+        Some(section.pointer_to_raw_data.value() as usize + delta as usize)
+    }
+
+    fn read_exports(&self, data: &[u8], rva: u32, size: u32) -> Option<ExportDirectory> {
+        let dir = self.rva_to_offset(rva)?;
+        let name = cstr_at(data, self.rva_to_offset(u32_at(data, dir + 12)?)?)?.to_vec();
+        let ordinal_base = u32_at(data, dir + 16)?;
+        let function_count = u32_at(data, dir + 20)? as usize;
+        let name_count = u32_at(data, dir + 24)? as usize;
+        let functions = self.rva_to_offset(u32_at(data, dir + 28)?)?;
+        let names = u32_at(data, dir + 32)?;
+        let ordinals = u32_at(data, dir + 36)?;
+
+        let mut export_names = vec![None; function_count.min(data.len() / 4)];
+        if name_count > 0 {
+            let names = self.rva_to_offset(names)?;
+            let ordinals = self.rva_to_offset(ordinals)?;
+            for i in 0..name_count.min(data.len() / 4) {
+                let index = u16_at(data, ordinals + i * 2)? as usize;
+                let name = cstr_at(data, self.rva_to_offset(u32_at(data, names + i * 4)?)?)?;
+                if let Some(slot) = export_names.get_mut(index) {
+                    *slot = Some(name.to_vec());
+                }
+            }
+        }
+
+        let mut exports = Vec::new();
+        for (i, name) in export_names.into_iter().enumerate() {
+            let address = u32_at(data, functions + i * 4)?;
+            if address == 0 {
+                continue;
+            }
+            // Addresses within the export directory point at forwarder strings.
+            let forwarder = if (rva..rva.saturating_add(size)).contains(&address) {
+                self.rva_to_offset(address)
+                    .and_then(|pos| cstr_at(data, pos))
+                    .map(<[u8]>::to_vec)
+            } else {
+                None
+            };
+            exports.push(PeExport {
+                ordinal: ordinal_base.wrapping_add(i as u32),
+                name,
+                address,
+                forwarder,
+            });
+        }
+        Some(ExportDirectory { name, exports })
+    }
+
+    fn read_imports(&self, data: &[u8], rva: u32) -> Option<Vec<ImportedModule>> {
+        let mut pos = self.rva_to_offset(rva)?;
+        let thunk_size = if self.is_pe32_plus() { 8 } else { 4 };
+        let mut modules = Vec::new();
+        loop {
+            let lookup = u32_at(data, pos)?;
+            let name = u32_at(data, pos + 12)?;
+            let address = u32_at(data, pos + 16)?;
+            if lookup == 0 && name == 0 && address == 0 {
+                break;
+            }
+            pos += 20;
+            let name = self
+                .rva_to_offset(name)
+                .and_then(|pos| cstr_at(data, pos))
+                .unwrap_or_default()
+                .to_vec();
+            // Bound imports only keep the names in the lookup table.
+            let thunks = if lookup != 0 { lookup } else { address };
+            let mut imports = Vec::new();
+            if let Some(mut thunk) = self.rva_to_offset(thunks) {
+                loop {
+                    let value = if thunk_size == 8 {
+                        u64_at(data, thunk)
+                    } else {
+                        u32_at(data, thunk).map(u64::from)
+                    };
+                    let value = match value {
+                        Some(0) | None => break,
+                        Some(value) => value,
+                    };
+                    thunk += thunk_size;
+                    if value >> (thunk_size * 8 - 1) != 0 {
+                        imports.push(PeImport::Ordinal(value as u16));
+                    } else if let Some(hint_name) = self.rva_to_offset(value as u32) {
+                        imports.push(PeImport::Name {
+                            hint: u16_at(data, hint_name).unwrap_or(0),
+                            name: cstr_at(data, hint_name + 2).unwrap_or_default().to_vec(),
+                        });
+                    }
+                }
+            }
+            modules.push(ImportedModule { name, imports });
+        }
+        Some(modules)
+    }
+
+    /// Prints the COFF header, the basic fields of the optional header, the
+    /// sections, the exports and the imports.
+    pub fn describe<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let header = &self.header;
+        let machine = header.machine.value();
+        writeln!(w, "File Type: Portable Executable")?;
+        writeln!(w, "Header:")?;
+        writeln!(
+            w,
+            "    Machine: {} (0x{:04X})",
+            machine_name(machine).unwrap_or("unknown"),
+            machine
+        )?;
+        writeln!(w, "    Sections: {}", header.number_of_sections.value())?;
+        writeln!(
+            w,
+            "    Time stamp: 0x{:08X}",
+            header.time_date_stamp.value()
+        )?;
+        writeln!(
+            w,
+            "    Characteristics: 0x{:04X}{}",
+            header.characteristics.value(),
+            if header.is_dll() { " (DLL)" } else { "" }
+        )?;
+        match &self.optional_header {
+            Some(optional) if matches!(optional.magic, PE32_MAGIC | PE32_PLUS_MAGIC) => {
+                if optional.magic == PE32_MAGIC {
+                    writeln!(w, "    Optional header: PE32")?;
+                } else {
+                    writeln!(w, "    Optional header: PE32+")?;
+                }
+                writeln!(w, "    Entry point (RVA): 0x{:08X}", optional.entry_point)?;
+                writeln!(w, "    Image base: 0x{:X}", optional.image_base)?;
+                writeln!(
+                    w,
+                    "    Subsystem: {} ({})",
+                    subsystem_name(optional.subsystem).unwrap_or("unknown"),
+                    optional.subsystem
+                )?;
+            }
+            Some(optional) => {
+                writeln!(
+                    w,
+                    "    Optional header: unknown (magic 0x{:04X})",
+                    optional.magic
+                )?;
+            }
+            None => writeln!(w, "    Optional header: none")?,
+        }
+
+        for (i, section) in self.sections.iter().enumerate() {
+            writeln!(w, "Section #{}:", i)?;
+            writeln!(w, "    Name: {}", String::from_utf8_lossy(section.name()))?;
+            writeln!(
+                w,
+                "    Virtual address: 0x{:08X}",
+                section.virtual_address.value()
+            )?;
+            writeln!(
+                w,
+                "    Virtual size: 0x{:08X}",
+                section.virtual_size.value()
+            )?;
+            writeln!(
+                w,
+                "    Offset on file: 0x{:08X}",
+                section.pointer_to_raw_data.value()
+            )?;
+            writeln!(
+                w,
+                "    Length on file: 0x{:08X}",
+                section.size_of_raw_data.value()
+            )?;
+            writeln!(
+                w,
+                "    Characteristics: 0x{:08X}",
+                section.characteristics.value()
+            )?;
+        }
+
+        if let Some(exports) = &self.exports {
+            writeln!(w, "Exports of {}:", String::from_utf8_lossy(&exports.name))?;
+            for export in &exports.exports {
+                let name = export
+                    .name
+                    .as_deref()
+                    .map_or_else(|| "<unnamed>".into(), String::from_utf8_lossy);
+                match &export.forwarder {
+                    Some(forwarder) => writeln!(
+                        w,
+                        "    {:>7}  {:<32} -> {}",
+                        export.ordinal,
+                        name,
+                        String::from_utf8_lossy(forwarder)
+                    )?,
+                    None => writeln!(
+                        w,
+                        "    {:>7}  {:<32} 0x{:08X}",
+                        export.ordinal, name, export.address
+                    )?,
+                }
+            }
+        }
+
+        if !self.imported_modules.is_empty() {
+            writeln!(w, "Imports:")?;
+        }
+        for module in &self.imported_modules {
+            writeln!(w, "    {}", String::from_utf8_lossy(&module.name))?;
+            for import in &module.imports {
+                match import {
+                    PeImport::Ordinal(ordinal) => writeln!(w, "        @{}", ordinal)?,
+                    PeImport::Name { name, .. } => {
+                        writeln!(w, "        {}", String::from_utf8_lossy(name))?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pe_header() {
+        let mut data = vec![0; 0x80];
+        data[0x40..0x58].copy_from_slice(
+            b"PE\0\0\x4C\x01\x03\x00\x78\x56\x34\x12\0\0\0\0\0\0\0\0\xE0\x00\x02\x21",
+        );
+        let header = PeHeader::read(&data, 0x40).unwrap();
+        assert_eq!(header.machine.value(), 0x014C);
+        assert_eq!(header.number_of_sections.value(), 3);
+        assert_eq!(header.time_date_stamp.value(), 0x12345678);
+        assert!(header.is_dll());
+        assert!(PeHeader::read(&data, 0x41).is_err());
+        assert!(PeHeader::read(&data, 0x70).is_err());
+    }
+
+    #[test]
+    fn test_pe_executable() {
+        let mut data = vec![0; 0x400];
+        data[0x40..0x58]
+            .copy_from_slice(b"PE\0\0\x4C\x01\x01\x00\0\0\0\0\0\0\0\0\0\0\0\0\xE0\x00\x02\x21");
+        let optional = 0x58;
+        data[optional..optional + 2].copy_from_slice(&PE32_MAGIC.to_le_bytes());
+        data[optional + 92] = 16;
+        // The export directory at 0x1000 and the import directory at 0x1100.
+        data[optional + 97] = 0x10;
+        data[optional + 100] = 0x80;
+        data[optional + 105] = 0x11;
+        let section = optional + 0xE0;
+        data[section..section + 5].copy_from_slice(b".data");
+        data[section + 8..section + 24]
+            .copy_from_slice(b"\x00\x03\x00\x00\x00\x10\x00\x00\x00\x03\x00\x00\x00\x01\x00\x00");
+
+        let mut put = |rva: usize, bytes: &[u8]| {
+            let pos = rva - 0x1000 + 0x100;
+            data[pos..pos + bytes.len()].copy_from_slice(bytes);
+        };
+        // Name, ordinal base, 2 functions, 1 name, and the three tables.
+        put(
+            0x100C,
+            b"\x50\x10\x00\x00\x05\x00\x00\x00\x02\x00\x00\x00\x01\x00\x00\x00",
+        );
+        put(0x101C, b"\x60\x10\x00\x00\x70\x10\x00\x00\x78\x10\x00\x00");
+        put(0x1050, b"TEST.DLL\0");
+        put(0x1060, b"\x34\x12\x00\x00\x5C\x10\x00\x00");
+        put(0x105C, b"A.B\0");
+        put(0x1070, b"\x7C\x10\x00\x00");
+        put(0x1078, b"\x01\x00");
+        put(0x107C, b"Func\0");
+        // One module, importing by name and by ordinal.
+        put(0x1100, b"\x40\x11\x00\x00\0\0\0\0\0\0\0\0\x30\x11\x00\x00");
+        put(0x1130, b"KERNEL32.dll\0");
+        put(0x1140, b"\x50\x11\x00\x00\x07\x00\x00\x80");
+        put(0x1150, b"\x02\x00Sleep\0");
+
+        let exe = PeExecutable::read(&data, 0x40).unwrap();
+        assert_eq!(exe.sections[0].name(), b".data");
+        let exports = exe.exports.unwrap();
+        assert_eq!(exports.name, b"TEST.DLL");
+        assert_eq!(
+            exports.exports,
+            vec![
+                PeExport {
+                    ordinal: 5,
+                    name: None,
+                    address: 0x1234,
+                    forwarder: None,
+                },
+                PeExport {
+                    ordinal: 6,
+                    name: Some(b"Func".to_vec()),
+                    address: 0x105C,
+                    forwarder: Some(b"A.B".to_vec()),
+                },
+            ]
+        );
+        assert_eq!(
+            exe.imported_modules,
+            vec![ImportedModule {
+                name: b"KERNEL32.dll".to_vec(),
+                imports: vec![
+                    PeImport::Name {
+                        hint: 2,
+                        name: b"Sleep".to_vec(),
+                    },
+                    PeImport::Ordinal(7),
+                ],
+            }]
+        );
+    }
+}
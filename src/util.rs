@@ -1 +1,6 @@
+pub mod bps;
+pub mod bytes;
+pub mod codepage;
 pub mod endian;
+pub mod ips;
+pub mod spamsum;
@@ -0,0 +1,203 @@
+//! BPS patches, which unlike IPS have no size limit and check the source,
+//! target and patch with CRC-32s.
+//!
+//! A patch is `BPS1`, the source, target and metadata sizes as variable
+//! length numbers, the metadata, a list of actions building the target, and
+//! the three checksums.
+
+use std::io;
+
+const MAGIC: &[u8; 4] = b"BPS1";
+/// Copies bytes from the source at the same offset.
+const SOURCE_READ: u64 = 0;
+/// Copies bytes stored in the patch.
+const TARGET_READ: u64 = 1;
+/// Copies bytes from elsewhere in the source.
+const SOURCE_COPY: u64 = 2;
+/// Matching bytes shorter than this are stored rather than starting a new
+/// action.
+const MIN_MATCH: usize = 4;
+
+/// Computes the CRC-32 (IEEE 802.3) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn push_number(patch: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | low);
+            return;
+        }
+        patch.push(low);
+        value -= 1;
+    }
+}
+
+fn read_number(patch: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 1u64;
+    loop {
+        let byte = *patch.get(*pos)?;
+        *pos += 1;
+        value = value.checked_add((byte as u64 & 0x7F).checked_mul(shift)?)?;
+        if byte & 0x80 != 0 {
+            return Some(value);
+        }
+        shift = shift.checked_shl(7)?;
+        value = value.checked_add(shift)?;
+    }
+}
+
+fn push_action(patch: &mut Vec<u8>, command: u64, length: usize) {
+    push_number(patch, (length as u64 - 1) << 2 | command);
+}
+
+/// Creates a patch turning `source` into `target`.
+///
+/// Bytes are copied from the same offset in the source where they match and
+/// stored otherwise, which suits in-place edits such as translations and
+/// fixes; data moved within the file is stored again.
+pub fn create(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let matches = |pos: usize| source.get(pos) == Some(&target[pos]);
+    let match_length = |pos: usize| (pos..target.len()).take_while(|&i| matches(i)).count();
+    let mut patch = MAGIC.to_vec();
+    push_number(&mut patch, source.len() as u64);
+    push_number(&mut patch, target.len() as u64);
+    push_number(&mut patch, 0);
+    let mut pos = 0;
+    while pos < target.len() {
+        let length = match_length(pos);
+        if length >= MIN_MATCH || pos + length == target.len() && length != 0 {
+            push_action(&mut patch, SOURCE_READ, length);
+            pos += length;
+            continue;
+        }
+        let start = pos;
+        while pos < target.len() {
+            let length = match_length(pos);
+            if length >= MIN_MATCH || pos + length == target.len() && length != 0 {
+                break;
+            }
+            pos += length.max(1);
+        }
+        push_action(&mut patch, TARGET_READ, pos - start);
+        patch.extend_from_slice(&target[start..pos]);
+    }
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    patch
+}
+
+/// Applies a patch to `source`, checking the checksums.
+pub fn apply(source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+    if !patch.starts_with(MAGIC) || patch.len() < MAGIC.len() + 12 {
+        return Err(invalid("not a BPS patch"));
+    }
+    let (body, checksums) = patch.split_at(patch.len() - 12);
+    let checksum = |i: usize| u32::from_le_bytes(checksums[4 * i..4 * i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != checksum(2) {
+        return Err(invalid("the BPS patch is corrupt"));
+    }
+    if crc32(source) != checksum(0) {
+        return Err(invalid("the BPS patch is for another file"));
+    }
+    let mut pos = MAGIC.len();
+    let number =
+        |pos: &mut usize| read_number(body, pos).ok_or_else(|| invalid("truncated BPS patch"));
+    let _source_size = number(&mut pos)?;
+    let target_size = number(&mut pos)? as usize;
+    let metadata_size = number(&mut pos)? as usize;
+    let mut output = Vec::with_capacity(target_size);
+    pos += metadata_size;
+    let (mut source_relative, mut target_relative) = (0i64, 0i64);
+    while pos < body.len() {
+        let action = number(&mut pos)?;
+        let length = (action >> 2) as usize + 1;
+        let signed = |value: u64| {
+            let magnitude = (value >> 1) as i64;
+            if value & 1 != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        };
+        match action & 3 {
+            SOURCE_READ => {
+                let start = output.len();
+                output.extend_from_slice(
+                    source
+                        .get(start..start + length)
+                        .ok_or_else(|| invalid("BPS read past the source"))?,
+                );
+            }
+            TARGET_READ => {
+                output.extend_from_slice(
+                    body.get(pos..pos + length)
+                        .ok_or_else(|| invalid("truncated BPS patch"))?,
+                );
+                pos += length;
+            }
+            SOURCE_COPY => {
+                source_relative += signed(number(&mut pos)?);
+                let start = source_relative as usize;
+                output.extend_from_slice(
+                    source
+                        .get(start..start + length)
+                        .ok_or_else(|| invalid("BPS read past the source"))?,
+                );
+                source_relative += length as i64;
+            }
+            // Copies bytes already written to the target.
+            _ => {
+                target_relative += signed(number(&mut pos)?);
+                for _ in 0..length {
+                    let byte = *output
+                        .get(target_relative as usize)
+                        .ok_or_else(|| invalid("BPS read past the target"))?;
+                    output.push(byte);
+                    target_relative += 1;
+                }
+            }
+        }
+    }
+    if output.len() != target_size || crc32(&output) != checksum(1) {
+        return Err(invalid("the patched file doesn't match the BPS checksum"));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        let source = (0..0x200u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        let mut target = source.clone();
+        target[0x10] = 0xFF;
+        target[0x12] = 0xFE;
+        target.truncate(0x180);
+        target.extend_from_slice(b"appended");
+        let patch = create(&source, &target);
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+        assert!(apply(&target, &patch).is_err());
+    }
+}
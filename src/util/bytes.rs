@@ -0,0 +1,27 @@
+//! Little-endian reads from byte slices, returning `None` past the end.
+
+pub fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+pub fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+pub fn u64_at(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u32_at(data, pos)? as u64 | (u32_at(data, pos + 4)? as u64) << 32)
+}
+
+/// Reads a zero-terminated string.
+pub fn cstr_at(data: &[u8], pos: usize) -> Option<&[u8]> {
+    let rest = data.get(pos..)?;
+    Some(&rest[..rest.iter().position(|&b| b == 0)?])
+}
+
+/// Reads a string prefixed with its length in a byte.
+pub fn pstr_at(data: &[u8], pos: usize) -> Option<&[u8]> {
+    let len = *data.get(pos)? as usize;
+    data.get(pos + 1..pos + 1 + len)
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::str::FromStr;
+
+use encoding::codec::singlebyte::SingleByteEncoding;
+use encoding::label::encoding_from_windows_code_page;
+use encoding::types::EncodingRef;
+use encoding::{DecoderTrap, EncoderTrap};
+
+/// A Windows codepage, used to decode names and strings embedded in executables.
+///
+/// 16bit Windows has no notion of Unicode; every string is stored in the
+/// codepage of the system it was built for.
+#[derive(Clone, Copy)]
+pub struct Codepage {
+    number: u16,
+    encoding: EncodingRef,
+}
+
+impl Codepage {
+    pub fn new(number: u16) -> Option<Self> {
+        let encoding = if number == 437 {
+            &IBM437 as EncodingRef
+        } else {
+            encoding_from_windows_code_page(number as usize)?
+        };
+        Some(Self { number, encoding })
+    }
+
+    pub fn number(self) -> u16 {
+        self.number
+    }
+
+    /// Decodes the bytes, replacing invalid sequences with U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        self.encoding
+            .decode(bytes, DecoderTrap::Replace)
+            .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Encodes the string, failing on unrepresentable characters.
+    pub fn encode(self, s: &str) -> Option<Vec<u8>> {
+        self.encoding.encode(s, EncoderTrap::Strict).ok()
+    }
+}
+
+impl Default for Codepage {
+    fn default() -> Self {
+        Codepage::new(1252).unwrap()
+    }
+}
+
+impl PartialEq for Codepage {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
+    }
+}
+
+impl Eq for Codepage {}
+
+impl fmt::Debug for Codepage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Codepage({}, {:?})", self.number, self.encoding.name())
+    }
+}
+
+impl fmt::Display for Codepage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.number.fmt(f)
+    }
+}
+
+impl FromStr for Codepage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s
+            .strip_prefix("cp")
+            .or_else(|| s.strip_prefix("CP"))
+            .unwrap_or(s);
+        let number = digits
+            .parse::<u16>()
+            .map_err(|_| format!("invalid codepage: {}", s))?;
+        Codepage::new(number).ok_or_else(|| format!("unsupported codepage: {}", number))
+    }
+}
+
+/// The original IBM PC codepage, which `encoding` doesn't ship.
+static IBM437: SingleByteEncoding = SingleByteEncoding {
+    name: "ibm437",
+    whatwg_name: None,
+    index_forward: ibm437_forward,
+    index_backward: ibm437_backward,
+};
+
+fn ibm437_forward(code: u8) -> u16 {
+    IBM437_TABLE[(code - 0x80) as usize]
+}
+
+fn ibm437_backward(code: u32) -> u8 {
+    IBM437_TABLE
+        .iter()
+        .position(|&x| x as u32 == code)
+        .map_or(0, |pos| pos as u8 + 0x80)
+}
+
+#[rustfmt::skip]
+const IBM437_TABLE: [u16; 128] = [
+    0x00C7, 0x00FC, 0x00E9, 0x00E2, 0x00E4, 0x00E0, 0x00E5, 0x00E7,
+    0x00EA, 0x00EB, 0x00E8, 0x00EF, 0x00EE, 0x00EC, 0x00C4, 0x00C5,
+    0x00C9, 0x00E6, 0x00C6, 0x00F4, 0x00F6, 0x00F2, 0x00FB, 0x00F9,
+    0x00FF, 0x00D6, 0x00DC, 0x00A2, 0x00A3, 0x00A5, 0x20A7, 0x0192,
+    0x00E1, 0x00ED, 0x00F3, 0x00FA, 0x00F1, 0x00D1, 0x00AA, 0x00BA,
+    0x00BF, 0x2310, 0x00AC, 0x00BD, 0x00BC, 0x00A1, 0x00AB, 0x00BB,
+    0x2591, 0x2592, 0x2593, 0x2502, 0x2524, 0x2561, 0x2562, 0x2556,
+    0x2555, 0x2563, 0x2551, 0x2557, 0x255D, 0x255C, 0x255B, 0x2510,
+    0x2514, 0x2534, 0x252C, 0x251C, 0x2500, 0x253C, 0x255E, 0x255F,
+    0x255A, 0x2554, 0x2569, 0x2566, 0x2560, 0x2550, 0x256C, 0x2567,
+    0x2568, 0x2564, 0x2565, 0x2559, 0x2558, 0x2552, 0x2553, 0x256B,
+    0x256A, 0x2518, 0x250C, 0x2588, 0x2584, 0x258C, 0x2590, 0x2580,
+    0x03B1, 0x00DF, 0x0393, 0x03C0, 0x03A3, 0x03C3, 0x00B5, 0x03C4,
+    0x03A6, 0x0398, 0x03A9, 0x03B4, 0x221E, 0x03C6, 0x03B5, 0x2229,
+    0x2261, 0x00B1, 0x2265, 0x2264, 0x2320, 0x2321, 0x00F7, 0x2248,
+    0x00B0, 0x2219, 0x00B7, 0x221A, 0x207F, 0x00B2, 0x25A0, 0x00A0,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_cp1252() {
+        let cp = Codepage::new(1252).unwrap();
+        assert_eq!(cp.decode(b"Caf\xE9 \x80"), "Café €");
+    }
+
+    #[test]
+    fn test_decode_cp437() {
+        let cp: Codepage = "437".parse().unwrap();
+        assert_eq!(cp.decode(b"\xC9\xCD\xBB \x81ber"), "╔═╗ über");
+        assert_eq!(cp.encode("╔═╗ über").unwrap(), b"\xC9\xCD\xBB \x81ber");
+    }
+
+    #[test]
+    fn test_decode_cp932() {
+        let cp: Codepage = "cp932".parse().unwrap();
+        assert_eq!(cp.decode(b"\x93\xFA\x96\x7B\x8C\xEA"), "日本語");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!("12345".parse::<Codepage>().is_err());
+        assert!("foo".parse::<Codepage>().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! IPS patches, the simplest format used to distribute ROM and game mods.
+//!
+//! A patch is `PATCH`, records of a 24-bit big-endian offset, a 16-bit
+//! length and the bytes to write there (or, with a zero length, a 16-bit
+//! count and a byte to repeat), then `EOF` and optionally the 24-bit length
+//! to truncate the output to.
+
+use std::io;
+
+const MAGIC: &[u8; 5] = b"PATCH";
+const EOF: &[u8; 3] = b"EOF";
+/// An offset that would read as the end marker.
+const EOF_OFFSET: usize = 0x454F46;
+const MAX_OFFSET: usize = 0xFFFFFF;
+const MAX_RECORD: usize = 0xFFFF;
+/// Runs of one byte at least this long are written as RLE records.
+const MIN_RUN: usize = 8;
+/// Unchanged bytes shorter than this are written over rather than starting
+/// a new record, which takes 5 bytes.
+const MIN_GAP: usize = 6;
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "IPS patches can't address past 16MiB",
+    )
+}
+
+fn push_record(patch: &mut Vec<u8>, offset: usize, data: &[u8]) -> io::Result<()> {
+    if offset > MAX_OFFSET {
+        return Err(too_large());
+    }
+    patch.extend_from_slice(&(offset as u32).to_be_bytes()[1..]);
+    let run = data.iter().take_while(|&&byte| byte == data[0]).count();
+    if run == data.len() && run >= MIN_RUN {
+        patch.extend_from_slice(&[0, 0]);
+        patch.extend_from_slice(&(run as u16).to_be_bytes());
+        patch.push(data[0]);
+    } else {
+        patch.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        patch.extend_from_slice(data);
+    }
+    Ok(())
+}
+
+/// Creates a patch turning `source` into `target`.
+pub fn create(source: &[u8], target: &[u8]) -> io::Result<Vec<u8>> {
+    if target.len() > MAX_OFFSET + 1 {
+        return Err(too_large());
+    }
+    let differs = |pos: usize| source.get(pos) != Some(&target[pos]);
+    let mut patch = MAGIC.to_vec();
+    let mut pos = 0;
+    while pos < target.len() {
+        if !differs(pos) {
+            pos += 1;
+            continue;
+        }
+        let mut start = pos;
+        // A record can't start at the offset spelling "EOF".
+        if start == EOF_OFFSET {
+            start -= 1;
+        }
+        let mut end = pos;
+        let mut same = 0;
+        while end < target.len() && end - start < MAX_RECORD && same < MIN_GAP {
+            same = if differs(end) { 0 } else { same + 1 };
+            end += 1;
+        }
+        end -= same;
+        // Split off runs of one byte so that they can be RLE-encoded.
+        let mut chunk_start = start;
+        let mut i = start;
+        while i < end {
+            let run = target[i..end]
+                .iter()
+                .take_while(|&&byte| byte == target[i])
+                .count();
+            if run >= MIN_RUN && i != EOF_OFFSET {
+                // Nor can the record following the run.
+                let run = if i + run == EOF_OFFSET { run - 1 } else { run };
+                if chunk_start < i {
+                    push_record(&mut patch, chunk_start, &target[chunk_start..i])?;
+                }
+                push_record(&mut patch, i, &target[i..i + run])?;
+                chunk_start = i + run;
+            }
+            i += run;
+        }
+        if chunk_start < end {
+            push_record(&mut patch, chunk_start, &target[chunk_start..end])?;
+        }
+        pos = end;
+    }
+    patch.extend_from_slice(EOF);
+    if target.len() < source.len() {
+        patch.extend_from_slice(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(patch)
+}
+
+/// Applies a patch to `source`.
+pub fn apply(source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "truncated IPS patch");
+    let rest = patch
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an IPS patch"))?;
+    let mut output = source.to_vec();
+    let mut pos = 0;
+    let mut take = |len: usize| -> io::Result<&[u8]> {
+        let bytes = rest.get(pos..pos + len).ok_or_else(invalid)?;
+        pos += len;
+        Ok(bytes)
+    };
+    let be = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .fold(0usize, |value, &byte| value << 8 | byte as usize)
+    };
+    loop {
+        let offset = take(3)?;
+        if offset == EOF {
+            if let Ok(length) = take(3) {
+                output.truncate(be(length));
+            }
+            return Ok(output);
+        }
+        let offset = be(offset);
+        let data = match be(take(2)?) {
+            0 => {
+                let count = be(take(2)?);
+                vec![take(1)?[0]; count]
+            }
+            len => take(len)?.to_vec(),
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let source = (0..0x200u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        let mut target = source.clone();
+        target[0x10] = 0xFF;
+        target[0x12] = 0xFE;
+        target[0x80..0xA0].fill(0x90);
+        target.extend_from_slice(b"appended");
+        let patch = create(&source, &target).unwrap();
+        assert!(patch.starts_with(b"PATCH") && patch.ends_with(b"EOF"));
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+
+        // Shortening the file adds the truncation length.
+        let patch = create(&source, &source[..0x100]).unwrap();
+        assert_eq!(patch, b"PATCHEOF\x00\x01\x00");
+        assert_eq!(apply(&source, &patch).unwrap(), &source[..0x100]);
+    }
+
+    #[test]
+    fn test_eof_offset() {
+        let source = vec![0; EOF_OFFSET + 0x10];
+        let mut target = source.clone();
+        target[EOF_OFFSET] = 1;
+        let patch = create(&source, &target).unwrap();
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+
+        // A run ending just before it.
+        target[EOF_OFFSET - 0x10..EOF_OFFSET].fill(2);
+        let patch = create(&source, &target).unwrap();
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+    }
+}
@@ -0,0 +1,119 @@
+//! The context-triggered piecewise hash of spamsum, as computed by ssdeep.
+//!
+//! Files whose hashes share long runs of characters have similar contents,
+//! even if bytes were inserted or removed.
+
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCKSIZE: u32 = 3;
+const HASH_PRIME: u32 = 0x01000193;
+const HASH_INIT: u32 = 0x28021967;
+const SPAMSUM_LENGTH: usize = 64;
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The rolling hash over the last [`ROLLING_WINDOW`] bytes, which decides
+/// where the pieces end.
+#[derive(Default)]
+struct RollingHash {
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+    window: [u8; ROLLING_WINDOW],
+}
+
+impl RollingHash {
+    fn roll(&mut self, c: u8) -> u32 {
+        self.h2 = self
+            .h2
+            .wrapping_sub(self.h1)
+            .wrapping_add(ROLLING_WINDOW as u32 * c as u32);
+        self.h1 = self
+            .h1
+            .wrapping_add(c as u32)
+            .wrapping_sub(self.window[self.n] as u32);
+        self.window[self.n] = c;
+        self.n = (self.n + 1) % ROLLING_WINDOW;
+        self.h3 = (self.h3 << 5) ^ c as u32;
+        self.sum()
+    }
+
+    fn sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+/// Hashes the pieces of `data` ended by the rolling hash at `block_size`,
+/// one character each, up to `max_len` characters. Returns the characters
+/// and the number of pieces ended within the data.
+fn block_digest(data: &[u8], block_size: u32, max_len: usize) -> (String, usize) {
+    let mut roll = RollingHash::default();
+    let mut digest = String::new();
+    let mut pieces = 0;
+    let mut h = HASH_INIT;
+    // Once the digest is full, the last character covers the rest of the data.
+    let mut last = None;
+    for &c in data {
+        h = h.wrapping_mul(HASH_PRIME) ^ c as u32;
+        if roll.roll(c) % block_size == block_size - 1 {
+            let ch = B64[(h % 64) as usize] as char;
+            if digest.len() < max_len - 1 {
+                digest.push(ch);
+                h = HASH_INIT;
+                pieces += 1;
+            } else {
+                last = Some(ch);
+            }
+        }
+    }
+    if roll.sum() != 0 {
+        digest.push(B64[(h % 64) as usize] as char);
+    } else if let Some(ch) = last {
+        digest.push(ch);
+    }
+    (digest, pieces)
+}
+
+/// Computes the hash of `data` in the format `BLOCKSIZE:HASH:HASH`.
+pub fn hash(data: &[u8]) -> String {
+    let mut block_size = MIN_BLOCKSIZE;
+    while (block_size as usize) * SPAMSUM_LENGTH < data.len() {
+        block_size *= 2;
+    }
+    loop {
+        let (digest, pieces) = block_digest(data, block_size, SPAMSUM_LENGTH);
+        // Too few pieces tell little; try again with smaller ones.
+        if block_size > MIN_BLOCKSIZE && pieces < SPAMSUM_LENGTH / 2 {
+            block_size /= 2;
+            continue;
+        }
+        let (digest2, _) = block_digest(data, block_size * 2, SPAMSUM_LENGTH / 2);
+        return format!("{}:{}:{}", block_size, digest, digest2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(b""), "3::");
+        let data = (0..0x4000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let digest = hash(&data);
+        let [block_size, part1, part2] = digest.split(':').collect::<Vec<_>>()[..] else {
+            panic!("{}", digest);
+        };
+        assert!(block_size.parse::<u32>().unwrap() >= MIN_BLOCKSIZE);
+        assert!((SPAMSUM_LENGTH / 2..=SPAMSUM_LENGTH).contains(&part1.len()));
+        assert!(part2.len() <= SPAMSUM_LENGTH / 2);
+
+        // A change in the middle leaves the ends of the hash alone.
+        let mut changed = data.clone();
+        changed[0x2000] ^= 0xFF;
+        let changed = hash(&changed);
+        assert_ne!(changed, digest);
+        assert_eq!(changed[..12], digest[..12]);
+    }
+}
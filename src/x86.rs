@@ -1,42 +1,341 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
 
-pub fn disassemble(code: &[u8], is_32: bool) {
-    let mut insts = Vec::new();
-    let mut pos = 0;
-    while pos < code.len() {
-        let mut inst = eat(&code[pos..], is_32).unwrap_or_else(|_| gen_invalid(code[pos]));
-        inst.pos = pos;
-        pos += inst.len();
-        insts.push(inst);
+#[cfg(feature = "iced")]
+pub mod iced;
+pub mod intel;
+pub mod interrupts;
+
+/// Metadata shown alongside the listing, keyed by code offset.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    /// Names printed as labels before the instruction at that offset.
+    pub labels: BTreeMap<usize, String>,
+    /// Comments printed next to the instruction covering that offset.
+    pub annotations: BTreeMap<usize, String>,
+    /// Names of the far pointers patched in at that offset; they replace the
+    /// raw operand of far calls.
+    pub far_pointers: BTreeMap<usize, String>,
+}
+
+/// Decodes and formats instructions for the listing.
+///
+/// Whichever backend is used, control flow, labels and fixup annotations
+/// are derived the same way.
+pub trait Backend {
+    /// Decodes the instruction at `pos`.
+    fn decode(&self, code: &[u8], pos: usize, is_32: bool) -> Inst;
+
+    /// Formats an instruction decoded by [`Backend::decode`].
+    fn format(&self, code: &[u8], inst: &Inst) -> String;
+
+    /// Decodes the instructions starting within the range, one after another.
+    fn decode_range(&self, code: &[u8], range: Range<usize>, is_32: bool) -> Vec<Inst> {
+        let mut insts = Vec::new();
+        let mut pos = range.start;
+        while pos < range.end.min(code.len()) {
+            let inst = self.decode(code, pos, is_32);
+            pos += inst.len();
+            insts.push(inst);
+        }
+        insts
+    }
+}
+
+/// The decoder built into this crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Builtin;
+
+impl Backend for Builtin {
+    fn decode(&self, code: &[u8], pos: usize, is_32: bool) -> Inst {
+        decode(code, pos, is_32)
     }
 
-    println!("0000:0000 <.text>:");
-    for inst in &insts {
+    fn format(&self, _code: &[u8], inst: &Inst) -> String {
+        inst.to_string()
+    }
+}
+
+/// Selects a [`Backend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    #[default]
+    Builtin,
+    /// The `iced-x86` crate, available with the `iced` feature.
+    Iced,
+}
+
+impl BackendKind {
+    /// Returns the backend, or `None` if it isn't compiled in.
+    pub fn backend(self) -> Option<&'static dyn Backend> {
+        match self {
+            BackendKind::Builtin => Some(&Builtin),
+            #[cfg(feature = "iced")]
+            BackendKind::Iced => Some(&iced::Iced),
+            #[cfg(not(feature = "iced"))]
+            BackendKind::Iced => None,
+        }
+    }
+}
+
+/// Writes the listing of the code, addressed as the given segment number.
+pub fn disassemble<W: Write>(
+    w: &mut W,
+    code: &[u8],
+    segment: u16,
+    is_32: bool,
+    symbols: &Symbols,
+) -> io::Result<()> {
+    writeln!(w, "{:04X}:0000:", segment)?;
+    write_listing(
+        w,
+        code,
+        segment,
+        &decode_linear(code, is_32),
+        symbols,
+        &Builtin,
+    )
+}
+
+/// Decodes the instruction at `pos`, falling back to an invalid one-byte instruction.
+pub fn decode(code: &[u8], pos: usize, is_32: bool) -> Inst {
+    let mut inst = eat(&code[pos..], is_32).unwrap_or_else(|_| gen_invalid(code[pos]));
+    inst.pos = pos;
+    inst
+}
+
+/// Decodes the code from start to end, one instruction after another.
+pub fn decode_linear(code: &[u8], is_32: bool) -> Vec<Inst> {
+    decode_range(code, 0..code.len(), is_32)
+}
+
+/// Decodes the instructions starting within the range, one after another.
+pub fn decode_range(code: &[u8], range: Range<usize>, is_32: bool) -> Vec<Inst> {
+    Builtin.decode_range(code, range, is_32)
+}
+
+/// Decodes the code reachable from the entry points of a real-mode DOS
+/// program, following near jumps and calls.
+///
+/// Execution stops at `int 20h` and at `int 21h` with AH known to be 00h or
+/// 4Ch (terminate), so that data after the exit isn't decoded. Returns the
+/// instructions sorted by position.
+pub fn decode_reachable(code: &[u8], entries: &[usize]) -> Vec<Inst> {
+    let mut visited = BTreeMap::new();
+    let mut pending = entries.to_vec();
+    while let Some(mut pos) = pending.pop() {
+        let mut ax = KnownAx::default();
+        while pos < code.len() && !visited.contains_key(&pos) {
+            let inst = decode(code, pos, false);
+            visited.insert(pos, inst);
+            let exits = match (inst.opcode, inst.immediate) {
+                (0xCD, Immediate::Byte(0x20)) => true,
+                (0xCD, Immediate::Byte(0x21)) => matches!(ax.ah, Some(0x00 | 0x4C)),
+                _ => false,
+            };
+            ax.update(&inst);
+            match inst.flow() {
+                _ if exits => break,
+                Flow::Next | Flow::FarCall => {}
+                Flow::Branch(target) | Flow::Call(target) => pending.push(target as usize),
+                Flow::Jump(target) => {
+                    pending.push(target as usize);
+                    break;
+                }
+                Flow::FarJump | Flow::Stop => break,
+            }
+            pos = inst.end();
+        }
+    }
+    visited.into_values().collect()
+}
+
+/// Writes the listing of the given instructions, which must be sorted by position.
+///
+/// Addresses are shown as `segment:offset`. Gaps between instructions, e.g.
+/// data skipped by the recursive traversal, are shown as `...`.
+pub fn write_listing<W: Write>(
+    w: &mut W,
+    code: &[u8],
+    segment: u16,
+    insts: &[Inst],
+    symbols: &Symbols,
+    backend: &dyn Backend,
+) -> io::Result<()> {
+    let mut next_pos = insts.first().map_or(0, |inst| inst.pos);
+    let mut ax = KnownAx::default();
+    for inst in insts {
         let pos = inst.pos;
         let len = inst.len();
-        for skip in 0..((len + 6) / 7) {
-            print!("{:4X}:   ", pos + skip);
+        if pos > next_pos {
+            writeln!(w, "        ...")?;
+            ax = KnownAx::default();
+        }
+        next_pos = pos + len;
+        for (_, label) in symbols.labels.range(pos..pos + len) {
+            writeln!(w, "{}:", label)?;
+            ax = KnownAx::default();
+        }
+        let service = ax.service_name(inst);
+        ax.update(inst);
+        let far_pointer = inst.far_pointer_pos().and_then(|far_pos| {
+            let name = symbols.far_pointers.get(&(pos + far_pos))?;
+            Some((pos + far_pos, name))
+        });
+        for (row, bytes) in code[pos..pos + len].chunks(7).enumerate() {
+            write!(w, "{:04X}:{:04X}:   ", segment, pos + row * 7)?;
             for i in 0..7 {
-                if i < len {
-                    print!("{:02X} ", code[pos + skip + i]);
+                if let Some(byte) = bytes.get(i) {
+                    write!(w, "{:02X} ", byte)?;
                 } else {
-                    print!("   ");
+                    write!(w, "   ")?;
                 }
             }
-            if skip == 0 {
-                println!("   {}", inst);
+            if row == 0 {
+                let text = format_inst_with(backend, code, inst, symbols);
+                // The patched far pointer is already shown as the operand.
+                let comments = symbols
+                    .annotations
+                    .range(pos..pos + len)
+                    .filter(|&(&offset, _)| Some(offset) != far_pointer.map(|(offset, _)| offset))
+                    .map(|(_, comment)| &comment[..])
+                    .chain(service.as_deref())
+                    .collect::<Vec<_>>();
+                if comments.is_empty() {
+                    writeln!(w, "   {}", text)?;
+                } else {
+                    writeln!(w, "   {:<32} ; {}", text, comments.join(", "))?;
+                }
+            } else {
+                writeln!(w)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The value of AX set by the preceding instructions, as far as it is known.
+///
+/// This is only tracked through straight-line code and forgotten at anything
+/// that might change AX.
+#[derive(Debug, Clone, Copy, Default)]
+struct KnownAx {
+    ah: Option<u8>,
+    al: Option<u8>,
+}
+
+impl KnownAx {
+    /// Names the service requested if the instruction is a software interrupt.
+    fn service_name(&self, inst: &Inst) -> Option<String> {
+        match (inst.opcode, inst.immediate) {
+            (0xCD, Immediate::Byte(number)) => interrupts::service_name(number, self.ah, self.al),
+            _ => None,
+        }
+    }
+
+    fn update(&mut self, inst: &Inst) {
+        let (_, reg, rm) = split233(inst.modrm.unwrap_or(0));
+        let writes_rm = inst.modrm.is_some_and(|modrm| modrm >> 6 == 3) && (rm & 3) == 0;
+        let is_16 = !inst.is_32d();
+        match (inst.opcode, inst.immediate) {
+            (0xB8, Immediate::Word(imm)) => {
+                let [al, ah] = imm.to_le_bytes();
+                *self = KnownAx {
+                    ah: Some(ah),
+                    al: Some(al),
+                };
+            }
+            (0xB4, Immediate::Byte(imm)) => self.ah = Some(imm),
+            (0xB0, Immediate::Byte(imm)) => self.al = Some(imm),
+            // xor %ax, %ax / sub %ax, %ax
+            (0x31 | 0x33 | 0x29 | 0x2B, _) if inst.modrm == Some(0xC0) && is_16 => {
+                *self = KnownAx {
+                    ah: Some(0),
+                    al: Some(0),
+                };
             }
+            // xor %ah, %ah
+            (0x30 | 0x32, _) if inst.modrm == Some(0xE4) => self.ah = Some(0),
+            // push, pop and moves that leave AX alone
+            (0x06 | 0x0E | 0x16 | 0x1E | 0x07 | 0x17 | 0x1F, _)
+            | (0x50..=0x57 | 0x59..=0x5F, _)
+            | (0xB1..=0xB3 | 0xB5..=0xB7 | 0xB9..=0xBF, _)
+            | (0x8E | 0x90, _) => {}
+            (0x8A | 0x8B | 0x8D, _) if reg & 3 != 0 => {}
+            (0x88 | 0x89 | 0x8C | 0xC6 | 0xC7, _) if !writes_rm => {}
+            _ => *self = KnownAx::default(),
         }
     }
 }
 
+/// Formats the instruction, naming its far pointer operand if it's patched by a
+/// fixup, and its near target if it has a label.
+pub fn format_inst(inst: &Inst, symbols: &Symbols) -> String {
+    format_inst_with(&Builtin, &[], inst, symbols)
+}
+
+fn format_inst_with(backend: &dyn Backend, code: &[u8], inst: &Inst, symbols: &Symbols) -> String {
+    let far_pointer = inst
+        .far_pointer_pos()
+        .and_then(|far_pos| symbols.far_pointers.get(&(inst.pos + far_pos)));
+    if let Some(name) = far_pointer {
+        return inst.format_with_far_target(name);
+    }
+    let label = match inst.flow() {
+        Flow::Jump(target) | Flow::Branch(target) | Flow::Call(target) => {
+            symbols.labels.get(&(target as usize))
+        }
+        _ => None,
+    };
+    let text = backend.format(code, inst);
+    match label {
+        Some(label) => format!("{} <{}>", text, label),
+        None => text,
+    }
+}
+
+/// Where execution may continue after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Continues with the next instruction.
+    Next,
+    /// Always jumps to the near target.
+    Jump(u32),
+    /// Jumps to the near target or continues with the next instruction.
+    Branch(u32),
+    /// Calls the near target, then continues with the next instruction.
+    Call(u32),
+    /// Calls through the far pointer operand, then continues with the next instruction.
+    FarCall,
+    /// Jumps through the far pointer operand.
+    FarJump,
+    /// Doesn't continue anywhere known: returns, indirect jumps, `hlt` and invalid code.
+    Stop,
+}
+
 fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
     let mut eater = SimpleEater::new(code);
-    let inst_prefix = eater.next_if(|b| b == 0xF0 || b == 0xF2 || b == 0xF3);
-    let addr_prefix = eater.next_if(|b| b == 0x67);
-    let size_prefix = eater.next_if(|b| b == 0x66);
-    let segm_prefix =
-        eater.next_if(|b| [0x26, 0x2E, 0x36, 0x3E, 0x64, 0x65].iter().any(|&x| b == x));
+    let mut inst_prefix = None;
+    let mut addr_prefix = None;
+    let mut size_prefix = None;
+    let mut segm_prefix = None;
+    // The prefixes may come in any order; the last one of a group wins.
+    let is_prefix = |b| {
+        matches!(
+            b,
+            0xF0 | 0xF2 | 0xF3 | 0x67 | 0x66 | 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65
+        )
+    };
+    while let Some(prefix) = eater.next_if(is_prefix) {
+        match prefix {
+            0xF0 | 0xF2 | 0xF3 => inst_prefix = Some(prefix),
+            0x67 => addr_prefix = Some(prefix),
+            0x66 => size_prefix = Some(prefix),
+            _ => segm_prefix = Some(prefix),
+        }
+    }
 
     let opcode = eater.next()?;
 
@@ -61,12 +360,12 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
     };
 
     const OPCODE2_VALIDITY_MAP: [u32; 8] = [
-        0b00000000_00000000_00000000_01001111,
-        0b00000000_00000000_00000000_01011111,
+        0b00000000_00000000_00000011_01101111,
+        0b00000000_00000000_00000000_00001111,
         0b00000000_00000000_00000000_00000000,
         0b00000000_00000000_00000000_00000000,
         0b11111111_11111111_11111111_11111111,
-        0b11111100_11111100_10111011_00111011,
+        0b11111100_11111100_10111111_00111111,
         0b00000000_00000000_00000000_00000000,
         0b00000000_00000000_00000000_00000000,
     ];
@@ -77,22 +376,22 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
     }
 
     const HAS_MODRM: [u32; 8] = [
-        0b00111111_00111111_00111111_00111111,
-        0b00111111_00111111_00111111_00111111,
+        0b00001111_00001111_00001111_00001111,
+        0b00001111_00001111_00001111_00001111,
         0b00000000_00000000_00000000_00000000,
-        0b00000000_00000000_11111010_00001100,
-        0b00000000_00000000_11111111_11111011,
+        0b00000000_00000000_00001010_00001100,
+        0b00000000_00000000_11111111_11111111,
         0b00000000_00000000_00000000_00000000,
-        0b00000000_00001111_00000000_11110011,
-        0b11000000_11000000_11110000_00000000,
+        0b11111111_00001111_00000000_11110011,
+        0b11000000_11000000_00000000_00000000,
     ];
     const HAS_MODRM2: [u32; 8] = [
-        0b00000000_00000000_00000000_00001100,
-        0b00000000_00000000_00000000_01011111,
-        0b00000000_00000000_00000000_00000000,
+        0b00000000_00000000_00000000_00001111,
+        0b00000000_00000000_00000000_00001111,
         0b00000000_00000000_00000000_00000000,
         0b00000000_00000000_00000000_00000000,
-        0b11111100_11111100_10111011_00111000,
+        0b11111111_11111111_00000000_00000000,
+        0b11111100_11111100_10111000_00111000,
         0b00000000_00000000_00000000_00000000,
         0b00000000_00000000_00000000_00000000,
     ];
@@ -102,25 +401,28 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
         lookup_byte(&HAS_MODRM, opcode)
     };
     let modrm = if has_modrm { Some(eater.next()?) } else { None };
+    // Moves to and from control and debug registers ignore mod and always
+    // take a register, so there's no SIB byte or displacement.
+    let rm_modrm = modrm.filter(|_| !matches!(opcode2, Some(0x20..=0x23)));
 
     let is_32a = is_32c ^ addr_prefix.is_some();
     let is_32d = is_32c ^ size_prefix.is_some();
 
-    let has_sib = if let Some(modrm) = modrm {
-        is_32a && (modrm & 56) == 32 && (modrm & 192) != 192
+    let has_sib = if let Some(modrm) = rm_modrm {
+        is_32a && (modrm & 7) == 4 && (modrm & 192) != 192
     } else {
         false
     };
     let sib = if has_sib { Some(eater.next()?) } else { None };
 
-    let disp_size = if let Some(modrm) = modrm {
+    let disp_size = if let Some(modrm) = rm_modrm {
         let (mod_, _, rm) = split233(modrm);
+        // A SIB byte with base 5 takes a 32-bit displacement instead of %ebp.
+        let no_base = rm == 5 || sib.is_some_and(|sib| sib & 7 == 5);
         if is_32a {
             if mod_ == 1 {
                 ImmediateSize::Byte
-            } else if mod_ == 2 {
-                ImmediateSize::DWord
-            } else if mod_ == 0 && rm == 5 {
+            } else if mod_ == 2 || (mod_ == 0 && no_base) {
                 ImmediateSize::DWord
             } else {
                 ImmediateSize::None
@@ -128,14 +430,19 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
         } else {
             if mod_ == 1 {
                 ImmediateSize::Byte
-            } else if mod_ == 2 {
-                ImmediateSize::Word
-            } else if mod_ == 0 && rm == 6 {
+            } else if mod_ == 2 || (mod_ == 0 && rm == 6) {
                 ImmediateSize::Word
             } else {
                 ImmediateSize::None
             }
         }
+    } else if opcode2.is_none() && (0xA0..0xA4).contains(&opcode) {
+        // The memory offset of `mov` between the accumulator and memory.
+        if is_32a {
+            ImmediateSize::DWord
+        } else {
+            ImmediateSize::Word
+        }
     } else {
         ImmediateSize::None
     };
@@ -149,6 +456,9 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
             eater.next()?,
             eater.next()?,
         ])),
+        ImmediateSize::WordByte | ImmediateSize::Far => {
+            unreachable!("displacements are plain numbers")
+        }
     };
 
     const IMMEDIATE_MAP: [u32; 8] = [
@@ -156,33 +466,42 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
         0b00110000_00110000_00110000_00110000,
         0b00000000_00000000_00000000_00000000,
         0b11111111_11111111_00001111_00000000,
-        0b00000000_00000000_00000000_00001011,
+        0b00000100_00000000_00000000_00001011,
         0b11111111_11111111_00000011_00000000,
-        0b00000000_00000000_00100101_11000111,
+        0b00000000_00110000_00100101_11000111,
         0b00000000_11000000_00001111_11111111,
     ];
     const IMMEDIATE_BYTE_MAP: [u32; 8] = [
         0b00010000_00010000_00010000_00010000,
         0b00010000_00010000_00010000_00010000,
         0b00000000_00000000_00000000_00000000,
-        0b11111111_11111111_00000101_00000000,
+        0b11111111_11111111_00001100_00000000,
         0b00000000_00000000_00000000_00001001,
         0b00000000_11111111_00000001_00000000,
-        0b00000000_00000000_00100001_01000001,
+        0b00000000_00110000_00100000_01000011,
         0b00000000_00000000_00001000_11111111,
     ];
     const IMMEDIATE_WIDE_MAP: [u32; 8] = [
         0b00100000_00100000_00100000_00100000,
         0b00100000_00100000_00100000_00100000,
         0b00000000_00000000_00000000_00000000,
-        0b00000000_00000000_00001010_00000000,
+        0b00000000_00000000_00000011_00000000,
         0b00000000_00000000_00000000_00000010,
         0b11111111_00000000_00000010_00000000,
-        0b00000000_00000000_00000000_10000010,
+        0b00000000_00000000_00000000_10000000,
         0b00000000_00000000_00000011_00000000,
     ];
 
-    let immediate_size = if !lookup_byte(&IMMEDIATE_MAP, opcode) {
+    let immediate_size = if let Some(opcode2) = opcode2 {
+        match opcode2 {
+            0x80..=0x8F if is_32d => ImmediateSize::DWord,
+            0x80..=0x8F => ImmediateSize::Word,
+            0xA4 | 0xAC | 0xBA => ImmediateSize::Byte,
+            _ => ImmediateSize::None,
+        }
+    } else if opcode == 0x9A || opcode == 0xEA {
+        ImmediateSize::Far
+    } else if !lookup_byte(&IMMEDIATE_MAP, opcode) {
         ImmediateSize::None
     } else if lookup_byte(&IMMEDIATE_BYTE_MAP, opcode) {
         ImmediateSize::Byte
@@ -194,8 +513,21 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
         }
     } else if opcode == 0xC2 || opcode == 0xCA {
         ImmediateSize::Word
+    } else if opcode == 0xC8 {
+        ImmediateSize::WordByte
+    } else if opcode == 0xF6 || opcode == 0xF7 {
+        // Only `test` (/0, and its alias /1) in group 3 takes an immediate.
+        let (_, subop, _) = split233(modrm.unwrap_or(0));
+        if subop >= 2 {
+            ImmediateSize::None
+        } else if opcode == 0xF6 {
+            ImmediateSize::Byte
+        } else if is_32d {
+            ImmediateSize::DWord
+        } else {
+            ImmediateSize::Word
+        }
     } else {
-        // TODO: EA, F6, F7
         ImmediateSize::None
     };
     let imm = match immediate_size {
@@ -208,6 +540,24 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
             eater.next()?,
             eater.next()?,
         ])),
+        ImmediateSize::WordByte => Immediate::WordByte(
+            u16::from_le_bytes([eater.next()?, eater.next()?]),
+            eater.next()?,
+        ),
+        ImmediateSize::Far => {
+            // The offset comes first, followed by the selector.
+            let offset = if is_32d {
+                u32::from_le_bytes([eater.next()?, eater.next()?, eater.next()?, eater.next()?])
+            } else {
+                u16::from_le_bytes([eater.next()?, eater.next()?]) as u32
+            };
+            let segment = u16::from_le_bytes([eater.next()?, eater.next()?]);
+            Immediate::Far {
+                segment,
+                offset,
+                is_32: is_32d,
+            }
+        }
     };
 
     Ok(Inst {
@@ -224,6 +574,7 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Inst, EatError> {
         sib,
         displacement: disp,
         immediate: imm,
+        length: eater.pos,
     })
 }
 
@@ -242,6 +593,7 @@ fn gen_invalid(byte: u8) -> Inst {
         sib: None,
         displacement: Immediate::None,
         immediate: Immediate::None,
+        length: 1,
     }
 }
 
@@ -260,20 +612,17 @@ pub struct Inst {
     pub sib: Option<u8>,
     pub displacement: Immediate,
     pub immediate: Immediate,
+    length: usize,
 }
 
 impl Inst {
+    /// Returns the position right after this instruction.
+    pub fn end(&self) -> usize {
+        self.pos + self.len()
+    }
+
     fn len(&self) -> usize {
-        self.inst_prefix.is_some() as usize
-            + self.addr_prefix.is_some() as usize
-            + self.size_prefix.is_some() as usize
-            + self.segm_prefix.is_some() as usize
-            + 1
-            + self.opcode2.is_some() as usize
-            + self.modrm.is_some() as usize
-            + self.sib.is_some() as usize
-            + self.displacement.len()
-            + self.immediate.len()
+        self.length
     }
 
     fn is_32a(&self) -> bool {
@@ -284,6 +633,43 @@ impl Inst {
         self.is_32c ^ self.size_prefix.is_some()
     }
 
+    /// Returns where execution may continue after this instruction.
+    pub fn flow(&self) -> Flow {
+        if self.is_invalid {
+            return Flow::Stop;
+        }
+        let (_, subop, _) = split233(self.modrm.unwrap_or(0));
+        match (self.opcode, self.opcode2) {
+            (0x70..=0x7F | 0xE0..=0xE3, None) | (0x0F, Some(0x80..=0x8F)) => {
+                Flow::Branch(self.rel_target())
+            }
+            (0xE9 | 0xEB, None) => Flow::Jump(self.rel_target()),
+            (0xE8, None) => Flow::Call(self.rel_target()),
+            (0x9A, None) => Flow::FarCall,
+            (0xEA, None) => Flow::FarJump,
+            (0xC2 | 0xC3 | 0xCA | 0xCB | 0xCF | 0xF4, None) => Flow::Stop,
+            (0xFF, None) if subop == 4 || subop == 5 => Flow::Stop,
+            _ => Flow::Next,
+        }
+    }
+
+    /// Returns the position of the far pointer operand within the instruction.
+    pub fn far_pointer_pos(&self) -> Option<usize> {
+        match self.immediate {
+            Immediate::Far { .. } => Some(self.len() - self.immediate.len()),
+            _ => None,
+        }
+    }
+
+    /// Formats the instruction with its far pointer operand replaced by a name.
+    fn format_with_far_target(&self, target: &str) -> String {
+        match self.opcode {
+            0x9A => format!("lcall {}", target),
+            0xEA => format!("ljmp {}", target),
+            _ => self.to_string(),
+        }
+    }
+
     fn rm_name(&self, wide: bool) -> RmDisp {
         RmDisp {
             wide,
@@ -291,6 +677,79 @@ impl Inst {
             is_32d: self.is_32d(),
             disp: self.displacement,
             modrm: self.modrm.unwrap_or(0),
+            sib: self.sib,
+            segment: self.segm_prefix,
+        }
+    }
+
+    fn sreg_name(&self) -> &'static str {
+        let (_, reg, _) = split233(self.modrm.unwrap_or(0));
+        SREG_NAMES[reg as usize]
+    }
+
+    fn acc_name(&self, wide: bool) -> &'static str {
+        regname(0, self.is_32d(), wide)
+    }
+
+    /// Chooses the mnemonic by operand size.
+    fn sized(&self, name16: &'static str, name32: &'static str) -> &'static str {
+        if self.is_32d() {
+            name32
+        } else {
+            name16
+        }
+    }
+
+    /// Returns the operand size suffix for an r/m operand that is in memory,
+    /// where no register operand gives the size away.
+    fn mem_suffix(&self, wide: bool) -> &'static str {
+        match self.modrm {
+            Some(modrm) if modrm >> 6 != 3 => {
+                if !wide {
+                    "b"
+                } else if self.is_32d() {
+                    "l"
+                } else {
+                    "w"
+                }
+            }
+            _ => "",
+        }
+    }
+
+    /// Returns the operand size suffix of string instructions.
+    fn string_suffix(&self) -> &'static str {
+        if self.opcode & 1 == 0 {
+            "b"
+        } else if self.is_32d() {
+            "l"
+        } else {
+            "w"
+        }
+    }
+
+    /// Returns the destination of a relative jump or call.
+    fn rel_target(&self) -> u32 {
+        let rel = match self.immediate {
+            Immediate::Byte(x) => x as i8 as i32,
+            Immediate::Word(x) => x as i16 as i32,
+            Immediate::DWord(x) => x as i32,
+            _ => 0,
+        };
+        let target = (self.pos + self.len()) as u32;
+        let target = target.wrapping_add(rel as u32);
+        if self.is_32d() {
+            target
+        } else {
+            target & 0xFFFF
+        }
+    }
+
+    /// Returns the r/m operand of instructions that always take 16 bits.
+    fn rm_name16(&self) -> RmDisp {
+        RmDisp {
+            is_32d: false,
+            ..self.rm_name(true)
         }
     }
 
@@ -305,12 +764,24 @@ impl fmt::Display for Inst {
         if self.is_invalid {
             return write!(f, "<invalid>");
         }
+        match self.inst_prefix {
+            Some(0xF0) => write!(f, "lock ")?,
+            Some(0xF2) => write!(f, "repne ")?,
+            // `cmps` and `scas` stop on mismatch; other string instructions just repeat.
+            Some(0xF3) if matches!(self.opcode, 0xA6 | 0xA7 | 0xAE | 0xAF) => write!(f, "repe ")?,
+            Some(0xF3) => write!(f, "rep ")?,
+            _ => {}
+        }
+        if let Some(opcode2) = self.opcode2 {
+            return self.fmt_two_byte(f, opcode2);
+        }
+        let imm = ImmDisp(self.immediate);
         match self.opcode {
             opcode if (0..0x40).contains(&opcode) && opcode & 7 < 6 => {
                 let opname =
                     ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"][(opcode >> 3) as usize];
+                let wide = opcode & 1 != 0;
                 if opcode & 4 == 0 {
-                    let wide = opcode & 1 != 0;
                     let reg = self.reg_name(wide);
                     let rm = self.rm_name(wide);
                     if opcode & 2 == 0 {
@@ -319,24 +790,74 @@ impl fmt::Display for Inst {
                         write!(f, "{} {}, %{}", opname, rm, reg)
                     }
                 } else {
-                    write!(f, "{} ...", opname)
+                    write!(f, "{} {}, %{}", opname, imm, self.acc_name(wide))
                 }
             }
-            0x55 => write!(f, "nop"),
+            0x06 | 0x0E | 0x16 | 0x1E => {
+                write!(f, "push %{}", SREG_NAMES[self.opcode as usize >> 3])
+            }
+            0x07 | 0x17 | 0x1F => write!(f, "pop %{}", SREG_NAMES[self.opcode as usize >> 3]),
+            0x27 => write!(f, "daa"),
+            0x2F => write!(f, "das"),
+            0x37 => write!(f, "aaa"),
+            0x3F => write!(f, "aas"),
+            opcode if (0x40..0x60).contains(&opcode) => {
+                let opname = ["inc", "dec", "push", "pop"][(opcode as usize - 0x40) >> 3];
+                write!(
+                    f,
+                    "{} %{}",
+                    opname,
+                    regname(opcode & 7, self.is_32d(), true)
+                )
+            }
+            0x60 => write!(f, "{}", self.sized("pusha", "pushad")),
+            0x61 => write!(f, "{}", self.sized("popa", "popad")),
+            0x62 => write!(f, "bound {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0x63 => write!(f, "arpl %{}, {}", self.reg_name(true), self.rm_name(true)),
+            0x68 | 0x6A => write!(f, "push {}", SignedImmDisp(self.immediate)),
+            0x69 | 0x6B => write!(
+                f,
+                "imul {}, {}, %{}",
+                SignedImmDisp(self.immediate),
+                self.rm_name(true),
+                self.reg_name(true)
+            ),
+            0x6C => write!(f, "insb"),
+            0x6D => write!(f, "{}", self.sized("insw", "insl")),
+            0x6E => write!(f, "outsb"),
+            0x6F => write!(f, "{}", self.sized("outsw", "outsl")),
             opcode if (0x70..0x80).contains(&opcode) => {
-                let cc = [
-                    "o", "no", "b", "nb", "z", "nz", "be", "nbe", "s", "ns", "p", "np", "l", "nl",
-                    "le", "nle",
-                ][opcode as usize & 0xF];
-                write!(f, "j{} ...", cc)
+                write!(
+                    f,
+                    "j{} {:#x}",
+                    CC_NAMES[opcode as usize & 0xF],
+                    self.rel_target()
+                )
             }
             0x80 | 0x81 | 0x83 => {
                 let (_, subop, _) = split233(self.modrm.unwrap_or(0));
                 let opname =
                     ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"][subop as usize];
-                let imm = SignedImmDisp(self.immediate);
-                let rm = self.rm_name(self.opcode != 0x80);
-                write!(f, "{} {}, {}", opname, imm, rm)
+                let wide = self.opcode != 0x80;
+                let suffix = self.mem_suffix(wide);
+                let rm = self.rm_name(wide);
+                let imm = if self.opcode == 0x83 {
+                    SignedImmDisp(self.immediate).to_string()
+                } else {
+                    imm.to_string()
+                };
+                write!(f, "{}{} {}, {}", opname, suffix, imm, rm)
+            }
+            opcode if (0x84..0x88).contains(&opcode) => {
+                let opname = if opcode < 0x86 { "test" } else { "xchg" };
+                let wide = opcode & 1 != 0;
+                write!(
+                    f,
+                    "{} %{}, {}",
+                    opname,
+                    self.reg_name(wide),
+                    self.rm_name(wide)
+                )
             }
             opcode if (0x88..0x8C).contains(&opcode) => {
                 let wide = opcode & 1 != 0;
@@ -348,17 +869,308 @@ impl fmt::Display for Inst {
                     write!(f, "mov {}, %{}", rm, reg)
                 }
             }
+            0x8C => write!(f, "mov %{}, {}", self.sreg_name(), self.rm_name(true)),
+            0x8D => write!(f, "lea {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0x8E => write!(f, "mov {}, %{}", self.rm_name(true), self.sreg_name()),
+            0x8F => write!(f, "pop {}", self.rm_name(true)),
+            0x90 => write!(f, "nop"),
+            opcode if (0x91..0x98).contains(&opcode) => write!(
+                f,
+                "xchg %{}, %{}",
+                regname(opcode & 7, self.is_32d(), true),
+                self.acc_name(true)
+            ),
+            0x98 => write!(f, "{}", self.sized("cbw", "cwde")),
+            0x99 => write!(f, "{}", self.sized("cwd", "cdq")),
+            0x9A => match self.immediate {
+                Immediate::Far {
+                    segment, offset, ..
+                } => write!(f, "lcall ${:#x},${:#x}", segment, offset),
+                _ => write!(f, "lcall ..."),
+            },
+            0x9B => write!(f, "fwait"),
+            0x9C => write!(f, "{}", self.sized("pushf", "pushfd")),
+            0x9D => write!(f, "{}", self.sized("popf", "popfd")),
+            0x9E => write!(f, "sahf"),
+            0x9F => write!(f, "lahf"),
+            opcode if (0xA0..0xA4).contains(&opcode) => {
+                let wide = opcode & 1 != 0;
+                let moffs = MemOffsetDisp {
+                    segment: self.segm_prefix,
+                    offset: self.displacement,
+                };
+                if opcode & 2 == 0 {
+                    write!(f, "mov {}, %{}", moffs, self.acc_name(wide))
+                } else {
+                    write!(f, "mov %{}, {}", self.acc_name(wide), moffs)
+                }
+            }
+            opcode if (0xA4..0xB0).contains(&opcode) && opcode & 0xE != 0x8 => {
+                let opname =
+                    ["movs", "cmps", "", "stos", "lods", "scas"][(opcode as usize - 0xA4) >> 1];
+                write!(f, "{}{}", opname, self.string_suffix())
+            }
+            0xA8 | 0xA9 => write!(f, "test {}, %{}", imm, self.acc_name(self.opcode == 0xA9)),
+            opcode if (0xB0..0xC0).contains(&opcode) => {
+                let wide = opcode >= 0xB8;
+                write!(
+                    f,
+                    "mov {}, %{}",
+                    imm,
+                    regname(opcode & 7, self.is_32d(), wide)
+                )
+            }
+            0xC0 | 0xC1 | 0xD0..=0xD3 => {
+                let (_, subop, _) = split233(self.modrm.unwrap_or(0));
+                let opname =
+                    ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"][subop as usize];
+                let wide = self.opcode & 1 != 0;
+                let suffix = self.mem_suffix(wide);
+                let rm = self.rm_name(wide);
+                match self.opcode {
+                    0xC0 | 0xC1 => write!(f, "{}{} {}, {}", opname, suffix, imm, rm),
+                    0xD0 | 0xD1 => write!(f, "{}{} {}", opname, suffix, rm),
+                    _ => write!(f, "{}{} %cl, {}", opname, suffix, rm),
+                }
+            }
+            0xC2 => write!(f, "ret {}", imm),
+            0xC3 => write!(f, "ret"),
+            0xC4 => write!(f, "les {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0xC5 => write!(f, "lds {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0xC6 | 0xC7 => {
+                let wide = self.opcode == 0xC7;
+                let suffix = self.mem_suffix(wide);
+                write!(f, "mov{} {}, {}", suffix, imm, self.rm_name(wide))
+            }
+            0xC8 => match self.immediate {
+                Immediate::WordByte(size, level) => write!(f, "enter ${:#x}, ${:#x}", size, level),
+                _ => write!(f, "enter ..."),
+            },
+            0xC9 => write!(f, "leave"),
+            0xCA => write!(f, "lret {}", imm),
+            0xCB => write!(f, "lret"),
+            0xCC => write!(f, "int3"),
+            0xCD => write!(f, "int {}", imm),
+            0xCE => write!(f, "into"),
+            0xCF => write!(f, "{}", self.sized("iret", "iretd")),
+            0xD4 | 0xD5 => {
+                let opname = if self.opcode == 0xD4 { "aam" } else { "aad" };
+                match self.immediate {
+                    Immediate::Byte(10) => write!(f, "{}", opname),
+                    _ => write!(f, "{} {}", opname, imm),
+                }
+            }
+            0xD7 => write!(f, "xlat"),
+            opcode if (0xD8..0xE0).contains(&opcode) => {
+                // Coprocessor instructions, shown in the generic 8086 form.
+                let (_, reg, _) = split233(self.modrm.unwrap_or(0));
+                write!(
+                    f,
+                    "esc ${:#x}, {}",
+                    (opcode & 7) << 3 | reg,
+                    self.rm_name(true)
+                )
+            }
+            opcode if (0xE0..0xE4).contains(&opcode) => {
+                let opname = ["loopne", "loope", "loop", "jcxz"][opcode as usize & 3];
+                write!(f, "{} {:#x}", opname, self.rel_target())
+            }
+            0xE4 | 0xE5 => write!(f, "in {}, %{}", imm, self.acc_name(self.opcode == 0xE5)),
+            0xE6 | 0xE7 => write!(f, "out %{}, {}", self.acc_name(self.opcode == 0xE7), imm),
+            0xE8 => write!(f, "call {:#x}", self.rel_target()),
+            0xE9 | 0xEB => write!(f, "jmp {:#x}", self.rel_target()),
+            0xEA => match self.immediate {
+                Immediate::Far {
+                    segment, offset, ..
+                } => write!(f, "ljmp ${:#x},${:#x}", segment, offset),
+                _ => write!(f, "ljmp ..."),
+            },
+            0xEC | 0xED => write!(f, "in (%dx), %{}", self.acc_name(self.opcode == 0xED)),
+            0xEE | 0xEF => write!(f, "out %{}, (%dx)", self.acc_name(self.opcode == 0xEF)),
+            0xF4 => write!(f, "hlt"),
+            0xF5 => write!(f, "cmc"),
+            0xF6 | 0xF7 => {
+                let (_, subop, _) = split233(self.modrm.unwrap_or(0));
+                let wide = self.opcode == 0xF7;
+                let suffix = self.mem_suffix(wide);
+                let rm = self.rm_name(wide);
+                match subop {
+                    0 | 1 => write!(f, "test{} {}, {}", suffix, imm, rm),
+                    _ => {
+                        let opname =
+                            ["not", "neg", "mul", "imul", "div", "idiv"][subop as usize - 2];
+                        write!(f, "{}{} {}", opname, suffix, rm)
+                    }
+                }
+            }
+            opcode if (0xF8..0xFE).contains(&opcode) => {
+                let opname = ["clc", "stc", "cli", "sti", "cld", "std"][opcode as usize - 0xF8];
+                write!(f, "{}", opname)
+            }
+            0xFE | 0xFF => {
+                let (_, subop, _) = split233(self.modrm.unwrap_or(0));
+                let wide = self.opcode == 0xFF;
+                let suffix = self.mem_suffix(wide);
+                let rm = self.rm_name(wide);
+                match subop {
+                    0 => write!(f, "inc{} {}", suffix, rm),
+                    1 => write!(f, "dec{} {}", suffix, rm),
+                    2 if wide => write!(f, "call *{}", rm),
+                    3 if wide => write!(f, "lcall *{}", rm),
+                    4 if wide => write!(f, "jmp *{}", rm),
+                    5 if wide => write!(f, "ljmp *{}", rm),
+                    6 if wide => write!(f, "push {}", rm),
+                    _ => write!(f, "<invalid>"),
+                }
+            }
+            _ => write!(f, "..."),
+        }
+    }
+}
+
+impl Inst {
+    /// Formats the instructions starting with `0F`.
+    fn fmt_two_byte(&self, f: &mut fmt::Formatter, opcode2: u8) -> fmt::Result {
+        let (_, subop, rm) = split233(self.modrm.unwrap_or(0));
+        match opcode2 {
+            0x00 => match subop {
+                0..=5 => {
+                    let opname = ["sldt", "str", "lldt", "ltr", "verr", "verw"][subop as usize];
+                    write!(f, "{} {}", opname, self.rm_name16())
+                }
+                _ => write!(f, "<invalid>"),
+            },
+            0x01 => match subop {
+                0..=3 => {
+                    let opname = ["sgdt", "sidt", "lgdt", "lidt"][subop as usize];
+                    write!(f, "{} {}", opname, self.rm_name(true))
+                }
+                4 => write!(f, "smsw {}", self.rm_name16()),
+                6 => write!(f, "lmsw {}", self.rm_name16()),
+                7 => write!(f, "invlpg {}", self.rm_name(true)),
+                _ => write!(f, "<invalid>"),
+            },
+            0x02 | 0x03 => {
+                let opname = if opcode2 == 0x02 { "lar" } else { "lsl" };
+                write!(
+                    f,
+                    "{} {}, %{}",
+                    opname,
+                    self.rm_name(true),
+                    self.reg_name(true)
+                )
+            }
+            0x05 => write!(f, "loadall"),
+            0x06 => write!(f, "clts"),
+            0x08 => write!(f, "invd"),
+            0x09 => write!(f, "wbinvd"),
+            0x20..=0x23 => {
+                let special = if opcode2 & 1 == 0 { "cr" } else { "dr" };
+                let gpr = regname(rm, true, true);
+                if opcode2 & 2 == 0 {
+                    write!(f, "mov %{}{}, %{}", special, subop, gpr)
+                } else {
+                    write!(f, "mov %{}, %{}{}", gpr, special, subop)
+                }
+            }
+            0x80..=0x8F => {
+                write!(
+                    f,
+                    "j{} {:#x}",
+                    CC_NAMES[opcode2 as usize & 0xF],
+                    self.rel_target()
+                )
+            }
+            0x90..=0x9F => {
+                write!(
+                    f,
+                    "set{} {}",
+                    CC_NAMES[opcode2 as usize & 0xF],
+                    self.rm_name(false)
+                )
+            }
+            0xA0 | 0xA8 => write!(f, "push %{}", SREG_NAMES[opcode2 as usize >> 3 & 7]),
+            0xA1 | 0xA9 => write!(f, "pop %{}", SREG_NAMES[opcode2 as usize >> 3 & 7]),
+            0xA2 => write!(f, "cpuid"),
+            0xAA => write!(f, "rsm"),
+            0xA3 | 0xAB | 0xB3 | 0xBB => {
+                let opname = ["bt", "bts", "btr", "btc"][(opcode2 as usize >> 3) & 3];
+                write!(
+                    f,
+                    "{} %{}, {}",
+                    opname,
+                    self.reg_name(true),
+                    self.rm_name(true)
+                )
+            }
+            0xA4 | 0xA5 | 0xAC | 0xAD => {
+                let opname = if opcode2 < 0xA8 { "shld" } else { "shrd" };
+                let count = if opcode2 & 1 == 0 {
+                    ImmDisp(self.immediate).to_string()
+                } else {
+                    "%cl".to_owned()
+                };
+                write!(
+                    f,
+                    "{} {}, %{}, {}",
+                    opname,
+                    count,
+                    self.reg_name(true),
+                    self.rm_name(true)
+                )
+            }
+            0xAF => write!(f, "imul {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0xB2 | 0xB4 | 0xB5 => {
+                let opname = ["lss", "", "lfs", "lgs"][opcode2 as usize - 0xB2];
+                write!(
+                    f,
+                    "{} {}, %{}",
+                    opname,
+                    self.rm_name(true),
+                    self.reg_name(true)
+                )
+            }
+            0xB6 | 0xB7 | 0xBE | 0xBF => {
+                let opname = if opcode2 < 0xB8 { "movzx" } else { "movsx" };
+                let src = if opcode2 & 1 == 0 {
+                    self.rm_name(false).to_string()
+                } else {
+                    self.rm_name16().to_string()
+                };
+                write!(f, "{} {}, %{}", opname, src, self.reg_name(true))
+            }
+            0xBA => match subop {
+                4..=7 => {
+                    let opname = ["bt", "bts", "btr", "btc"][subop as usize - 4];
+                    let imm = ImmDisp(self.immediate);
+                    let suffix = self.mem_suffix(true);
+                    write!(f, "{}{} {}, {}", opname, suffix, imm, self.rm_name(true))
+                }
+                _ => write!(f, "<invalid>"),
+            },
+            0xBC => write!(f, "bsf {}, %{}", self.rm_name(true), self.reg_name(true)),
+            0xBD => write!(f, "bsr {}, %{}", self.rm_name(true), self.reg_name(true)),
             _ => write!(f, "..."),
         }
     }
 }
 
+const SREG_NAMES: [&str; 8] = ["es", "cs", "ss", "ds", "fs", "gs", "?", "?"];
+
+const CC_NAMES: [&str; 16] = [
+    "o", "no", "b", "nb", "z", "nz", "be", "nbe", "s", "ns", "p", "np", "l", "nl", "le", "nle",
+];
+
 #[derive(Debug, Clone, Copy)]
 pub enum ImmediateSize {
     None,
     Byte,
     Word,
     DWord,
+    /// A word followed by a byte, as in `enter`.
+    WordByte,
+    /// A `selector:offset` pointer.
+    Far,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -367,6 +1179,12 @@ pub enum Immediate {
     Byte(u8),
     Word(u16),
     DWord(u32),
+    WordByte(u16, u8),
+    Far {
+        segment: u16,
+        offset: u32,
+        is_32: bool,
+    },
 }
 
 impl Immediate {
@@ -377,6 +1195,14 @@ impl Immediate {
             Byte(_) => 1,
             Word(_) => 2,
             DWord(_) => 4,
+            WordByte(..) => 3,
+            Far { is_32, .. } => {
+                if *is_32 {
+                    6
+                } else {
+                    4
+                }
+            }
         }
     }
 }
@@ -414,13 +1240,6 @@ impl<'a> SimpleEater<'a> {
             Err(EatError)
         }
     }
-    fn peek(&self) -> Option<u8> {
-        if self.pos < self.code.len() {
-            Some(self.code[self.pos])
-        } else {
-            None
-        }
-    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -429,19 +1248,34 @@ struct RmDisp {
     is_32d: bool,
     wide: bool,
     modrm: u8,
+    sib: Option<u8>,
     disp: Immediate,
+    segment: Option<u8>,
 }
 
 impl fmt::Display for RmDisp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (mod_, _, rm) = split233(self.modrm);
+        if mod_ != 3 {
+            if let Some(prefix) = self.segment {
+                write!(f, "%{}:", segment_prefix_name(prefix))?;
+            }
+        }
         if mod_ == 3 {
             write!(f, "%{}", regname(rm, self.is_32d, self.wide))
         } else if self.is_32a {
-            write!(f, "...")
+            self.fmt_32a(f)
         } else {
             if mod_ == 0 && rm == 6 {
-                write!(f, "{}", DispDisp(self.disp))
+                // A direct address, which isn't signed.
+                write!(
+                    f,
+                    "{}",
+                    MemOffsetDisp {
+                        segment: None,
+                        offset: self.disp
+                    }
+                )
             } else if mod_ == 0 {
                 write!(f, "{}", RM16_TABLE[rm as usize])
             } else {
@@ -450,6 +1284,55 @@ impl fmt::Display for RmDisp {
         }
     }
 }
+
+impl RmDisp {
+    /// Formats a memory operand with 32-bit addressing, as in `0x4(%ebx,%esi,2)`.
+    fn fmt_32a(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (mod_, _, rm) = split233(self.modrm);
+        let (base, index) = match self.sib {
+            Some(sib) => {
+                let (scale, index, base) = split233(sib);
+                let base = if mod_ == 0 && base == 5 {
+                    None
+                } else {
+                    Some(base)
+                };
+                // Index 4 means no index.
+                (
+                    base,
+                    Some((index, 1 << scale)).filter(|&(index, _)| index != 4),
+                )
+            }
+            None if mod_ == 0 && rm == 5 => (None, None),
+            None => (Some(rm), None),
+        };
+        if base.is_none() {
+            // A direct address, which isn't signed.
+            write!(
+                f,
+                "{}",
+                MemOffsetDisp {
+                    segment: None,
+                    offset: self.disp
+                }
+            )?;
+            if index.is_none() {
+                return Ok(());
+            }
+        } else {
+            write!(f, "{}", DispDisp(self.disp))?;
+        }
+        write!(f, "(")?;
+        if let Some(base) = base {
+            write!(f, "%{}", regname(base, true, true))?;
+        }
+        if let Some((index, scale)) = index {
+            write!(f, ",%{},{}", regname(index, true, true), scale)?;
+        }
+        write!(f, ")")
+    }
+}
+
 const RM16_TABLE: [&str; 8] = [
     "(%bx,%si)",
     "(%bx,%di)",
@@ -471,17 +1354,65 @@ fn regname(id: u8, is_32d: bool, wide: bool) -> &'static str {
     }
 }
 
+fn segment_prefix_name(prefix: u8) -> &'static str {
+    match prefix {
+        0x26 => "es",
+        0x2E => "cs",
+        0x36 => "ss",
+        0x3E => "ds",
+        0x64 => "fs",
+        _ => "gs",
+    }
+}
+
+/// A direct memory operand, as in `mov 0x1234, %ax`.
+#[derive(Debug, Clone, Copy)]
+struct MemOffsetDisp {
+    segment: Option<u8>,
+    offset: Immediate,
+}
+
+impl fmt::Display for MemOffsetDisp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = self.segment {
+            write!(f, "%{}:", segment_prefix_name(prefix))?;
+        }
+        match self.offset {
+            Immediate::Word(x) => write!(f, "{:#x}", x),
+            Immediate::DWord(x) => write!(f, "{:#x}", x),
+            _ => write!(f, "..."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ImmDisp(Immediate);
+
+impl fmt::Display for ImmDisp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Immediate::*;
+        match self.0 {
+            None | WordByte(..) | Far { .. } => Ok(()),
+            Byte(x) => write!(f, "${:#x}", x),
+            Word(x) => write!(f, "${:#x}", x),
+            DWord(x) => write!(f, "${:#x}", x),
+        }
+    }
+}
+
+/// An immediate whose byte form is sign-extended to the operand size, as in
+/// `push $-0x2`.
 #[derive(Debug, Clone, Copy)]
 struct SignedImmDisp(Immediate);
 
 impl fmt::Display for SignedImmDisp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use self::Immediate::*;
         match self.0 {
-            None => Ok(()),
-            Byte(x) => write!(f, "${:#x}", x as i8),
-            Word(x) => write!(f, "${:#x}", x as i16),
-            DWord(x) => write!(f, "${:#x}", x as i32),
+            Immediate::Byte(x) => {
+                write!(f, "$")?;
+                write_signed(f, x as i8 as i64)
+            }
+            imm => write!(f, "{}", ImmDisp(imm)),
         }
     }
 }
@@ -493,14 +1424,22 @@ impl fmt::Display for DispDisp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::Immediate::*;
         match self.0 {
-            None => Ok(()),
-            Byte(x) => write!(f, "{:#x}", x as i8),
-            Word(x) => write!(f, "{:#x}", x as i16),
-            DWord(x) => write!(f, "{:#x}", x as i32),
+            None | WordByte(..) | Far { .. } => Ok(()),
+            Byte(x) => write_signed(f, x as i8 as i64),
+            Word(x) => write_signed(f, x as i16 as i64),
+            DWord(x) => write_signed(f, x as i32 as i64),
         }
     }
 }
 
+fn write_signed(f: &mut fmt::Formatter, x: i64) -> fmt::Result {
+    if x < 0 {
+        write!(f, "-{:#x}", -x)
+    } else {
+        write!(f, "{:#x}", x)
+    }
+}
+
 fn lookup_byte(table: &[u32; 8], byte: u8) -> bool {
     (table[(byte >> 5) as usize] >> (byte & 31)) & 1 != 0
 }
@@ -508,3 +1447,303 @@ fn lookup_byte(table: &[u32; 8], byte: u8) -> bool {
 fn split233(byte: u8) -> (u8, u8, u8) {
     (byte >> 6, (byte >> 3) & 7, byte & 7)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(code: &[u8]) -> (String, usize) {
+        let inst = eat(code, false).unwrap();
+        (inst.to_string(), inst.len())
+    }
+
+    #[test]
+    fn test_decode_reachable() {
+        let code = b"\x0E\x1F\xBA\x0E\x00\xB4\x09\xCD\x21\xB8\x01\x4C\xCD\x21This program";
+        let insts = decode_reachable(code, &[0]);
+        assert_eq!(insts.len(), 7);
+        assert_eq!(insts.last().unwrap().end(), 14);
+    }
+
+    #[test]
+    fn test_one_byte_opcodes() {
+        assert_eq!(format(b"\x05\x34\x12"), ("add $0x1234, %ax".to_owned(), 3));
+        assert_eq!(format(b"\x1E"), ("push %ds".to_owned(), 1));
+        assert_eq!(format(b"\x47"), ("inc %di".to_owned(), 1));
+        assert_eq!(format(b"\x6A\xFF"), ("push $-0x1".to_owned(), 2));
+        assert_eq!(format(b"\x68\x00\x10"), ("push $0x1000".to_owned(), 3));
+        assert_eq!(format(b"\x75\xFE"), ("jnz 0x0".to_owned(), 2));
+        assert_eq!(format(b"\x85\xC0"), ("test %ax, %ax".to_owned(), 2));
+        assert_eq!(format(b"\x8E\xD8"), ("mov %ax, %ds".to_owned(), 2));
+        assert_eq!(
+            format(b"\x26\xA1\x34\x12"),
+            ("mov %es:0x1234, %ax".to_owned(), 4)
+        );
+        assert_eq!(format(b"\xAD"), ("lodsw".to_owned(), 1));
+        assert_eq!(format(b"\xC4\x5E\x06"), ("les 0x6(%bp), %bx".to_owned(), 3));
+        assert_eq!(
+            format(b"\xC8\x04\x00\x00"),
+            ("enter $0x4, $0x0".to_owned(), 4)
+        );
+        assert_eq!(format(b"\xD4\x0A"), ("aam".to_owned(), 2));
+        assert_eq!(format(b"\xE2\x10"), ("loop 0x12".to_owned(), 2));
+        assert_eq!(format(b"\xEC"), ("in (%dx), %al".to_owned(), 1));
+        assert_eq!(format(b"\xE8\x00\x01"), ("call 0x103".to_owned(), 3));
+    }
+
+    #[test]
+    fn test_group_opcodes() {
+        assert_eq!(format(b"\xF6\xC4\x80"), ("test $0x80, %ah".to_owned(), 3));
+        assert_eq!(
+            format(b"\xF7\x06\x00\x10\x01\x00"),
+            ("testw $0x1, 0x1000".to_owned(), 6)
+        );
+        assert_eq!(format(b"\xF7\xE3"), ("mul %bx".to_owned(), 2));
+        assert_eq!(format(b"\xF6\xDB"), ("neg %bl".to_owned(), 2));
+        assert_eq!(format(b"\xFE\x07"), ("incb (%bx)".to_owned(), 2));
+        assert_eq!(format(b"\xFF\xD3"), ("call *%bx".to_owned(), 2));
+        assert_eq!(format(b"\xFF\x5E\x04"), ("lcall *0x4(%bp)".to_owned(), 3));
+        assert_eq!(format(b"\xFF\x76\xFE"), ("push -0x2(%bp)".to_owned(), 3));
+        assert_eq!(format(b"\xFF\xFF"), ("<invalid>".to_owned(), 2));
+    }
+
+    #[test]
+    fn test_signed_immediates() {
+        assert_eq!(format(b"\x83\xC0\x86"), ("add $-0x7a, %ax".to_owned(), 3));
+        assert_eq!(format(b"\x83\xE4\xF0"), ("and $-0x10, %sp".to_owned(), 3));
+        assert_eq!(format(b"\x83\xF9\x10"), ("cmp $0x10, %cx".to_owned(), 3));
+        assert_eq!(format(b"\x6A\xFE"), ("push $-0x2".to_owned(), 2));
+        assert_eq!(
+            format(b"\x6B\xC0\xFE"),
+            ("imul $-0x2, %ax, %ax".to_owned(), 3)
+        );
+        // Only the byte forms are sign-extended.
+        assert_eq!(format(b"\x80\xC4\x86"), ("add $0x86, %ah".to_owned(), 3));
+        assert_eq!(
+            format(b"\x81\xC4\x00\x80"),
+            ("add $0x8000, %sp".to_owned(), 4)
+        );
+        assert_eq!(format(b"\x68\xFE\xFF"), ("push $0xfffe".to_owned(), 3));
+    }
+
+    #[test]
+    fn test_memory_suffixes() {
+        assert_eq!(
+            format(b"\x80\x06\x11\x22\x33"),
+            ("addb $0x33, 0x2211".to_owned(), 5)
+        );
+        assert_eq!(
+            format(b"\x81\x06\x11\x22\x33\x44"),
+            ("addw $0x4433, 0x2211".to_owned(), 6)
+        );
+        assert_eq!(
+            format(b"\x66\x83\x7E\x04\xFF"),
+            ("cmpl $-0x1, 0x4(%bp)".to_owned(), 5)
+        );
+        assert_eq!(format(b"\xC6\x07\x00"), ("movb $0x0, (%bx)".to_owned(), 3));
+        assert_eq!(
+            format(b"\xC7\x46\xFE\x01\x00"),
+            ("movw $0x1, -0x2(%bp)".to_owned(), 5)
+        );
+        assert_eq!(format(b"\xF6\x1F"), ("negb (%bx)".to_owned(), 2));
+        assert_eq!(format(b"\xFF\x4E\xFE"), ("decw -0x2(%bp)".to_owned(), 3));
+        assert_eq!(
+            format(b"\x0F\xBA\x27\x03"),
+            ("btw $0x3, (%bx)".to_owned(), 4)
+        );
+        // Register operands already give the size away.
+        assert_eq!(format(b"\xFE\xC0"), ("inc %al".to_owned(), 2));
+    }
+
+    #[test]
+    fn test_32bit_addressing() {
+        assert_eq!(format(b"\x67\x8B\x03"), ("mov (%ebx), %ax".to_owned(), 3));
+        assert_eq!(
+            format(b"\x67\x8B\x45\xFC"),
+            ("mov -0x4(%ebp), %ax".to_owned(), 4)
+        );
+        assert_eq!(
+            format(b"\x67\x8B\x05\x78\x56\x34\x12"),
+            ("mov 0x12345678, %ax".to_owned(), 7)
+        );
+        assert_eq!(
+            format(b"\x67\x8B\x04\x24"),
+            ("mov (%esp), %ax".to_owned(), 4)
+        );
+        assert_eq!(
+            format(b"\x67\x8B\x44\x73\x04"),
+            ("mov 0x4(%ebx,%esi,2), %ax".to_owned(), 5)
+        );
+        assert_eq!(
+            format(b"\x67\x8B\x04\x8D\x00\x10\x00\x00"),
+            ("mov 0x1000(,%ecx,4), %ax".to_owned(), 8)
+        );
+        assert_eq!(
+            format(b"\x67\x66\xC7\x40\x08\x01\x00\x00\x00"),
+            ("movl $0x1, 0x8(%eax)".to_owned(), 9)
+        );
+        // The reg field doesn't bring a SIB byte.
+        assert_eq!(format(b"\x67\x8B\xE0"), ("mov %ax, %sp".to_owned(), 3));
+        assert_eq!(format(b"\x67\x89\x20"), ("mov %sp, (%eax)".to_owned(), 3));
+    }
+
+    /// Asserts that every valid encoding starting with the bytes, in either
+    /// address size, gets full operands.
+    fn assert_no_placeholders(opcode: &[u8]) {
+        for prefix in [&b""[..], b"\x67"] {
+            for modrm in 0..=0xFF {
+                let mut code = [prefix, opcode].concat();
+                code.extend([modrm, 0x24, 0, 0, 0, 0, 0, 0, 0, 0]);
+                if let Ok(inst) = eat(&code, false) {
+                    let text = inst.to_string();
+                    assert!(!text.contains("..."), "{:02X?}: {}", code, text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_placeholders() {
+        for opcode in 0..=0xFF {
+            if opcode != 0x0F {
+                assert_no_placeholders(&[opcode]);
+            }
+            assert_no_placeholders(&[0x0F, opcode]);
+        }
+    }
+
+    #[test]
+    fn test_shift_opcodes() {
+        assert_eq!(format(b"\xC1\xE0\x04"), ("shl $0x4, %ax".to_owned(), 3));
+        assert_eq!(format(b"\xD1\xF8"), ("sar %ax".to_owned(), 2));
+        assert_eq!(format(b"\xD0\x47\x02"), ("rolb 0x2(%bx)".to_owned(), 3));
+        assert_eq!(format(b"\xD3\xEA"), ("shr %cl, %dx".to_owned(), 2));
+        assert_eq!(format(b"\xD2\xD4"), ("rcl %cl, %ah".to_owned(), 2));
+    }
+
+    #[test]
+    fn test_far_pointers() {
+        assert_eq!(
+            format(b"\x9A\x10\x00\x34\x12"),
+            ("lcall $0x1234,$0x10".to_owned(), 5)
+        );
+        assert_eq!(
+            format(b"\xEA\x00\x00\xFF\xFF"),
+            ("ljmp $0xffff,$0x0".to_owned(), 5)
+        );
+        assert_eq!(
+            format(b"\x66\xEA\x78\x56\x34\x12\x08\x00"),
+            ("ljmp $0x8,$0x12345678".to_owned(), 8)
+        );
+    }
+
+    #[test]
+    fn test_flow() {
+        let code = b"\x74\x02\xEB\xFC\xE8\x00\x00\xC3\xFF\xE0\x9A\x00\x00\x00\x00";
+        let flows = decode_linear(code, false)
+            .iter()
+            .map(|inst| inst.flow())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flows,
+            vec![
+                Flow::Branch(4),
+                Flow::Jump(0),
+                Flow::Call(7),
+                Flow::Stop,
+                Flow::Stop,
+                Flow::FarCall
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_inst() {
+        let code = b"\xE8\x02\x00\x9A\x00\x00\x00\x00\xC3";
+        let mut symbols = Symbols::default();
+        symbols.labels.insert(5, "HELPER".to_owned());
+        symbols
+            .far_pointers
+            .insert(4, "KERNEL.GETVERSION".to_owned());
+        let texts = decode_linear(code, false)
+            .iter()
+            .map(|inst| format_inst(inst, &symbols))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec!["call 0x5 <HELPER>", "lcall KERNEL.GETVERSION", "ret"]
+        );
+    }
+
+    #[test]
+    fn test_known_ax() {
+        // mov $0x3d, %ah; mov $0x0, %al; mov %dx, %bx; int $0x21; int $0x21
+        let code = b"\xB4\x3D\xB0\x00\x89\xD3\xCD\x21\xCD\x21";
+        let mut ax = KnownAx::default();
+        let services = decode_linear(code, false)
+            .iter()
+            .map(|inst| {
+                let service = ax.service_name(inst);
+                ax.update(inst);
+                service
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            services,
+            vec![
+                None,
+                None,
+                None,
+                Some("DOS: open file (AH=3Dh)".to_owned()),
+                Some("DOS services".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(format(b"\xF3\xA5"), ("rep movsw".to_owned(), 2));
+        assert_eq!(format(b"\xF2\xAE"), ("repne scasb".to_owned(), 2));
+        assert_eq!(format(b"\xF3\xA6"), ("repe cmpsb".to_owned(), 2));
+        assert_eq!(format(b"\xF3\x66\xAB"), ("rep stosl".to_owned(), 3));
+        assert_eq!(format(b"\xF0\xFF\x07"), ("lock incw (%bx)".to_owned(), 3));
+        assert_eq!(format(b"\x66\xF3\xAB"), ("rep stosl".to_owned(), 3));
+        assert_eq!(format(b"\x26\xF3\xA4"), ("rep movsb".to_owned(), 3));
+        assert_eq!(
+            format(b"\x2E\x66\xA1\x34\x12"),
+            ("mov %cs:0x1234, %eax".to_owned(), 5)
+        );
+    }
+
+    #[test]
+    fn test_two_byte_opcodes() {
+        assert_eq!(format(b"\x0F\x00\xD0"), ("lldt %ax".to_owned(), 3));
+        assert_eq!(
+            format(b"\x0F\x01\x16\x00\x10"),
+            ("lgdt 0x1000".to_owned(), 5)
+        );
+        assert_eq!(format(b"\x0F\x84\x00\x01"), ("jz 0x104".to_owned(), 4));
+        assert_eq!(format(b"\x0F\x94\xC0"), ("setz %al".to_owned(), 3));
+        assert_eq!(format(b"\x0F\xA0"), ("push %fs".to_owned(), 2));
+        assert_eq!(format(b"\x0F\xB6\xC3"), ("movzx %bl, %ax".to_owned(), 3));
+        assert_eq!(
+            format(b"\x0F\xA4\xC2\x04"),
+            ("shld $0x4, %ax, %dx".to_owned(), 4)
+        );
+        assert_eq!(format(b"\x0F\x22\xC0"), ("mov %eax, %cr0".to_owned(), 3));
+        assert_eq!(format(b"\x0F\xAA"), ("rsm".to_owned(), 2));
+    }
+
+    #[test]
+    fn test_special_register_moves() {
+        // The mod field is ignored, so there's never a displacement.
+        assert_eq!(format(b"\x0F\x20\x00"), ("mov %cr0, %eax".to_owned(), 3));
+        assert_eq!(format(b"\x0F\x20\x43"), ("mov %cr0, %ebx".to_owned(), 3));
+        assert_eq!(format(b"\x0F\x21\x86"), ("mov %dr0, %esi".to_owned(), 3));
+        assert_eq!(
+            format(b"\x67\x0F\x23\x3C"),
+            ("mov %esp, %dr7".to_owned(), 4)
+        );
+        assert_eq!(format(b"\x0F\x22\xD8"), ("mov %eax, %cr3".to_owned(), 3));
+    }
+}
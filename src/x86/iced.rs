@@ -0,0 +1,77 @@
+use iced_x86::{Decoder, DecoderOptions, Formatter, GasFormatter, Instruction};
+
+use super::{gen_invalid, Backend, Inst};
+
+/// A [`Backend`] using `iced-x86`, which covers the whole instruction set.
+///
+/// The instruction is also decoded by the built-in decoder to follow its
+/// control flow; if the two disagree on the length, it is treated as one
+/// the built-in decoder doesn't know.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Iced;
+
+fn decode_iced(code: &[u8], pos: usize, is_32: bool) -> Instruction {
+    let bitness = if is_32 { 32 } else { 16 };
+    let mut decoder = Decoder::with_ip(bitness, &code[pos..], pos as u64, DecoderOptions::NONE);
+    decoder.decode()
+}
+
+impl Backend for Iced {
+    fn decode(&self, code: &[u8], pos: usize, is_32: bool) -> Inst {
+        let instruction = decode_iced(code, pos, is_32);
+        let len = instruction.len();
+        if instruction.is_invalid() {
+            let mut inst = gen_invalid(code[pos]);
+            inst.pos = pos;
+            return inst;
+        }
+        let inst = super::decode(&code[..pos + len], pos, is_32);
+        if inst.len() == len {
+            return inst;
+        }
+        let mut inst = gen_invalid(code[pos]);
+        inst.pos = pos;
+        inst.length = len;
+        inst
+    }
+
+    fn format(&self, code: &[u8], inst: &Inst) -> String {
+        let instruction = decode_iced(code, inst.pos, inst.is_32c);
+        if instruction.is_invalid() {
+            return inst.to_string();
+        }
+        // Match the built-in formatting.
+        let mut formatter = GasFormatter::new();
+        formatter.options_mut().set_uppercase_hex(false);
+        formatter
+            .options_mut()
+            .set_space_after_operand_separator(true);
+        let mut output = String::new();
+        formatter.format(&instruction, &mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iced() {
+        // push %bp; fsin (unknown to the built-in decoder); ret
+        let code = b"\x55\xD9\xFE\xC3";
+        let insts = Iced.decode_range(code, 0..code.len(), false);
+        let texts = insts
+            .iter()
+            .map(|inst| (inst.pos, Iced.format(code, inst)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                (0, "push %bp".to_owned()),
+                (1, "fsin".to_owned()),
+                (3, "ret".to_owned())
+            ]
+        );
+    }
+}
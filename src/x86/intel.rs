@@ -0,0 +1,598 @@
+//! Instructions in the Intel syntax of MASM and NASM, for source that
+//! assembles back to the same bytes.
+
+use super::{regname, segment_prefix_name, split233, Flow, Immediate, Inst, CC_NAMES, SREG_NAMES};
+
+/// The assembler the source is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Masm,
+    Nasm,
+}
+
+impl Dialect {
+    fn hex(self, x: u32) -> String {
+        match self {
+            Dialect::Masm => format!("0{:X}h", x),
+            Dialect::Nasm => format!("0x{:X}", x),
+        }
+    }
+
+    fn signed(self, x: i32) -> String {
+        if x < 0 {
+            format!("-{}", self.hex(x.unsigned_abs()))
+        } else {
+            self.hex(x as u32)
+        }
+    }
+
+    /// Writes the size of a memory operand, as in `word ptr [bx]`.
+    fn sized(self, size: &str, mem: String) -> String {
+        match self {
+            Dialect::Masm => format!("{} ptr {}", size, mem),
+            Dialect::Nasm => format!("{} {}", size, mem),
+        }
+    }
+}
+
+const ARITH_NAMES: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+
+const SHIFT_NAMES: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "", "sar"];
+
+const BASE_NAMES: [&str; 8] = ["bx+si", "bx+di", "bp+si", "bp+di", "si", "di", "bp", "bx"];
+
+/// Formats a 16-bit instruction for the assembler, naming its near target
+/// with `label`.
+///
+/// Returns `None` where the assembler would pick another encoding for the
+/// same text, e.g. `05 01 00` for `add ax, 1`, which is shorter written as
+/// `83 C0 01`, and for the instructions not covered here. Such bytes are
+/// better kept as data.
+pub fn format(
+    inst: &Inst,
+    dialect: Dialect,
+    label: &dyn Fn(u32) -> Option<String>,
+) -> Option<String> {
+    if inst.is_invalid
+        || inst.opcode2.is_some()
+        || inst.addr_prefix.is_some()
+        || inst.size_prefix.is_some()
+    {
+        return None;
+    }
+    let is_memory = inst.modrm.is_some_and(|modrm| modrm >> 6 != 3);
+    if inst.segm_prefix.is_some() && !is_memory && !(0xA0..0xA4).contains(&inst.opcode) {
+        return None;
+    }
+    let is_string = matches!(inst.opcode, 0x6C..=0x6F | 0xA4..=0xA7 | 0xAA..=0xAF);
+    let prefix = match inst.inst_prefix {
+        None => "",
+        Some(0xF2) if is_string => "repne ",
+        Some(0xF3) if matches!(inst.opcode, 0xA6 | 0xA7 | 0xAE | 0xAF) => "repe ",
+        Some(0xF3) if is_string => "rep ",
+        Some(_) => return None,
+    };
+
+    let (mod_, reg, rm) = split233(inst.modrm.unwrap_or(0));
+    let byte = match inst.immediate {
+        Immediate::Byte(x) => x,
+        _ => 0,
+    };
+    let word = match inst.immediate {
+        Immediate::Word(x) => x,
+        _ => 0,
+    };
+    let fits_byte = (-0x80..0x80).contains(&(word as i16));
+    let hex = |x: u32| dialect.hex(x);
+    let sized = |wide: bool| if wide { "word" } else { "byte" };
+    let mem = |size: Option<&str>| memory_operand(inst, dialect, size);
+    let target = || match inst.flow() {
+        Flow::Jump(target) | Flow::Branch(target) | Flow::Call(target) => label(target),
+        _ => None,
+    };
+    // Between registers, MASM encodes the operands the other way round.
+    let is_reg_form = |opcode: u8| {
+        mod_ == 3
+            && match dialect {
+                Dialect::Masm => opcode & 2 != 0,
+                Dialect::Nasm => opcode & 2 == 0,
+            }
+    };
+
+    let text = match inst.opcode {
+        opcode if opcode < 0x40 && opcode & 7 < 4 => {
+            let opname = ARITH_NAMES[opcode as usize >> 3];
+            let wide = opcode & 1 != 0;
+            let reg = regname(reg, false, wide);
+            let rm = if is_reg_form(opcode) {
+                regname(rm, false, wide).to_owned()
+            } else {
+                mem(None)?
+            };
+            if opcode & 2 == 0 {
+                format!("{} {}, {}", opname, rm, reg)
+            } else {
+                format!("{} {}, {}", opname, reg, rm)
+            }
+        }
+        opcode if opcode < 0x40 && opcode & 7 == 4 => {
+            format!(
+                "{} al, {}",
+                ARITH_NAMES[opcode as usize >> 3],
+                hex(byte as u32)
+            )
+        }
+        // A byte would do with the sign-extending form.
+        opcode if opcode < 0x40 && opcode & 7 == 5 && !fits_byte => {
+            format!(
+                "{} ax, {}",
+                ARITH_NAMES[opcode as usize >> 3],
+                hex(word as u32)
+            )
+        }
+        0x06 | 0x0E | 0x16 | 0x1E => format!("push {}", SREG_NAMES[inst.opcode as usize >> 3]),
+        0x07 | 0x17 | 0x1F => format!("pop {}", SREG_NAMES[inst.opcode as usize >> 3]),
+        0x27 => "daa".to_owned(),
+        0x2F => "das".to_owned(),
+        0x37 => "aaa".to_owned(),
+        0x3F => "aas".to_owned(),
+        opcode @ 0x40..=0x5F => {
+            let opname = ["inc", "dec", "push", "pop"][(opcode as usize - 0x40) >> 3];
+            format!("{} {}", opname, regname(opcode & 7, false, true))
+        }
+        0x60 => "pusha".to_owned(),
+        0x61 => "popa".to_owned(),
+        0x68 if !fits_byte => format!("push {}", hex(word as u32)),
+        0x6A => format!("push {}", dialect.signed(byte as i8 as i32)),
+        0x69 | 0x6B => {
+            let imm = match inst.immediate {
+                Immediate::Byte(x) => dialect.signed(x as i8 as i32),
+                Immediate::Word(x) if !fits_byte => hex(x as u32),
+                _ => return None,
+            };
+            let rm = if mod_ == 3 {
+                regname(rm, false, true).to_owned()
+            } else {
+                mem(None)?
+            };
+            format!("imul {}, {}, {}", regname(reg, false, true), rm, imm)
+        }
+        0x6C => "insb".to_owned(),
+        0x6D => "insw".to_owned(),
+        0x6E => "outsb".to_owned(),
+        0x6F => "outsw".to_owned(),
+        opcode @ 0x70..=0x7F => format!("j{} short {}", CC_NAMES[opcode as usize & 0xF], target()?),
+        0x80 | 0x81 | 0x83 => {
+            let wide = inst.opcode != 0x80;
+            // The accumulator has shorter forms, and so do bytes.
+            if mod_ == 3 && rm == 0 || inst.opcode == 0x81 && fits_byte {
+                return None;
+            }
+            let rm = if mod_ == 3 {
+                regname(rm, false, wide).to_owned()
+            } else {
+                mem(Some(sized(wide)))?
+            };
+            let imm = match inst.immediate {
+                Immediate::Byte(x) if inst.opcode == 0x83 => dialect.signed(x as i8 as i32),
+                Immediate::Byte(x) => hex(x as u32),
+                _ => hex(word as u32),
+            };
+            format!("{} {}, {}", ARITH_NAMES[reg as usize], rm, imm)
+        }
+        // Between registers, the operands may be taken either way round.
+        0x84..=0x87 if mod_ != 3 => {
+            let opname = if inst.opcode < 0x86 { "test" } else { "xchg" };
+            let wide = inst.opcode & 1 != 0;
+            format!("{} {}, {}", opname, mem(None)?, regname(reg, false, wide))
+        }
+        opcode @ 0x88..=0x8B => {
+            let wide = opcode & 1 != 0;
+            // The accumulator has shorter forms for direct addresses.
+            if reg == 0 && mod_ == 0 && rm == 6 {
+                return None;
+            }
+            let reg = regname(reg, false, wide);
+            let rm = if is_reg_form(opcode) {
+                regname(rm, false, wide).to_owned()
+            } else {
+                mem(None)?
+            };
+            if opcode & 2 == 0 {
+                format!("mov {}, {}", rm, reg)
+            } else {
+                format!("mov {}, {}", reg, rm)
+            }
+        }
+        0x8C | 0x8E if reg < 4 && !(inst.opcode == 0x8E && reg == 1) => {
+            let rm = if mod_ == 3 {
+                regname(rm, false, true).to_owned()
+            } else {
+                mem(None)?
+            };
+            let sreg = SREG_NAMES[reg as usize];
+            if inst.opcode == 0x8C {
+                format!("mov {}, {}", rm, sreg)
+            } else {
+                format!("mov {}, {}", sreg, rm)
+            }
+        }
+        0x8D if mod_ != 3 => format!("lea {}, {}", regname(reg, false, true), mem(None)?),
+        0x8F if mod_ != 3 && reg == 0 => format!("pop {}", mem(Some("word"))?),
+        0x90 => "nop".to_owned(),
+        opcode @ 0x91..=0x97 => format!("xchg ax, {}", regname(opcode & 7, false, true)),
+        0x98 => "cbw".to_owned(),
+        0x99 => "cwd".to_owned(),
+        0x9B => "wait".to_owned(),
+        0x9C => "pushf".to_owned(),
+        0x9D => "popf".to_owned(),
+        0x9E => "sahf".to_owned(),
+        0x9F => "lahf".to_owned(),
+        opcode @ 0xA0..=0xA3 => {
+            let acc = regname(0, false, opcode & 1 != 0);
+            let moffs = direct_operand(inst, dialect)?;
+            if opcode & 2 == 0 {
+                format!("mov {}, {}", acc, moffs)
+            } else {
+                format!("mov {}, {}", moffs, acc)
+            }
+        }
+        opcode @ (0xA4..=0xA7 | 0xAA..=0xAF) => {
+            let opname =
+                ["movs", "cmps", "", "stos", "lods", "scas"][(opcode as usize - 0xA4) >> 1];
+            let suffix = if opcode & 1 == 0 { "b" } else { "w" };
+            format!("{}{}", opname, suffix)
+        }
+        0xA8 => format!("test al, {}", hex(byte as u32)),
+        0xA9 => format!("test ax, {}", hex(word as u32)),
+        opcode @ 0xB0..=0xBF => {
+            let imm = if opcode < 0xB8 {
+                byte as u32
+            } else {
+                word as u32
+            };
+            format!(
+                "mov {}, {}",
+                regname(opcode & 7, false, opcode >= 0xB8),
+                hex(imm)
+            )
+        }
+        0xC0 | 0xC1 | 0xD0..=0xD3 => {
+            // `sal` is an alias of `shl`, and a count of 1 has its own form.
+            if reg == 6 || inst.opcode < 0xD0 && byte == 1 {
+                return None;
+            }
+            let wide = inst.opcode & 1 != 0;
+            let rm = if mod_ == 3 {
+                regname(rm, false, wide).to_owned()
+            } else {
+                mem(Some(sized(wide)))?
+            };
+            let count = match inst.opcode {
+                0xC0 | 0xC1 => hex(byte as u32),
+                0xD0 | 0xD1 => "1".to_owned(),
+                _ => "cl".to_owned(),
+            };
+            format!("{} {}, {}", SHIFT_NAMES[reg as usize], rm, count)
+        }
+        0xC2 => format!("ret {}", hex(word as u32)),
+        0xC3 => "ret".to_owned(),
+        0xC4 | 0xC5 if mod_ != 3 => {
+            let opname = if inst.opcode == 0xC4 { "les" } else { "lds" };
+            let size = match dialect {
+                Dialect::Masm => Some("dword"),
+                Dialect::Nasm => None,
+            };
+            format!("{} {}, {}", opname, regname(reg, false, true), mem(size)?)
+        }
+        // Registers have shorter forms.
+        0xC6 | 0xC7 if mod_ != 3 && reg == 0 => {
+            let wide = inst.opcode == 0xC7;
+            let imm = if wide { word as u32 } else { byte as u32 };
+            format!("mov {}, {}", mem(Some(sized(wide)))?, hex(imm))
+        }
+        0xC8 => match inst.immediate {
+            Immediate::WordByte(size, level) => {
+                format!("enter {}, {}", hex(size as u32), hex(level as u32))
+            }
+            _ => return None,
+        },
+        0xC9 => "leave".to_owned(),
+        0xCA => format!("retf {}", hex(word as u32)),
+        0xCB => "retf".to_owned(),
+        0xCC => match dialect {
+            Dialect::Masm => "int 3".to_owned(),
+            Dialect::Nasm => "int3".to_owned(),
+        },
+        // MASM writes `int 3` as `CC`.
+        0xCD if !(dialect == Dialect::Masm && byte == 3) => format!("int {}", hex(byte as u32)),
+        0xCE => "into".to_owned(),
+        0xCF => "iret".to_owned(),
+        0xD4 if byte == 10 => "aam".to_owned(),
+        0xD5 if byte == 10 => "aad".to_owned(),
+        0xD7 => "xlatb".to_owned(),
+        opcode @ 0xE0..=0xE3 => {
+            let opname = ["loopne", "loope", "loop", "jcxz"][opcode as usize & 3];
+            format!("{} {}", opname, target()?)
+        }
+        0xE4 => format!("in al, {}", hex(byte as u32)),
+        0xE5 => format!("in ax, {}", hex(byte as u32)),
+        0xE6 => format!("out {}, al", hex(byte as u32)),
+        0xE7 => format!("out {}, ax", hex(byte as u32)),
+        0xE8 => format!("call {}", target()?),
+        0xE9 => match dialect {
+            Dialect::Masm => format!("jmp near ptr {}", target()?),
+            Dialect::Nasm => format!("jmp near {}", target()?),
+        },
+        0xEB => format!("jmp short {}", target()?),
+        0xEC => "in al, dx".to_owned(),
+        0xED => "in ax, dx".to_owned(),
+        0xEE => "out dx, al".to_owned(),
+        0xEF => "out dx, ax".to_owned(),
+        0xF4 => "hlt".to_owned(),
+        0xF5 => "cmc".to_owned(),
+        0xF6 | 0xF7 => {
+            let wide = inst.opcode == 0xF7;
+            let is_acc = mod_ == 3 && rm == 0;
+            let rm = if mod_ == 3 {
+                regname(rm, false, wide).to_owned()
+            } else {
+                mem(Some(sized(wide)))?
+            };
+            match reg {
+                // The accumulator has a shorter form, and /1 is an alias.
+                0 if !is_acc => {
+                    let imm = if wide { word as u32 } else { byte as u32 };
+                    format!("test {}, {}", rm, hex(imm))
+                }
+                2..=7 => {
+                    let opname = ["not", "neg", "mul", "imul", "div", "idiv"][reg as usize - 2];
+                    format!("{} {}", opname, rm)
+                }
+                _ => return None,
+            }
+        }
+        opcode @ 0xF8..=0xFD => {
+            ["clc", "stc", "cli", "sti", "cld", "std"][opcode as usize - 0xF8].to_owned()
+        }
+        0xFE if reg < 2 => {
+            let rm = if mod_ == 3 {
+                regname(rm, false, false).to_owned()
+            } else {
+                mem(Some("byte"))?
+            };
+            format!("{} {}", ["inc", "dec"][reg as usize], rm)
+        }
+        0xFF => match (reg, mod_) {
+            // Registers have shorter forms.
+            (0 | 1 | 6, 3) => return None,
+            (0 | 1 | 6, _) => {
+                format!(
+                    "{} {}",
+                    ["inc", "dec", "", "", "", "", "push"][reg as usize],
+                    mem(Some("word"))?
+                )
+            }
+            (2 | 4, 3) => format!(
+                "{} {}",
+                ["call", "jmp"][reg as usize / 4],
+                regname(rm, false, true)
+            ),
+            (2 | 4, _) => format!(
+                "{} {}",
+                ["call", "jmp"][reg as usize / 4],
+                mem(Some("word"))?
+            ),
+            (3 | 5, 3) => return None,
+            (3 | 5, _) => {
+                let opname = ["call", "jmp"][reg as usize / 4];
+                let size = match dialect {
+                    Dialect::Masm => "dword",
+                    Dialect::Nasm => "far",
+                };
+                format!("{} {}", opname, mem(Some(size))?)
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(format!("{}{}", prefix, text))
+}
+
+/// Formats the memory operand of the ModRM byte, with its size if given.
+///
+/// Returns `None` if the assembler would encode the address differently,
+/// e.g. a zero displacement that could be left out, or if the segment
+/// override is the default one.
+fn memory_operand(inst: &Inst, dialect: Dialect, size: Option<&str>) -> Option<String> {
+    let (mod_, _, rm) = split233(inst.modrm?);
+    let (base, disp) = match (mod_, inst.displacement) {
+        (0, Immediate::Word(addr)) if rm == 6 => (None, addr as i32),
+        (0, Immediate::None) => (Some(rm), 0),
+        (1, Immediate::Byte(disp)) if disp != 0 || rm == 6 => (Some(rm), disp as i8 as i32),
+        (2, Immediate::Word(disp)) if !(-0x80..0x80).contains(&(disp as i16)) => {
+            (Some(rm), disp as i16 as i32)
+        }
+        _ => return None,
+    };
+    let segment = segment_override(inst, matches!(base, Some(2 | 3 | 6)))?;
+    let operand = match base {
+        Some(base) => {
+            let address = match disp {
+                0 => BASE_NAMES[base as usize].to_owned(),
+                disp if disp < 0 => {
+                    format!("{}{}", BASE_NAMES[base as usize], dialect.signed(disp))
+                }
+                disp => format!("{}+{}", BASE_NAMES[base as usize], dialect.hex(disp as u32)),
+            };
+            address_operand(dialect, segment, &address)
+        }
+        None => address_operand(
+            dialect,
+            Some(segment.unwrap_or("ds")),
+            &dialect.hex(disp as u32),
+        ),
+    };
+    Some(match size {
+        Some(size) => dialect.sized(size, operand),
+        None => operand,
+    })
+}
+
+/// Formats the direct address of `mov` between the accumulator and memory.
+fn direct_operand(inst: &Inst, dialect: Dialect) -> Option<String> {
+    let segment = segment_override(inst, false)?;
+    match inst.displacement {
+        Immediate::Word(addr) => Some(address_operand(
+            dialect,
+            Some(segment.unwrap_or("ds")),
+            &dialect.hex(addr as u32),
+        )),
+        _ => None,
+    }
+}
+
+/// Returns the name of the segment override, or `None` inside if there's none.
+///
+/// Returns `None` if the override is the default segment, which assemblers
+/// leave out, or FS or GS, which aren't 16-bit registers.
+fn segment_override(inst: &Inst, uses_bp: bool) -> Option<Option<&'static str>> {
+    let default = if uses_bp { 0x36 } else { 0x3E };
+    match inst.segm_prefix {
+        None => Some(None),
+        Some(prefix) if prefix == default || prefix == 0x64 || prefix == 0x65 => None,
+        Some(prefix) => Some(Some(segment_prefix_name(prefix))),
+    }
+}
+
+fn address_operand(dialect: Dialect, segment: Option<&str>, address: &str) -> String {
+    match (dialect, segment) {
+        // A bare number would be an immediate in MASM.
+        (Dialect::Masm, Some(segment)) => format!("{}:[{}]", segment, address),
+        (Dialect::Nasm, Some("ds")) | (_, None) => format!("[{}]", address),
+        (Dialect::Nasm, Some(segment)) => format!("[{}:{}]", segment, address),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x86::decode;
+
+    fn format_both(code: &[u8]) -> (Option<String>, Option<String>) {
+        let inst = decode(code, 0, false);
+        let label = |target: u32| Some(format!("loc_{:04X}", target));
+        (
+            format(&inst, Dialect::Masm, &label),
+            format(&inst, Dialect::Nasm, &label),
+        )
+    }
+
+    fn same(text: &str) -> (Option<String>, Option<String>) {
+        (Some(text.to_owned()), Some(text.to_owned()))
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_both(b"\x55"), same("push bp"));
+        assert_eq!(
+            format_both(b"\xB8\x34\x12"),
+            (
+                Some("mov ax, 01234h".to_owned()),
+                Some("mov ax, 0x1234".to_owned())
+            )
+        );
+        assert_eq!(
+            format_both(b"\x8B\x46\xFE"),
+            (
+                Some("mov ax, [bp-02h]".to_owned()),
+                Some("mov ax, [bp-0x2]".to_owned())
+            )
+        );
+        assert_eq!(
+            format_both(b"\x26\x89\x07"),
+            (
+                Some("mov es:[bx], ax".to_owned()),
+                Some("mov [es:bx], ax".to_owned())
+            )
+        );
+        assert_eq!(
+            format_both(b"\x83\x7E\x04\xFF"),
+            (
+                Some("cmp word ptr [bp+04h], -01h".to_owned()),
+                Some("cmp word [bp+0x4], -0x1".to_owned())
+            )
+        );
+        assert_eq!(
+            format_both(b"\xA1\x00\x10"),
+            (
+                Some("mov ax, ds:[01000h]".to_owned()),
+                Some("mov ax, [0x1000]".to_owned())
+            )
+        );
+        assert_eq!(
+            format_both(b"\xFF\x5E\x04"),
+            (
+                Some("call dword ptr [bp+04h]".to_owned()),
+                Some("call far [bp+0x4]".to_owned())
+            )
+        );
+        assert_eq!(format_both(b"\xF3\xA5"), same("rep movsw"));
+        assert_eq!(format_both(b"\x74\x02"), same("jz short loc_0004"));
+        assert_eq!(format_both(b"\xE8\xFD\xFF"), same("call loc_0000"));
+        assert_eq!(
+            format_both(b"\xE9\x00\x01"),
+            (
+                Some("jmp near ptr loc_0103".to_owned()),
+                Some("jmp near loc_0103".to_owned())
+            )
+        );
+        assert_eq!(format_both(b"\xD1\xE0"), same("shl ax, 1"));
+    }
+
+    #[test]
+    fn test_register_direction() {
+        // MASM writes `mov bp, sp` as `8B EC`, NASM as `89 E5`.
+        assert_eq!(
+            format_both(b"\x8B\xEC"),
+            (Some("mov bp, sp".to_owned()), None)
+        );
+        assert_eq!(
+            format_both(b"\x89\xE5"),
+            (None, Some("mov bp, sp".to_owned()))
+        );
+        assert_eq!(
+            format_both(b"\x33\xC0"),
+            (Some("xor ax, ax".to_owned()), None)
+        );
+    }
+
+    #[test]
+    fn test_other_encodings() {
+        // Each of these would be assembled into other bytes.
+        for code in [
+            &b"\x05\x01\x00"[..],
+            b"\x81\xC3\x01\x00",
+            b"\x83\xC0\x01",
+            b"\x80\xC0\x01",
+            b"\x8B\x47\x00",
+            b"\x8B\x87\x10\x00",
+            b"\x3E\x8B\x07",
+            b"\x36\x8B\x46\x02",
+            b"\x8B\x06\x00\x10",
+            b"\xC7\xC0\x01\x00",
+            b"\xFF\xC0",
+            b"\xF7\xC0\x01\x00",
+            b"\xD1\xF0",
+            b"\xC1\xE0\x01",
+            b"\x68\x01\x00",
+            b"\x87\xD8",
+            b"\xF0\xFF\x07",
+            b"\x66\x40",
+            b"\x0F\xA0",
+        ] {
+            assert_eq!(format_both(code), (None, None), "{:02X?}", code);
+        }
+        assert_eq!(format_both(b"\xCD\x03"), (None, Some("int 0x3".to_owned())));
+    }
+}
@@ -0,0 +1,213 @@
+/// DOS functions (`int 21h`), by AH.
+static DOS_FUNCTIONS: &[(u8, &str)] = &[
+    (0x00, "terminate program"),
+    (0x01, "read character with echo"),
+    (0x02, "write character"),
+    (0x06, "direct console I/O"),
+    (0x07, "direct character input"),
+    (0x08, "read character"),
+    (0x09, "write string"),
+    (0x0A, "buffered input"),
+    (0x0B, "check input status"),
+    (0x0C, "flush buffer and read"),
+    (0x0D, "disk reset"),
+    (0x0E, "select drive"),
+    (0x19, "get current drive"),
+    (0x1A, "set DTA"),
+    (0x25, "set interrupt vector"),
+    (0x2A, "get date"),
+    (0x2B, "set date"),
+    (0x2C, "get time"),
+    (0x2D, "set time"),
+    (0x2F, "get DTA"),
+    (0x30, "get DOS version"),
+    (0x31, "terminate and stay resident"),
+    (0x33, "get/set Ctrl-Break checking"),
+    (0x35, "get interrupt vector"),
+    (0x36, "get free disk space"),
+    (0x38, "get/set country information"),
+    (0x39, "create directory"),
+    (0x3A, "remove directory"),
+    (0x3B, "set current directory"),
+    (0x3C, "create file"),
+    (0x3D, "open file"),
+    (0x3E, "close file"),
+    (0x3F, "read file"),
+    (0x40, "write file"),
+    (0x41, "delete file"),
+    (0x42, "seek"),
+    (0x43, "get/set file attributes"),
+    (0x44, "IOCTL"),
+    (0x45, "duplicate handle"),
+    (0x46, "force duplicate handle"),
+    (0x47, "get current directory"),
+    (0x48, "allocate memory"),
+    (0x49, "free memory"),
+    (0x4A, "resize memory block"),
+    (0x4B, "execute program"),
+    (0x4C, "terminate with return code"),
+    (0x4D, "get return code"),
+    (0x4E, "find first file"),
+    (0x4F, "find next file"),
+    (0x50, "set PSP"),
+    (0x51, "get PSP"),
+    (0x54, "get verify flag"),
+    (0x56, "rename file"),
+    (0x57, "get/set file date and time"),
+    (0x58, "get/set allocation strategy"),
+    (0x59, "get extended error"),
+    (0x5A, "create temporary file"),
+    (0x5B, "create new file"),
+    (0x5C, "lock/unlock file region"),
+    (0x5D, "network/sharing functions"),
+    (0x5E, "network functions"),
+    (0x5F, "network redirection"),
+    (0x60, "canonicalize path"),
+    (0x62, "get PSP"),
+    (0x65, "get extended country information"),
+    (0x66, "get/set code page"),
+    (0x67, "set handle count"),
+    (0x68, "commit file"),
+    (0x6C, "extended open/create"),
+];
+
+/// Multiplex interrupt (`int 2Fh`) functions, by AX.
+static MULTIPLEX_FUNCTIONS: &[(u16, &str)] = &[
+    (0x1600, "Windows enhanced mode installation check"),
+    (0x1605, "Windows startup broadcast"),
+    (0x1606, "Windows exit broadcast"),
+    (0x1680, "release time slice"),
+    (0x1681, "begin critical section"),
+    (0x1682, "end critical section"),
+    (0x1683, "get current virtual machine ID"),
+    (0x1686, "get CPU mode"),
+    (0x1687, "get DPMI entry point"),
+    (0x168A, "get vendor-specific API entry point"),
+    (0x4300, "XMS installation check"),
+    (0x4310, "get XMS driver entry point"),
+    (0x4A01, "query free HMA space"),
+    (0x4A02, "allocate HMA space"),
+];
+
+/// Multiplex interrupt (`int 2Fh`) services, by AH.
+static MULTIPLEX_SERVICES: &[(u8, &str)] = &[
+    (0x11, "network redirector"),
+    (0x12, "DOS internal services"),
+    (0x15, "CD-ROM extensions"),
+    (0x16, "Windows/DPMI services"),
+    (0x19, "SHELLB"),
+    (0x1A, "ANSI.SYS"),
+    (0x43, "XMS"),
+    (0x4A, "HMA"),
+    (0xAD, "DISPLAY.SYS/KEYB"),
+    (0xB7, "APPEND"),
+];
+
+/// DPMI functions (`int 31h`), by AX.
+static DPMI_FUNCTIONS: &[(u16, &str)] = &[
+    (0x0000, "allocate LDT descriptors"),
+    (0x0001, "free LDT descriptor"),
+    (0x0002, "segment to descriptor"),
+    (0x0003, "get selector increment"),
+    (0x0006, "get segment base address"),
+    (0x0007, "set segment base address"),
+    (0x0008, "set segment limit"),
+    (0x0009, "set descriptor access rights"),
+    (0x000A, "create alias descriptor"),
+    (0x000B, "get descriptor"),
+    (0x000C, "set descriptor"),
+    (0x0100, "allocate DOS memory block"),
+    (0x0101, "free DOS memory block"),
+    (0x0102, "resize DOS memory block"),
+    (0x0200, "get real mode interrupt vector"),
+    (0x0201, "set real mode interrupt vector"),
+    (0x0202, "get exception handler"),
+    (0x0203, "set exception handler"),
+    (0x0204, "get protected mode interrupt vector"),
+    (0x0205, "set protected mode interrupt vector"),
+    (0x0300, "simulate real mode interrupt"),
+    (0x0301, "call real mode far procedure"),
+    (0x0302, "call real mode interrupt procedure"),
+    (0x0303, "allocate real mode callback"),
+    (0x0304, "free real mode callback"),
+    (0x0400, "get DPMI version"),
+    (0x0500, "get free memory information"),
+    (0x0501, "allocate memory block"),
+    (0x0502, "free memory block"),
+    (0x0503, "resize memory block"),
+    (0x0600, "lock linear region"),
+    (0x0601, "unlock linear region"),
+    (0x0800, "physical address mapping"),
+    (0x0900, "disable virtual interrupt state"),
+    (0x0901, "enable virtual interrupt state"),
+    (0x0902, "get virtual interrupt state"),
+];
+
+fn find<K: Ord + Copy>(table: &[(K, &'static str)], key: K) -> Option<&'static str> {
+    table
+        .binary_search_by_key(&key, |&(k, _)| k)
+        .ok()
+        .map(|i| table[i].1)
+}
+
+/// Names the service requested by `int number`, given what is known of AH and AL.
+///
+/// Returns `None` for interrupts other than the DOS (21h), multiplex (2Fh),
+/// DPMI (31h) and moveable entry (3Fh) ones.
+pub fn service_name(number: u8, ah: Option<u8>, al: Option<u8>) -> Option<String> {
+    let ax = ah.zip(al).map(|(ah, al)| u16::from_be_bytes([ah, al]));
+    Some(match number {
+        0x21 => match ah {
+            Some(ah) => match find(DOS_FUNCTIONS, ah) {
+                Some(name) => format!("DOS: {} (AH={:02X}h)", name, ah),
+                None => format!("DOS: AH={:02X}h", ah),
+            },
+            None => "DOS services".to_owned(),
+        },
+        0x2F => match (ax.and_then(|ax| find(MULTIPLEX_FUNCTIONS, ax)), ah) {
+            (Some(name), _) => format!("multiplex: {} (AX={:04X}h)", name, ax.unwrap()),
+            (None, Some(ah)) => match find(MULTIPLEX_SERVICES, ah) {
+                Some(name) => format!("multiplex: {} (AH={:02X}h)", name, ah),
+                None => format!("multiplex: AH={:02X}h", ah),
+            },
+            (None, None) => "multiplex services".to_owned(),
+        },
+        0x31 => match ax {
+            Some(ax) => match find(DPMI_FUNCTIONS, ax) {
+                Some(name) => format!("DPMI: {} (AX={:04X}h)", name, ax),
+                None => format!("DPMI: AX={:04X}h", ax),
+            },
+            None => "DPMI services".to_owned(),
+        },
+        0x3F => "load segment of moveable entry".to_owned(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_name() {
+        for table in [DOS_FUNCTIONS, MULTIPLEX_SERVICES] {
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+        for table in [MULTIPLEX_FUNCTIONS, DPMI_FUNCTIONS] {
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+        assert_eq!(
+            service_name(0x21, Some(0x3D), None).as_deref(),
+            Some("DOS: open file (AH=3Dh)")
+        );
+        assert_eq!(
+            service_name(0x2F, Some(0x16), Some(0x87)).as_deref(),
+            Some("multiplex: get DPMI entry point (AX=1687h)")
+        );
+        assert_eq!(
+            service_name(0x31, Some(0x00), None).as_deref(),
+            Some("DPMI services")
+        );
+        assert_eq!(service_name(0x10, Some(0x00), None), None);
+    }
+}